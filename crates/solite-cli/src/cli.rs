@@ -90,7 +90,7 @@ Examples:
   solite run notebook.ipynb                  # SQL cells of a notebook
   cat script.sql | solite run app.db         # SQL from stdin
   solite run app.db -c \"SELECT count(*) FROM users\"
-  solite run -c \"SELECT * FROM 'data.csv'\"   # query a CSV/TSV file directly
+  solite run -c \"SELECT * FROM 'data.csv'\"   # query a data file directly
   solite run --readonly app.db script.sql    # statements that write will fail
   solite run script.sql --trace trace.db     # record an execution trace
 
@@ -102,6 +102,10 @@ virtual table. Explore it with, for example:
   solite query \"SELECT opcode, sum(ncycle) FROM steps \\
                 GROUP BY 1 ORDER BY 2 DESC\" trace.db
 
+Data files referenced as tables are queried directly: .csv, .tsv, .json (an
+array of objects), .ndjson/.jsonl and .txt (one `line` per row), optionally
+.gz/.zst compressed. JSON columns are inferred from the first 100 records.
+
 Scripts may contain dot commands (.export, .param set, .run, .load, ...;
 see `.help` in the REPL) and procedure definitions (`-- name: getUser :row`).
 Not available in run mode: .ask, .tui, .clear.";
@@ -124,6 +128,16 @@ pub enum QueryFormat {
     Json,
     /// Newline-delimited JSON, one object per row
     Ndjson,
+    /// GitHub-flavored Markdown table
+    Markdown,
+    /// INSERT statements, replayable into another database (see --table)
+    Sql,
+    /// Excel workbook
+    Xlsx,
+    /// Parquet file, typed from declared column types
+    Parquet,
+    /// Arrow IPC file (Feather v2), typed from declared column types
+    Arrow,
     /// Bare value of the first column of the first row, for shell interpolation
     Value,
     /// Copy results to the system clipboard
@@ -137,6 +151,11 @@ impl From<QueryFormat> for ExportFormat {
             QueryFormat::Tsv => ExportFormat::Tsv,
             QueryFormat::Json => ExportFormat::Json,
             QueryFormat::Ndjson => ExportFormat::Ndjson,
+            QueryFormat::Markdown => ExportFormat::Markdown,
+            QueryFormat::Sql => ExportFormat::SqlInsert { table: None },
+            QueryFormat::Xlsx => ExportFormat::Xlsx,
+            QueryFormat::Parquet => ExportFormat::Parquet,
+            QueryFormat::Arrow => ExportFormat::Arrow,
             QueryFormat::Value => ExportFormat::Value,
             QueryFormat::Clipboard => ExportFormat::Clipboard,
         }
@@ -148,7 +167,10 @@ Examples:
   solite query \"SELECT count(*) FROM users\" app.db
   solite query app.db report.sql -f json          # SQL from a file; order-agnostic
   solite query \"SELECT * FROM users\" app.db -o users.csv.gz
-  solite query \"SELECT * FROM 'data.csv' LIMIT 5\" # query a CSV/TSV file directly
+  solite query \"SELECT * FROM events\" app.db -o events.parquet
+  solite query \"SELECT * FROM users LIMIT 10\" app.db -f markdown
+  solite query \"SELECT * FROM users WHERE org = 7\" app.db -o org7.sql --table users
+  solite query \"SELECT * FROM 'data.csv' LIMIT 5\" # query a data file directly
   solite query \"SELECT name FROM users WHERE id = $id\" app.db -p id 42
   solite q \"SELECT 1\"                             # 'q' alias, in-memory database
   solite q \"SELECT 1\" :memory:                    # explicit in-memory database
@@ -169,7 +191,8 @@ pub struct QueryArgs {
     pub database: Option<PathBuf>,
 
     /// Write results to a file; format inferred from extension
-    /// (.csv, .tsv, .json, .ndjson; .gz/.zst compression supported)
+    /// (.csv, .tsv, .json, .ndjson, .md, .sql, .xlsx, .parquet,
+    /// .arrow/.feather; .gz/.zst compression supported)
     #[arg(long, short = 'o', value_hint = clap::ValueHint::AnyPath)]
     pub output: Option<PathBuf>,
 
@@ -177,6 +200,11 @@ pub struct QueryArgs {
     #[arg(long, short = 'f', value_enum)]
     pub format: Option<QueryFormat>,
 
+    /// Table name for `-f sql` INSERT statements (default: the query's
+    /// source table, or `data`)
+    #[arg(long, value_name = "NAME")]
+    pub table: Option<String>,
+
     /// Bind a SQL parameter, e.g. -p id 42 for `WHERE id = $id`.
     /// Integer/decimal values bind as numbers; single-quote to force
     /// text, e.g. -p id "'42'"
@@ -233,15 +261,23 @@ Remote databases:
   solite repl --allow-ssh ssh://user@host/var/data/app.db
   solite repl --allow-ssh user@host:app.db      # scp-style also works
   solite tui --allow-ssh --transport \"fly ssh console -a my-app -C\" app.db
+  solite repl --allow-ssh tcp://10.0.0.5:7878   # a `solite serve --listen` socket
+  solite repl --allow-ssh unix:/run/app.sock
 
 Requires solite installed on the remote machine (override the path with
 --remote-bin; default: `solite` on the remote $PATH). --transport replaces
-ssh with a custom command that connects stdin/stdout to the remote shell.";
+ssh with a custom command that connects stdin/stdout to the remote shell.
+tcp:// and unix: connections authenticate with the server's token, read
+from SOLITE_SERVE_TOKEN. Exports run on the server and stream back, and
+`solite --allow-ssh backup ssh://host/app.db app.db` copies a remote
+database page by page.";
 
 const REPL_ENV_HELP: &str = "\
 Inside the REPL, `.help` lists all dot commands. Environment:
   EDITOR              used by the \\e scratch-buffer command (default: vi)
-  OPENROUTER_API_KEY  required by .ask / ?<question>
+  OPENROUTER_API_KEY  API key for .ask / ?<question> with the default provider
+  SOLITE_ASK_*        override .ask settings (BASE_URL, MODEL, API_KEY_ENV,
+                      PROMPT_FILE, SAMPLE_ROWS, MAX_REPAIRS) from solite-ask.toml
   SOLITE_HISTORY      readline history file (default: ~/.solite_history)";
 
 #[derive(Args, Debug)]
@@ -364,23 +400,110 @@ assertion, compared against the first column of the first row:
   SELECT * FROM nope;       -- error: no such table: nope
   SELECT * FROM users;      -- @snap all-users
   SELECT slow();            -- TODO speed this up (fails until resolved)
+  SELECT * FROM users;      -- @rows 3
+  SELECT id FROM users;     -- @type integer
+  SELECT pi();              -- ~3.14 ±0.01
+  SELECT name FROM users;   -- ~/^user_\\d+$/   (or a LIKE pattern: ~'user_%')
+  SELECT id, name FROM users ORDER BY id; -- @table
+  -- 1 | 'alice'
+  -- 2 | 'bob'
 
 Statements without an assertion comment are setup and run silently, against
-an in-memory database. `error:` matches the error message exactly.
+an in-memory database. `error:` matches the error message exactly. @table
+rows are the `--` lines right after the statement; `@unordered` compares
+them in any order.
 Snapshots (`@snap <name>`) are stored in __snapshots__/ next to the test
 file; use --update to accept changes, --review to accept interactively.
 Multiple files and directories may be given; each file runs against its
-own fresh in-memory database and the summary is aggregated.
+own fresh in-memory database and the summary is aggregated. With --jobs N,
+files run N at a time; each file's output is buffered and printed in order.
 Dot commands available in tests: .load, .param, .print, .call, .run;
-any other dot command (or a failing one) aborts the test file.";
+any other dot command (or a failing one) aborts the test file.
+
+For CI, --reporter junit or --reporter json writes every assertion (file,
+line, column, expected and actual values, snapshot outcome, duration) to
+--output, or to stdout with the usual output moved to stderr:
+
+  solite test tests/ --reporter junit --output results.xml
+
+`-- @test <name>` lines split a file into tests that each run in a SAVEPOINT
+rolled back afterwards, after the file's setup (everything above the first
+test). `-- @before-each` blocks run at the start of every test below them,
+and `-- @fixture <file>` runs a shared SQL file like `.run`:
+
+  CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT);
+  -- @fixture fixtures/users.sql
+  -- @before-each
+  INSERT INTO users(name) VALUES ('carol');
+  -- @test delete-removes-a-row
+  DELETE FROM users WHERE name = 'carol';
+  SELECT count(*) FROM users WHERE name = 'carol'; -- 0
+
+Directories are searched recursively, skipping hidden directories and
+anything a .gitignore ignores. --filter runs only the matching assertions
+(setup statements always run):
+
+  solite test tests/ --filter users.sql:12
+  solite test tests/ --filter '/^auth-.*-snapshot$/'
+
+A solite-test.toml in the current directory or a parent sets defaults,
+with paths relative to it:
+
+  roots = [\"tests\"]                    # run when no paths are given
+  exclude = [\"fixtures/\", \"*.wip.sql\"] # .gitignore syntax
+  database = \"tests/seed.db\"           # default --database";
+
+/// A `--filter` pattern.
+#[derive(Debug, Clone)]
+pub enum TestFilter {
+    Substring(String),
+    Regex(regex::Regex),
+}
+
+impl TestFilter {
+    pub fn matches(&self, s: &str) -> bool {
+        match self {
+            TestFilter::Substring(needle) => s.contains(needle.as_str()),
+            TestFilter::Regex(regex) => regex.is_match(s),
+        }
+    }
+}
+
+/// Parse a `--filter`: `/…/` is a regex, anything else a substring.
+pub(crate) fn parse_test_filter(s: &str) -> Result<TestFilter, String> {
+    match s.strip_prefix('/').and_then(|s| s.strip_suffix('/')) {
+        Some(pattern) => regex::Regex::new(pattern)
+            .map(TestFilter::Regex)
+            .map_err(|e| format!("invalid regex: {e}")),
+        None if s.is_empty() => Err("empty filter".to_string()),
+        None => Ok(TestFilter::Substring(s.to_string())),
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TestReporter {
+    /// Progress marks, diagnostics and a summary
+    #[default]
+    Human,
+    /// JUnit XML, one <testcase> per assertion
+    Junit,
+    /// JSON, with a summary and every assertion
+    Json,
+}
 
 #[derive(Args, Debug)]
 pub struct TestArgs {
     /// SQL test files with inline `-- expected` assertions; a directory
-    /// expands to the *.sql files directly inside it (non-recursive)
-    #[arg(required = true, num_args = 1.., value_hint = clap::ValueHint::FilePath, add = sql_script_completer())]
+    /// expands to the *.sql files under it, honoring .gitignore. Defaults
+    /// to the roots in solite-test.toml
+    #[arg(num_args = 0.., value_hint = clap::ValueHint::FilePath, add = sql_script_completer())]
     pub files: Vec<PathBuf>,
 
+    /// Only run assertions whose location (file:line:column), snapshot
+    /// name or @test name contains this; /…/ for a regex
+    #[arg(long, short = 'f', value_name = "PATTERN", value_parser = parse_test_filter)]
+    pub filter: Option<TestFilter>,
+
     /// Seed each test file's in-memory database from this SQLite file
     /// (the file itself is never modified)
     #[arg(long, value_name = "PATH", value_hint = clap::ValueHint::FilePath)]
@@ -397,6 +520,24 @@ pub struct TestArgs {
     /// Interactively review each snapshot change
     #[arg(long)]
     pub review: bool,
+
+    /// Also write a machine-readable report of every assertion
+    #[arg(long, value_enum, default_value_t = TestReporter::Human)]
+    pub reporter: TestReporter,
+
+    /// Write the --reporter report here instead of stdout
+    #[arg(short, long, value_name = "PATH", value_hint = clap::ValueHint::AnyPath)]
+    pub output: Option<PathBuf>,
+
+    /// Run up to N test files at once (--review always runs one at a time)
+    #[arg(
+        long,
+        short = 'j',
+        value_name = "N",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub jobs: u32,
 }
 
 #[derive(Args, Debug)]
@@ -628,7 +769,7 @@ pub struct SchemaArgs {
 
 #[derive(Args, Debug)]
 pub struct BackupArgs {
-    /// Source database path
+    /// Source database path or ssh:// URL (with --allow-ssh)
     #[arg(value_hint = clap::ValueHint::AnyPath, add = database_completer())]
     pub database: PathBuf,
 
@@ -664,10 +805,118 @@ pub struct VacuumArgs {
     pub force: bool,
 }
 
+const MCP_AFTER_HELP: &str = "\
+Speaks the Model Context Protocol over stdio. Register it with an MCP client
+as a command, e.g. for a JSON-configured client:
+
+  {\"command\": \"solite\", \"args\": [\"mcp\", \"app.db\", \"--procedures\", \"queries.sql\"]}
+
+Tools: list_tables, describe_schema, query (a single read-only statement),
+list_procedures and call_procedure (the `-- name:` procedures defined in
+--procedures files). The database is opened read-only.";
+
+#[derive(Args, Debug)]
+pub struct McpArgs {
+    /// Database file to expose (opened read-only)
+    #[arg(value_hint = clap::ValueHint::FilePath, add = database_completer())]
+    pub database: PathBuf,
+
+    /// SQL file with `-- name:` procedures to expose through
+    /// call_procedure. Repeatable
+    #[arg(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath, add = sql_script_completer())]
+    pub procedures: Vec<PathBuf>,
+
+    /// Max rows returned by a single query or procedure call [default: 1000]
+    #[arg(long, value_name = "N")]
+    pub max_rows: Option<usize>,
+}
+
+const SERVE_AFTER_HELP: &str = "\
+Examples:
+  solite serve app.db                               # stdin/stdout, as used over ssh
+  solite serve app.db --listen unix:/run/app.sock
+  solite serve app.db --listen 127.0.0.1:7878 --token-file /etc/solite/token
+  solite serve app.db --http 127.0.0.1:8080         # JSON API, read-only
+
+With --listen, clients must present a shared token: the contents of
+--token-file, or SOLITE_SERVE_TOKEN. Each client gets its own connection.
+
+--http serves:
+  POST /query     {\"sql\": \"...\", \"params\": {\"id\": 1}} -> {\"columns\": [...], \"rows\": [[...]]}
+                  (one JSON row per line with Accept: application/x-ndjson)
+  POST /execute   {\"sql\": \"...\", \"params\": {...}} -> {\"changes\": N}  (needs --allow-writes)
+  GET  /schema    the database schema as JSON
+When a token is set, requests need an `Authorization: Bearer <token>` header.
+A token is required unless the server is read-only on a loopback address.";
+
 #[derive(Args, Debug)]
 pub struct ServeArgs {
     /// Path to the database file to serve
     pub database: String,
+
+    /// Accept clients on a socket instead of stdin/stdout: host:port,
+    /// tcp://host:port or unix:/path/to.sock
+    #[arg(long, value_name = "ADDRESS")]
+    pub listen: Option<String>,
+
+    /// Serve a JSON query API over HTTP on host:port
+    #[arg(long, value_name = "ADDRESS", conflicts_with = "listen")]
+    pub http: Option<String>,
+
+    /// Let --http clients write: open the database read-write and enable
+    /// POST /execute
+    #[arg(long, requires = "http")]
+    pub allow_writes: bool,
+
+    /// File holding the token clients must present (with --listen or
+    /// --http). Defaults to SOLITE_SERVE_TOKEN. Required unless --http is
+    /// read-only on a loopback address
+    #[arg(long, value_name = "PATH")]
+    pub token_file: Option<PathBuf>,
+
+    /// Largest request a --listen client may send, in megabytes; larger
+    /// ones end its session
+    #[arg(long, value_name = "MB", default_value_t = 64, requires = "listen")]
+    pub max_request_mb: usize,
+}
+
+#[derive(Args, Debug)]
+pub struct RemoteNamespace {
+    #[command(subcommand)]
+    pub command: RemoteCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RemoteCommand {
+    /// Keep ssh:// sessions warm for later solite invocations
+    #[command(after_long_help = REMOTE_DAEMON_AFTER_HELP)]
+    Daemon(RemoteDaemonArgs),
+}
+
+const REMOTE_DAEMON_AFTER_HELP: &str = "\
+While the daemon runs, every `solite ... --allow-ssh ssh://...` on this
+machine attaches to it instead of spawning ssh. The daemon keeps one
+`solite serve` session per URL warm, so repeated invocations against the
+same host skip the ssh connection and remote startup. Between clients it
+closes their cursors, rolls back any open transaction, detaches attached
+databases and drops temp tables, views and triggers; pragmas and loaded
+extensions carry over.
+
+The socket lives in $XDG_RUNTIME_DIR/solite (or a per-user temp directory);
+set SOLITE_REMOTE_DAEMON to use another path, for the daemon and clients
+alike. Its directory must belong to you and be closed to other users, and
+clients refuse a daemon run by another user.";
+
+#[derive(Args, Debug)]
+pub struct RemoteDaemonArgs {
+    /// Socket to listen on [default: $SOLITE_REMOTE_DAEMON, else
+    /// $XDG_RUNTIME_DIR/solite/remote.sock]
+    #[arg(long, value_name = "PATH", value_hint = clap::ValueHint::FilePath)]
+    pub socket: Option<PathBuf>,
+
+    /// Close sessions unused for this many seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = solite_core::daemon::DEFAULT_IDLE_TIMEOUT.as_secs())]
+    pub idle_timeout: u64,
 }
 
 impl VacuumArgs {
@@ -689,7 +938,61 @@ pub enum StreamCommand {
     /// Sync WAL changes to a replica
     Sync(StreamSyncArgs),
     /// Restore a database from a replica
+    #[command(after_long_help = STREAM_RESTORE_AFTER_HELP)]
     Restore(StreamRestoreArgs),
+    /// List the files in a replica, with their transaction ranges
+    Status(StreamStatusArgs),
+    /// Merge old replica files into a snapshot and delete what it covers
+    #[command(after_long_help = STREAM_COMPACT_AFTER_HELP)]
+    Compact(StreamCompactArgs),
+}
+
+#[cfg(feature = "ritestream")]
+const STREAM_RESTORE_AFTER_HELP: &str = "\
+Point-in-time restores:
+  solite stream restore file:///backups/app app.db --txid 1234
+  solite stream restore s3://bucket/app app.db --timestamp 2026-10-17T09:30:00Z
+
+--txid and --timestamp restore the newest snapshot at or before that point,
+then the contiguous LTX files after it, stopping at the point. --timestamp
+compares against the commit time recorded in each LTX file's header. The
+result is the last replicated state at or before the point; if the replica
+only has files spanning it (e.g. after compaction), the restore fails.";
+
+#[cfg(feature = "ritestream")]
+const STREAM_COMPACT_AFTER_HELP: &str = "\
+Retention:
+  solite stream compact s3://bucket/app --keep 7d
+  solite stream compact file:///backups/app --keep 12h --dry-run
+  solite stream sync app.db s3://bucket/app --watch --retention 7d
+
+Every state from the last --keep window stays restorable. Compaction takes
+the newest snapshot (an LTX file starting at txid 1) uploaded before the
+window, merges it with the contiguous files after it that are also older
+than the window into a new snapshot, then deletes every LTX file the new
+snapshot covers. Until sync has uploaded a snapshot that old, nothing
+changes.";
+
+/// Parse an age like `90s`, `30m`, `12h`, `7d` or `2w`.
+#[cfg(feature = "ritestream")]
+pub(crate) fn parse_age(s: &str) -> Result<std::time::Duration, String> {
+    const HINT: &str = "expected a number with an s/m/h/d/w suffix, e.g. 7d";
+    let t = s.trim().to_ascii_lowercase();
+    let unit = match t.chars().last() {
+        Some('s') => 1,
+        Some('m') => 60,
+        Some('h') => 60 * 60,
+        Some('d') => 24 * 60 * 60,
+        Some('w') => 7 * 24 * 60 * 60,
+        _ => return Err(format!("invalid age '{s}': {HINT}")),
+    };
+    let n: u64 = t[..t.len() - 1]
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid age '{s}': {HINT}"))?;
+    n.checked_mul(unit)
+        .map(std::time::Duration::from_secs)
+        .ok_or_else(|| format!("age '{s}' is too large"))
 }
 
 #[cfg(feature = "ritestream")]
@@ -700,6 +1003,18 @@ pub struct StreamSyncArgs {
     pub database: PathBuf,
     /// Replica URL (s3://bucket/prefix, file:///path, or bare path)
     pub url: String,
+
+    /// Keep running, syncing again whenever the WAL changes
+    #[arg(long)]
+    pub watch: bool,
+
+    /// How often --watch checks the WAL, in milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 1000, requires = "watch")]
+    pub interval: u64,
+
+    /// After each sync, compact the replica keeping this much history (e.g. 7d)
+    #[arg(long, value_name = "AGE", value_parser = parse_age)]
+    pub retention: Option<std::time::Duration>,
 }
 
 #[cfg(feature = "ritestream")]
@@ -710,6 +1025,36 @@ pub struct StreamRestoreArgs {
     /// Destination database path
     #[arg(value_hint = clap::ValueHint::AnyPath)]
     pub database: PathBuf,
+
+    /// Restore the state as of this transaction ID
+    #[arg(long, value_name = "N", conflicts_with = "timestamp")]
+    pub txid: Option<u64>,
+
+    /// Restore the state as of this time (RFC 3339, e.g. 2026-10-17T09:30:00Z)
+    #[arg(long, value_name = "TIME")]
+    pub timestamp: Option<jiff::Timestamp>,
+}
+
+#[cfg(feature = "ritestream")]
+#[derive(Args, Debug)]
+pub struct StreamStatusArgs {
+    /// Replica URL (s3://bucket/prefix, file:///path, or bare path)
+    pub url: String,
+}
+
+#[cfg(feature = "ritestream")]
+#[derive(Args, Debug)]
+pub struct StreamCompactArgs {
+    /// Replica URL (s3://bucket/prefix, file:///path, or bare path)
+    pub url: String,
+
+    /// How much history to keep restorable (e.g. 12h, 7d, 2w)
+    #[arg(long, value_name = "AGE", value_parser = parse_age)]
+    pub keep: std::time::Duration,
+
+    /// List what would be deleted without deleting it
+    #[arg(long)]
+    pub dry_run: bool,
 }
 
 const COMPLETIONS_AFTER_HELP: &str = "\
@@ -831,10 +1176,18 @@ pub enum Commands {
     /// Rebuild a database file, repacking it into minimal disk space
     Vacuum(VacuumArgs),
 
-    /// Serve a database over stdin/stdout (used by SSH remote connections)
-    #[command(hide = true)]
+    /// Serve a database over stdin/stdout (used by SSH remote connections),
+    /// to several clients on a TCP/Unix socket with --listen, or as JSON with --http
+    #[command(after_long_help = SERVE_AFTER_HELP)]
     Serve(ServeArgs),
 
+    /// Manage remote connections (see `remote daemon --help`)
+    Remote(RemoteNamespace),
+
+    /// Serve a database to AI agents over the Model Context Protocol (stdio)
+    #[command(after_long_help = MCP_AFTER_HELP)]
+    Mcp(McpArgs),
+
     /// Print the shell completion registration script (see `completions --help`)
     #[command(after_long_help = COMPLETIONS_AFTER_HELP)]
     Completions(CompletionsArgs),
@@ -878,6 +1231,9 @@ SQL:
   format, fmt      Format SQL files
   lint             Lint SQL files for potential issues
   lsp              Start the Language Server Protocol (LSP) server
+  serve            Serve a database to remote solite clients
+  remote           Keep ssh:// sessions warm between invocations (remote daemon)
+  mcp              Serve a database to AI agents over the Model Context Protocol
 {replication}
 Compatibility:
   sqlite3          Run the sqlite3 shell directly
@@ -894,7 +1250,7 @@ Shell:
 /// Render the top-level help, including feature-gated sections.
 fn help_template() -> String {
     let replication = if cfg!(feature = "ritestream") {
-        "\nReplication:\n  stream           Streaming replication (sync/restore)\n"
+        "\nReplication:\n  stream           Streaming replication (sync/restore/status/compact)\n"
     } else {
         ""
     };
@@ -913,7 +1269,8 @@ fn help_template() -> String {
   help_template = help_template(),
 )]
 pub struct Cli {
-    /// Allow connecting to ssh:// database URLs and custom --transport commands
+    /// Allow connecting to ssh://, tcp:// and unix: database URLs and custom
+    /// --transport commands
     #[arg(long, global = true)]
     pub allow_ssh: bool,
 
@@ -994,4 +1351,35 @@ mod tests {
             assert_eq!(value_hint(sub, "args"), clap::ValueHint::Unknown, "{sub}");
         }
     }
+
+    #[cfg(feature = "ritestream")]
+    #[test]
+    fn age_suffixes() {
+        use std::time::Duration;
+        assert_eq!(parse_age("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_age("12h"), Ok(Duration::from_secs(12 * 3600)));
+        assert_eq!(parse_age(" 7D "), Ok(Duration::from_secs(7 * 86400)));
+        assert_eq!(parse_age("2w"), Ok(Duration::from_secs(14 * 86400)));
+        for bad in ["", "7", "d", "7y", "-1d"] {
+            assert!(parse_age(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_filter_patterns() {
+        let filter = parse_test_filter("users.sql:12").unwrap();
+        assert!(matches!(filter, TestFilter::Substring(_)));
+        assert!(filter.matches("tests/users.sql:12:5"));
+        assert!(!filter.matches("tests/users.sql:13:5"));
+
+        let filter = parse_test_filter("/^auth-.*$/").unwrap();
+        assert!(matches!(filter, TestFilter::Regex(_)));
+        assert!(filter.matches("auth-login"));
+        assert!(!filter.matches("tests/auth-login.sql:1:1"));
+
+        assert!(parse_test_filter("/(/").is_err());
+        assert!(parse_test_filter("").is_err());
+        // A lone slash is a substring, not an empty regex
+        assert!(matches!(parse_test_filter("/"), Ok(TestFilter::Substring(_))));
+    }
 }
@@ -1,24 +1,30 @@
 use std::ffi::CString;
+use std::io::{BufWriter, Write};
 use std::ptr;
 use std::time::Instant;
 
 use console::style;
 use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
 use libsqlite3_sys::*;
-use solite_core::sqlite::Connection;
+use solite_core::sqlite::{self, Connection};
+use solite_core::Runtime;
 
 use crate::cli::BackupArgs;
 
 fn get_page_size(conn: &Connection) -> u64 {
-    let Ok((_, Some(mut stmt))) = conn.prepare("PRAGMA page_size") else {
-        return 4096;
+    pragma_u64(conn, "PRAGMA page_size").unwrap_or(4096)
+}
+
+fn pragma_u64(conn: &Connection, sql: &str) -> Option<u64> {
+    let Ok((_, Some(mut stmt))) = conn.prepare(sql) else {
+        return None;
     };
     if let Ok(Some(row)) = stmt.next() {
         if let solite_core::sqlite::ValueRefXValue::Int(v) = row[0].value {
-            return v as u64;
+            return Some(v as u64);
         }
     }
-    4096
+    None
 }
 
 /// Error message from a SQLite handle. A null handle (sqlite3_open_v2 only
@@ -51,15 +57,9 @@ fn remove_failed_destination(path: &std::path::Path) {
     }
 }
 
-// TODO: make a safe(r) Rust wrapper around raw C API
-pub fn backup(args: BackupArgs) -> Result<(), ()> {
-    let source_path = args.database.to_string_lossy();
-    let source = Connection::open(&source_path).map_err(|e| {
-        eprintln!("Error opening source database: {}", e.message);
-    })?;
-
-    let page_size = get_page_size(&source);
-
+/// Clear the way for the destination file: refuse to overwrite it without
+/// `--force`.
+fn prepare_destination(args: &BackupArgs) -> Result<(), ()> {
     if args.destination.exists() {
         if args.force {
             std::fs::remove_file(&args.destination).map_err(|e| {
@@ -76,6 +76,82 @@ pub fn backup(args: BackupArgs) -> Result<(), ()> {
             return Err(());
         }
     }
+    Ok(())
+}
+
+fn progress_bar(args: &BackupArgs) -> ProgressBar {
+    let pb = ProgressBar::new(0);
+    if let Ok(style) = ProgressStyle::with_template(&format!(
+        "{} -> {}\n[{{bar:60}}] {{msg}} ({{elapsed}} / ETA {{eta}})",
+        args.database.display(),
+        args.destination.display()
+    )) {
+        pb.set_style(style);
+    }
+    pb
+}
+
+fn print_done(args: &BackupArgs, start: Instant) {
+    let size = std::fs::metadata(&args.destination).map(|m| m.len()).unwrap_or(0);
+    println!(
+        "{} Backed up to {} ({}, {:.2?})",
+        style("\u{2714}").green(),
+        args.destination.display(),
+        HumanBytes(size),
+        start.elapsed(),
+    );
+}
+
+/// Back up a remote database by streaming its pages from the server.
+fn remote_backup(args: &BackupArgs, allow_ssh: bool) -> Result<(), ()> {
+    let source_path = args.database.to_string_lossy().to_string();
+    let runtime = Runtime::new_with_options(Some(source_path), None, None, allow_ssh).map_err(|e| {
+        eprintln!("Error opening source database: {}", e.message);
+    })?;
+    let source = &runtime.connection;
+    prepare_destination(args)?;
+
+    let schema = args.db.replace('"', "\"\"");
+    let total_bytes = get_page_size(source)
+        * pragma_u64(source, &format!("PRAGMA \"{schema}\".page_count")).unwrap_or(0);
+    let file = std::fs::File::create(&args.destination).map_err(|e| {
+        eprintln!("Error creating destination file {}: {e}", args.destination.display());
+    })?;
+
+    let start = Instant::now();
+    let pb = progress_bar(args);
+    pb.set_length(total_bytes);
+    let mut out = pb.wrap_write(BufWriter::new(file));
+    let result = source
+        .backup_to(&args.db, &mut out)
+        .map_err(|e| e.message)
+        .and_then(|_| out.flush().map_err(|e| e.to_string()));
+    pb.finish_and_clear();
+    if let Err(msg) = result {
+        eprintln!("Backup failed: {msg}");
+        drop(out);
+        remove_failed_destination(&args.destination);
+        return Err(());
+    }
+    drop(out);
+
+    print_done(args, start);
+    Ok(())
+}
+
+// TODO: make a safe(r) Rust wrapper around raw C API
+pub fn backup(args: BackupArgs, allow_ssh: bool) -> Result<(), ()> {
+    let source_path = args.database.to_string_lossy();
+    if sqlite::is_remote_path(&source_path) {
+        return remote_backup(&args, allow_ssh);
+    }
+    let source = Connection::open(&source_path).map_err(|e| {
+        eprintln!("Error opening source database: {}", e.message);
+    })?;
+
+    let page_size = get_page_size(&source);
+
+    prepare_destination(&args)?;
 
     let dest_path = CString::new(args.destination.to_string_lossy().as_ref()).map_err(|_| {
         eprintln!("Invalid destination path");
@@ -113,16 +189,8 @@ pub fn backup(args: BackupArgs) -> Result<(), ()> {
         return Err(());
     }
 
-    let src_display = args.database.display();
-    let dest_display = args.destination.display();
-
     let start = Instant::now();
-    let pb = ProgressBar::new(0);
-    if let Ok(style) = ProgressStyle::with_template(
-        &format!("{src_display} -> {dest_display}\n[{{bar:60}}] {{msg}} ({{elapsed}} / ETA {{eta}})")
-    ) {
-        pb.set_style(style);
-    }
+    let pb = progress_bar(&args);
 
     loop {
         let rc = unsafe { sqlite3_backup_step(backup, 100) };
@@ -164,15 +232,6 @@ pub fn backup(args: BackupArgs) -> Result<(), ()> {
 
     unsafe { sqlite3_close(dest_db) };
 
-    let elapsed = start.elapsed();
-    let size = std::fs::metadata(&args.destination).map(|m| m.len()).unwrap_or(0);
-
-    println!(
-        "{} Backed up to {} ({}, {:.2?})",
-        style("\u{2714}").green(),
-        dest_display,
-        HumanBytes(size),
-        elapsed,
-    );
+    print_done(&args, start);
     Ok(())
 }
@@ -0,0 +1,48 @@
+//! Conversions between JSON values and SQLite values, shared by the JSON
+//! speaking servers (`solite mcp` and `solite serve --http`).
+
+use serde_json::Value;
+use solite_core::sqlite::OwnedValue;
+
+/// Scalar JSON values map onto SQLite values; arrays and objects don't.
+pub(crate) fn json_to_owned_value(value: &Value) -> Option<OwnedValue> {
+    match value {
+        Value::Null => Some(OwnedValue::Null),
+        Value::Bool(b) => Some(OwnedValue::Integer(*b as i64)),
+        Value::Number(n) => n
+            .as_i64()
+            .map(OwnedValue::Integer)
+            .or_else(|| n.as_f64().map(OwnedValue::Double)),
+        Value::String(s) => Some(OwnedValue::Text(s.as_bytes().to_vec())),
+        Value::Array(_) | Value::Object(_) => None,
+    }
+}
+
+/// Convert a value to JSON. Non-finite doubles become `null` and blobs a
+/// hex string, since neither has a JSON representation.
+pub(crate) fn owned_value_to_json(value: &OwnedValue) -> Value {
+    match value {
+        OwnedValue::Null => Value::Null,
+        OwnedValue::Integer(i) => Value::from(*i),
+        OwnedValue::Double(f) => serde_json::Number::from_f64(*f)
+            .map(Value::Number)
+            .unwrap_or(Value::Null),
+        OwnedValue::Text(s) => Value::String(String::from_utf8_lossy(s).into_owned()),
+        OwnedValue::Blob(b) => Value::String(hex::encode(b)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_values_round_trip() {
+        assert!(matches!(json_to_owned_value(&json!(true)), Some(OwnedValue::Integer(1))));
+        assert!(matches!(json_to_owned_value(&json!(1.5)), Some(OwnedValue::Double(_))));
+        assert!(json_to_owned_value(&json!([1])).is_none());
+        assert_eq!(owned_value_to_json(&OwnedValue::Blob(vec![0xde, 0xad])), json!("dead"));
+        assert_eq!(owned_value_to_json(&OwnedValue::Double(f64::NAN)), Value::Null);
+    }
+}
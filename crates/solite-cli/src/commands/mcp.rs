@@ -0,0 +1,628 @@
+//! `solite mcp`: a Model Context Protocol server over stdio.
+//!
+//! Speaks JSON-RPC 2.0 with one message per line on stdin/stdout (the MCP
+//! stdio transport) and exposes a database to agents through a small set of
+//! tools:
+//!
+//! - `list_tables`: table/view names, via [`TablesCommand`]
+//! - `describe_schema`: the JSON schema document from `solite_schema`
+//! - `query`: run a single read-only SQL statement
+//! - `list_procedures` / `call_procedure`: `-- name:` procedures loaded
+//!   from `--procedures` files
+//!
+//! The database is always opened read-only, and statements that would write
+//! are rejected before they run, the same way `solite query` does. ATTACH
+//! and the stdlib's file, network and clipboard functions are denied too.
+
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context};
+use serde_json::{json, Map, Value};
+use solite_core::dot::TablesCommand;
+use solite_core::procedure::parse_parameter;
+use solite_core::sqlite::{OwnedValue, Statement};
+use solite_core::Runtime;
+
+use crate::cli::McpArgs;
+use crate::commands::json_value::{json_to_owned_value, owned_value_to_json};
+
+/// MCP protocol revision this server implements.
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Rows returned by a single `query`/`call_procedure` before truncating.
+const DEFAULT_MAX_ROWS: usize = 1000;
+
+// JSON-RPC 2.0 error codes.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+pub fn mcp(args: McpArgs) -> Result<(), ()> {
+    mcp_impl(args).map_err(|e| eprintln!("Error: {e:#}"))
+}
+
+fn mcp_impl(args: McpArgs) -> anyhow::Result<()> {
+    let mut server = McpServer::open(&args.database, &args.procedures)?;
+    if let Some(max_rows) = args.max_rows {
+        server.max_rows = max_rows;
+    }
+
+    let stdin = io::stdin();
+    let mut stdout = io::stdout().lock();
+    for line in stdin.lock().lines() {
+        let line = line.context("failed to read from stdin")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.handle_message(&line) {
+            writeln!(stdout, "{response}")?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// An error returned to the client as a JSON-RPC `error` object.
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+pub(crate) struct McpServer {
+    runtime: Runtime,
+    max_rows: usize,
+}
+
+impl McpServer {
+    /// Open `database` read-only and register the procedures defined in
+    /// each of `procedure_files`.
+    fn open(database: &Path, procedure_files: &[PathBuf]) -> anyhow::Result<Self> {
+        if !database.exists() {
+            bail!("no such file: {}", database.display());
+        }
+        let mut runtime = Runtime::new_readonly(&database.to_string_lossy())
+            .map_err(|e| anyhow!("failed to open {}: {}", database.display(), e))?;
+        for file in procedure_files {
+            runtime
+                .load_file(&file.to_string_lossy())
+                .map_err(|e| anyhow!("{e}"))?;
+        }
+        // Agents' queries can't reach the filesystem, network or other
+        // databases, even through statements SQLite counts as read-only
+        runtime
+            .connection
+            .deny_host_access()
+            .map_err(|e| anyhow!("failed to restrict {}: {}", database.display(), e))?;
+        Ok(Self {
+            runtime,
+            max_rows: DEFAULT_MAX_ROWS,
+        })
+    }
+
+    /// Handle one JSON-RPC message, returning the serialized response.
+    /// Notifications (messages without an `id`) produce no response.
+    pub(crate) fn handle_message(&mut self, line: &str) -> Option<String> {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(e) => {
+                return Some(error_response(
+                    Value::Null,
+                    RpcError::new(PARSE_ERROR, format!("invalid JSON: {e}")),
+                ))
+            }
+        };
+        let id = message.get("id").cloned();
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            return id.map(|id| {
+                error_response(id, RpcError::new(INVALID_REQUEST, "missing `method`"))
+            });
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let result = self.dispatch(method, &params);
+
+        // Notifications never get a response, even when they fail.
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}).to_string(),
+            Err(error) => error_response(id, error),
+        })
+    }
+
+    fn dispatch(&mut self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": {"tools": {}},
+                "serverInfo": {
+                    "name": "solite",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            })),
+            "notifications/initialized" | "notifications/cancelled" => Ok(Value::Null),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({"tools": tool_definitions()})),
+            "tools/call" => {
+                let name = params
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing tool `name`"))?;
+                let empty = Map::new();
+                let arguments = params
+                    .get("arguments")
+                    .and_then(Value::as_object)
+                    .unwrap_or(&empty);
+                // Tool failures are reported in-band so the agent can see
+                // (and react to) the SQL error message.
+                Ok(match self.call_tool(name, arguments) {
+                    Ok(value) => tool_result(&value, false),
+                    Err(ToolError::UnknownTool) => {
+                        return Err(RpcError::new(
+                            INVALID_PARAMS,
+                            format!("unknown tool: {name}"),
+                        ))
+                    }
+                    Err(ToolError::Failed(message)) => tool_result(&Value::String(message), true),
+                })
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("method not found: {method}"),
+            )),
+        }
+    }
+
+    fn call_tool(&mut self, name: &str, arguments: &Map<String, Value>) -> Result<Value, ToolError> {
+        match name {
+            "list_tables" => {
+                let schema = arguments
+                    .get("schema")
+                    .and_then(Value::as_str)
+                    .map(str::to_owned);
+                let tables = TablesCommand { schema }
+                    .execute(&self.runtime)
+                    .map_err(|e| ToolError::Failed(e.to_string()))?;
+                Ok(json!(tables))
+            }
+            "describe_schema" => self.describe_schema(),
+            "query" => {
+                let sql = arguments
+                    .get("sql")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| ToolError::Failed("missing required argument `sql`".into()))?;
+                let params = object_argument(arguments, "params")?;
+                self.run_readonly(sql, params)
+            }
+            "list_procedures" => {
+                let mut procedures: Vec<_> = self.runtime.procedures().values().collect();
+                procedures.sort_by(|a, b| a.name.cmp(&b.name));
+                Ok(Value::Array(
+                    procedures
+                        .into_iter()
+                        .map(|p| {
+                            json!({
+                                "name": p.name,
+                                "sql": p.sql,
+                                "parameters": p.parameters.iter().map(|param| json!({
+                                    "name": param.name,
+                                    "type": param.annotated_type,
+                                    "required": !param.nullable,
+                                })).collect::<Vec<_>>(),
+                            })
+                        })
+                        .collect(),
+                ))
+            }
+            "call_procedure" => {
+                let name = arguments
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| ToolError::Failed("missing required argument `name`".into()))?;
+                let procedure = self
+                    .runtime
+                    .get_procedure(name)
+                    .ok_or_else(|| ToolError::Failed(format!("unknown procedure: '{name}'")))?;
+                let sql = procedure.sql.clone();
+                let params = object_argument(arguments, "params")?;
+                self.run_readonly(&sql, params)
+            }
+            _ => Err(ToolError::UnknownTool),
+        }
+    }
+
+    fn describe_schema(&self) -> Result<Value, ToolError> {
+        use solite_schema::json::JsonSchema;
+
+        // SAFETY: the runtime's connection outlives the call
+        let introspected = unsafe { solite_schema::introspect_handle(self.runtime.connection.db()) }
+            .map_err(|e| ToolError::Failed(e.to_string()))?;
+        serde_json::to_value(JsonSchema::from(&introspected))
+            .map_err(|e| ToolError::Failed(e.to_string()))
+    }
+
+    /// Prepare a single statement, bind `params` by name, reject writes, and
+    /// collect up to `max_rows` rows as JSON objects.
+    fn run_readonly(
+        &mut self,
+        sql: &str,
+        params: Option<&Map<String, Value>>,
+    ) -> Result<Value, ToolError> {
+        let mut stmt = match self.runtime.prepare_with_parameters(sql) {
+            Ok((rest, Some(stmt))) => {
+                if let Some(offset) = rest {
+                    if !matches!(self.runtime.prepare_with_parameters(&sql[offset..]), Ok((_, None))) {
+                        return Err(ToolError::Failed(
+                            "only a single SQL statement is allowed".into(),
+                        ));
+                    }
+                }
+                stmt
+            }
+            Ok((_, None)) => return Err(ToolError::Failed("no SQL statement provided".into())),
+            Err(e) => return Err(ToolError::Failed(e.to_string())),
+        };
+        if !stmt.readonly() {
+            return Err(ToolError::Failed(
+                "only read-only statements are allowed".into(),
+            ));
+        }
+        if let Some(params) = params {
+            bind_json_params(&stmt, params)?;
+        }
+        collect_rows(&mut stmt, self.max_rows)
+    }
+}
+
+/// Why a `tools/call` failed.
+#[derive(Debug)]
+enum ToolError {
+    /// No tool with that name; a protocol error rather than a tool result.
+    UnknownTool,
+    /// The tool ran and failed; reported to the agent with `isError: true`.
+    Failed(String),
+}
+
+fn error_response(id: Value, error: RpcError) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": error.code, "message": error.message},
+    })
+    .to_string()
+}
+
+/// Wrap a tool's output in an MCP `CallToolResult` with a single text block.
+fn tool_result(value: &Value, is_error: bool) -> Value {
+    let text = match value {
+        Value::String(s) => s.clone(),
+        other => serde_json::to_string_pretty(other).unwrap_or_else(|_| other.to_string()),
+    };
+    json!({
+        "content": [{"type": "text", "text": text}],
+        "isError": is_error,
+    })
+}
+
+fn object_argument<'a>(
+    arguments: &'a Map<String, Value>,
+    key: &str,
+) -> Result<Option<&'a Map<String, Value>>, ToolError> {
+    match arguments.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Object(map)) => Ok(Some(map)),
+        Some(_) => Err(ToolError::Failed(format!("`{key}` must be an object"))),
+    }
+}
+
+/// Bind `params` to the statement's placeholders by name. Keys match with or
+/// without the `$`/`:`/`@` prefix and ignore `::type` annotations, so
+/// `{"id": 1}` binds `$id`, `:id` and `$id::int`.
+fn bind_json_params(stmt: &Statement, params: &Map<String, Value>) -> Result<(), ToolError> {
+    for (idx, placeholder) in stmt.bind_parameters().iter().enumerate() {
+        let name = parse_parameter(placeholder).name;
+        let Some(value) = params.get(&name).or_else(|| params.get(placeholder)) else {
+            continue;
+        };
+        let idx = (idx + 1) as i32;
+        let bound = match json_to_owned_value(value) {
+            Some(OwnedValue::Null) => stmt.bind_null(idx),
+            Some(OwnedValue::Integer(v)) => stmt.bind_int64(idx, v),
+            Some(OwnedValue::Double(v)) => stmt.bind_double(idx, v),
+            Some(OwnedValue::Text(v)) => stmt.bind_text(idx, String::from_utf8_lossy(&v)),
+            Some(OwnedValue::Blob(v)) => stmt.bind_blob(idx, &v),
+            None => {
+                return Err(ToolError::Failed(format!(
+                    "parameter `{name}` must be a string, number, boolean or null"
+                )))
+            }
+        };
+        bound.map_err(|e| ToolError::Failed(e.to_string()))?;
+    }
+    Ok(())
+}
+
+fn collect_rows(stmt: &mut Statement, max_rows: usize) -> Result<Value, ToolError> {
+    let columns = stmt
+        .column_names()
+        .map_err(|e| ToolError::Failed(e.to_string()))?;
+    let mut rows = Vec::new();
+    let mut truncated = false;
+    loop {
+        match stmt.next() {
+            Ok(Some(row)) => {
+                if rows.len() == max_rows {
+                    truncated = true;
+                    break;
+                }
+                let object: Map<String, Value> = columns
+                    .iter()
+                    .zip(row.iter())
+                    .map(|(column, value)| {
+                        (column.clone(), owned_value_to_json(&OwnedValue::from_value_ref(value)))
+                    })
+                    .collect();
+                rows.push(Value::Object(object));
+            }
+            Ok(None) => break,
+            Err(e) => return Err(ToolError::Failed(e.to_string())),
+        }
+    }
+    Ok(json!({
+        "columns": columns,
+        "rows": rows,
+        "truncated": truncated,
+    }))
+}
+
+fn tool_definitions() -> Value {
+    let params_schema = json!({
+        "type": "object",
+        "description": "Values for named parameters, e.g. {\"id\": 1} binds $id",
+        "additionalProperties": true,
+    });
+    json!([
+        {
+            "name": "list_tables",
+            "description": "List the tables and views in the database.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "schema": {
+                        "type": "string",
+                        "description": "Attached schema to list (default: main)",
+                    },
+                },
+            },
+        },
+        {
+            "name": "describe_schema",
+            "description": "Describe every table, column, view, index and trigger as JSON.",
+            "inputSchema": {"type": "object", "properties": {}},
+        },
+        {
+            "name": "query",
+            "description": "Run a single read-only SQLite statement and return its rows.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "sql": {"type": "string", "description": "The SQL statement to run"},
+                    "params": params_schema,
+                },
+                "required": ["sql"],
+            },
+        },
+        {
+            "name": "list_procedures",
+            "description": "List the named procedures available to call_procedure.",
+            "inputSchema": {"type": "object", "properties": {}},
+        },
+        {
+            "name": "call_procedure",
+            "description": "Run a named read-only procedure and return its rows.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "name": {"type": "string", "description": "Procedure name"},
+                    "params": params_schema,
+                },
+                "required": ["name"],
+            },
+        },
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_server(dir: &tempfile::TempDir, procedures: Option<&str>) -> McpServer {
+        let db = dir.path().join("app.db");
+        {
+            let runtime = Runtime::new(Some(db.to_string_lossy().to_string())).unwrap();
+            runtime
+                .connection
+                .execute_script(
+                    "CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT);
+                     INSERT INTO users VALUES (1, 'alex'), (2, 'brian');
+                     CREATE VIEW user_names AS SELECT name FROM users;",
+                )
+                .unwrap();
+        }
+        let mut files = vec![];
+        if let Some(contents) = procedures {
+            let path = dir.path().join("queries.sql");
+            std::fs::write(&path, contents).unwrap();
+            files.push(path);
+        }
+        McpServer::open(&db, &files).unwrap()
+    }
+
+    fn request(server: &mut McpServer, method: &str, params: Value) -> Value {
+        let message = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+        let response = server.handle_message(&message.to_string()).unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    fn call(server: &mut McpServer, name: &str, arguments: Value) -> (Value, bool) {
+        let response = request(server, "tools/call", json!({"name": name, "arguments": arguments}));
+        let result = &response["result"];
+        let text = result["content"][0]["text"].as_str().unwrap();
+        let value = serde_json::from_str(text).unwrap_or(Value::String(text.to_string()));
+        (value, result["isError"].as_bool().unwrap())
+    }
+
+    #[test]
+    fn test_initialize_and_list_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = test_server(&dir, None);
+        let response = request(&mut server, "initialize", json!({}));
+        assert_eq!(response["result"]["protocolVersion"], PROTOCOL_VERSION);
+        assert_eq!(response["result"]["serverInfo"]["name"], "solite");
+
+        let response = request(&mut server, "tools/list", json!({}));
+        let names: Vec<_> = response["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            ["list_tables", "describe_schema", "query", "list_procedures", "call_procedure"]
+        );
+    }
+
+    #[test]
+    fn test_notifications_get_no_response() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = test_server(&dir, None);
+        let message = json!({"jsonrpc": "2.0", "method": "notifications/initialized"});
+        assert!(server.handle_message(&message.to_string()).is_none());
+    }
+
+    #[test]
+    fn test_unknown_method_and_bad_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = test_server(&dir, None);
+        let response = request(&mut server, "resources/list", json!({}));
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        let response: Value =
+            serde_json::from_str(&server.handle_message("{not json").unwrap()).unwrap();
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+    }
+
+    #[test]
+    fn test_list_tables_and_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = test_server(&dir, None);
+        let (tables, is_error) = call(&mut server, "list_tables", json!({}));
+        assert!(!is_error);
+        assert_eq!(tables, json!(["user_names", "users"]));
+
+        let (schema, is_error) = call(&mut server, "describe_schema", json!({}));
+        assert!(!is_error);
+        assert!(schema.to_string().contains("\"users\""), "{schema}");
+    }
+
+    #[test]
+    fn test_query_with_params() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = test_server(&dir, None);
+        let (result, is_error) = call(
+            &mut server,
+            "query",
+            json!({"sql": "SELECT name FROM users WHERE id = $id", "params": {"id": 2}}),
+        );
+        assert!(!is_error);
+        assert_eq!(result["rows"], json!([{"name": "brian"}]));
+        assert_eq!(result["truncated"], false);
+    }
+
+    #[test]
+    fn test_query_rejects_writes_and_multiple_statements() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = test_server(&dir, None);
+        let (message, is_error) =
+            call(&mut server, "query", json!({"sql": "DELETE FROM users"}));
+        assert!(is_error);
+        assert!(message.as_str().unwrap().contains("read-only"), "{message}");
+
+        let (_, is_error) = call(&mut server, "query", json!({"sql": "SELECT 1; SELECT 2"}));
+        assert!(is_error);
+
+        let (message, is_error) = call(&mut server, "query", json!({"sql": "SELECT * FROM nope"}));
+        assert!(is_error);
+        assert!(message.as_str().unwrap().contains("no such table"), "{message}");
+
+        // Data files aren't replacement-scanned, so agents can't read files
+        std::fs::write(dir.path().join("secrets.csv"), "a\n1\n").unwrap();
+        let sql = format!("SELECT * FROM \"{}\"", dir.path().join("secrets.csv").display());
+        let (message, is_error) = call(&mut server, "query", json!({"sql": sql}));
+        assert!(is_error);
+        assert!(message.as_str().unwrap().contains("no such table"), "{message}");
+    }
+
+    #[test]
+    fn test_query_denies_host_access() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = test_server(&dir, None);
+        let written = dir.path().join("written.txt");
+        for sql in [
+            format!("SELECT writefile('{}', 'pwned')", written.display()),
+            format!("SELECT readfile('{}')", dir.path().join("app.db").display()),
+            format!("ATTACH '{}' AS other", dir.path().join("other.db").display()),
+        ] {
+            let (message, is_error) = call(&mut server, "query", json!({"sql": sql}));
+            assert!(is_error, "{sql}");
+            assert!(message.as_str().unwrap().contains("not authorized"), "{message}");
+        }
+        assert!(!written.exists());
+        assert!(!dir.path().join("other.db").exists());
+    }
+
+    #[test]
+    fn test_query_truncates_at_max_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = test_server(&dir, None);
+        server.max_rows = 1;
+        let (result, _) = call(&mut server, "query", json!({"sql": "SELECT * FROM users"}));
+        assert_eq!(result["rows"].as_array().unwrap().len(), 1);
+        assert_eq!(result["truncated"], true);
+    }
+
+    #[test]
+    fn test_procedures() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = test_server(
+            &dir,
+            Some("-- name: userById :row\nSELECT name FROM users WHERE id = $id::int;\n"),
+        );
+        let (procedures, _) = call(&mut server, "list_procedures", json!({}));
+        assert_eq!(procedures[0]["name"], "userById");
+        assert_eq!(procedures[0]["parameters"][0]["name"], "id");
+        assert_eq!(procedures[0]["parameters"][0]["type"], "int");
+
+        let (result, is_error) = call(
+            &mut server,
+            "call_procedure",
+            json!({"name": "userById", "params": {"id": 1}}),
+        );
+        assert!(!is_error);
+        assert_eq!(result["rows"], json!([{"name": "alex"}]));
+
+        let (_, is_error) = call(&mut server, "call_procedure", json!({"name": "nope"}));
+        assert!(is_error);
+    }
+}
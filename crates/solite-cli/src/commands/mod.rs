@@ -60,6 +60,9 @@ pub mod schema;
 pub mod backup;
 pub mod vacuum;
 pub mod serve;
+pub mod remote;
+pub mod mcp;
+mod json_value;
 pub mod completions;
 #[cfg(feature = "ritestream")]
 pub mod stream;
@@ -172,6 +172,21 @@ fn query_impl(args: QueryArgs) -> Result<(), QueryError> {
             ));
         }
 
+        // Columnar formats are binary; don't dump them onto a terminal
+        if matches!(
+            format,
+            ExportFormat::Xlsx | ExportFormat::Parquet | ExportFormat::Arrow
+        )
+            && args.output.is_none()
+            && stdout().is_terminal()
+        {
+            return Err(QueryError::ExecutionFailed(
+                "refusing to write binary Excel/Parquet/Arrow output to a terminal; pass -o FILE \
+                 or redirect stdout"
+                    .to_string(),
+            ));
+        }
+
         // Set up output (created only once the format is known to use it)
         let output: Box<dyn Write> = match &args.output {
             Some(output) => solite_core::exporter::output_from_path(output)
@@ -340,12 +355,18 @@ fn prepare_statement(
 
 /// Determine the output format from arguments.
 fn determine_format(args: &QueryArgs) -> ExportFormat {
-    match &args.format {
+    let format = match &args.format {
         Some(format) => (*format).into(),
         None => match &args.output {
             Some(p) => solite_core::exporter::format_from_path(p).unwrap_or(ExportFormat::Json),
             None => ExportFormat::Json,
         },
+    };
+    match format {
+        ExportFormat::SqlInsert { .. } => ExportFormat::SqlInsert {
+            table: args.table.clone(),
+        },
+        format => format,
     }
 }
 
@@ -376,6 +397,7 @@ mod tests {
             load_extension: None,
             parameters: vec![],
             blob_limit: None,
+            table: None,
             remote: Default::default(),
         };
         let format = determine_format(&args);
@@ -392,12 +414,26 @@ mod tests {
             load_extension: None,
             parameters: vec![],
             blob_limit: None,
+            table: None,
             remote: Default::default(),
         };
         let format = determine_format(&args);
         assert!(matches!(format, ExportFormat::Csv));
     }
 
+    #[test]
+    fn test_determine_format_sql_table() {
+        let mut args = query_args("SELECT 1", None);
+        args.output = Some(PathBuf::from("slice.sql"));
+        args.table = Some("users".to_string());
+        assert_eq!(
+            determine_format(&args),
+            ExportFormat::SqlInsert {
+                table: Some("users".to_string())
+            }
+        );
+    }
+
     #[test]
     fn test_determine_format_default() {
         let args = QueryArgs {
@@ -408,6 +444,7 @@ mod tests {
             load_extension: None,
             parameters: vec![],
             blob_limit: None,
+            table: None,
             remote: Default::default(),
         };
         let format = determine_format(&args);
@@ -460,6 +497,7 @@ mod tests {
             load_extension: None,
             parameters: vec![],
             blob_limit: None,
+            table: None,
             remote: Default::default(),
         };
         let (db, sql) = parse_arguments(&args, false).unwrap();
@@ -477,6 +515,7 @@ mod tests {
             load_extension: None,
             parameters: vec![],
             blob_limit: None,
+            table: None,
             remote: Default::default(),
         };
         let (db, sql) = parse_arguments(&args, false).unwrap();
@@ -494,6 +533,7 @@ mod tests {
             load_extension: None,
             parameters: vec![],
             blob_limit: None,
+            table: None,
             remote: Default::default(),
         };
         let (db, sql) = parse_arguments(&args, false).unwrap();
@@ -510,6 +550,7 @@ mod tests {
             load_extension: None,
             parameters: vec![],
             blob_limit: None,
+            table: None,
             remote: Default::default(),
         }
     }
@@ -614,6 +655,7 @@ mod tests {
             load_extension: None,
             parameters: vec![],
             blob_limit: None,
+            table: None,
             remote: Default::default(),
         };
         let (db, sql) = parse_arguments(&args, true).unwrap();
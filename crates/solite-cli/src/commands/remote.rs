@@ -0,0 +1,24 @@
+//! `solite remote`: helpers for remote connections. `remote daemon` runs
+//! the transport pool of [`solite_core::daemon`].
+
+use std::time::Duration;
+
+use crate::cli::{RemoteCommand, RemoteDaemonArgs, RemoteNamespace};
+
+fn daemon(args: RemoteDaemonArgs) -> Result<(), ()> {
+    let socket = args.socket.unwrap_or_else(solite_core::daemon::socket_path);
+    eprintln!("Keeping remote sessions warm on {}", socket.display());
+    solite_core::daemon::run(&socket, Duration::from_secs(args.idle_timeout)).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::AddrInUse {
+            eprintln!("A remote daemon is already listening on {}", socket.display());
+        } else {
+            eprintln!("Failed to run the remote daemon on {}: {}", socket.display(), e);
+        }
+    })
+}
+
+pub fn remote(cmd: RemoteNamespace) -> Result<(), ()> {
+    match cmd.command {
+        RemoteCommand::Daemon(args) => daemon(args),
+    }
+}
@@ -12,7 +12,7 @@ use rustyline::hint::HistoryHinter;
 use rustyline::validate::{ValidationContext, ValidationResult, Validator};
 use rustyline::{Completer, CompletionType, Config, EditMode, Editor, Helper, Hinter, Result, Validator};
 
-use solite_core::dot::{DotCommand, LoadCommandSource};
+use solite_core::dot::{AskEvent, DotCommand, LoadCommandSource};
 use solite_core::{BlockSource, Runtime, StepError, StepResult};
 use solite_table::TableConfig;
 use std::borrow::Cow::{self, Borrowed, Owned};
@@ -21,7 +21,7 @@ use std::cell::RefCell;
 use std::io::Write;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 /// Simple matching bracket validator.
 #[derive(Default)]
@@ -215,17 +215,32 @@ fn handle_dot_command(runtime: &mut Runtime, cmd: DotCommand, timer: &mut bool)
             }
         },
         DotCommand::Ask(ask_command) => {
-            match ask_command.execute(runtime) {
-                Ok(rx) => {
-                    let stdout = std::io::stdout();
-                    let mut handle = stdout.lock();
-                    while let Ok(msg) = rx.recv() {
-                        if let Ok(text) = msg {
-                            let _ = write!(handle, "{}", text);
-                        }
+            let stdout = std::io::stdout();
+            let result = ask_command.execute(runtime, |event| {
+                let mut handle = stdout.lock();
+                match event {
+                    AskEvent::Chunk(text) => {
+                        let _ = write!(handle, "{}", text);
+                    }
+                    AskEvent::Repair { attempt, max, error } => {
+                        let _ = writeln!(
+                            handle,
+                            "\n✗ {}\n↻ asking for a fix ({}/{})",
+                            error, attempt, max
+                        );
+                    }
+                }
+                let _ = handle.flush();
+            });
+            println!();
+            match result {
+                Ok(outcome) => {
+                    if let Some(error) = outcome.error {
+                        eprintln!(
+                            "✗ query still fails to prepare after {} repair attempts: {}",
+                            outcome.repairs, error
+                        );
                     }
-                    let _ = handle.flush();
-                    println!();
                 }
                 Err(e) => eprintln!("✗ ask command failed: {}", e),
             }
@@ -495,15 +510,26 @@ Enter \".help\" for usage hints.
     }
 
     // Ctrl-C while a statement is running raises SIGINT (rustyline is not
-    // reading, so the terminal is in its normal mode). The handler just sets
-    // a flag; a SQLite progress handler polls it and aborts the running
-    // statement with SQLITE_INTERRUPT so the REPL survives.
+    // reading, so the terminal is in its normal mode). The handler sets a
+    // flag, which a SQLite progress handler polls to abort the running
+    // statement with SQLITE_INTERRUPT so the REPL survives. Remote
+    // connections have no progress handler, so it also interrupts through
+    // the connection's interrupt handle, which reaches the server.
     let interrupted = Arc::new(AtomicBool::new(false));
+    let interrupt_handle = Arc::new(Mutex::new(
+        runtime_ref.borrow().connection.interrupt_handle(),
+    ));
     {
         let flag = Arc::clone(&interrupted);
+        let interrupt_handle = Arc::clone(&interrupt_handle);
         // Failure to install the handler (e.g. another handler already
         // registered) only loses query cancellation, not the REPL itself.
-        let _ = ctrlc::set_handler(move || flag.store(true, Ordering::SeqCst));
+        let _ = ctrlc::set_handler(move || {
+            flag.store(true, Ordering::SeqCst);
+            if let Ok(handle) = interrupt_handle.lock() {
+                handle.interrupt();
+            }
+        });
     }
 
     // Most recently executed input; seeds the `\e` editor scratch buffer.
@@ -532,6 +558,7 @@ Enter \".help\" for usage hints.
                     let flag = Arc::clone(&interrupted);
                     rt.connection
                         .set_progress_handler(1000, move || flag.load(Ordering::SeqCst));
+                    *interrupt_handle.lock().unwrap() = rt.connection.interrupt_handle();
                     execute(&mut rt, &mut timer, line, &last_input)
                 };
                 // Record what actually ran (for `\e`, the editor buffer's
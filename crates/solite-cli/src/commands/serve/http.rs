@@ -0,0 +1,650 @@
+//! `solite serve --http`: a JSON API over HTTP/1.1.
+//!
+//! - `POST /query` runs a single statement and returns
+//!   `{"columns": [...], "rows": [[...], ...]}`. With
+//!   `Accept: application/x-ndjson` the rows stream instead, one JSON line
+//!   each after a `{"columns": [...]}` line.
+//! - `POST /execute` runs a single statement and returns `{"changes": N}`.
+//! - `GET /schema` returns the `solite_schema` JSON schema document.
+//!
+//! Both POST endpoints take `{"sql": "...", "params": {"id": 1}}` with
+//! `Content-Type: application/json`; other bodies get a 415, so a web page
+//! can't reach the server with a "simple" cross-origin POST. Columns
+//! are [`ColumnMeta`] as the RPC sends them; row values come from the same
+//! [`WireValue`]s, so values SQLite tagged as JSON are embedded as JSON.
+//!
+//! The database is opened read-only unless `--allow-writes`, in which case
+//! `/execute` is enabled and `/query` may run writes too. Read-only
+//! connections also deny ATTACH and the stdlib's file and network
+//! functions. Each request gets
+//! its own thread and connection, and the connection closes after the
+//! response.
+
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use solite_core::rpc::{self, WireValue, FETCH_ROWS};
+use solite_core::sqlite::{ColumnMeta, Connection, OwnedValue, SQLiteError, Statement, JSON_SUBTYPE};
+
+use crate::commands::json_value::{json_to_owned_value, owned_value_to_json};
+
+/// Largest request body accepted.
+const MAX_BODY: usize = 16 * 1024 * 1024;
+
+/// Longest request or header line accepted, and most headers.
+const MAX_LINE: usize = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+
+/// How long a client may take to send each read of its request.
+const READ_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+pub(super) fn serve(
+    database: &str,
+    address: &str,
+    readonly: bool,
+    token: Option<String>,
+) -> Result<(), ()> {
+    // Fail now on a bad database rather than on the first request
+    super::open(database, readonly).map_err(|e| {
+        eprintln!("Failed to open database: {}", e);
+    })?;
+    let listener = TcpListener::bind(address).map_err(|e| {
+        eprintln!("Failed to listen on {}: {}", address, e);
+    })?;
+    eprintln!(
+        "Serving {} on http://{}{}",
+        database,
+        address,
+        if readonly { " (read-only)" } else { "" }
+    );
+
+    let server = Arc::new(HttpServer {
+        database: database.to_string(),
+        readonly,
+        token,
+    });
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let server = Arc::clone(&server);
+        std::thread::spawn(move || {
+            // The client hung up; nothing left to tell it
+            let _ = server.handle_connection(stream);
+        });
+    }
+    Ok(())
+}
+
+struct HttpServer {
+    database: String,
+    readonly: bool,
+    /// Bearer token requests must carry, if any.
+    token: Option<String>,
+}
+
+/// A parsed HTTP request. Header names are lowercased.
+#[derive(Debug)]
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether the body is declared as JSON (parameters like `charset`
+    /// are ignored).
+    fn is_json(&self) -> bool {
+        self.header("content-type").is_some_and(|value| {
+            let media_type = value.split(';').next().unwrap_or("").trim();
+            media_type.eq_ignore_ascii_case("application/json")
+        })
+    }
+
+    fn accepts_ndjson(&self) -> bool {
+        self.header("accept")
+            .is_some_and(|accept| accept.contains("application/x-ndjson"))
+    }
+}
+
+/// A failure reported to the client as `{"error": message}`.
+#[derive(Debug)]
+struct HttpError {
+    status: u16,
+    message: String,
+}
+
+impl HttpError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+fn sql_error(e: SQLiteError) -> HttpError {
+    HttpError::new(400, e.to_string())
+}
+
+impl HttpServer {
+    fn handle_connection(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut out = BufWriter::new(stream);
+        match read_request(&mut reader) {
+            Ok(request) => self.handle(&request, &mut out)?,
+            Err(e) => write_error(&mut out, e)?,
+        }
+        out.flush()
+    }
+
+    fn handle<W: Write>(&self, request: &Request, out: &mut W) -> io::Result<()> {
+        match self.respond(request, out) {
+            Ok(written) => written,
+            Err(e) => write_error(out, e),
+        }
+    }
+
+    /// Route `request` and write the response. `Err` means nothing has
+    /// been written yet, so the caller can still send an error status.
+    fn respond<W: Write>(&self, request: &Request, out: &mut W) -> Result<io::Result<()>, HttpError> {
+        self.authorize(request)?;
+        if request.method == "POST" && !request.is_json() {
+            return Err(HttpError::new(415, "the request body must be application/json"));
+        }
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/schema") => Ok(write_json(out, 200, &self.schema()?)),
+            ("POST", "/query") => self.query(request, out),
+            ("POST", "/execute") => Ok(write_json(out, 200, &self.execute(request)?)),
+            (method, path @ ("/schema" | "/query" | "/execute")) => Err(HttpError::new(
+                405,
+                format!("{} is not allowed on {}", method, path),
+            )),
+            (_, path) => Err(HttpError::new(404, format!("no such endpoint: {}", path))),
+        }
+    }
+
+    /// Check the bearer token. `serve` refuses to start without one unless
+    /// the server is read-only on a loopback address.
+    fn authorize(&self, request: &Request) -> Result<(), HttpError> {
+        let Some(token) = &self.token else {
+            return Ok(());
+        };
+        let given = request
+            .header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or("");
+        if rpc::token_matches(token, given) {
+            Ok(())
+        } else {
+            Err(HttpError::new(401, "missing or invalid bearer token"))
+        }
+    }
+
+    fn connect(&self) -> Result<Connection, HttpError> {
+        super::open(&self.database, self.readonly)
+            .map_err(|e| HttpError::new(500, format!("failed to open database: {}", e)))
+    }
+
+    fn schema(&self) -> Result<Value, HttpError> {
+        use solite_schema::json::JsonSchema;
+
+        let connection = self.connect()?;
+        // SAFETY: the handle stays open until `connection` drops below
+        let introspected = unsafe { solite_schema::introspect_handle(connection.db()) }
+            .map_err(|e| HttpError::new(500, e.to_string()))?;
+        serde_json::to_value(JsonSchema::from(&introspected))
+            .map_err(|e| HttpError::new(500, e.to_string()))
+    }
+
+    fn query<W: Write>(&self, request: &Request, out: &mut W) -> Result<io::Result<()>, HttpError> {
+        let (sql, params) = parse_body(request)?;
+        let connection = self.connect()?;
+        // Declared after the connection, so it's finalized first
+        let mut stmt = prepare_single(&connection, &sql, &params)?;
+        if self.readonly && !stmt.readonly() {
+            return Err(HttpError::new(
+                403,
+                "the server is read-only; start it with --allow-writes",
+            ));
+        }
+        let columns = stmt.column_meta();
+        if request.accepts_ndjson() {
+            return Ok(stream_rows(out, &mut stmt, &columns));
+        }
+
+        let mut rows = Vec::new();
+        loop {
+            let (page, done) = rpc::read_rows(&mut stmt, FETCH_ROWS).map_err(sql_error)?;
+            rows.extend(page.iter().map(|row| row_to_json(row)));
+            if done {
+                break;
+            }
+        }
+        Ok(write_json(out, 200, &json!({"columns": columns, "rows": rows})))
+    }
+
+    fn execute(&self, request: &Request) -> Result<Value, HttpError> {
+        if self.readonly {
+            return Err(HttpError::new(
+                403,
+                "POST /execute needs a server started with --allow-writes",
+            ));
+        }
+        let (sql, params) = parse_body(request)?;
+        let connection = self.connect()?;
+        let stmt = prepare_single(&connection, &sql, &params)?;
+        stmt.execute().map_err(sql_error)?;
+        let changes = unsafe { libsqlite3_sys::sqlite3_changes64(connection.db()) };
+        Ok(json!({ "changes": changes }))
+    }
+}
+
+/// Read one line of at most [`MAX_LINE`] bytes into `line`, returning its
+/// length (0 at EOF).
+fn read_limited_line<R: BufRead>(reader: &mut R, line: &mut String) -> Result<usize, HttpError> {
+    let n = Read::take(&mut *reader, MAX_LINE as u64)
+        .read_line(line)
+        .map_err(|e| HttpError::new(400, e.to_string()))?;
+    if n == MAX_LINE && !line.ends_with('\n') {
+        return Err(HttpError::new(431, "request line or header too long"));
+    }
+    Ok(n)
+}
+
+/// Read a request line, headers and a `Content-Length` body.
+fn read_request<R: BufRead>(reader: &mut R) -> Result<Request, HttpError> {
+    let mut line = String::new();
+    read_limited_line(reader, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(HttpError::new(400, "malformed request line"));
+    };
+    let method = method.to_string();
+    let path = target.split('?').next().unwrap_or(target).to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        let n = read_limited_line(reader, &mut line)?;
+        let header = line.trim_end_matches(['\r', '\n']);
+        if n == 0 || header.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err(HttpError::new(431, "too many headers"));
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    let mut request = Request {
+        method,
+        path,
+        headers,
+        body: Vec::new(),
+    };
+    let length = match request.header("content-length") {
+        Some(length) => length
+            .parse::<usize>()
+            .map_err(|_| HttpError::new(400, "invalid Content-Length"))?,
+        None => 0,
+    };
+    if length > MAX_BODY {
+        return Err(HttpError::new(413, "request body too large"));
+    }
+    request.body = vec![0; length];
+    reader
+        .read_exact(&mut request.body)
+        .map_err(|e| HttpError::new(400, e.to_string()))?;
+    Ok(request)
+}
+
+/// The `{"sql": ..., "params": {...}}` body of `/query` and `/execute`.
+fn parse_body(request: &Request) -> Result<(String, Vec<(String, OwnedValue)>), HttpError> {
+    let body: Value = serde_json::from_slice(&request.body)
+        .map_err(|e| HttpError::new(400, format!("invalid JSON body: {}", e)))?;
+    let sql = body
+        .get("sql")
+        .and_then(Value::as_str)
+        .ok_or_else(|| HttpError::new(400, "missing `sql`"))?
+        .to_string();
+    let params = match body.get("params") {
+        None | Some(Value::Null) => Vec::new(),
+        Some(Value::Object(params)) => params
+            .iter()
+            .map(|(name, value)| {
+                json_to_owned_value(value)
+                    .map(|value| (name.clone(), value))
+                    .ok_or_else(|| {
+                        HttpError::new(
+                            400,
+                            format!("parameter `{}` must be a string, number, boolean or null", name),
+                        )
+                    })
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err(HttpError::new(400, "`params` must be an object")),
+    };
+    Ok((sql, params))
+}
+
+/// Prepare exactly one statement from `sql` and bind `params` by name.
+fn prepare_single(
+    connection: &Connection,
+    sql: &str,
+    params: &[(String, OwnedValue)],
+) -> Result<Statement, HttpError> {
+    let stmt = match connection.prepare(sql).map_err(sql_error)? {
+        (rest, Some(stmt)) => {
+            if let Some(offset) = rest {
+                if !matches!(connection.prepare(&sql[offset..]), Ok((_, None))) {
+                    return Err(HttpError::new(400, "only a single SQL statement is allowed"));
+                }
+            }
+            stmt
+        }
+        (_, None) => return Err(HttpError::new(400, "no SQL statement provided")),
+    };
+    rpc::bind_params(&stmt, params).map_err(sql_error)?;
+    Ok(stmt)
+}
+
+/// A row as a JSON array. Values SQLite tagged as JSON are embedded as JSON
+/// rather than as strings.
+fn row_to_json(row: &[WireValue]) -> Value {
+    Value::Array(
+        row.iter()
+            .map(|v| match (&v.value, v.subtype) {
+                (OwnedValue::Text(text), Some(JSON_SUBTYPE)) => serde_json::from_slice(text)
+                    .unwrap_or_else(|_| owned_value_to_json(&v.value)),
+                (value, _) => owned_value_to_json(value),
+            })
+            .collect(),
+    )
+}
+
+/// Write a `{"columns": [...]}` line, then each row as a JSON array on its
+/// own line. An error partway through becomes a final `{"error": ...}` line,
+/// since the status has already been sent.
+fn stream_rows<W: Write>(out: &mut W, stmt: &mut Statement, columns: &[ColumnMeta]) -> io::Result<()> {
+    write_head(out, 200, "application/x-ndjson", None)?;
+    writeln!(out, "{}", json!({ "columns": columns }))?;
+    loop {
+        match rpc::read_rows(stmt, FETCH_ROWS) {
+            Ok((rows, done)) => {
+                for row in &rows {
+                    writeln!(out, "{}", row_to_json(row))?;
+                }
+                out.flush()?;
+                if done {
+                    return Ok(());
+                }
+            }
+            Err(e) => {
+                writeln!(out, "{}", json!({ "error": e.to_string() }))?;
+                return out.flush();
+            }
+        }
+    }
+}
+
+fn status_text(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+/// Status line and headers. Without a `length`, the body runs until the
+/// connection closes.
+fn write_head<W: Write>(out: &mut W, status: u16, content_type: &str, length: Option<usize>) -> io::Result<()> {
+    write!(
+        out,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nConnection: close\r\n",
+        status,
+        status_text(status),
+        content_type
+    )?;
+    if let Some(length) = length {
+        write!(out, "Content-Length: {}\r\n", length)?;
+    }
+    write!(out, "\r\n")
+}
+
+fn write_json<W: Write>(out: &mut W, status: u16, value: &Value) -> io::Result<()> {
+    let body = value.to_string();
+    write_head(out, status, "application/json", Some(body.len()))?;
+    out.write_all(body.as_bytes())
+}
+
+fn write_error<W: Write>(out: &mut W, error: HttpError) -> io::Result<()> {
+    write_json(out, error.status, &json!({ "error": error.message }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_server(dir: &tempfile::TempDir, readonly: bool, token: Option<&str>) -> HttpServer {
+        let db = dir.path().join("app.db");
+        Connection::open(&db.to_string_lossy())
+            .unwrap()
+            .execute_script(
+                "CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+                 INSERT INTO users VALUES (1, 'alex'), (2, 'brian');",
+            )
+            .unwrap();
+        HttpServer {
+            database: db.to_string_lossy().to_string(),
+            readonly,
+            token: token.map(str::to_string),
+        }
+    }
+
+    /// Send a request, returning the status and the raw body.
+    fn call(
+        server: &HttpServer,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: Value,
+    ) -> (u16, String) {
+        let json = [("content-type", "application/json")];
+        let request = Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers: headers
+                .iter()
+                .chain(&json)
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: if body.is_null() {
+                vec![]
+            } else {
+                body.to_string().into_bytes()
+            },
+        };
+        let mut out = Vec::new();
+        server.handle(&request, &mut out).unwrap();
+        let response = String::from_utf8(out).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, body.to_string())
+    }
+
+    fn call_json(server: &HttpServer, method: &str, path: &str, body: Value) -> (u16, Value) {
+        let (status, body) = call(server, method, path, &[], body);
+        (status, serde_json::from_str(&body).unwrap())
+    }
+
+    #[test]
+    fn test_query() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = test_server(&dir, true, None);
+        let (status, body) = call_json(
+            &server,
+            "POST",
+            "/query",
+            json!({"sql": "select id, name, json_object('n', id) as doc from users where id >= :min", "params": {"min": 2}}),
+        );
+        assert_eq!(status, 200);
+        assert_eq!(body["columns"][1]["name"], "name");
+        assert_eq!(body["columns"][1]["origin_table"], "users");
+        assert_eq!(body["columns"][1]["nullable"], false);
+        assert_eq!(body["rows"], json!([[2, "brian", {"n": 2}]]));
+
+        let (status, body) = call_json(&server, "POST", "/query", json!({"sql": "select 1; select 2"}));
+        assert_eq!(status, 400);
+        assert_eq!(body["error"], "only a single SQL statement is allowed");
+    }
+
+    #[test]
+    fn test_query_ndjson() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = test_server(&dir, true, None);
+        let (status, body) = call(
+            &server,
+            "POST",
+            "/query",
+            &[("accept", "application/x-ndjson")],
+            json!({"sql": "select id, name from users order by id"}),
+        );
+        assert_eq!(status, 200);
+        let lines: Vec<Value> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["columns"][0]["name"], "id");
+        assert_eq!(lines[1], json!([1, "alex"]));
+        assert_eq!(lines[2], json!([2, "brian"]));
+    }
+
+    #[test]
+    fn test_readonly_by_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = test_server(&dir, true, None);
+        let insert = json!({"sql": "insert into users(name) values (:name)", "params": {"name": "cara"}});
+        assert_eq!(call_json(&server, "POST", "/query", insert.clone()).0, 403);
+        assert_eq!(call_json(&server, "POST", "/execute", insert.clone()).0, 403);
+
+        let server = HttpServer {
+            readonly: false,
+            ..server
+        };
+        let (status, body) = call_json(&server, "POST", "/execute", insert);
+        assert_eq!(status, 200);
+        assert_eq!(body, json!({"changes": 1}));
+        let (_, body) = call_json(&server, "POST", "/query", json!({"sql": "select count(*) from users"}));
+        assert_eq!(body["rows"], json!([[3]]));
+    }
+
+    #[test]
+    fn test_schema_and_auth() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = test_server(&dir, true, Some("s3cret"));
+        let (status, _) = call_json(&server, "GET", "/schema", Value::Null);
+        assert_eq!(status, 401);
+
+        let (status, body) = call(
+            &server,
+            "GET",
+            "/schema",
+            &[("authorization", "Bearer s3cret")],
+            Value::Null,
+        );
+        assert_eq!(status, 200);
+        let schema: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(schema["tables"][0]["name"], "users");
+
+        let auth = [("authorization", "Bearer s3cret")];
+        assert_eq!(call(&server, "GET", "/query", &auth, Value::Null).0, 405);
+        assert_eq!(call(&server, "GET", "/nope", &auth, Value::Null).0, 404);
+    }
+
+    #[test]
+    fn test_read_request() {
+        let raw = "POST /query?pretty HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: 16\r\n\r\n{\"sql\":\"select\"}";
+        let request = read_request(&mut raw.as_bytes()).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/query");
+        assert_eq!(request.header("content-type"), Some("application/json"));
+        assert_eq!(request.body, b"{\"sql\":\"select\"}");
+
+        let raw = "POST /query HTTP/1.1\r\nContent-Length: 999999999999\r\n\r\n";
+        assert_eq!(read_request(&mut raw.as_bytes()).unwrap_err().status, 413);
+
+        let raw = format!("POST /query HTTP/1.1\r\nX-Long: {}\r\n\r\n", "a".repeat(MAX_LINE));
+        assert_eq!(read_request(&mut raw.as_bytes()).unwrap_err().status, 431);
+        let raw = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE));
+        assert_eq!(read_request(&mut raw.as_bytes()).unwrap_err().status, 431);
+        let raw = format!("GET / HTTP/1.1\r\n{}\r\n", "X: y\r\n".repeat(MAX_HEADERS + 1));
+        assert_eq!(read_request(&mut raw.as_bytes()).unwrap_err().status, 431);
+    }
+
+    #[test]
+    fn test_post_requires_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = test_server(&dir, true, None);
+        let body = br#"{"sql": "select 1"}"#.to_vec();
+        for content_type in [None, Some("text/plain"), Some("application/x-www-form-urlencoded")] {
+            let request = Request {
+                method: "POST".to_string(),
+                path: "/query".to_string(),
+                headers: content_type
+                    .map(|value| ("content-type".to_string(), value.to_string()))
+                    .into_iter()
+                    .collect(),
+                body: body.clone(),
+            };
+            let mut out = Vec::new();
+            server.handle(&request, &mut out).unwrap();
+            assert!(String::from_utf8(out).unwrap().starts_with("HTTP/1.1 415"));
+        }
+        let (status, _) = call(
+            &server,
+            "POST",
+            "/query",
+            &[("content-type", "Application/JSON; charset=utf-8")],
+            json!({"sql": "select 1"}),
+        );
+        assert_eq!(status, 200);
+    }
+
+    #[test]
+    fn test_readonly_denies_host_access() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = test_server(&dir, true, None);
+        let written = dir.path().join("written.txt");
+        for sql in [
+            format!("select writefile('{}', 'x')", written.display()),
+            format!("select readfile('{}')", dir.path().join("app.db").display()),
+            "select http_get_body('http://127.0.0.1:9')".to_string(),
+            format!("attach '{}' as other", dir.path().join("other.db").display()),
+        ] {
+            let (status, body) = call_json(&server, "POST", "/query", json!({ "sql": sql }));
+            assert_eq!(status, 400, "{sql}");
+            assert!(body["error"].as_str().unwrap().contains("not authorized"), "{body}");
+        }
+        assert!(!written.exists());
+    }
+}
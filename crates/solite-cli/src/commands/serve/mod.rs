@@ -0,0 +1,121 @@
+//! `solite serve`: the server side of remote connections. Speaks the
+//! MessagePack RPC of [`solite_core::rpc`] on stdin/stdout or, with
+//! `--listen`, on a socket; `--http` serves a JSON API instead (see [`http`]).
+
+mod http;
+
+use solite_core::rpc::SocketAddress;
+use solite_core::sqlite::{Connection, SQLiteError};
+use solite_stdlib::solite_stdlib_init;
+use std::io::{self, BufReader, BufWriter};
+use std::net::ToSocketAddrs;
+
+use crate::cli::ServeArgs;
+
+/// Open `database` with the stdlib loaded. Read-only connections also
+/// deny ATTACH and the stdlib's file, network and clipboard functions
+/// (see [`Connection::deny_host_access`]), which `stmt.readonly()` allows.
+fn open(database: &str, readonly: bool) -> Result<Connection, SQLiteError> {
+    let connection = if readonly {
+        Connection::open_readonly(database)?
+    } else {
+        Connection::open(database)?
+    };
+    unsafe {
+        solite_stdlib_init(connection.db(), std::ptr::null_mut(), std::ptr::null_mut());
+    }
+    if readonly {
+        connection.deny_host_access()?;
+    }
+    Ok(connection)
+}
+
+/// The token clients must present: `--token-file`, else
+/// `SOLITE_SERVE_TOKEN`. `None` when neither is set.
+fn token(args: &ServeArgs) -> Result<Option<String>, String> {
+    let token = match &args.token_file {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+            .trim()
+            .to_string(),
+        None => std::env::var("SOLITE_SERVE_TOKEN").unwrap_or_default(),
+    };
+    Ok(Some(token).filter(|token| !token.is_empty()))
+}
+
+/// Whether `address` only resolves to loopback addresses, so nothing off
+/// this machine can connect.
+fn is_loopback(address: &str) -> bool {
+    match address.to_socket_addrs() {
+        Ok(addrs) => {
+            let addrs: Vec<_> = addrs.collect();
+            !addrs.is_empty() && addrs.iter().all(|addr| addr.ip().is_loopback())
+        }
+        Err(_) => false,
+    }
+}
+
+pub fn serve(args: ServeArgs) -> Result<(), ()> {
+    if let Some(address) = &args.http {
+        let token = token(&args).map_err(|e| eprintln!("{}", e))?;
+        if token.is_none() && (args.allow_writes || !is_loopback(address)) {
+            eprintln!(
+                "--http requires a token unless it's read-only on a loopback address: \
+                 pass --token-file or set SOLITE_SERVE_TOKEN"
+            );
+            return Err(());
+        }
+        return http::serve(&args.database, address, !args.allow_writes, token);
+    }
+
+    if let Some(listen) = &args.listen {
+        let address = SocketAddress::from_listen(listen).map_err(|e| eprintln!("{}", e))?;
+        let token = token(&args)
+            .map_err(|e| eprintln!("{}", e))?
+            .ok_or_else(|| {
+                eprintln!("--listen requires a token: pass --token-file or set SOLITE_SERVE_TOKEN")
+            })?;
+        // Fail now on a bad database rather than on the first client
+        open(&args.database, false).map_err(|e| {
+            eprintln!("Failed to open database: {}", e);
+        })?;
+        eprintln!("Serving {} on {}", args.database, address);
+        let database = args.database.clone();
+        let max_request = args.max_request_mb.saturating_mul(1024 * 1024);
+        return solite_core::rpc::listen(&address, token, max_request, move || {
+            open(&database, false)
+        })
+        .map_err(|e| {
+            eprintln!("Failed to listen on {}: {}", address, e);
+        });
+    }
+
+    let connection = open(&args.database, false).map_err(|e| {
+        eprintln!("Failed to open database: {}", e);
+    })?;
+
+    // Requests are read on a separate thread (see `rpc::serve`), so stdin
+    // isn't locked up front
+    let reader = BufReader::new(io::stdin());
+    let stdout = io::stdout();
+    let writer = BufWriter::new(stdout.lock());
+
+    solite_core::rpc::serve(connection, reader, writer).map_err(|e| {
+        eprintln!("Failed to serve requests: {}", e);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_loopback() {
+        assert!(is_loopback("127.0.0.1:8080"));
+        assert!(is_loopback("[::1]:8080"));
+        assert!(is_loopback("localhost:8080"));
+        assert!(!is_loopback("0.0.0.0:8080"));
+        assert!(!is_loopback("[::]:8080"));
+        assert!(!is_loopback("not an address"));
+    }
+}
@@ -0,0 +1,500 @@
+//! Reading and writing LTX files, the unit of a `solite stream` replica.
+//!
+//! An LTX file is a 100-byte header, the pages its transactions wrote (each
+//! a 4-byte page number, then the page), an empty page header, and a
+//! 16-byte trailer: the database checksum after the transactions, then a
+//! CRC-64 of everything before it. Integers are big-endian.
+//!
+//! With the LZ4 header flag, as ritestream writes them, everything between
+//! the header and the trailer is one LZ4 frame, and the CRC covers the
+//! bytes before compression. Files with any other flag are rejected rather
+//! than misread. Merged files are written uncompressed.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+pub(super) const HEADER_SIZE: usize = 100;
+const MAGIC: &[u8; 4] = b"LTX1";
+/// Set on every checksum, so that zero means "no checksum".
+const CHECKSUM_FLAG: u64 = 1 << 63;
+/// Header flag: the page frames are LZ4-compressed.
+const FLAG_COMPRESS_LZ4: u32 = 1 << 0;
+static CRC64: crc::Crc<u64> = crc::Crc::<u64>::new(&crc::CRC_64_GO_ISO);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Header {
+    pub flags: u32,
+    pub page_size: u32,
+    /// Size of the database in pages after the file's transactions.
+    pub commit: u32,
+    pub min_txid: u64,
+    pub max_txid: u64,
+    /// When the last transaction committed, in milliseconds since the epoch.
+    pub timestamp: i64,
+    /// Database checksum before the first transaction; 0 for snapshots.
+    pub pre_apply_checksum: u64,
+}
+
+impl Header {
+    /// Decode the header at the start of `bytes`.
+    pub(super) fn decode(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+            return Err(invalid("not an LTX file"));
+        }
+        let u32_at = |at: usize| u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap());
+        let u64_at = |at: usize| u64::from_be_bytes(bytes[at..at + 8].try_into().unwrap());
+        Ok(Header {
+            flags: u32_at(4),
+            page_size: u32_at(8),
+            commit: u32_at(12),
+            min_txid: u64_at(16),
+            max_txid: u64_at(24),
+            timestamp: u64_at(32) as i64,
+            pre_apply_checksum: u64_at(40),
+        })
+    }
+
+    /// The WAL position and node ID fields that follow are left zeroed.
+    fn encode(&self) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0; HEADER_SIZE];
+        bytes[..4].copy_from_slice(MAGIC);
+        bytes[4..8].copy_from_slice(&self.flags.to_be_bytes());
+        bytes[8..12].copy_from_slice(&self.page_size.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.commit.to_be_bytes());
+        bytes[16..24].copy_from_slice(&self.min_txid.to_be_bytes());
+        bytes[24..32].copy_from_slice(&self.max_txid.to_be_bytes());
+        bytes[32..40].copy_from_slice(&self.timestamp.to_be_bytes());
+        bytes[40..48].copy_from_slice(&self.pre_apply_checksum.to_be_bytes());
+        bytes
+    }
+
+    /// Whether the file holds the whole database rather than changes to it.
+    pub(super) fn is_snapshot(&self) -> bool {
+        self.min_txid == 1
+    }
+}
+
+/// Passes bytes through to `inner`, folding them into a CRC-64.
+struct Hashing<T> {
+    inner: T,
+    digest: crc::Digest<'static, u64>,
+}
+
+impl<T> Hashing<T> {
+    fn new(inner: T) -> Self {
+        Hashing {
+            inner,
+            digest: CRC64.digest(),
+        }
+    }
+}
+
+impl<T: Read> Read for Hashing<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.digest.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<T: Write> Write for Hashing<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.digest.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// An LTX file on disk, indexed but not loaded: where each page is, so
+/// merging reads one page at a time.
+pub(super) struct LtxFile {
+    /// The file holding the pages: the LTX file itself, or for a
+    /// compressed one, its pages decompressed into a file beside it.
+    pages_path: PathBuf,
+    pub header: Header,
+    /// Page numbers with the offset of each page's data in `pages_path`.
+    pages: Vec<(u32, u64)>,
+    pub post_apply_checksum: u64,
+}
+
+impl LtxFile {
+    /// Index the file at `path`, verifying its checksum. A compressed
+    /// file's pages are decompressed to `<path>.pages`.
+    pub(super) fn open(path: &Path) -> io::Result<Self> {
+        let truncated = |e: io::Error| match e.kind() {
+            io::ErrorKind::UnexpectedEof => invalid("truncated LTX file"),
+            _ => e,
+        };
+        let mut reader = Hashing::new(BufReader::new(File::open(path)?));
+        let mut header = [0; HEADER_SIZE];
+        reader.read_exact(&mut header).map_err(truncated)?;
+        let header = Header::decode(&header)?;
+        if header.flags & !FLAG_COMPRESS_LZ4 != 0 {
+            return Err(invalid(format!("unsupported LTX flags {:#x}", header.flags)));
+        }
+
+        let page_size = header.page_size as usize;
+        let (mut reader, pages, pages_path) = if header.flags & FLAG_COMPRESS_LZ4 == 0 {
+            let pages = index_pages(&mut reader, page_size, None).map_err(truncated)?;
+            (reader, pages, path.to_path_buf())
+        } else {
+            let mut pages_path = path.as_os_str().to_owned();
+            pages_path.push(".pages");
+            let pages_path = PathBuf::from(pages_path);
+            let mut copy = BufWriter::new(File::create(&pages_path)?);
+            let Hashing { inner, digest } = reader;
+            let mut body = Hashing {
+                inner: lz4_flex::frame::FrameDecoder::new(inner),
+                digest,
+            };
+            let pages = index_pages(&mut body, page_size, Some(&mut copy)).map_err(truncated)?;
+            copy.flush()?;
+            // Reading past the last page consumes the frame's end mark,
+            // leaving the file at the trailer
+            if body.read(&mut [0]).map_err(truncated)? != 0 {
+                return Err(invalid("unexpected data after the LTX pages"));
+            }
+            let Hashing { inner, digest } = body;
+            let reader = Hashing {
+                inner: inner.into_inner(),
+                digest,
+            };
+            (reader, pages, pages_path)
+        };
+
+        let mut post_apply_checksum = [0; 8];
+        reader.read_exact(&mut post_apply_checksum).map_err(truncated)?;
+        let Hashing { mut inner, digest } = reader;
+        let expected = CHECKSUM_FLAG | digest.finalize();
+        let mut file_checksum = [0; 8];
+        inner.read_exact(&mut file_checksum).map_err(truncated)?;
+        if u64::from_be_bytes(file_checksum) != expected {
+            return Err(invalid("LTX file checksum mismatch"));
+        }
+        if inner.read(&mut [0])? != 0 {
+            return Err(invalid("unexpected data after the LTX trailer"));
+        }
+        Ok(LtxFile {
+            pages_path,
+            header,
+            pages,
+            post_apply_checksum: u64::from_be_bytes(post_apply_checksum),
+        })
+    }
+}
+
+/// Read page frames up to the empty page header, returning each page number
+/// with the offset of its data: in the LTX file, or in `copy` when the
+/// pages are copied out.
+fn index_pages<R: Read>(
+    reader: &mut R,
+    page_size: usize,
+    mut copy: Option<&mut dyn Write>,
+) -> io::Result<Vec<(u32, u64)>> {
+    let mut pages = Vec::new();
+    let mut offset = if copy.is_some() { 0 } else { HEADER_SIZE as u64 };
+    let mut page = vec![0; page_size];
+    loop {
+        let mut pgno = [0; 4];
+        reader.read_exact(&mut pgno)?;
+        let pgno = u32::from_be_bytes(pgno);
+        if pgno == 0 {
+            return Ok(pages);
+        }
+        reader.read_exact(&mut page)?;
+        match copy.as_mut() {
+            Some(copy) => copy.write_all(&page)?,
+            None => offset += 4,
+        }
+        pages.push((pgno, offset));
+        offset += page.len() as u64;
+    }
+}
+
+/// Writes an LTX file: the header, then pages in ascending order, then the
+/// trailer on [`Encoder::finish`].
+pub(super) struct Encoder {
+    writer: Hashing<BufWriter<File>>,
+    page_size: usize,
+}
+
+impl Encoder {
+    pub(super) fn create(path: &Path, header: &Header) -> io::Result<Self> {
+        let mut writer = Hashing::new(BufWriter::new(File::create(path)?));
+        writer.write_all(&header.encode())?;
+        Ok(Encoder {
+            writer,
+            page_size: header.page_size as usize,
+        })
+    }
+
+    pub(super) fn write_page(&mut self, pgno: u32, data: &[u8]) -> io::Result<()> {
+        if pgno == 0 || data.len() != self.page_size {
+            return Err(invalid(format!("invalid LTX page {pgno}")));
+        }
+        self.writer.write_all(&pgno.to_be_bytes())?;
+        self.writer.write_all(data)
+    }
+
+    pub(super) fn finish(mut self, post_apply_checksum: u64) -> io::Result<()> {
+        self.writer.write_all(&[0; 4])?;
+        self.writer.write_all(&post_apply_checksum.to_be_bytes())?;
+        let Hashing { mut inner, digest } = self.writer;
+        inner.write_all(&(CHECKSUM_FLAG | digest.finalize()).to_be_bytes())?;
+        inner.flush()
+    }
+}
+
+/// The page holding SQLite's lock bytes at offset 1 GiB, which is never
+/// written.
+fn lock_page(page_size: u32) -> u32 {
+    0x4000_0000 / page_size + 1
+}
+
+/// Merge `files`, consecutive transaction ranges in order, into one LTX
+/// file at `out` holding the latest version of each page. Merging from a
+/// snapshot gives a snapshot, which must then have every page.
+pub(super) fn merge(files: &[LtxFile], out: &Path) -> io::Result<Header> {
+    let (Some(first), Some(last)) = (files.first(), files.last()) else {
+        return Err(invalid("no LTX files to merge"));
+    };
+    for pair in files.windows(2) {
+        let (before, after) = (&pair[0].header, &pair[1].header);
+        if after.page_size != first.header.page_size {
+            return Err(invalid("LTX files have different page sizes"));
+        }
+        if after.min_txid != before.max_txid + 1 {
+            return Err(invalid(format!(
+                "LTX files skip from txid {} to {}",
+                before.max_txid, after.min_txid
+            )));
+        }
+    }
+
+    // Page number -> (index into `files`, offset of its latest version)
+    let mut latest = BTreeMap::new();
+    for (index, file) in files.iter().enumerate() {
+        for &(pgno, offset) in &file.pages {
+            latest.insert(pgno, (index, offset));
+        }
+        // Pages past the end of a shrunk database are gone
+        latest.split_off(&(file.header.commit + 1));
+    }
+
+    let header = Header {
+        flags: 0,
+        page_size: first.header.page_size,
+        commit: last.header.commit,
+        min_txid: first.header.min_txid,
+        max_txid: last.header.max_txid,
+        timestamp: last.header.timestamp,
+        pre_apply_checksum: first.header.pre_apply_checksum,
+    };
+    if header.is_snapshot() {
+        let lock_page = lock_page(header.page_size);
+        if let Some(missing) =
+            (1..=header.commit).find(|pgno| *pgno != lock_page && !latest.contains_key(pgno))
+        {
+            return Err(invalid(format!("merged snapshot would be missing page {missing}")));
+        }
+    }
+
+    let mut sources = files
+        .iter()
+        .map(|file| File::open(&file.pages_path))
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut encoder = Encoder::create(out, &header)?;
+    let mut page = vec![0; header.page_size as usize];
+    for (pgno, (index, offset)) in latest {
+        let source = &mut sources[index];
+        source.seek(SeekFrom::Start(offset))?;
+        source.read_exact(&mut page)?;
+        encoder.write_page(pgno, &page)?;
+    }
+    encoder.finish(last.post_apply_checksum)?;
+    Ok(header)
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    /// Write an LTX file of 4-byte pages, each filled with its byte.
+    pub(in crate::commands::stream) fn write_ltx(
+        path: &Path,
+        (min_txid, max_txid): (u64, u64),
+        commit: u32,
+        pages: &[(u32, u8)],
+    ) {
+        let header = Header {
+            flags: 0,
+            page_size: 4,
+            commit,
+            min_txid,
+            max_txid,
+            timestamp: max_txid as i64 * 1000,
+            pre_apply_checksum: if min_txid == 1 { 0 } else { CHECKSUM_FLAG | min_txid },
+        };
+        let mut encoder = Encoder::create(path, &header).unwrap();
+        for &(pgno, fill) in pages {
+            encoder.write_page(pgno, &[fill; 4]).unwrap();
+        }
+        encoder.finish(CHECKSUM_FLAG | max_txid).unwrap();
+    }
+
+    /// Each page of `file` as (page number, fill byte).
+    pub(in crate::commands::stream) fn read_pages(file: &LtxFile) -> Vec<(u32, u8)> {
+        let mut source = File::open(&file.pages_path).unwrap();
+        file.pages
+            .iter()
+            .map(|&(pgno, offset)| {
+                let mut page = [0; 4];
+                source.seek(SeekFrom::Start(offset)).unwrap();
+                source.read_exact(&mut page).unwrap();
+                (pgno, page[0])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_crc64() {
+        assert_eq!(CRC64.checksum(b"123456789"), 0xb90956c775a41001);
+    }
+
+    #[test]
+    fn test_round_trip_and_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.ltx");
+        write_ltx(&path, (3, 5), 9, &[(2, 7), (9, 8)]);
+        let file = LtxFile::open(&path).unwrap();
+        assert_eq!(file.header.min_txid, 3);
+        assert_eq!(file.header.max_txid, 5);
+        assert_eq!(file.header.commit, 9);
+        assert_eq!(file.header.timestamp, 5000);
+        assert!(!file.header.is_snapshot());
+        assert_eq!(file.post_apply_checksum, CHECKSUM_FLAG | 5);
+        assert_eq!(read_pages(&file), vec![(2, 7), (9, 8)]);
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[HEADER_SIZE + 4] ^= 1;
+        std::fs::write(&path, &bytes).unwrap();
+        let error = LtxFile::open(&path).err().unwrap();
+        assert!(error.to_string().contains("checksum mismatch"), "{error}");
+
+        std::fs::write(&path, &bytes[..HEADER_SIZE + 2]).unwrap();
+        let error = LtxFile::open(&path).err().unwrap();
+        assert!(error.to_string().contains("truncated"), "{error}");
+    }
+
+    /// Rewrite the LTX file at `path` with its page frames LZ4-compressed,
+    /// the way ritestream writes them.
+    fn compress(path: &Path) {
+        let bytes = std::fs::read(path).unwrap();
+        let (header, rest) = bytes.split_at(HEADER_SIZE);
+        let (frames, trailer) = rest.split_at(rest.len() - 16);
+        let mut header = Header::decode(header).unwrap();
+        header.flags |= FLAG_COMPRESS_LZ4;
+        let header = header.encode();
+
+        let mut digest = CRC64.digest();
+        digest.update(&header);
+        digest.update(frames);
+        digest.update(&trailer[..8]);
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+        encoder.write_all(frames).unwrap();
+        let mut out = header.to_vec();
+        out.extend(encoder.finish().unwrap());
+        out.extend(&trailer[..8]);
+        out.extend((CHECKSUM_FLAG | digest.finalize()).to_be_bytes());
+        std::fs::write(path, out).unwrap();
+    }
+
+    #[test]
+    fn test_compressed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name);
+        write_ltx(&path("1"), (1, 1), 3, &[(1, 1), (2, 1), (3, 1)]);
+        write_ltx(&path("2"), (2, 2), 3, &[(2, 2)]);
+        compress(&path("1"));
+        compress(&path("2"));
+
+        let files = vec![
+            LtxFile::open(&path("1")).unwrap(),
+            LtxFile::open(&path("2")).unwrap(),
+        ];
+        assert_eq!(files[0].header.flags, FLAG_COMPRESS_LZ4);
+        assert_eq!(read_pages(&files[0]), vec![(1, 1), (2, 1), (3, 1)]);
+        let header = merge(&files, &path("merged")).unwrap();
+        assert_eq!(header.flags, 0);
+        let merged = LtxFile::open(&path("merged")).unwrap();
+        assert_eq!(read_pages(&merged), vec![(1, 1), (2, 2), (3, 1)]);
+
+        let mut bytes = std::fs::read(path("2")).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        std::fs::write(path("2"), &bytes).unwrap();
+        let error = LtxFile::open(&path("2")).err().unwrap();
+        assert!(error.to_string().contains("checksum mismatch"), "{error}");
+
+        let mut header = Header::decode(&bytes).unwrap();
+        header.flags = 1 << 4;
+        bytes[..HEADER_SIZE].copy_from_slice(&header.encode());
+        std::fs::write(path("2"), &bytes).unwrap();
+        let error = LtxFile::open(&path("2")).err().unwrap();
+        assert!(error.to_string().contains("unsupported LTX flags"), "{error}");
+    }
+
+    #[test]
+    fn test_merge() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name);
+        write_ltx(&path("1"), (1, 1), 3, &[(1, 1), (2, 1), (3, 1)]);
+        write_ltx(&path("2"), (2, 3), 4, &[(2, 2), (4, 2)]);
+        // Shrinks to two pages, dropping pages 3 and 4
+        write_ltx(&path("3"), (4, 4), 2, &[(1, 4)]);
+        let mut files: Vec<_> = ["1", "2", "3"]
+            .iter()
+            .map(|name| LtxFile::open(&path(name)).unwrap())
+            .collect();
+
+        let header = merge(&files, &path("merged")).unwrap();
+        assert!(header.is_snapshot());
+        assert_eq!((header.min_txid, header.max_txid, header.commit), (1, 4, 2));
+        let merged = LtxFile::open(&path("merged")).unwrap();
+        assert_eq!(merged.header, header);
+        assert_eq!(merged.post_apply_checksum, CHECKSUM_FLAG | 4);
+        assert_eq!(read_pages(&merged), vec![(1, 4), (2, 2)]);
+
+        // Increments alone merge into a wider increment
+        let header = merge(&files[1..], &path("increment")).unwrap();
+        assert_eq!((header.min_txid, header.max_txid), (2, 4));
+        assert_eq!(header.pre_apply_checksum, CHECKSUM_FLAG | 2);
+
+        let error = merge(&[files.remove(0), files.remove(1)], &path("gap")).unwrap_err();
+        assert!(error.to_string().contains("skip from txid 1 to 4"), "{error}");
+    }
+
+    #[test]
+    fn test_merge_rejects_incomplete_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name);
+        write_ltx(&path("1"), (1, 1), 1, &[(1, 1)]);
+        write_ltx(&path("2"), (2, 2), 3, &[(3, 2)]);
+        let files = vec![
+            LtxFile::open(&path("1")).unwrap(),
+            LtxFile::open(&path("2")).unwrap(),
+        ];
+        let error = merge(&files, &path("merged")).unwrap_err();
+        assert!(error.to_string().contains("missing page 2"), "{error}");
+    }
+}
@@ -0,0 +1,779 @@
+//! CLI handler for the `solite stream` subcommand.
+//!
+//! `sync` and `restore` go through `ritestream_api`. `status` and
+//! point-in-time restores read the replica's files directly: each LTX file
+//! is named after the transaction IDs it covers (`<min>-<max>.ltx`, in
+//! hex), so a restore to a past point is a restore from a local copy of a
+//! snapshot and the contiguous files after it, up to that point. `compact` merges the files from before the retention
+//! window into one snapshot (see [`ltx`]) and deletes what it covers.
+
+mod ltx;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{bail, Context};
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
+use jiff::Timestamp;
+use solite_core::object_store::{self, ObjectEntry};
+
+use crate::cli::{StreamCommand, StreamNamespace, StreamRestoreArgs, StreamSyncArgs};
+use crate::colors;
+
+fn sync_impl(database: std::path::PathBuf, url: String) -> anyhow::Result<()> {
+    let pb = ProgressBar::new_spinner();
+    if let Ok(style) =
+        ProgressStyle::with_template("{spinner:.cyan} {elapsed} {wide_msg}")
+    {
+        pb.set_style(style);
+    }
+    pb.enable_steady_tick(std::time::Duration::from_millis(80));
+    let start = Instant::now();
+
+    let result = ritestream_api::sync_with_progress(&url, &database, |event| {
+        use ritestream_api::SyncProgress::*;
+        match event {
+            Generating => {
+                pb.set_message("generating LTX…");
+            }
+            Uploading { bytes } => {
+                pb.set_message(format!("uploading {}…", HumanBytes(bytes)));
+            }
+            Uploaded { .. } => {}
+        }
+    })?;
+
+    pb.finish_and_clear();
+
+    match result {
+        Some(r) => {
+            println!(
+                "{} synced to {} (txid={}, {} pages, {:.2?})",
+                colors::green("✓"),
+                url,
+                r.txid,
+                r.page_count,
+                start.elapsed(),
+            );
+        }
+        None => {
+            println!(
+                "{} nothing to sync (database empty or missing)",
+                colors::yellow("⚠"),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Size and modification time of the database's WAL, to notice commits.
+fn wal_state(database: &Path) -> Option<(u64, SystemTime)> {
+    let mut wal = database.as_os_str().to_owned();
+    wal.push("-wal");
+    let meta = std::fs::metadata(wal).ok()?;
+    Some((meta.len(), meta.modified().ok()?))
+}
+
+/// Sync, then compact the replica if a retention window is set.
+fn sync_and_compact(args: &StreamSyncArgs) -> anyhow::Result<()> {
+    sync_impl(args.database.clone(), args.url.clone())?;
+    if let Some(keep) = args.retention {
+        compact_impl(&args.url, keep, false)?;
+    }
+    Ok(())
+}
+
+/// Sync now, then again each time the WAL changes. Runs until interrupted;
+/// a failed sync is reported and retried.
+fn watch_impl(args: StreamSyncArgs) -> anyhow::Result<()> {
+    let database = &args.database;
+    let interval = Duration::from_millis(args.interval);
+    eprintln!(
+        "watching {} for changes (ctrl-c to stop)",
+        database.display()
+    );
+    loop {
+        if let Err(error) = sync_and_compact(&args) {
+            eprintln!("{} sync failed: {error}", colors::red("✗"));
+            std::thread::sleep(interval);
+            continue;
+        }
+        let synced = wal_state(database);
+        while wal_state(database) == synced {
+            std::thread::sleep(interval);
+        }
+    }
+}
+
+/// An object store URL for the replica: bare paths become `file://` URLs.
+fn replica_url(url: &str) -> String {
+    if object_store::is_object_store_url(url) {
+        url.to_string()
+    } else {
+        format!("file://{}", url)
+    }
+}
+
+/// The transaction IDs an LTX file covers, from its name.
+fn ltx_txids(path: &str) -> Option<(u64, u64)> {
+    let name = path.rsplit('/').next()?.strip_suffix(".ltx")?;
+    let (min, max) = name.split_once('-')?;
+    Some((
+        u64::from_str_radix(min, 16).ok()?,
+        u64::from_str_radix(max, 16).ok()?,
+    ))
+}
+
+fn format_time(time: SystemTime) -> String {
+    Timestamp::try_from(time)
+        .map(|t| t.strftime("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|_| "?".to_string())
+}
+
+fn status_impl(url: String) -> anyhow::Result<()> {
+    let entries = object_store::list(&replica_url(&url))?;
+    if entries.is_empty() {
+        bail!("no replica found at {url}");
+    }
+
+    // Group files by directory, e.g. litestream's `ltx/<level>`
+    let mut generation = None;
+    for entry in &entries {
+        let (dir, name) = entry.path.rsplit_once('/').unwrap_or(("", &entry.path));
+        if generation != Some(dir) {
+            generation = Some(dir);
+            println!("{}", colors::bold(if dir.is_empty() { "." } else { dir }));
+        }
+        let txids = match ltx_txids(name) {
+            Some((min, max)) if min == max => format!("txid {min}"),
+            Some((min, max)) => format!("txid {min}-{max}"),
+            None => String::new(),
+        };
+        println!(
+            "  {:<40} {:>18} {:>10}  {}",
+            name,
+            txids,
+            HumanBytes(entry.size).to_string(),
+            format_time(entry.last_modified),
+        );
+    }
+
+    let bytes: u64 = entries.iter().map(|e| e.size).sum();
+    let latest = entries
+        .iter()
+        .filter_map(|e| Some((ltx_txids(&e.path)?.1, e.last_modified)))
+        .max();
+    print!(
+        "{} {} files, {}",
+        colors::green("✓"),
+        entries.len(),
+        HumanBytes(bytes)
+    );
+    match latest {
+        Some((txid, time)) => println!(", latest txid {} ({})", txid, format_time(time)),
+        None => println!(", no LTX files"),
+    }
+    Ok(())
+}
+
+/// The name of the LTX file covering transactions `min` through `max`.
+fn ltx_name(min: u64, max: u64) -> String {
+    format!("{min:016x}-{max:016x}.ltx")
+}
+
+/// What a compaction does to a replica.
+struct Compaction<'a> {
+    /// The snapshot that stays: every LTX file covering only transactions
+    /// up to its last is deleted.
+    snapshot: String,
+    /// Files to merge into `snapshot` first, when it doesn't exist yet: an
+    /// older snapshot and the contiguous run of files after it.
+    merge: Vec<&'a ObjectEntry>,
+    expired: Vec<&'a ObjectEntry>,
+}
+
+/// Plan a compaction. Starting from the newest snapshot (an LTX file
+/// starting at txid 1) uploaded no later than `cutoff`, follow the files
+/// after it, also uploaded by then, for as long as their transactions are
+/// contiguous, taking the widest file at each step. Those merge into one
+/// snapshot next to the first, and every LTX file it covers is deleted.
+/// Restoring any state after `cutoff` only needs that snapshot and the
+/// files after it. `None` when there's no such snapshot or nothing to do.
+fn compaction_plan(entries: &[ObjectEntry], cutoff: SystemTime) -> Option<Compaction<'_>> {
+    let old: Vec<(u64, u64, &ObjectEntry)> = entries
+        .iter()
+        .filter(|entry| entry.last_modified <= cutoff)
+        .filter_map(|entry| {
+            let (min, max) = ltx_txids(&entry.path)?;
+            Some((min, max, entry))
+        })
+        .collect();
+    let widest_from = |txid: u64| {
+        old.iter()
+            .filter(|(min, ..)| *min == txid)
+            .max_by_key(|(_, max, _)| *max)
+            .copied()
+    };
+
+    let (_, mut max_txid, base) = widest_from(1)?;
+    let mut chain = vec![base];
+    while let Some((_, max, entry)) = widest_from(max_txid + 1) {
+        chain.push(entry);
+        max_txid = max;
+    }
+
+    let dir = base.path.rsplit_once('/').map_or("", |(dir, _)| dir);
+    let snapshot = match dir {
+        "" => ltx_name(1, max_txid),
+        dir => format!("{}/{}", dir, ltx_name(1, max_txid)),
+    };
+    let merge = if entries.iter().any(|entry| entry.path == snapshot) {
+        vec![]
+    } else {
+        chain
+    };
+    let expired: Vec<_> = entries
+        .iter()
+        .filter(|entry| entry.path != snapshot)
+        .filter(|entry| ltx_txids(&entry.path).is_some_and(|(_, max)| max <= max_txid))
+        .collect();
+    if merge.is_empty() && expired.is_empty() {
+        return None;
+    }
+    Some(Compaction {
+        snapshot,
+        merge,
+        expired,
+    })
+}
+
+/// Download `files` from the replica, merge them and upload the result as
+/// `snapshot`.
+fn write_snapshot(replica: &str, files: &[&ObjectEntry], snapshot: &str) -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let entries: Vec<ObjectEntry> = files.iter().map(|entry| (*entry).clone()).collect();
+    object_store::download_into(replica, &entries, dir.path())?;
+    let files = entries
+        .iter()
+        .map(|entry| {
+            ltx::LtxFile::open(&dir.path().join(&entry.path))
+                .with_context(|| format!("failed to read {}", entry.path))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let merged = dir.path().join("merged.ltx");
+    ltx::merge(&files, &merged).context("failed to merge LTX files")?;
+
+    let mut merged = std::fs::File::open(&merged)?;
+    let url = format!("{}/{}", replica.trim_end_matches('/'), snapshot);
+    object_store::upload_to_url(&url, |out| {
+        std::io::copy(&mut merged, out)?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn compact_impl(url: &str, keep: Duration, dry_run: bool) -> anyhow::Result<()> {
+    let replica = replica_url(url);
+    let entries = object_store::list(&replica)?;
+    if entries.is_empty() {
+        bail!("no replica found at {url}");
+    }
+    let cutoff = SystemTime::now()
+        .checked_sub(keep)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let Some(plan) = compaction_plan(&entries, cutoff) else {
+        println!(
+            "{} nothing to compact (no snapshot from before {} with files to merge or delete)",
+            colors::yellow("⚠"),
+            format_time(cutoff),
+        );
+        return Ok(());
+    };
+
+    if !plan.merge.is_empty() {
+        let verb = if dry_run { "would merge" } else { "merge" };
+        println!(
+            "  {} {} files into {}",
+            verb,
+            plan.merge.len(),
+            plan.snapshot
+        );
+    }
+    let bytes: u64 = plan.expired.iter().map(|entry| entry.size).sum();
+    for entry in &plan.expired {
+        let verb = if dry_run { "would delete" } else { "delete" };
+        println!("  {} {} ({})", verb, entry.path, HumanBytes(entry.size));
+    }
+    if dry_run {
+        println!(
+            "{} would reclaim {} from {} files in {}",
+            colors::yellow("⚠"),
+            HumanBytes(bytes),
+            plan.expired.len(),
+            url,
+        );
+        return Ok(());
+    }
+
+    // The snapshot is in place before anything it replaces is deleted
+    if !plan.merge.is_empty() {
+        write_snapshot(&replica, &plan.merge, &plan.snapshot)?;
+    }
+    let paths: Vec<&str> = plan.expired.iter().map(|entry| entry.path.as_str()).collect();
+    object_store::delete(&replica, &paths)?;
+    println!(
+        "{} compacted {} into {}, reclaiming {} from {} files",
+        colors::green("✓"),
+        url,
+        plan.snapshot,
+        HumanBytes(bytes),
+        plan.expired.len(),
+    );
+    Ok(())
+}
+
+/// Where a point-in-time restore stops.
+enum RestorePoint {
+    Txid(u64),
+    Timestamp(Timestamp),
+}
+
+impl RestorePoint {
+    fn from_args(args: &StreamRestoreArgs) -> Option<Self> {
+        match (args.txid, args.timestamp) {
+            (Some(txid), _) => Some(RestorePoint::Txid(txid)),
+            (None, Some(timestamp)) => Some(RestorePoint::Timestamp(timestamp)),
+            (None, None) => None,
+        }
+    }
+
+    /// Whether an LTX file ending at txid `max`, whose last transaction
+    /// committed at `timestamp` (milliseconds since the epoch, from its
+    /// header), only holds changes up to this point.
+    fn includes(&self, max: u64, timestamp: Option<i64>) -> bool {
+        match self {
+            RestorePoint::Txid(txid) => max <= *txid,
+            RestorePoint::Timestamp(point) => {
+                timestamp.is_some_and(|timestamp| timestamp <= point.as_millisecond())
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for RestorePoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RestorePoint::Txid(txid) => write!(f, "txid {}", txid),
+            RestorePoint::Timestamp(timestamp) => write!(f, "{}", timestamp),
+        }
+    }
+}
+
+/// The commit time each LTX file in `entries` records in its header, in
+/// milliseconds since the epoch.
+fn ltx_timestamps<'a>(
+    url: &str,
+    entries: &'a [ObjectEntry],
+) -> anyhow::Result<HashMap<&'a str, i64>> {
+    let files: Vec<&ObjectEntry> = entries
+        .iter()
+        .filter(|entry| ltx_txids(&entry.path).is_some())
+        .collect();
+    let listed: Vec<ObjectEntry> = files.iter().map(|entry| (*entry).clone()).collect();
+    let heads = object_store::read_heads(url, &listed, ltx::HEADER_SIZE as u64)?;
+    files
+        .into_iter()
+        .zip(heads)
+        .map(|(entry, head)| {
+            let header = ltx::Header::decode(&head)
+                .with_context(|| format!("failed to read {}", entry.path))?;
+            Ok((entry.path.as_str(), header.timestamp))
+        })
+        .collect()
+}
+
+/// The LTX files a restore to `point` replays: the newest snapshot wholly
+/// at or before it, then the contiguous files after it that also are,
+/// taking the widest at each step. `timestamps` are the files' header
+/// commit times, needed for timestamp points.
+///
+/// Errors when no snapshot qualifies, or when the run stops short of
+/// `point` because every file that continues it spans the point, such as a
+/// compacted file holding transactions from both sides of it.
+fn restore_chain<'a>(
+    entries: &'a [ObjectEntry],
+    point: &RestorePoint,
+    timestamps: &HashMap<&str, i64>,
+) -> anyhow::Result<Vec<&'a ObjectEntry>> {
+    let files: Vec<(u64, u64, &ObjectEntry)> = entries
+        .iter()
+        .filter_map(|entry| {
+            let (min, max) = ltx_txids(&entry.path)?;
+            Some((min, max, entry))
+        })
+        .collect();
+    let widest_from = |txid: u64| {
+        files
+            .iter()
+            .filter(|(min, max, entry)| {
+                *min == txid && point.includes(*max, timestamps.get(entry.path.as_str()).copied())
+            })
+            .max_by_key(|(_, max, _)| *max)
+            .copied()
+    };
+
+    let Some((_, mut last, base)) = widest_from(1) else {
+        bail!("no snapshot at or before {point}");
+    };
+    let mut chain = vec![base];
+    while let Some((_, max, entry)) = widest_from(last + 1) {
+        chain.push(entry);
+        last = max;
+    }
+
+    // The files that would continue the chain all end past `point`. That's
+    // fine if `point` comes before their first transaction.
+    let next: Vec<_> = files.iter().filter(|(min, ..)| *min == last + 1).collect();
+    let reached = next.is_empty()
+        || match point {
+            RestorePoint::Txid(txid) => last >= *txid,
+            // A single-transaction file committed after the point
+            RestorePoint::Timestamp(_) => next.iter().any(|(min, max, _)| min == max),
+        };
+    if !reached {
+        let spanning: Vec<&str> = next.iter().map(|(.., entry)| entry.path.as_str()).collect();
+        bail!(
+            "can't restore to {point}: after txid {last}, the replica only has files spanning it ({})",
+            spanning.join(", ")
+        );
+    }
+    Ok(chain)
+}
+
+/// Copy a snapshot and the files after it, up to `point`, into a temporary
+/// local replica, so restoring from the copy stops there. Files that
+/// aren't LTX files are copied too.
+fn stage_replica(url: &str, point: &RestorePoint) -> anyhow::Result<tempfile::TempDir> {
+    let url = replica_url(url);
+    let entries = object_store::list(&url)?;
+    let timestamps = match point {
+        RestorePoint::Timestamp(_) => ltx_timestamps(&url, &entries)?,
+        RestorePoint::Txid(_) => HashMap::new(),
+    };
+    let chain = restore_chain(&entries, point, &timestamps)?;
+    let staged: Vec<ObjectEntry> = entries
+        .iter()
+        .filter(|entry| ltx_txids(&entry.path).is_none())
+        .chain(chain)
+        .cloned()
+        .collect();
+    let dir = tempfile::tempdir()?;
+    object_store::download_into(&url, &staged, dir.path())?;
+    Ok(dir)
+}
+
+fn restore_impl(url: String, database: PathBuf, point: Option<RestorePoint>) -> anyhow::Result<()> {
+    // Kept alive until the restore has read it
+    let staged = point
+        .as_ref()
+        .map(|point| stage_replica(&url, point))
+        .transpose()?;
+    let source = match &staged {
+        Some(dir) => format!("file://{}", dir.path().display()),
+        None => url.clone(),
+    };
+
+    let pb = ProgressBar::new(0);
+    if let Ok(style) = ProgressStyle::with_template(
+        "{spinner:.cyan} [{bar:40}] {bytes}/{total_bytes} ({elapsed})",
+    ) {
+        pb.set_style(style.progress_chars("=> "));
+    }
+    pb.enable_steady_tick(std::time::Duration::from_millis(80));
+    let start = Instant::now();
+
+    ritestream_api::restore_with_progress(&source, &database, |event| {
+        use ritestream_api::RestoreProgress::*;
+        match event {
+            Listed { total_bytes, .. } => {
+                pb.set_length(total_bytes);
+            }
+            Progress { bytes_downloaded, .. } => {
+                pb.set_position(bytes_downloaded);
+            }
+            Writing => {}
+        }
+    })?;
+
+    pb.finish_and_clear();
+
+    let size = std::fs::metadata(&database).map(|m| m.len()).unwrap_or(0);
+    let as_of = point.map(|point| format!(" as of {point}")).unwrap_or_default();
+    println!(
+        "{} restored {} from {}{} ({}, {:.2?})",
+        colors::green("✓"),
+        database.display(),
+        url,
+        as_of,
+        HumanBytes(size),
+        start.elapsed(),
+    );
+    Ok(())
+}
+
+pub fn stream(cmd: StreamNamespace) -> Result<(), ()> {
+    let result = match cmd.command {
+        StreamCommand::Sync(args) if args.watch => watch_impl(args),
+        StreamCommand::Sync(args) => sync_and_compact(&args),
+        StreamCommand::Restore(args) => {
+            let point = RestorePoint::from_args(&args);
+            restore_impl(args.url, args.database, point)
+        }
+        StreamCommand::Status(args) => status_impl(args.url),
+        StreamCommand::Compact(args) => compact_impl(&args.url, args.keep, args.dry_run),
+    };
+    match result {
+        Ok(()) => Ok(()),
+        Err(error) => {
+            eprintln!("{error:#}");
+            Err(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ltx_txids() {
+        assert_eq!(
+            ltx_txids("ltx/0/0000000000000001-000000000000000a.ltx"),
+            Some((1, 10))
+        );
+        assert_eq!(ltx_txids("ltx/0/notes.txt"), None);
+        assert_eq!(ltx_txids("garbage-name.ltx"), None);
+    }
+
+    #[test]
+    fn test_stage_replica_up_to_txid() {
+        let replica = tempfile::tempdir().unwrap();
+        let level = replica.path().join("ltx/0");
+        std::fs::create_dir_all(&level).unwrap();
+        for name in [
+            "0000000000000001-0000000000000003.ltx",
+            "0000000000000004-0000000000000004.ltx",
+            "0000000000000005-0000000000000009.ltx",
+        ] {
+            std::fs::write(level.join(name), name).unwrap();
+        }
+        let url = replica.path().display().to_string();
+
+        let staged = stage_replica(&url, &RestorePoint::Txid(4)).unwrap();
+        let staged_level = staged.path().join("ltx/0");
+        assert!(staged_level.join("0000000000000001-0000000000000003.ltx").exists());
+        assert!(staged_level.join("0000000000000004-0000000000000004.ltx").exists());
+        assert!(!staged_level.join("0000000000000005-0000000000000009.ltx").exists());
+
+        assert!(stage_replica(&url, &RestorePoint::Txid(0)).is_err());
+    }
+
+    /// Names of the LTX files `stage_replica` copies for `point`.
+    fn staged_files(url: &str, point: RestorePoint) -> anyhow::Result<Vec<String>> {
+        let staged = stage_replica(url, &point)?;
+        let url = format!("file://{}", staged.path().display());
+        Ok(object_store::list(&url)?
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect())
+    }
+
+    #[test]
+    fn test_stage_replica_follows_a_contiguous_chain() {
+        let replica = tempfile::tempdir().unwrap();
+        let file = |level: u32, (min, max): (u64, u64)| {
+            let dir = replica.path().join(format!("ltx/{level}"));
+            std::fs::create_dir_all(&dir).unwrap();
+            // Committed at `max` seconds past the epoch
+            let path = dir.join(ltx_name(min, max));
+            ltx::tests::write_ltx(&path, (min, max), 1, &[(1, max as u8)]);
+        };
+        file(9, (1, 1));
+        file(0, (2, 2));
+        file(0, (3, 3));
+        file(0, (4, 4));
+        file(1, (2, 4));
+        let url = replica.path().display().to_string();
+        let at = |secs: i64| RestorePoint::Timestamp(Timestamp::from_millisecond(secs).unwrap());
+
+        // The compacted 2-4 file is the widest step when it fits...
+        assert_eq!(
+            staged_files(&url, RestorePoint::Txid(4)).unwrap(),
+            vec![
+                "ltx/1/0000000000000002-0000000000000004.ltx",
+                "ltx/9/0000000000000001-0000000000000001.ltx",
+            ]
+        );
+        // ...and the single-transaction files are used when it doesn't
+        assert_eq!(
+            staged_files(&url, at(3500)).unwrap(),
+            vec![
+                "ltx/0/0000000000000002-0000000000000002.ltx",
+                "ltx/0/0000000000000003-0000000000000003.ltx",
+                "ltx/9/0000000000000001-0000000000000001.ltx",
+            ]
+        );
+        assert_eq!(
+            staged_files(&url, at(999)).unwrap_err().to_string(),
+            "no snapshot at or before 1970-01-01T00:00:00.999Z"
+        );
+
+        // Without them, a point inside the compacted file is unreachable
+        for txid in 2..=4 {
+            let path = replica.path().join("ltx/0").join(ltx_name(txid, txid));
+            std::fs::remove_file(path).unwrap();
+        }
+        let error = staged_files(&url, at(3500)).unwrap_err().to_string();
+        assert!(error.contains("after txid 1, the replica only has files spanning it"), "{error}");
+        assert!(staged_files(&url, RestorePoint::Txid(3)).is_err());
+        assert_eq!(staged_files(&url, RestorePoint::Txid(9)).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_compaction_plan() {
+        let epoch = SystemTime::UNIX_EPOCH;
+        let entry = |path: &str, secs: u64| ObjectEntry {
+            path: path.to_string(),
+            size: 10,
+            last_modified: epoch + Duration::from_secs(secs),
+        };
+        let entries = vec![
+            entry("ltx/0/0000000000000002-0000000000000004.ltx", 20),
+            entry("ltx/0/0000000000000005-0000000000000006.ltx", 30),
+            entry("ltx/0/0000000000000007-0000000000000007.ltx", 60),
+            entry("ltx/0/0000000000000008-0000000000000008.ltx", 80),
+            entry("ltx/1/0000000000000005-0000000000000007.ltx", 60),
+            entry("ltx/9/0000000000000001-0000000000000001.ltx", 10),
+            entry("ltx/9/0000000000000001-0000000000000006.ltx", 40),
+            entry("ltx/9/0000000000000001-0000000000000007.ltx", 70),
+            entry("meta", 0),
+        ];
+        let plan = |cutoff: u64| {
+            compaction_plan(&entries, epoch + Duration::from_secs(cutoff)).map(|plan| {
+                let paths = |entries: Vec<&ObjectEntry>| -> Vec<String> {
+                    entries.into_iter().map(|entry| entry.path.clone()).collect()
+                };
+                (plan.snapshot, paths(plan.merge), paths(plan.expired))
+            })
+        };
+
+        // The old snapshot already covers everything before the cutoff
+        let (snapshot, merge, expired) = plan(50).unwrap();
+        assert_eq!(snapshot, "ltx/9/0000000000000001-0000000000000006.ltx");
+        assert!(merge.is_empty());
+        assert_eq!(
+            expired,
+            vec![
+                "ltx/0/0000000000000002-0000000000000004.ltx",
+                "ltx/0/0000000000000005-0000000000000006.ltx",
+                "ltx/9/0000000000000001-0000000000000001.ltx",
+            ]
+        );
+
+        // Files after the snapshot merge into a new one, up to the first
+        // file uploaded after the cutoff
+        let (snapshot, merge, expired) = plan(65).unwrap();
+        assert_eq!(snapshot, "ltx/9/0000000000000001-0000000000000007.ltx");
+        assert!(merge.is_empty(), "the merged snapshot already exists");
+        assert_eq!(expired.len(), 6);
+
+        assert!(plan(5).is_none());
+
+        let entries: Vec<_> = entries
+            .into_iter()
+            .filter(|entry| !entry.path.ends_with("1-0000000000000007.ltx"))
+            .collect();
+        let (snapshot, merge, expired) = compaction_plan(&entries, epoch + Duration::from_secs(65))
+            .map(|plan| (plan.snapshot, plan.merge, plan.expired))
+            .unwrap();
+        assert_eq!(snapshot, "ltx/9/0000000000000001-0000000000000007.ltx");
+        let merge: Vec<&str> = merge.iter().map(|entry| entry.path.as_str()).collect();
+        assert_eq!(
+            merge,
+            vec![
+                "ltx/9/0000000000000001-0000000000000006.ltx",
+                "ltx/0/0000000000000007-0000000000000007.ltx",
+            ]
+        );
+        assert_eq!(expired.len(), 6);
+        assert!(expired.iter().all(|entry| !entry.path.contains("8-")));
+    }
+
+    #[test]
+    fn test_compact_merges_file_replica() {
+        let replica = tempfile::tempdir().unwrap();
+        let level = |n: u32| {
+            let dir = replica.path().join(format!("ltx/{n}"));
+            std::fs::create_dir_all(&dir).unwrap();
+            dir
+        };
+        ltx::tests::write_ltx(&level(9).join(ltx_name(1, 1)), (1, 1), 2, &[(1, 1), (2, 1)]);
+        ltx::tests::write_ltx(&level(0).join(ltx_name(2, 2)), (2, 2), 2, &[(2, 2)]);
+        ltx::tests::write_ltx(&level(0).join(ltx_name(3, 4)), (3, 4), 3, &[(1, 3), (3, 3)]);
+        let url = format!("file://{}", replica.path().display());
+
+        compact_impl(&url, Duration::ZERO, true).unwrap();
+        assert_eq!(object_store::list(&url).unwrap().len(), 3);
+
+        compact_impl(&url, Duration::ZERO, false).unwrap();
+        let remaining: Vec<String> = object_store::list(&url)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect();
+        assert_eq!(remaining, vec![format!("ltx/9/{}", ltx_name(1, 4))]);
+
+        let merged = ltx::LtxFile::open(&level(9).join(ltx_name(1, 4))).unwrap();
+        assert_eq!((merged.header.min_txid, merged.header.max_txid), (1, 4));
+        assert_eq!(merged.header.commit, 3);
+        assert_eq!(ltx::tests::read_pages(&merged), vec![(1, 3), (2, 2), (3, 3)]);
+
+        // Nothing left to merge or delete
+        compact_impl(&url, Duration::ZERO, false).unwrap();
+        assert_eq!(object_store::list(&url).unwrap().len(), 1);
+    }
+
+    /// Files written by ritestream itself, not by `ltx::tests::write_ltx`,
+    /// survive a compaction and restore.
+    #[test]
+    fn test_sync_compact_restore() {
+        use solite_core::sqlite::Connection;
+
+        let dir = tempfile::tempdir().unwrap();
+        let database = dir.path().join("app.db");
+        let url = format!("file://{}", dir.path().join("replica").display());
+        let conn = Connection::open(&database.to_string_lossy()).unwrap();
+        conn.execute_script("pragma journal_mode = wal; create table t(x);")
+            .unwrap();
+        for _ in 0..3 {
+            conn.execute_script(
+                "with recursive c(x) as (select 1 union all select x + 1 from c limit 100)
+                 insert into t select x from c",
+            )
+            .unwrap();
+            ritestream_api::sync_with_progress(&url, &database, |_| {}).unwrap();
+        }
+        let synced = object_store::list(&url).unwrap().len();
+
+        compact_impl(&url, Duration::ZERO, false).unwrap();
+        let compacted = object_store::list(&url).unwrap();
+        assert!(compacted.len() < synced, "{compacted:?}");
+
+        let restored = dir.path().join("restored.db");
+        restore_impl(url, restored.clone(), None).unwrap();
+        let restored = Connection::open(&restored.to_string_lossy()).unwrap();
+        let (_, stmt) = restored.prepare("select count(*) from t").unwrap();
+        assert_eq!(stmt.unwrap().next().unwrap().unwrap()[0].as_int64(), 300);
+    }
+}
@@ -0,0 +1,271 @@
+//! Finding test files: recursive directory walks that honor `.gitignore`
+//! files, and the `solite-test.toml` project config.
+//!
+//! ```toml
+//! # solite-test.toml, found in the current directory or a parent
+//! roots = ["tests"]                    # what `solite test` runs with no paths
+//! exclude = ["fixtures/", "*.wip.sql"] # .gitignore syntax
+//! database = "tests/seed.db"           # default --database
+//! ```
+//!
+//! Paths in the config are relative to the directory it's in.
+
+use std::path::{Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+use serde::Deserialize;
+
+use super::TestError;
+
+/// The config file's name.
+pub const CONFIG_FILE: &str = "solite-test.toml";
+
+/// A `solite-test.toml`, with its paths resolved.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TestConfig {
+    /// Files and directories to test when none are given.
+    pub roots: Vec<PathBuf>,
+    /// `.gitignore`-style patterns for test files and directories to skip.
+    /// A `!pattern` only re-includes what an earlier exclude skipped.
+    pub exclude: Vec<String>,
+    /// The seed database used when `--database` isn't given.
+    pub database: Option<PathBuf>,
+    /// The directory the config is in.
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+impl TestConfig {
+    /// Look for a config in the current directory, then its parents.
+    pub fn discover() -> Result<Option<Self>, TestError> {
+        match std::env::current_dir() {
+            Ok(dir) => Self::discover_from(&dir),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Look for a config in `start`, then its parents.
+    pub fn discover_from(start: &Path) -> Result<Option<Self>, TestError> {
+        for dir in start.ancestors() {
+            let path = dir.join(CONFIG_FILE);
+            if path.is_file() {
+                return Self::load(&path).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, TestError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| TestError::Config(format!("{}: {}", path.display(), e)))?;
+        let mut config: TestConfig = toml::from_str(&content)
+            .map_err(|e| TestError::Config(format!("{}: {}", path.display(), e)))?;
+        config.dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        config.roots = config.roots.iter().map(|root| config.dir.join(root)).collect();
+        config.database = config.database.map(|database| config.dir.join(database));
+        Ok(config)
+    }
+
+    /// The `exclude` patterns, relative to the config's directory.
+    pub fn excludes(&self) -> Result<Gitignore, TestError> {
+        let base = self.dir.canonicalize().unwrap_or_else(|_| self.dir.clone());
+        let mut builder = GitignoreBuilder::new(base);
+        for pattern in &self.exclude {
+            builder
+                .add_line(None, pattern)
+                .map_err(|e| TestError::Config(format!("{}: {}", CONFIG_FILE, e)))?;
+        }
+        builder
+            .build()
+            .map_err(|e| TestError::Config(format!("{}: {}", CONFIG_FILE, e)))
+    }
+}
+
+/// Expand the test path arguments: directories are searched recursively for
+/// `*.sql` files (sorted), skipping hidden entries and anything matched by
+/// a `.gitignore` or `excludes`. Plain files are used as-is.
+pub fn expand_test_paths(
+    paths: &[PathBuf],
+    excludes: Option<&Gitignore>,
+) -> Result<Vec<PathBuf>, TestError> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut sql_files = walk(path, excludes)?;
+            if sql_files.is_empty() {
+                return Err(TestError::FileRead(format!(
+                    "{}: no .sql files found in directory",
+                    path.display()
+                )));
+            }
+            sql_files.sort();
+            files.append(&mut sql_files);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+/// Collect the `*.sql` files under `dir`. Symlinks aren't followed, so
+/// walks can't loop.
+fn walk(dir: &Path, excludes: Option<&Gitignore>) -> Result<Vec<PathBuf>, TestError> {
+    let mut builder = WalkBuilder::new(dir);
+    if let Some(excludes) = excludes.cloned() {
+        // Excludes are matched against canonical paths, so relative
+        // arguments line up with the config's directory
+        let canonical = dir
+            .canonicalize()
+            .map_err(|e| TestError::FileRead(format!("{}: {}", dir.display(), e)))?;
+        let root = dir.to_path_buf();
+        builder.filter_entry(move |entry| {
+            let Ok(relative) = entry.path().strip_prefix(&root) else {
+                return true;
+            };
+            let path = canonical.join(relative);
+            if !path.starts_with(excludes.path()) {
+                return true;
+            }
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            !excludes.matched(&path, is_dir).is_ignore()
+        });
+    }
+
+    let mut found = Vec::new();
+    for entry in builder.build() {
+        let entry = entry.map_err(|e| TestError::FileRead(e.to_string()))?;
+        let is_file = entry.file_type().is_some_and(|t| t.is_file());
+        if is_file && entry.path().extension().is_some_and(|ext| ext == "sql") {
+            found.push(entry.into_path());
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn config(dir: &Path, exclude: &[&str]) -> TestConfig {
+        TestConfig {
+            exclude: exclude.iter().map(|e| e.to_string()).collect(),
+            dir: dir.to_path_buf(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_exclude_patterns() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        for dir in ["build", "a/build", "fixtures/x/gen", "other/gen", "a"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "build.sql",
+            "build/b.sql",
+            "a/build/b.sql",
+            "a/draft.wip.sql",
+            "top.sql",
+            "a/top.sql",
+            "fixtures/x/gen/g.sql",
+            "other/gen/g.sql",
+            "keep.wip.sql",
+            "test7.sql",
+            "testx.sql",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        let excludes = config(
+            &root,
+            &[
+                "# comment",
+                "",
+                "build/",
+                "*.wip.sql",
+                "!keep.wip.sql",
+                "/top.sql",
+                "fixtures/**/gen",
+                "test[0-9].sql",
+            ],
+        )
+        .excludes()
+        .unwrap();
+
+        let files = expand_test_paths(std::slice::from_ref(&root), Some(&excludes)).unwrap();
+        let relative: Vec<_> = files
+            .iter()
+            .map(|f| f.strip_prefix(&root).unwrap())
+            .collect();
+        assert_eq!(
+            relative,
+            ["a/top.sql", "build.sql", "keep.wip.sql", "other/gen/g.sql", "testx.sql"].map(Path::new)
+        );
+
+        assert!(matches!(
+            config(&root, &["a/**/[z-a]"]).excludes(),
+            Err(TestError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_walk_honors_gitignore_and_excludes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::write(root.join(".gitignore"), "generated/\n").unwrap();
+        for dir in ["tests/a", "tests/generated", "tests/fixtures", "tests/.hidden"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("tests/a/.gitignore"), "*.tmp.sql\n").unwrap();
+        for file in [
+            "tests/top.sql",
+            "tests/a/nested.sql",
+            "tests/a/scratch.tmp.sql",
+            "tests/generated/g.sql",
+            "tests/fixtures/f.sql",
+            "tests/.hidden/h.sql",
+            "tests/notes.txt",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        let excludes = config(&root, &["tests/fixtures/"]).excludes().unwrap();
+
+        let files = expand_test_paths(&[root.join("tests")], Some(&excludes)).unwrap();
+        assert_eq!(
+            files,
+            vec![root.join("tests/a/nested.sql"), root.join("tests/top.sql")]
+        );
+
+        // Explicit files are always run
+        let generated = root.join("tests/generated/g.sql");
+        let files = expand_test_paths(std::slice::from_ref(&generated), Some(&excludes)).unwrap();
+        assert_eq!(files, vec![generated]);
+    }
+
+    #[test]
+    fn test_config_paths_are_relative_to_it() {
+        let tmp = tempfile::tempdir().unwrap();
+        let nested = tmp.path().join("a/b");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(
+            tmp.path().join(CONFIG_FILE),
+            "roots = [\"tests\"]\nexclude = [\"slow/\"]\ndatabase = \"seed.db\"\n",
+        )
+        .unwrap();
+
+        let config = TestConfig::discover_from(&nested).unwrap().unwrap();
+        assert_eq!(config.roots, vec![tmp.path().join("tests")]);
+        assert_eq!(config.database, Some(tmp.path().join("seed.db")));
+        assert_eq!(config.exclude, vec!["slow/"]);
+
+        fs::write(tmp.path().join(CONFIG_FILE), "root = [\"typo\"]\n").unwrap();
+        assert!(matches!(
+            TestConfig::discover_from(&nested),
+            Err(TestError::Config(_))
+        ));
+    }
+}
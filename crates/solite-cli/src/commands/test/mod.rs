@@ -19,6 +19,12 @@
 //! - `-- error: <message>`: Expect a specific error message
 //! - `-- TODO ...`: Record as a TODO (listed in the summary and fails the run until resolved)
 //! - `-- @snap <name>`: Snapshot assertion (captures full output to a .snap file)
+//! - `-- @rows 3`: Expect exactly this many rows
+//! - `-- @type integer`: Expect the value's `typeof()` (null, integer, real, text, blob)
+//! - `-- ~3.14 ±0.01`: Expect a number within a tolerance (default: half the last digit)
+//! - `-- ~/^user_\d+$/`, `-- ~'user_%'`: Expect text matching a regex or `LIKE` pattern
+//! - `-- @table` / `-- @unordered`: Expect every row, listed on the `--` lines
+//!   that follow (`-- 1 | 'alice'`), in order or in any order
 //!
 //! # Example Test File
 //!
@@ -33,8 +39,90 @@
 //!
 //! -- Snapshot assertions
 //! SELECT * FROM users ORDER BY id; -- @snap all-users
+//!
+//! -- Structured assertions
+//! SELECT * FROM users; -- @rows 2
+//! SELECT id, name FROM users; -- @unordered
+//! -- 2 | 'Bob'
+//! -- 1 | 'Alice'
+//! ```
+//!
+//! # Test sections
+//!
+//! `-- @test <name>` lines split a file into tests. Everything above the
+//! first marker is setup and runs once; each test then runs inside a
+//! `SAVEPOINT` that's rolled back afterwards, so tests can't see each
+//! other's changes and a failing test only aborts itself.
+//! `-- @before-each` starts a block that runs at the start of every test
+//! below it, and `-- @fixture <file>` runs a shared SQL file (like `.run`)
+//! wherever it appears. `--filter` matching a test's name runs all of its
+//! assertions.
+//!
+//! ```sql
+//! CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT);
+//! -- @fixture fixtures/users.sql
+//!
+//! -- @before-each
+//! INSERT INTO users(name) VALUES ('carol');
+//!
+//! -- @test delete-removes-a-row
+//! DELETE FROM users WHERE name = 'carol';
+//! SELECT count(*) FROM users WHERE name = 'carol'; -- 0
+//!
+//! -- @test rows-are-restored
+//! SELECT count(*) FROM users WHERE name = 'carol'; -- 1
 //! ```
+//!
+//! # Reporters
+//!
+//! `--reporter junit` and `--reporter json` also write every assertion's
+//! result as JUnit XML or JSON, to `--output` or stdout. While the report
+//! goes to stdout, the human-readable output moves to stderr.
+//!
+//! # Parallel runs
+//!
+//! With `--jobs N`, files run on N worker threads. Each file's output is
+//! captured and replayed in file order once it finishes, so the output is
+//! the same as a sequential run's.
+//!
+//! # Discovery
+//!
+//! Directory arguments are walked recursively, honoring `.gitignore` files
+//! (see [`discover`]). `--filter` selects assertions by `file:line:column`
+//! location or `@snap` name; unselected ones still execute, so later
+//! assertions see the same database state.
+
+/// `print!` for the human-readable output; see [`report::write_human`].
+macro_rules! human_print {
+    ($($arg:tt)*) => {
+        $crate::commands::test::report::write_human(
+            $crate::commands::test::report::Stream::Out,
+            format_args!($($arg)*),
+        )
+    };
+}
 
+/// `println!` for the human-readable output; see [`report::write_human`].
+macro_rules! human_println {
+    () => {
+        human_print!("\n")
+    };
+    ($($arg:tt)*) => {
+        human_print!("{}\n", format_args!($($arg)*))
+    };
+}
+
+/// `eprintln!` for diagnostics; see [`report::write_human`].
+macro_rules! human_eprintln {
+    ($($arg:tt)*) => {
+        $crate::commands::test::report::write_human(
+            $crate::commands::test::report::Stream::Err,
+            format_args!("{}\n", format_args!($($arg)*)),
+        )
+    };
+}
+
+mod discover;
 mod parser;
 mod report;
 pub(crate) mod snap;
@@ -42,26 +130,59 @@ mod value;
 
 use console::Style;
 use solite_core::dot::DotCommand;
-use solite_core::{BlockSource, Runtime, StepResult};
+use solite_core::sqlite::{SQLiteError, Statement};
+use solite_core::{BlockSource, Runtime, StepReference, StepResult};
 use std::fs::read_to_string;
+use std::collections::HashMap;
 use std::io::Write as _;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::Instant;
+
+use crate::cli::{TestArgs, TestFilter, TestReporter};
 
-use crate::cli::TestArgs;
+use discover::{expand_test_paths, TestConfig};
 
 use parser::{
-    line_col_to_offset, parse_epilogue_comment, parse_snap_directive, prepare_error_epilogue,
+    line_col_to_offset, parse_assertion, parse_epilogue_comment, parse_snap_directive,
+    prepare_error_epilogue, Assertion,
 };
-use report::{report_mismatch, TestStats};
+use report::{report_mismatch, Outcome, SnapOutcome, TestCase, TestStats};
 use snap::{handle_orphans, handle_snap_assertion, SnapMode, SnapState};
 use value::value_to_string;
 
 /// Run SQL tests from every given file/directory and aggregate the results.
-fn test_impl(args: TestArgs) -> Result<(), TestError> {
-    let files = expand_test_paths(&args.files)?;
+fn test_impl(mut args: TestArgs) -> Result<(), TestError> {
+    if args.reporter == TestReporter::Human && args.output.is_some() {
+        return Err(TestError::Report(
+            "--output needs --reporter junit or json".to_string(),
+        ));
+    }
+    report::set_human_to_stderr(args.reporter != TestReporter::Human && args.output.is_none());
+
+    let config = TestConfig::discover()?;
+    if args.files.is_empty() {
+        args.files = match &config {
+            Some(config) if !config.roots.is_empty() => config.roots.clone(),
+            Some(config) => vec![config.dir.clone()],
+            None => {
+                return Err(TestError::Config(format!(
+                    "no test paths given and no {} found",
+                    discover::CONFIG_FILE
+                )))
+            }
+        };
+    }
+    let excludes = config.as_ref().map(TestConfig::excludes).transpose()?;
+    let files = expand_test_paths(&args.files, excludes.as_ref())?;
+    if args.database.is_none() {
+        args.database = config.and_then(|config| config.database);
+    }
 
     if files.len() == 1 {
         let (stats, snap_state) = run_file(&files[0], &args)?;
+        write_report(&args, &stats)?;
         return summarize(&stats, &snap_state);
     }
 
@@ -69,69 +190,183 @@ fn test_impl(args: TestArgs) -> Result<(), TestError> {
     // Counter-only aggregate; its path is never used for printing.
     let mut snap_total = SnapState::new(Path::new(""), snap_mode(&args));
 
-    for file in &files {
-        print!("{}: ", file.display());
-        let _ = std::io::stdout().flush();
-        match run_file(file, &args) {
-            Ok((stats, snap_state)) => {
-                let mut line =
-                    format!(" {} passed, {} failed", stats.successes, stats.failures);
-                if !stats.todos.is_empty() {
-                    line.push_str(&format!(", {} todo(s)", stats.todos.len()));
-                }
-                if snap_state.rejected > 0 {
-                    line.push_str(&format!(
-                        ", {} snapshot(s) rejected",
-                        snap_state.rejected
-                    ));
-                }
-                println!("{}", line);
-                total.successes += stats.successes;
-                total.failures += stats.failures;
-                total.todos.extend(stats.todos);
-                snap_total.matches += snap_state.matches;
-                snap_total.new += snap_state.new;
-                snap_total.updated += snap_state.updated;
-                snap_total.rejected += snap_state.rejected;
-                snap_total.removed += snap_state.removed;
-            }
-            Err(e) => {
-                println!();
-                eprintln!("Error: {}", e);
-                total.failures += 1;
-            }
+    // Review prompts one snapshot at a time, so it always runs in order
+    let jobs = if args.review { 1 } else { args.jobs as usize };
+    if jobs <= 1 {
+        for file in &files {
+            human_print!("{}: ", file.display());
+            let _ = std::io::stdout().flush();
+            let result = run_file(file, &args);
+            add_file_result(file, result, &mut total, &mut snap_total);
         }
+    } else {
+        run_parallel(&files, &args, jobs, |file, result, captured| {
+            human_print!("{}: ", file.display());
+            captured.replay();
+            add_file_result(file, result, &mut total, &mut snap_total);
+        });
     }
 
+    write_report(&args, &total)?;
     summarize(&total, &snap_total)
 }
 
-/// Expand the CLI path arguments: directories become their `*.sql` files
-/// (non-recursive, sorted), plain files are used as-is.
-fn expand_test_paths(paths: &[PathBuf]) -> Result<Vec<PathBuf>, TestError> {
-    let mut files = Vec::new();
-    for path in paths {
-        if path.is_dir() {
-            let entries = std::fs::read_dir(path)
-                .map_err(|e| TestError::FileRead(format!("{}: {}", path.display(), e)))?;
-            let mut sql_files: Vec<PathBuf> = entries
-                .flatten()
-                .map(|e| e.path())
-                .filter(|p| p.extension().is_some_and(|ext| ext == "sql") && p.is_file())
-                .collect();
-            if sql_files.is_empty() {
-                return Err(TestError::FileRead(format!(
-                    "{}: no .sql files found in directory",
-                    path.display()
-                )));
+/// Run `files` on `jobs` worker threads, each capturing its files' output.
+/// `done` is called on this thread for each file in order, as soon as it
+/// and every file before it have finished.
+fn run_parallel(
+    files: &[PathBuf],
+    args: &TestArgs,
+    jobs: usize,
+    mut done: impl FnMut(&Path, Result<(TestStats, SnapState), TestError>, report::Captured),
+) {
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.min(files.len()) {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(file) = files.get(index) else {
+                    return;
+                };
+                let (result, captured) = report::capture(|| run_file(file, args));
+                if sender.send((index, result, captured)).is_err() {
+                    return;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut finished = HashMap::new();
+        let mut reported = 0;
+        for (index, result, captured) in receiver {
+            finished.insert(index, (result, captured));
+            while let Some((result, captured)) = finished.remove(&reported) {
+                done(files[reported].as_path(), result, captured);
+                reported += 1;
+            }
+        }
+    });
+}
+
+/// Print a file's one-line result and add it to the totals.
+fn add_file_result(
+    file: &Path,
+    result: Result<(TestStats, SnapState), TestError>,
+    total: &mut TestStats,
+    snap_total: &mut SnapState,
+) {
+    match result {
+        Ok((stats, snap_state)) => {
+            let mut line =
+                format!(" {} passed, {} failed", stats.successes, stats.failures);
+            if !stats.todos.is_empty() {
+                line.push_str(&format!(", {} todo(s)", stats.todos.len()));
             }
-            sql_files.sort();
-            files.append(&mut sql_files);
-        } else {
-            files.push(path.clone());
+            if snap_state.rejected > 0 {
+                line.push_str(&format!(
+                    ", {} snapshot(s) rejected",
+                    snap_state.rejected
+                ));
+            }
+            human_println!("{}", line);
+            total.successes += stats.successes;
+            total.failures += stats.failures;
+            total.todos.extend(stats.todos);
+            total.cases.extend(stats.cases);
+            snap_total.matches += snap_state.matches;
+            snap_total.new += snap_state.new;
+            snap_total.updated += snap_state.updated;
+            snap_total.rejected += snap_state.rejected;
+            snap_total.removed += snap_state.removed;
+        }
+        Err(e) => {
+            human_println!();
+            eprintln!("Error: {}", e);
+            let started = Instant::now();
+            let file = file.to_string_lossy();
+            total.record(TestCase {
+                outcome: Outcome::Failed(e.to_string()),
+                ..new_case(&file, 0, 0, "(test file)".to_string(), None, started)
+            });
+        }
+    }
+}
+
+/// Write the `--reporter` report, if any, to `--output` or stdout.
+fn write_report(args: &TestArgs, stats: &TestStats) -> Result<(), TestError> {
+    let report = match args.reporter {
+        TestReporter::Human => return Ok(()),
+        TestReporter::Junit => report::junit_report(&stats.cases),
+        TestReporter::Json => report::json_report(&stats.cases),
+    };
+    match &args.output {
+        Some(path) => std::fs::write(path, report)
+            .map_err(|e| {
+                TestError::Report(format!("Failed to write report {}: {}", path.display(), e))
+            }),
+        None => {
+            print!("{}", report);
+            Ok(())
         }
     }
-    Ok(files)
+}
+
+/// A passing case for the statement at `line:column`, timed from `started`.
+/// Callers fill in the actual value and outcome.
+fn new_case(
+    file: &str,
+    line: usize,
+    column: usize,
+    sql: String,
+    expected: Option<&str>,
+    started: Instant,
+) -> TestCase {
+    TestCase {
+        file: file.to_string(),
+        line,
+        column,
+        sql,
+        expected: expected.map(str::to_string),
+        actual: None,
+        outcome: Outcome::Passed,
+        snapshot: None,
+        test: None,
+        duration: started.elapsed(),
+    }
+}
+
+/// `snap_state`'s (new, updated, rejected) counters.
+fn snap_counts(snap_state: &SnapState) -> (usize, usize, usize) {
+    (snap_state.new, snap_state.updated, snap_state.rejected)
+}
+
+/// The case for a `@snap` assertion, from how it changed `snap_state`'s
+/// counters since `before`.
+fn snap_case(
+    mut case: TestCase,
+    before: (usize, usize, usize),
+    snap_state: &SnapState,
+) -> TestCase {
+    let (new, updated, rejected) = before;
+    let snapshot = if snap_state.rejected > rejected {
+        SnapOutcome::Rejected
+    } else if snap_state.new > new {
+        SnapOutcome::Created
+    } else if snap_state.updated > updated {
+        SnapOutcome::Updated
+    } else {
+        SnapOutcome::Matched
+    };
+    if snapshot == SnapOutcome::Rejected {
+        case.outcome = Outcome::Failed(
+            "snapshot does not match (accept with --update or --review)".to_string(),
+        );
+    }
+    case.snapshot = Some(snapshot);
+    case
 }
 
 /// The snapshot mode selected by the CLI flags.
@@ -175,7 +410,7 @@ fn run_file(source_path: &Path, args: &TestArgs) -> Result<(TestStats, SnapState
     let content = read_to_string(source_path)
         .map_err(|e| TestError::FileRead(format!("{}: {}", source_path.display(), e)))?;
 
-    let mut rt = Runtime::new(None)
+    let rt = Runtime::new(None)
         .map_err(|e| TestError::FileRead(format!("Failed to initialize runtime: {}", e)))?;
 
     // Seed the in-memory database from a fixture file; copy-on-open
@@ -184,326 +419,762 @@ fn run_file(source_path: &Path, args: &TestArgs) -> Result<(TestStats, SnapState
         seed_database(&rt.connection, fixture).map_err(TestError::Database)?;
     }
 
-    rt.enqueue(
-        &source_path.to_string_lossy(),
-        &content,
-        BlockSource::File(source_path.to_path_buf()),
-    );
+    let mut run = FileRun {
+        source_path,
+        args,
+        content: &content,
+        block_name: source_path.to_string_lossy().to_string(),
+        filestem: source_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "test".to_string()),
+        rt,
+        stats: TestStats::new(),
+        snap_state: SnapState::new(source_path, snap_mode(args)),
+        warned_multi_column: false,
+        warned_multi_row: false,
+    };
 
-    let mut stats = TestStats::new();
-    let mut snap_state = SnapState::new(source_path, snap_mode(args));
+    let sections = match parser::split_sections(&content) {
+        Ok(sections) => sections,
+        Err((line, message)) => {
+            run.stats.record(TestCase {
+                outcome: Outcome::Failed(message.clone()),
+                ..new_case(&run.block_name, line, 1, "(test file)".to_string(), None, Instant::now())
+            });
+            human_print!("{}", Style::new().red().apply_to("x"));
+            human_eprintln!("{}:{}: {}", run.block_name, line, message);
+            return Ok((run.stats, run.snap_state));
+        }
+    };
+    if !sections.tests.is_empty() {
+        // Created lazily otherwise, and a rolled-back test would drop it
+        run.rt.init_sqlite_parameters_table();
+    }
 
-    let filestem = source_path
-        .file_stem()
-        .map(|s| s.to_string_lossy().to_string())
-        .unwrap_or_else(|| "test".to_string());
+    let mut aborted = false;
+    // Some test aborted, so @snap directives after its failure never ran
+    let mut incomplete = false;
+
+    // The setup (the whole file, without `-- @test` sections) runs first,
+    // then each test inside a savepoint that's rolled back afterwards
+    let tests = std::iter::once(None).chain(sections.tests.iter().map(Some));
+    for test in tests {
+        let first_case = run.stats.cases.len();
+        // A test selected by name runs all its assertions
+        let filter = args
+            .filter
+            .as_ref()
+            .filter(|f| !test.is_some_and(|test| f.matches(&test.name)));
+        let scope = match test {
+            None => "test file".to_string(),
+            Some(test) => format!("test '{}'", test.name),
+        };
+        let blocks: Vec<&String> = match test {
+            None => vec![&sections.setup],
+            Some(test) => {
+                if let Err(err) = run.rt.connection.execute("SAVEPOINT solite_test") {
+                    return Err(TestError::Database(format!(
+                        "Failed to start {}: {}",
+                        scope, err.message
+                    )));
+                }
+                test.before_each.iter().chain([&test.body]).collect()
+            }
+        };
 
-    let stdout = std::io::stdout();
-    let mut handle = stdout.lock();
+        // One block at a time: queued blocks would interleave their statements
+        for block in blocks {
+            if run.run_block(block, filter, &scope) {
+                aborted = true;
+                break;
+            }
+        }
 
-    let mut aborted = false;
-    let mut warned_multi_column = false;
-    let mut warned_multi_row = false;
-
-    loop {
-        match rt.next_stepx() {
-            None => break,
-            Some(Err(solite_core::StepError::Prepare {
-                file_name,
-                src,
-                offset,
-                error,
-            })) => {
-                // Prepare-time errors (syntax errors, missing tables, ...)
-                // never produce a Step, so recover any trailing `-- error:`
-                // or `-- TODO` epilogue on the failing statement ourselves.
-                let recovered = prepare_error_epilogue(&src, offset);
-                match recovered {
-                    Some((ref ep, resume_offset)) if parser::is_todo_epilogue(ep) => {
-                        // A TODO'd statement that can't even prepare is
-                        // still a TODO (e.g. `SELECT slow(); -- TODO ...`).
-                        let line = src[..offset].matches('\n').count() + 1;
-                        let col =
-                            offset - src[..offset].rfind('\n').map_or(0, |i| i + 1) + 1;
-                        stats.record_todo(file_name.clone(), line, col, ep.clone());
-                        print!("{}", Style::new().yellow().apply_to("-"));
-                        resume_after_prepare_error(
-                            &mut rt,
-                            &file_name,
-                            &src,
-                            resume_offset,
-                            &source_path,
-                        );
-                    }
-                    Some((ref ep, resume_offset))
-                        if ep.strip_prefix("error:").map(str::trim)
-                            == Some(error.message.as_str()) =>
-                    {
-                        stats.record_success();
-                        print!("{}", Style::new().green().apply_to("."));
-                        resume_after_prepare_error(
-                            &mut rt,
-                            &file_name,
-                            &src,
-                            resume_offset,
-                            &source_path,
-                        );
+        let Some(test) = test else {
+            if aborted {
+                break;
+            }
+            continue;
+        };
+        for case in &mut run.stats.cases[first_case..] {
+            case.test = Some(test.name.clone());
+        }
+        if aborted {
+            // Skip the rest of the test, not the file
+            run.rt.clear_queue();
+            incomplete = true;
+            aborted = false;
+        }
+        let rolled_back = run
+            .rt
+            .connection
+            .execute("ROLLBACK TO solite_test")
+            .and_then(|_| run.rt.connection.execute("RELEASE solite_test"));
+        if let Err(err) = rolled_back {
+            // Later tests would see this one's changes
+            run.stats.record(TestCase {
+                outcome: Outcome::Failed(format!(
+                    "couldn't roll back {} (did it COMMIT or ROLLBACK?): {}",
+                    scope, err.message
+                )),
+                ..new_case(&run.block_name, test.line, 1, "(test)".to_string(), None, Instant::now())
+            });
+            human_print!("{}", Style::new().red().apply_to("x"));
+            human_eprintln!(
+                "{}:{}: couldn't roll back {}: {}; aborting test file.",
+                run.block_name, test.line, scope, err.message
+            );
+            aborted = true;
+            break;
+        }
+    }
+
+    // Handle orphaned snapshots — skipped on abort, where unreached @snap
+    // directives would be misreported (and in update mode deleted) as orphans,
+    // and with --filter, which skips @snap directives the same way
+    if !aborted && !incomplete && args.filter.is_none() {
+        handle_orphans(&mut run.snap_state, &run.filestem, source_path);
+    }
+
+    Ok((run.stats, run.snap_state))
+}
+
+/// A test file being run: its runtime, and what its statements have
+/// recorded so far.
+struct FileRun<'a> {
+    source_path: &'a Path,
+    args: &'a TestArgs,
+    /// The file as written, for error reports and `@table` rows.
+    content: &'a str,
+    block_name: String,
+    /// Names the file's snapshots.
+    filestem: String,
+    rt: Runtime,
+    stats: TestStats,
+    snap_state: SnapState,
+    warned_multi_column: bool,
+    warned_multi_row: bool,
+}
+
+impl FileRun<'_> {
+    /// Run `block` (the setup, a `-- @before-each` block or a test body)
+    /// statement by statement, recording a case per assertion. Returns
+    /// true if a failed setup statement or dot command aborted `scope`.
+    fn run_block(&mut self, block: &str, filter: Option<&TestFilter>, scope: &str) -> bool {
+        self.rt.enqueue(
+            &self.block_name,
+            block,
+            BlockSource::File(self.source_path.to_path_buf()),
+        );
+        loop {
+            let started = Instant::now();
+            match self.rt.next_stepx() {
+                None => break,
+                Some(Err(solite_core::StepError::Prepare {
+                    file_name,
+                    src,
+                    offset,
+                    error,
+                })) => {
+                    // Prepare-time errors (syntax errors, missing tables, ...)
+                    // never produce a Step, so recover any trailing `-- error:`
+                    // or `-- TODO` epilogue on the failing statement ourselves.
+                    let recovered = prepare_error_epilogue(&src, offset);
+                    let line = src[..offset].matches('\n').count() + 1;
+                    let col = offset - src[..offset].rfind('\n').map_or(0, |i| i + 1) + 1;
+                    let sql = statement_sql(&src, offset);
+                    let actual = format!("error: {}", error.message);
+                    if let Some((ref ep, resume_offset)) = recovered {
+                        let location = format!("{}:{}:{}", file_name, line, col);
+                        if !selected(filter, &location, ep) {
+                            resume_after_prepare_error(
+                                &mut self.rt,
+                                &file_name,
+                                &src,
+                                resume_offset,
+                                &self.source_path,
+                            );
+                            continue;
+                        }
                     }
-                    _ => {
-                        stats.record_failure();
-                        print!("{}", Style::new().red().apply_to("x"));
-                        if src.is_empty() {
-                            eprintln!("Error preparing step: {}", error.message);
-                        } else {
-                            crate::errors::report_error(
+                    match recovered {
+                        Some((ref ep, resume_offset)) if parser::is_todo_epilogue(ep) => {
+                            // A TODO'd statement that can't even prepare is
+                            // still a TODO (e.g. `SELECT slow(); -- TODO ...`).
+                            self.stats.record(TestCase {
+                                actual: Some(actual),
+                                outcome: Outcome::Todo(ep.clone()),
+                                ..new_case(&file_name, line, col, sql, None, started)
+                            });
+                            human_print!("{}", Style::new().yellow().apply_to("-"));
+                            resume_after_prepare_error(
+                                &mut self.rt,
+                                &file_name,
+                                &src,
+                                resume_offset,
+                                &self.source_path,
+                            );
+                        }
+                        Some((ref ep, resume_offset))
+                            if ep.strip_prefix("error:").map(str::trim)
+                                == Some(error.message.as_str()) =>
+                        {
+                            self.stats.record(TestCase {
+                                actual: Some(actual),
+                                ..new_case(&file_name, line, col, sql, Some(ep.as_str()), started)
+                            });
+                            human_print!("{}", Style::new().green().apply_to("."));
+                            resume_after_prepare_error(
+                                &mut self.rt,
                                 &file_name,
                                 &src,
-                                &error,
-                                Some(offset),
+                                resume_offset,
+                                &self.source_path,
                             );
                         }
-                        if args.verbose {
-                            if let Some(expected) = recovered
-                                .as_ref()
-                                .and_then(|(ep, _)| ep.strip_prefix("error:"))
-                                .map(str::trim)
-                            {
-                                eprintln!(
-                                    "\nExpected error: '{}' got: '{}'",
-                                    expected, error.message
+                        _ => {
+                            let expected = recovered.as_ref().map(|(ep, _)| ep.as_str());
+                            self.stats.record(TestCase {
+                                actual: Some(actual),
+                                outcome: Outcome::Failed(format!(
+                                    "statement failed to prepare: {}",
+                                    error.message
+                                )),
+                                ..new_case(&file_name, line, col, sql, expected, started)
+                            });
+                            human_print!("{}", Style::new().red().apply_to("x"));
+                            if src.is_empty() {
+                                human_eprintln!("Error preparing step: {}", error.message);
+                            } else {
+                                report::report_error(
+                                    &file_name,
+                                    &src,
+                                    &error,
+                                    Some(offset),
                                 );
                             }
+                            if self.args.verbose {
+                                if let Some(expected) = recovered
+                                    .as_ref()
+                                    .and_then(|(ep, _)| ep.strip_prefix("error:"))
+                                    .map(str::trim)
+                                {
+                                    human_eprintln!(
+                                        "\nExpected error: '{}' got: '{}'",
+                                        expected, error.message
+                                    );
+                                }
+                            }
+                            human_eprintln!("Statement failed to prepare; aborting {}.", scope);
+                            return true;
                         }
-                        eprintln!("Statement failed to prepare; aborting test file.");
-                        aborted = true;
-                        break;
                     }
                 }
-            }
-            Some(Err(e @ solite_core::StepError::ParseDot { .. })) => {
-                stats.record_failure();
-                eprintln!("Error preparing step: {}", e);
-                print!("{}", Style::new().red().apply_to("x"));
-            }
-            Some(Ok(step)) => match step.result {
-                StepResult::DotCommand(cmd) => {
-                    // Dot commands are setup; a failure invalidates every
-                    // assertion after it, so abort like a failed setup
-                    // statement.
-                    if let Err(msg) = handle_dot_command(&cmd, &mut rt, &mut stats) {
-                        stats.record_failure();
-                        print!("{}", Style::new().red().apply_to("x"));
-                        eprintln!("{}", msg);
-                        eprintln!("Dot command failed; aborting test file.");
-                        aborted = true;
-                        break;
-                    }
+                Some(Err(e @ solite_core::StepError::ParseDot { .. })) => {
+                    self.stats.record(TestCase {
+                        outcome: Outcome::Failed(e.to_string()),
+                        ..new_case(
+                            &self.source_path.to_string_lossy(),
+                            0,
+                            0,
+                            "(dot command)".to_string(),
+                            None,
+                            started,
+                        )
+                    });
+                    human_eprintln!("Error preparing step: {}", e);
+                    human_print!("{}", Style::new().red().apply_to("x"));
                 }
-                StepResult::ProcedureDefinition(_) => { /* already registered in runtime */ }
-                StepResult::SqlStatement { mut stmt, .. } => {
-                    let epilogue = match &step.epilogue {
-                        Some(s) => parse_epilogue_comment(s),
-                        None => {
-                            // No epilogue = setup statement. A failure here
-                            // invalidates every assertion after it, so abort
-                            // the file instead of testing against broken state.
-                            if let Err(err) = stmt.execute() {
-                                stats.record_failure();
-                                print!("{}", Style::new().red().apply_to("x"));
-                                let maybe_offset = line_col_to_offset(
-                                    &content,
+                Some(Ok(step)) => match step.result {
+                    StepResult::DotCommand(cmd) => {
+                        // Dot commands are setup; a failure invalidates every
+                        // assertion after it, so abort like a failed setup
+                        // statement.
+                        if let Err(msg) = handle_dot_command(&cmd, &mut self.rt, &mut self.stats) {
+                            self.stats.record(TestCase {
+                                outcome: Outcome::Failed(msg.clone()),
+                                ..new_case(
+                                    step.reference.block_name(),
                                     step.reference.line_number(),
                                     step.reference.column_number(),
-                                );
-                                crate::errors::report_error(
-                                    &source_path.to_string_lossy(),
-                                    &content,
-                                    &err,
-                                    maybe_offset,
-                                );
-                                eprintln!("Setup statement failed; aborting test file.");
-                                aborted = true;
-                                break;
-                            }
-                            continue;
+                                    "(dot command)".to_string(),
+                                    None,
+                                    started,
+                                )
+                            });
+                            human_print!("{}", Style::new().red().apply_to("x"));
+                            human_eprintln!("{}", msg);
+                            human_eprintln!("Dot command failed; aborting {}.", scope);
+                            return true;
                         }
-                    };
-
-                    // Handle TODO annotations
-                    if parser::is_todo_epilogue(&epilogue) {
-                        stats.record_todo(
-                            step.reference.block_name().to_string(),
+                    }
+                    StepResult::ProcedureDefinition(_) => { /* already registered in runtime */ }
+                    StepResult::SqlStatement { mut stmt, .. } => {
+                        let sql = stmt.sql();
+                        let case = |expected: Option<&str>| {
+                            new_case(
+                                step.reference.block_name(),
+                                step.reference.line_number(),
+                                step.reference.column_number(),
+                                sql.clone(),
+                                expected,
+                                started,
+                            )
+                        };
+                        let epilogue = match &step.epilogue {
+                            Some(s) => parse_epilogue_comment(s),
+                            None => {
+                                // No epilogue = setup statement. A failure here
+                                // invalidates every assertion after it, so abort
+                                // the file instead of testing against broken state.
+                                if let Err(err) = stmt.execute() {
+                                    self.stats.record(TestCase {
+                                        actual: Some(format!("error: {}", err.message)),
+                                        outcome: Outcome::Failed(format!(
+                                            "setup statement failed: {}",
+                                            err.message
+                                        )),
+                                        ..case(None)
+                                    });
+                                    human_print!("{}", Style::new().red().apply_to("x"));
+                                    let maybe_offset = line_col_to_offset(
+                                        &self.content,
+                                        step.reference.line_number(),
+                                        step.reference.column_number(),
+                                    );
+                                    report::report_error(
+                                        &self.source_path.to_string_lossy(),
+                                        &self.content,
+                                        &err,
+                                        maybe_offset,
+                                    );
+                                    human_eprintln!("Setup statement failed; aborting {}.", scope);
+                                    return true;
+                                }
+                                continue;
+                            }
+                        };
+
+                        // Assertions outside --filter still run (later ones may
+                        // depend on their side effects) but aren't recorded;
+                        // TODOs never run, filtered or not.
+                        let location = format!(
+                            "{}:{}:{}",
+                            step.reference.block_name(),
                             step.reference.line_number(),
-                            step.reference.column_number(),
-                            epilogue.clone(),
+                            step.reference.column_number()
                         );
-                        print!("{}", Style::new().yellow().apply_to("-"));
-                        continue;
-                    }
-
-                    // Handle @snap directives
-                    match parse_snap_directive(&epilogue) {
-                        Ok(Some(snap_dir)) => {
-                            handle_snap_assertion(
-                                &mut snap_state,
-                                &mut stmt,
-                                &snap_dir.name,
-                                &filestem,
-                                &source_path,
-                            );
-                            let _ = handle.flush();
+                        if !selected(filter, &location, &epilogue) {
+                            if !parser::is_todo_epilogue(&epilogue) {
+                                let _ = stmt.execute();
+                            }
                             continue;
                         }
-                        Ok(None) => {
-                            // Not a snap directive, fall through to inline assertion
-                        }
-                        Err(e) => {
-                            stats.record_failure();
-                            eprintln!("\n{}", e);
-                            print!("{}", Style::new().red().apply_to("x"));
-                            let _ = handle.flush();
+
+                        // Handle TODO annotations
+                        if parser::is_todo_epilogue(&epilogue) {
+                            self.stats.record(TestCase {
+                                outcome: Outcome::Todo(epilogue.clone()),
+                                ..case(None)
+                            });
+                            human_print!("{}", Style::new().yellow().apply_to("-"));
                             continue;
                         }
-                    }
 
-                    // Inline assertion: execute and compare
-                    match stmt.next() {
-                        Err(err) => {
-                            let maybe_offset = line_col_to_offset(
-                                &content,
-                                step.reference.line_number(),
-                                step.reference.column_number(),
-                            );
+                        // Handle @snap directives
+                        match parse_snap_directive(&epilogue) {
+                            Ok(Some(snap_dir)) => {
+                                let before = snap_counts(&self.snap_state);
+                                handle_snap_assertion(
+                                    &mut self.snap_state,
+                                    &mut stmt,
+                                    &snap_dir.name,
+                                    &self.filestem,
+                                    &self.source_path,
+                                );
+                                let snap = snap_case(case(Some(epilogue.as_str())), before, &self.snap_state);
+                                self.stats.record(snap);
+                                let _ = std::io::stdout().flush();
+                                continue;
+                            }
+                            Ok(None) => {
+                                // Not a snap directive, fall through to inline assertion
+                            }
+                            Err(e) => {
+                                self.stats.record(TestCase {
+                                    outcome: Outcome::Failed(e.to_string()),
+                                    ..case(Some(epilogue.as_str()))
+                                });
+                                human_eprintln!("\n{}", e);
+                                human_print!("{}", Style::new().red().apply_to("x"));
+                                let _ = std::io::stdout().flush();
+                                continue;
+                            }
+                        }
 
-                            if let Some(expected) = epilogue.strip_prefix("error:").map(str::trim) {
-                                if expected == err.message {
-                                    stats.record_success();
-                                    print!("{}", Style::new().green().apply_to("."));
-                                } else {
-                                    stats.record_failure();
-                                    print!("{}", Style::new().red().apply_to("x"));
-                                    crate::errors::report_error(
-                                        &source_path.to_string_lossy(),
-                                        &content,
-                                        &err,
-                                        maybe_offset,
-                                    );
-                                    if args.verbose {
-                                        eprintln!(
-                                            "\nExpected error: '{}' got: '{}'",
-                                            expected, err.message
+                        // Structured assertions: @rows, @type, ~ matchers, @table
+                        let assertion = parse_assertion(&epilogue).and_then(|assertion| {
+                            resolve_table_rows(
+                                assertion,
+                                &self.content,
+                                &step.reference,
+                                step.epilogue.as_deref().unwrap_or_default(),
+                            )
+                        });
+                        match assertion {
+                            Ok(Some(assertion)) => {
+                                let expected = match &assertion {
+                                    Assertion::Table { rows, .. } => {
+                                        format!("{}\n{}", epilogue, value::format_table(rows))
+                                    }
+                                    _ => epilogue.clone(),
+                                };
+                                match check_assertion(&assertion, &mut stmt) {
+                                    Ok((actual, None)) => {
+                                        self.stats.record(TestCase {
+                                            actual: Some(actual),
+                                            ..case(Some(expected.as_str()))
+                                        });
+                                        human_print!("{}", Style::new().green().apply_to("."));
+                                    }
+                                    Ok((actual, Some(failure))) => {
+                                        self.stats.record(TestCase {
+                                            actual: Some(actual.clone()),
+                                            outcome: Outcome::Failed(failure),
+                                            ..case(Some(expected.as_str()))
+                                        });
+                                        human_print!("{}", Style::new().red().apply_to("x"));
+                                        report_mismatch(
+                                            &self.source_path.to_string_lossy(),
+                                            &self.content,
+                                            step.reference.line_number(),
+                                            step.reference.column_number(),
+                                            &expected,
+                                            &actual,
+                                        );
+                                    }
+                                    Err(err) => {
+                                        self.stats.record(TestCase {
+                                            actual: Some(format!("error: {}", err.message)),
+                                            outcome: Outcome::Failed(format!(
+                                                "execution error: {}",
+                                                err.message
+                                            )),
+                                            ..case(Some(expected.as_str()))
+                                        });
+                                        human_print!("{}", Style::new().red().apply_to("x"));
+                                        report::report_error(
+                                            &self.source_path.to_string_lossy(),
+                                            &self.content,
+                                            &err,
+                                            line_col_to_offset(
+                                                &self.content,
+                                                step.reference.line_number(),
+                                                step.reference.column_number(),
+                                            ),
                                         );
                                     }
                                 }
-                            } else {
-                                stats.record_failure();
-                                print!("{}", Style::new().red().apply_to("x"));
-                                crate::errors::report_error(
-                                    &source_path.to_string_lossy(),
-                                    &content,
-                                    &err,
-                                    maybe_offset,
-                                );
-                                if args.verbose {
-                                    eprintln!("\nExecution error: {}", err.message);
-                                }
+                                let _ = std::io::stdout().flush();
+                                continue;
+                            }
+                            Ok(None) => {
+                                // A plain value, compared below
+                            }
+                            Err(e) => {
+                                self.stats.record(TestCase {
+                                    outcome: Outcome::Failed(e.to_string()),
+                                    ..case(Some(epilogue.as_str()))
+                                });
+                                human_eprintln!("\n{}", e);
+                                human_print!("{}", Style::new().red().apply_to("x"));
+                                let _ = std::io::stdout().flush();
+                                continue;
                             }
                         }
-                        Ok(None) => {
-                            if epilogue == "[no results]" {
-                                stats.record_success();
-                                print!("{}", Style::new().green().apply_to("."));
-                            } else {
-                                stats.record_failure();
-                                print!("{}", Style::new().red().apply_to("x"));
-                                report_mismatch(
-                                    &source_path.to_string_lossy(),
-                                    &content,
+
+                        // Inline assertion: execute and compare
+                        match stmt.next() {
+                            Err(err) => {
+                                let maybe_offset = line_col_to_offset(
+                                    &self.content,
                                     step.reference.line_number(),
                                     step.reference.column_number(),
-                                    &epilogue,
-                                    "[no results]",
-                                );
-                            }
-                        }
-                        Ok(Some(row)) => {
-                            if row.len() > 1 && !warned_multi_column {
-                                warned_multi_column = true;
-                                eprintln!(
-                                    "note: {} returns {} columns; inline assertions compare only the first (further notes suppressed)",
-                                    step.reference,
-                                    row.len()
                                 );
+
+                                let actual = Some(format!("error: {}", err.message));
+                                if let Some(expected) = epilogue.strip_prefix("error:").map(str::trim) {
+                                    if expected == err.message {
+                                        self.stats.record(TestCase {
+                                            actual,
+                                            ..case(Some(epilogue.as_str()))
+                                        });
+                                        human_print!("{}", Style::new().green().apply_to("."));
+                                    } else {
+                                        self.stats.record(TestCase {
+                                            actual,
+                                            outcome: Outcome::Failed(
+                                                "error message does not match".to_string(),
+                                            ),
+                                            ..case(Some(epilogue.as_str()))
+                                        });
+                                        human_print!("{}", Style::new().red().apply_to("x"));
+                                        report::report_error(
+                                            &self.source_path.to_string_lossy(),
+                                            &self.content,
+                                            &err,
+                                            maybe_offset,
+                                        );
+                                        if self.args.verbose {
+                                            human_eprintln!(
+                                                "\nExpected error: '{}' got: '{}'",
+                                                expected, err.message
+                                            );
+                                        }
+                                    }
+                                } else {
+                                    self.stats.record(TestCase {
+                                        actual,
+                                        outcome: Outcome::Failed(format!(
+                                            "execution error: {}",
+                                            err.message
+                                        )),
+                                        ..case(Some(epilogue.as_str()))
+                                    });
+                                    human_print!("{}", Style::new().red().apply_to("x"));
+                                    report::report_error(
+                                        &self.source_path.to_string_lossy(),
+                                        &self.content,
+                                        &err,
+                                        maybe_offset,
+                                    );
+                                    if self.args.verbose {
+                                        human_eprintln!("\nExecution error: {}", err.message);
+                                    }
+                                }
                             }
-                            let v = match row.first() {
-                                Some(v) => v,
-                                None => {
-                                    stats.record_failure();
-                                    print!("{}", Style::new().red().apply_to("x"));
+                            Ok(None) => {
+                                let actual = Some("[no results]".to_string());
+                                if epilogue == "[no results]" {
+                                    self.stats.record(TestCase {
+                                        actual,
+                                        ..case(Some(epilogue.as_str()))
+                                    });
+                                    human_print!("{}", Style::new().green().apply_to("."));
+                                } else {
+                                    self.stats.record(TestCase {
+                                        actual,
+                                        outcome: Outcome::Failed("expected a row".to_string()),
+                                        ..case(Some(epilogue.as_str()))
+                                    });
+                                    human_print!("{}", Style::new().red().apply_to("x"));
                                     report_mismatch(
-                                        &source_path.to_string_lossy(),
-                                        &content,
+                                        &self.source_path.to_string_lossy(),
+                                        &self.content,
                                         step.reference.line_number(),
                                         step.reference.column_number(),
                                         &epilogue,
-                                        "<zero-column row>",
+                                        "[no results]",
                                     );
-                                    continue;
                                 }
-                            };
-
-                            let actual = value_to_string(v);
-                            drop(row);
-                            // Numeric fallback: `-- 1.0`, `-- 1`, and
-                            // `-- 1.0e+20` should match regardless of how
-                            // either side was formatted.
-                            if actual == epilogue
-                                || value::values_numerically_equal(&epilogue, &actual)
-                            {
-                                stats.record_success();
-                                print!("{}", Style::new().green().apply_to("."));
-                            } else {
-                                stats.record_failure();
-                                print!("{}", Style::new().red().apply_to("x"));
-                                report_mismatch(
-                                    &source_path.to_string_lossy(),
-                                    &content,
-                                    step.reference.line_number(),
-                                    step.reference.column_number(),
-                                    &epilogue,
-                                    &actual,
-                                );
                             }
-
-                            // Inline assertions only ever compare the first
-                            // row; warn once if the query has more. Probing
-                            // only read-only statements avoids driving
-                            // side-effecting DML/RETURNING further.
-                            if !warned_multi_row && stmt.readonly() {
-                                if let Ok(Some(_)) = stmt.next() {
-                                    warned_multi_row = true;
-                                    eprintln!(
-                                        "note: {} returns more than one row; inline assertions compare only the first (further notes suppressed)",
-                                        step.reference
+                            Ok(Some(row)) => {
+                                if row.len() > 1 && !self.warned_multi_column {
+                                    self.warned_multi_column = true;
+                                    human_eprintln!(
+                                        "note: {} returns {} columns; inline assertions compare only the first (further notes suppressed)",
+                                        step.reference,
+                                        row.len()
                                     );
                                 }
+                                let v = match row.first() {
+                                    Some(v) => v,
+                                    None => {
+                                        self.stats.record(TestCase {
+                                            actual: Some("<zero-column row>".to_string()),
+                                            outcome: Outcome::Failed(
+                                                "the row has no columns".to_string(),
+                                            ),
+                                            ..case(Some(epilogue.as_str()))
+                                        });
+                                        human_print!("{}", Style::new().red().apply_to("x"));
+                                        report_mismatch(
+                                            &self.source_path.to_string_lossy(),
+                                            &self.content,
+                                            step.reference.line_number(),
+                                            step.reference.column_number(),
+                                            &epilogue,
+                                            "<zero-column row>",
+                                        );
+                                        continue;
+                                    }
+                                };
+
+                                let actual = value_to_string(v);
+                                drop(row);
+                                // Numeric fallback: `-- 1.0`, `-- 1`, and
+                                // `-- 1.0e+20` should match regardless of how
+                                // either side was formatted.
+                                if actual == epilogue
+                                    || value::values_numerically_equal(&epilogue, &actual)
+                                {
+                                    self.stats.record(TestCase {
+                                        actual: Some(actual),
+                                        ..case(Some(epilogue.as_str()))
+                                    });
+                                    human_print!("{}", Style::new().green().apply_to("."));
+                                } else {
+                                    self.stats.record(TestCase {
+                                        actual: Some(actual.clone()),
+                                        outcome: Outcome::Failed(
+                                            "expected vs actual mismatch".to_string(),
+                                        ),
+                                        ..case(Some(epilogue.as_str()))
+                                    });
+                                    human_print!("{}", Style::new().red().apply_to("x"));
+                                    report_mismatch(
+                                        &self.source_path.to_string_lossy(),
+                                        &self.content,
+                                        step.reference.line_number(),
+                                        step.reference.column_number(),
+                                        &epilogue,
+                                        &actual,
+                                    );
+                                }
+
+                                // Inline assertions only ever compare the first
+                                // row; warn once if the query has more. Probing
+                                // only read-only statements avoids driving
+                                // side-effecting DML/RETURNING further.
+                                if !self.warned_multi_row && stmt.readonly() {
+                                    if let Ok(Some(_)) = stmt.next() {
+                                        self.warned_multi_row = true;
+                                        human_eprintln!(
+                                            "note: {} returns more than one row; inline assertions compare only the first (further notes suppressed)",
+                                            step.reference
+                                        );
+                                    }
+                                }
                             }
                         }
                     }
-                }
-            },
-        }
+                },
+            }
 
-        let _ = handle.flush();
+            let _ = std::io::stdout().flush();
+        }
+        false
     }
+}
 
-    // Handle orphaned snapshots — skipped on abort, where unreached @snap
-    // directives would be misreported (and in update mode deleted) as orphans
-    if !aborted {
-        handle_orphans(&mut snap_state, &filestem, source_path);
+/// Fill in a `@table`/`@unordered` assertion's rows from the comment lines
+/// after the statement's epilogue (`raw_epilogue`, as written).
+fn resolve_table_rows(
+    assertion: Option<Assertion>,
+    content: &str,
+    reference: &StepReference,
+    raw_epilogue: &str,
+) -> Result<Option<Assertion>, String> {
+    let Some(Assertion::Table { unordered, .. }) = assertion else {
+        return Ok(assertion);
+    };
+    // The epilogue follows the statement, so search from where it starts
+    let start = line_col_to_offset(content, reference.line_number(), reference.column_number())
+        .unwrap_or(0);
+    let end = content
+        .get(start..)
+        .and_then(|rest| rest.find(raw_epilogue))
+        .map(|i| start + i + raw_epilogue.len())
+        .ok_or_else(|| "couldn't find the @table rows in the test file".to_string())?;
+    let rows = parser::table_rows(content, end)?;
+    Ok(Some(Assertion::Table { rows, unordered }))
+}
+
+/// Run `stmt` for a structured assertion. Returns the rendered actual
+/// result, and why the assertion failed if it did.
+fn check_assertion(
+    assertion: &Assertion,
+    stmt: &mut Statement,
+) -> Result<(String, Option<String>), SQLiteError> {
+    match assertion {
+        Assertion::Rows(expected) => {
+            let mut count = 0;
+            while stmt.next()?.is_some() {
+                count += 1;
+            }
+            let failure = (count != *expected)
+                .then(|| format!("expected {} rows, got {}", expected, count));
+            return Ok((format!("@rows {}", count), failure));
+        }
+        Assertion::Table {
+            rows: expected,
+            unordered,
+        } => {
+            let mut rows = vec![];
+            while let Some(row) = stmt.next()? {
+                rows.push(row.iter().map(value_to_string).collect::<Vec<_>>());
+            }
+            let failure = (!value::tables_match(expected, &rows, *unordered))
+                .then(|| "rows do not match".to_string());
+            let actual = if rows.is_empty() {
+                "[no results]".to_string()
+            } else {
+                value::format_table(&rows)
+            };
+            return Ok((actual, failure));
+        }
+        _ => {}
     }
 
-    Ok((stats, snap_state))
+    // The rest check the first column of the first row
+    let Some(row) = stmt.next()? else {
+        return Ok(("[no results]".to_string(), Some("expected a row".to_string())));
+    };
+    let Some(v) = row.first() else {
+        return Ok((
+            "<zero-column row>".to_string(),
+            Some("the row has no columns".to_string()),
+        ));
+    };
+    let actual_type = value::value_type(v);
+    let failure = match assertion {
+        Assertion::Type(expected) => {
+            let failure = (actual_type != *expected)
+                .then(|| format!("expected type {}, got {}", expected, actual_type));
+            return Ok((format!("@type {}", actual_type), failure));
+        }
+        Assertion::Approx {
+            expected,
+            tolerance,
+        } => match value::value_as_f64(v) {
+            Some(n) if (n - expected).abs() <= *tolerance => None,
+            Some(_) => Some(format!("not within ±{} of {}", tolerance, expected)),
+            None => Some(format!("expected a number, got {}", actual_type)),
+        },
+        Assertion::Regex(regex) => match value::value_as_text(v) {
+            Some(text) if regex.is_match(&text) => None,
+            Some(_) => Some("text does not match the regex".to_string()),
+            None => Some(format!("expected text, got {}", actual_type)),
+        },
+        Assertion::Like(pattern) => match value::value_as_text(v) {
+            Some(text) if value::like_match(pattern, &text) => None,
+            Some(_) => Some("text does not match the LIKE pattern".to_string()),
+            None => Some(format!("expected text, got {}", actual_type)),
+        },
+        Assertion::Rows(_) | Assertion::Table { .. } => unreachable!("checked above"),
+    };
+    Ok((value_to_string(v), failure))
+}
+
+/// Whether `--filter` selects the assertion at `location` (`file:line:col`),
+/// matching either the location or its `@snap` name.
+fn selected(filter: Option<&TestFilter>, location: &str, epilogue: &str) -> bool {
+    let Some(filter) = filter else {
+        return true;
+    };
+    filter.matches(location)
+        || matches!(parse_snap_directive(epilogue), Ok(Some(snap)) if filter.matches(&snap.name))
 }
 
 /// Re-enqueue the remainder of a block after a recovered prepare error.
@@ -525,6 +1196,18 @@ fn resume_after_prepare_error(
     );
 }
 
+/// The text of the statement starting at `offset`, up to its `;` (or the
+/// end of the line), for naming a statement that failed to prepare.
+fn statement_sql(src: &str, offset: usize) -> String {
+    let rest = src.get(offset..).unwrap_or("");
+    let end = rest
+        .find(';')
+        .map(|i| i + 1)
+        .or_else(|| rest.find('\n'))
+        .unwrap_or(rest.len());
+    rest[..end].to_string()
+}
+
 /// Copy the contents of a fixture SQLite database into `dest` (the test's
 /// in-memory connection) via the backup API. The fixture is opened
 /// read-only and is never modified.
@@ -583,7 +1266,7 @@ fn seed_database(
 /// (missing `.run` file, unknown or failing procedure, `.load` error,
 /// unsupported command) is returned as `Err` and aborts the file, mirroring
 /// the setup-statement policy in `test_impl`. The caller records exactly one
-/// failure per `Err`, so nothing here calls `record_failure` for those paths.
+/// failure per `Err`, so nothing here records those paths.
 fn handle_dot_command(
     cmd: &DotCommand,
     rt: &mut Runtime,
@@ -591,7 +1274,7 @@ fn handle_dot_command(
 ) -> Result<(), String> {
     match cmd {
         DotCommand::Print(print_cmd) => {
-            print_cmd.execute();
+            human_println!("{}", print_cmd.message);
             Ok(())
         }
         DotCommand::Load(load_cmd) => load_cmd
@@ -611,7 +1294,7 @@ fn handle_dot_command(
                     Some(mut stmt) => loop {
                         match stmt.next() {
                             Ok(Some(row)) => match (row.first(), row.get(1)) {
-                                (Some(key), Some(value)) => println!(
+                                (Some(key), Some(value)) => human_println!(
                                     "{} = {}",
                                     value_to_string(key),
                                     value_to_string(value)
@@ -625,7 +1308,7 @@ fn handle_dot_command(
                         }
                     },
                     None => {
-                        println!("No parameters set");
+                        human_println!("No parameters set");
                         Ok(())
                     }
                 }
@@ -674,9 +1357,24 @@ fn handle_dot_command(
                         None => break,
                         Some(Ok(step)) => match step.result {
                             solite_core::StepResult::SqlStatement { stmt, .. } => {
+                                let started = Instant::now();
                                 if let Err(e) = stmt.execute() {
-                                    stats.record_failure();
-                                    eprintln!(
+                                    stats.record(TestCase {
+                                        actual: Some(format!("error: {}", e.message)),
+                                        outcome: Outcome::Failed(format!(
+                                            "statement in '{}' failed: {}",
+                                            run_cmd.file, e.message
+                                        )),
+                                        ..new_case(
+                                            step.reference.block_name(),
+                                            step.reference.line_number(),
+                                            step.reference.column_number(),
+                                            stmt.sql(),
+                                            None,
+                                            started,
+                                        )
+                                    });
+                                    human_eprintln!(
                                         "Error executing statement in '{}': {}",
                                         run_cmd.file, e.message
                                     );
@@ -713,6 +1411,10 @@ pub enum TestError {
     FileRead(String),
     /// Failed to seed from the `--database` fixture.
     Database(String),
+    /// Bad `--reporter`/`--output` options, or the report couldn't be written.
+    Report(String),
+    /// `solite-test.toml` couldn't be read or parsed.
+    Config(String),
     /// Tests failed.
     TestsFailed { failures: usize, todos: usize },
 }
@@ -722,6 +1424,8 @@ impl std::fmt::Display for TestError {
         match self {
             TestError::FileRead(msg) => write!(f, "Failed to read file: {}", msg),
             TestError::Database(msg) => write!(f, "{}", msg),
+            TestError::Report(msg) => write!(f, "{}", msg),
+            TestError::Config(msg) => write!(f, "{}", msg),
             TestError::TestsFailed { failures, todos } => {
                 write!(f, "{} failures; {} todos", failures, todos)
             }
@@ -786,6 +1490,10 @@ mod tests {
             verbose: false,
             update,
             review,
+            reporter: TestReporter::Human,
+            output: None,
+            jobs: 1,
+            filter: None,
         }
     }
 
@@ -800,6 +1508,10 @@ mod tests {
             verbose: false,
             update: false,
             review: false,
+            reporter: TestReporter::Human,
+            output: None,
+            jobs: 1,
+            filter: None,
         }
     }
 
@@ -905,6 +1617,213 @@ SELECT 1; -- 1
         cleanup(&tmp);
     }
 
+    #[test]
+    fn test_structured_assertions_pass() {
+        let tmp = temp_dir();
+        let file = write_sql(
+            &tmp,
+            "structured.sql",
+            "CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT);\n\
+             INSERT INTO users VALUES (1, 'user_1'), (2, 'user_22');\n\
+             SELECT * FROM users; -- @rows 2\n\
+             SELECT * FROM users WHERE 0; -- @rows 0\n\
+             SELECT id FROM users; -- @type integer\n\
+             SELECT 22.0 / 7; -- ~3.14 ±0.01\n\
+             SELECT 3.1416; -- ~3.14159 +/- 0.0001\n\
+             SELECT name FROM users; -- ~/^user_\\d+$/\n\
+             SELECT name FROM users; -- ~'USER%'\n\
+             SELECT id, name FROM users ORDER BY id; -- @table\n\
+             -- 1 | 'user_1'\n\
+             -- 2.0 | 'user_22'\n\
+             SELECT id, name FROM users; -- @unordered\n\
+             -- 2 | 'user_22'\n\
+             -- 1 | 'user_1'\n\
+             SELECT 1; -- 1\n",
+        );
+        let (stats, _) = run_file(&file, &default_args(file.clone())).unwrap();
+        assert_eq!((stats.successes, stats.failures), (10, 0));
+        cleanup(&tmp);
+    }
+
+    #[test]
+    fn test_structured_assertions_fail() {
+        let tmp = temp_dir();
+        for (i, sql) in [
+            "SELECT 1 UNION ALL SELECT 2; -- @rows 3\n",
+            "SELECT '1'; -- @type integer\n",
+            "SELECT 3.2; -- ~3.14 ±0.01\n",
+            "SELECT 3.15; -- ~3.14\n",
+            "SELECT 'x'; -- ~3.14\n",
+            "SELECT 'admin_1'; -- ~/^user_\\d+$/\n",
+            "SELECT 42; -- ~/42/\n",
+            "SELECT 'admin'; -- ~'user%'\n",
+            "SELECT 1 UNION ALL SELECT 2; -- @table\n-- 2\n-- 1\n",
+            "SELECT 1; -- @table\n-- 1\n-- 2\n",
+            "SELECT 1; -- @table\n\n-- 1\n",
+            "SELECT 1; -- @type number\n",
+            "SELECT * FROM (SELECT 1) WHERE 0; -- ~1\n",
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let file = write_sql(&tmp, &format!("fail{i}.sql"), sql);
+            let (stats, _) = run_file(&file, &default_args(file.clone())).unwrap();
+            assert_eq!((stats.successes, stats.failures), (0, 1), "{sql}");
+        }
+        cleanup(&tmp);
+    }
+
+    #[test]
+    fn test_sections_run_isolated() {
+        let tmp = temp_dir();
+        let file = write_sql(
+            &tmp,
+            "sections.sql",
+            "CREATE TABLE t(x);\n\
+             INSERT INTO t VALUES (0);\n\
+             -- @before-each\n\
+             INSERT INTO t VALUES (1);\n\
+             -- @test adds-a-row\n\
+             INSERT INTO t VALUES (2);\n\
+             SELECT count(*) FROM t; -- 3\n\
+             -- @test starts-clean\n\
+             SELECT count(*) FROM t; -- 2\n\
+             SELECT max(x) FROM t; -- 1\n",
+        );
+        let (stats, _) = run_file(&file, &default_args(file.clone())).unwrap();
+        assert_eq!((stats.successes, stats.failures), (3, 0));
+        let tests: Vec<_> = stats.cases.iter().map(|c| c.test.as_deref()).collect();
+        assert_eq!(
+            tests,
+            vec![Some("adds-a-row"), Some("starts-clean"), Some("starts-clean")]
+        );
+        // Line numbers still point into the file
+        assert_eq!(stats.cases[1].line, 9);
+        cleanup(&tmp);
+    }
+
+    #[test]
+    fn test_failing_test_only_aborts_itself() {
+        let tmp = temp_dir();
+        let file = write_sql(
+            &tmp,
+            "aborts.sql",
+            "CREATE TABLE t(x);\n\
+             -- @test broken\n\
+             INSERT INTO t VALUES (1);\n\
+             SELECT * FROM nope;\n\
+             SELECT 1; -- 1\n\
+             -- @test next\n\
+             SELECT count(*) FROM t; -- 0\n",
+        );
+        let (stats, _) = run_file(&file, &default_args(file.clone())).unwrap();
+        assert_eq!((stats.successes, stats.failures), (1, 1));
+        assert_eq!(stats.failed_tests(), vec![(stats.cases[0].file.as_str(), "broken")]);
+        cleanup(&tmp);
+    }
+
+    #[test]
+    fn test_fixture_runs_shared_file() {
+        let tmp = temp_dir();
+        fs::create_dir_all(tmp.join("fixtures")).unwrap();
+        write_sql(&tmp, "fixtures/rows.sql", "INSERT INTO t VALUES (1), (2);\n");
+        let file = write_sql(
+            &tmp,
+            "fixture.sql",
+            "CREATE TABLE t(x);\n\
+             -- @fixture fixtures/rows.sql\n\
+             -- @test sees-fixture-rows\n\
+             -- @fixture fixtures/rows.sql\n\
+             SELECT count(*) FROM t; -- 4\n\
+             -- @test fixture-rows-rolled-back\n\
+             SELECT count(*) FROM t; -- 2\n",
+        );
+        let (stats, _) = run_file(&file, &default_args(file.clone())).unwrap();
+        assert_eq!((stats.successes, stats.failures), (2, 0));
+        cleanup(&tmp);
+    }
+
+    #[test]
+    fn test_params_set_in_a_test_are_rolled_back() {
+        let tmp = temp_dir();
+        let file = write_sql(
+            &tmp,
+            "params.sql",
+            "-- @test sets\n\
+             .param set :x 1\n\
+             SELECT :x; -- '1'\n\
+             -- @test unset\n\
+             SELECT :x; -- NULL\n\
+             .param set :y 2\n\
+             SELECT :y; -- '2'\n",
+        );
+        let (stats, _) = run_file(&file, &default_args(file.clone())).unwrap();
+        assert_eq!((stats.successes, stats.failures), (3, 0));
+        cleanup(&tmp);
+    }
+
+    #[test]
+    fn test_commit_inside_a_test_aborts_the_file() {
+        let tmp = temp_dir();
+        let file = write_sql(
+            &tmp,
+            "commit.sql",
+            "CREATE TABLE t(x);\n\
+             -- @test commits\n\
+             COMMIT;\n\
+             -- @test never-runs\n\
+             SELECT 1; -- 1\n",
+        );
+        let (stats, _) = run_file(&file, &default_args(file.clone())).unwrap();
+        assert_eq!((stats.successes, stats.failures), (0, 1));
+        assert!(matches!(&stats.cases[0].outcome, Outcome::Failed(m) if m.contains("roll back")));
+        cleanup(&tmp);
+    }
+
+    #[test]
+    fn test_filter_selects_tests_by_name() {
+        let tmp = temp_dir();
+        let file = write_sql(
+            &tmp,
+            "by_name.sql",
+            "-- @test first\n\
+             SELECT 1; -- 999\n\
+             -- @test second\n\
+             SELECT 2; -- 2\n\
+             SELECT 3; -- 3\n",
+        );
+        let mut args = default_args(file.clone());
+        args.filter = Some(crate::cli::parse_test_filter("second").unwrap());
+        let (stats, _) = run_file(&file, &args).unwrap();
+        assert_eq!((stats.successes, stats.failures), (2, 0));
+        cleanup(&tmp);
+    }
+
+    #[test]
+    fn test_invalid_section_marker_fails_the_file() {
+        let tmp = temp_dir();
+        let file = write_sql(&tmp, "marker.sql", "SELECT 1; -- 1\n-- @test\nSELECT 2; -- 2\n");
+        let (stats, _) = run_file(&file, &default_args(file.clone())).unwrap();
+        assert_eq!((stats.successes, stats.failures), (0, 1));
+        assert_eq!(stats.cases[0].line, 2);
+        cleanup(&tmp);
+    }
+
+    #[test]
+    fn test_table_rows_are_not_statements() {
+        // The rows are comments, so the statement after them runs normally
+        let tmp = temp_dir();
+        let file = write_sql(
+            &tmp,
+            "table.sql",
+            "SELECT 1 AS a, NULL AS b; -- @table\n-- 1 | NULL\nSELECT 2; -- 2\n",
+        );
+        let (stats, _) = run_file(&file, &default_args(file.clone())).unwrap();
+        assert_eq!((stats.successes, stats.failures), (2, 0));
+        assert_eq!(stats.cases[0].expected.as_deref(), Some("@table\n1 | NULL"));
+        cleanup(&tmp);
+    }
+
     #[test]
     fn test_inline_assertion_string_value() {
         let tmp = temp_dir();
@@ -1847,6 +2766,49 @@ SELECT 1; -- @snap keep
         cleanup(&tmp);
     }
 
+    #[test]
+    fn test_directory_argument_is_recursive() {
+        let tmp = temp_dir();
+        write_sql(&tmp, "a.sql", "SELECT 1; -- 1\n");
+        fs::create_dir_all(tmp.join("nested/deeper")).unwrap();
+        write_sql(&tmp, "nested/deeper/b.sql", "SELECT 2; -- 999\n");
+        match test_impl(multi_args(vec![tmp.clone()], false, false)) {
+            Err(TestError::TestsFailed { failures, .. }) => assert_eq!(failures, 1),
+            other => panic!("Expected TestsFailed, got {:?}", other),
+        }
+        cleanup(&tmp);
+    }
+
+    #[test]
+    fn test_filter_runs_only_matching_assertions() {
+        let tmp = temp_dir();
+        let file = write_sql(
+            &tmp,
+            "filtered.sql",
+            "CREATE TABLE t(x);\n\
+             INSERT INTO t VALUES (1) RETURNING x; -- 999\n\
+             SELECT count(*) FROM t; -- 1\n\
+             SELECT nope(); -- TODO later\n\
+             SELECT * FROM t; -- @snap rows\n",
+        );
+
+        // Line 3 only: the unselected INSERT still ran
+        let mut args = default_args(file.clone());
+        args.filter = Some(crate::cli::parse_test_filter("filtered.sql:3:").unwrap());
+        let (stats, _) = run_file(&file, &args).unwrap();
+        assert_eq!((stats.successes, stats.failures), (1, 0));
+        assert!(stats.todos.is_empty());
+        assert_eq!(stats.cases.len(), 1);
+
+        // By snapshot name; the snapshot is created, nothing else recorded
+        let mut args = make_args(file.clone(), true, false);
+        args.filter = Some(crate::cli::parse_test_filter("/^row/").unwrap());
+        let (stats, snap_state) = run_file(&file, &args).unwrap();
+        assert_eq!(stats.cases.len(), 1);
+        assert_eq!(snap_state.new, 1);
+        cleanup(&tmp);
+    }
+
     #[test]
     fn test_multi_file_snapshots_resolve_per_file() {
         let tmp = temp_dir();
@@ -2093,4 +3055,135 @@ SELECT * FROM t; -- @snap data
 
         cleanup(&tmp);
     }
+
+    // ===== Parallel runs =====
+
+    #[test]
+    fn test_jobs_aggregate_like_a_sequential_run() {
+        let tmp = temp_dir();
+        let files: Vec<PathBuf> = (0..8)
+            .map(|i| {
+                let expected = if i == 5 { 0 } else { i };
+                write_sql(
+                    &tmp,
+                    &format!("f{i}.sql"),
+                    &format!("CREATE TABLE t(x);\nSELECT {i}; -- {expected}\nSELECT 1; -- 1\n"),
+                )
+            })
+            .collect();
+        let output = tmp.join("results.json");
+        let args = TestArgs {
+            jobs: 4,
+            reporter: TestReporter::Json,
+            output: Some(output.clone()),
+            ..multi_args(files, false, false)
+        };
+        match test_impl(args) {
+            Err(TestError::TestsFailed { failures, todos }) => {
+                assert_eq!((failures, todos), (1, 0));
+            }
+            other => panic!("expected one failure, got {:?}", other),
+        }
+
+        // Cases stay in file order
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+        let files: Vec<String> = report["cases"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|case| case["file"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(files.len(), 16);
+        let mut sorted = files.clone();
+        sorted.sort();
+        assert_eq!(files, sorted);
+        assert_eq!(report["summary"]["passed"], 15);
+
+        cleanup(&tmp);
+    }
+
+    #[test]
+    fn test_run_parallel_reports_in_file_order() {
+        let tmp = temp_dir();
+        let files: Vec<PathBuf> = (0..6)
+            .map(|i| write_sql(&tmp, &format!("f{i}.sql"), "SELECT 1; -- 1\n"))
+            .collect();
+        let mut order = Vec::new();
+        run_parallel(&files, &multi_args(files.clone(), false, false), 3, |file, result, _| {
+            assert!(result.is_ok());
+            order.push(file.to_path_buf());
+        });
+        assert_eq!(order, files);
+        cleanup(&tmp);
+    }
+
+    // ===== Reporters =====
+
+    fn report_args(file: PathBuf, reporter: TestReporter, output: PathBuf) -> TestArgs {
+        TestArgs {
+            reporter,
+            output: Some(output),
+            ..default_args(file)
+        }
+    }
+
+    #[test]
+    fn test_json_report_has_a_case_per_assertion() {
+        let tmp = temp_dir();
+        let file = write_sql(&tmp, "report.sql", "\
+CREATE TABLE t(x);
+SELECT 1 + 1; -- 2
+SELECT 'a';   -- 'b'
+SELECT slow(); -- TODO speed this up
+SELECT * FROM t; -- @snap empty
+");
+        let output = tmp.join("results.json");
+        let result = test_impl(report_args(file, TestReporter::Json, output.clone()));
+        assert!(result.is_err());
+
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+        let cases = report["cases"].as_array().unwrap();
+        assert_eq!(cases.len(), 4);
+        assert_eq!(cases[0]["line"], 2);
+        assert_eq!(cases[0]["column"], 1);
+        assert_eq!(cases[0]["outcome"], "passed");
+        assert_eq!(cases[1]["outcome"], "failed");
+        assert_eq!(cases[1]["expected"], "'b'");
+        assert_eq!(cases[1]["actual"], "'a'");
+        assert_eq!(cases[2]["outcome"], "todo");
+        assert_eq!(cases[3]["snapshot"], "rejected");
+        assert_eq!(report["summary"]["passed"], 1);
+
+        cleanup(&tmp);
+    }
+
+    #[test]
+    fn test_junit_report_includes_setup_failures() {
+        let tmp = temp_dir();
+        let file = write_sql(&tmp, "setup.sql", "\
+SELECT 1; -- 1
+CREATE TABLE t(x CHECK (x > 0));
+INSERT INTO t VALUES (0);
+");
+        let output = tmp.join("results.xml");
+        let result = test_impl(report_args(file, TestReporter::Junit, output.clone()));
+        assert!(result.is_err());
+
+        let xml = fs::read_to_string(&output).unwrap();
+        assert!(xml.contains(r#"tests="2" failures="1""#), "{xml}");
+        assert!(xml.contains("setup statement failed"), "{xml}");
+
+        cleanup(&tmp);
+    }
+
+    #[test]
+    fn test_output_requires_a_reporter() {
+        let tmp = temp_dir();
+        let file = write_sql(&tmp, "t.sql", "SELECT 1; -- 1\n");
+        let args = report_args(file, TestReporter::Human, tmp.join("out"));
+        assert!(matches!(test_impl(args), Err(TestError::Report(_))));
+        cleanup(&tmp);
+    }
 }
@@ -122,6 +122,353 @@ pub fn parse_snap_directive(epilogue: &str) -> Result<Option<SnapDirective>, Str
     }))
 }
 
+/// A structured assertion from an epilogue comment, beyond an exact value.
+#[derive(Debug)]
+pub enum Assertion {
+    /// `@rows N`: the statement returns exactly N rows.
+    Rows(usize),
+    /// `@type <name>`: the first value's storage class, as `typeof()` names it.
+    Type(&'static str),
+    /// `~3.14 ±0.01`: a number within a tolerance. Without one, the
+    /// tolerance is half a unit in the last given digit (`~3.14` is ±0.005).
+    Approx { expected: f64, tolerance: f64 },
+    /// `~/regex/`: a text value matching a regex.
+    Regex(regex::Regex),
+    /// `~'pattern'`: a text value matching a `LIKE` pattern.
+    Like(String),
+    /// `@table` or `@unordered`: every row, listed on the `--` lines that
+    /// follow the statement (filled in by [`table_rows`]).
+    Table {
+        rows: Vec<Vec<String>>,
+        unordered: bool,
+    },
+}
+
+/// The `typeof()` names `@type` accepts.
+const TYPE_NAMES: [&str; 5] = ["null", "integer", "real", "text", "blob"];
+
+/// Strip a `@directive` from an epilogue, requiring a word boundary after
+/// it (the same rule as `@snap`). Returns the trimmed remainder.
+fn strip_directive<'a>(epilogue: &'a str, directive: &str) -> Option<&'a str> {
+    let rest = epilogue.strip_prefix(directive)?;
+    if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+        Some(rest.trim())
+    } else {
+        None
+    }
+}
+
+/// Try to parse a structured assertion from a stripped epilogue string.
+///
+/// Returns `None` for plain expected values (and `[no results]`,
+/// `error: ...`, `TODO` and `@snap`, which are handled elsewhere), or an
+/// error message if the epilogue uses assertion syntax incorrectly.
+pub fn parse_assertion(epilogue: &str) -> Result<Option<Assertion>, String> {
+    let epilogue = epilogue.trim();
+    if let Some(rest) = strip_directive(epilogue, "@rows") {
+        return rest
+            .parse()
+            .map(|n| Some(Assertion::Rows(n)))
+            .map_err(|_| "@rows requires a row count (e.g. @rows 3)".to_string());
+    }
+    if let Some(rest) = strip_directive(epilogue, "@type") {
+        let name = rest.to_ascii_lowercase();
+        return match TYPE_NAMES.iter().find(|t| **t == name) {
+            Some(t) => Ok(Some(Assertion::Type(*t))),
+            None => Err(format!(
+                "@type '{}' is invalid: expected one of {}",
+                rest,
+                TYPE_NAMES.join(", ")
+            )),
+        };
+    }
+    for (directive, unordered) in [("@table", false), ("@unordered", true)] {
+        if let Some(rest) = strip_directive(epilogue, directive) {
+            if !rest.is_empty() {
+                return Err(format!(
+                    "{directive} takes no arguments; list the rows on the following `--` lines"
+                ));
+            }
+            return Ok(Some(Assertion::Table {
+                rows: vec![],
+                unordered,
+            }));
+        }
+    }
+    let Some(rest) = epilogue.strip_prefix('~') else {
+        return Ok(None);
+    };
+    let rest = rest.trim();
+    if let Some(pattern) = rest
+        .strip_prefix('/')
+        .and_then(|r| r.strip_suffix('/'))
+    {
+        return regex::Regex::new(pattern)
+            .map(|regex| Some(Assertion::Regex(regex)))
+            .map_err(|e| format!("invalid regex in '{}': {}", epilogue, e));
+    }
+    if rest.starts_with('\'') {
+        return match parse_sql_string(rest) {
+            Some((pattern, "")) => Ok(Some(Assertion::Like(pattern))),
+            _ => Err(format!(
+                "invalid LIKE pattern in '{}': expected a single-quoted string",
+                epilogue
+            )),
+        };
+    }
+    parse_approx(rest)
+        .map(|(expected, tolerance)| Some(Assertion::Approx { expected, tolerance }))
+        .ok_or_else(|| {
+            format!(
+                "unrecognized assertion '{}': expected ~<number> [±<tolerance>], ~/regex/ or ~'like pattern'",
+                epilogue
+            )
+        })
+}
+
+/// Parse `3.14`, `3.14 ±0.01` or `3.14 +/- 0.01`.
+fn parse_approx(s: &str) -> Option<(f64, f64)> {
+    let (value, tolerance) = match s.split_once('±').or_else(|| s.split_once("+/-")) {
+        Some((value, tolerance)) => (value.trim(), Some(tolerance.trim())),
+        None => (s, None),
+    };
+    let expected: f64 = value.parse().ok()?;
+    let tolerance = match tolerance {
+        Some(t) => t.parse::<f64>().ok().filter(|t| *t >= 0.0)?,
+        None => {
+            // Half a unit in the last digit: 3.14 → 0.005, 2.5e3 → 50
+            let (mantissa, exponent) = match value.split_once(['e', 'E']) {
+                Some((m, e)) => (m, e.parse::<i32>().ok()?),
+                None => (value, 0),
+            };
+            let decimals = mantissa.split_once('.').map_or(0, |(_, d)| d.len()) as i32;
+            0.5 * 10f64.powi(exponent - decimals)
+        }
+    };
+    expected.is_finite().then_some((expected, tolerance))
+}
+
+/// Parse a leading single-quoted SQL string (`''` escapes a quote).
+/// Returns its contents and the rest of `s`.
+fn parse_sql_string(s: &str) -> Option<(String, &str)> {
+    let mut chars = s.strip_prefix('\'')?.char_indices().peekable();
+    let mut contents = String::new();
+    while let Some((i, c)) = chars.next() {
+        if c == '\'' {
+            if chars.peek().is_some_and(|(_, next)| *next == '\'') {
+                chars.next();
+            } else {
+                return Some((contents, s[i + 2..].trim()));
+            }
+        }
+        contents.push(c);
+    }
+    None
+}
+
+/// Read the rows of a `@table`/`@unordered` literal: the `--` comment
+/// lines directly after `offset` (the end of the epilogue), up to the first
+/// line that isn't one. Cells are separated by `|` and written the way
+/// inline assertions are (`1 | 'alice' | NULL`).
+pub fn table_rows(content: &str, offset: usize) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = vec![];
+    for line in content.get(offset..).unwrap_or("").lines().skip(1) {
+        let Some(row) = line.trim().strip_prefix("--") else {
+            break;
+        };
+        rows.push(split_row(row.trim()).ok_or_else(|| {
+            format!("unterminated string in table row '{}'", row.trim())
+        })?);
+    }
+    if rows.is_empty() {
+        return Err(
+            "@table/@unordered needs rows on the following `--` lines (use [no results] for none)"
+                .to_string(),
+        );
+    }
+    Ok(rows)
+}
+
+/// Split a table row on `|`s outside of single-quoted strings.
+fn split_row(row: &str) -> Option<Vec<String>> {
+    let mut cells = vec![];
+    let mut cell = String::new();
+    let mut quoted = false;
+    for c in row.chars() {
+        match c {
+            // `''` inside a string toggles twice, so escapes work out
+            '\'' => quoted = !quoted,
+            '|' if !quoted => {
+                cells.push(cell.trim().to_string());
+                cell.clear();
+                continue;
+            }
+            _ => {}
+        }
+        cell.push(c);
+    }
+    if quoted {
+        return None;
+    }
+    cells.push(cell.trim().to_string());
+    Some(cells)
+}
+
+/// A `-- @test <name>` section of a test file.
+#[derive(Debug)]
+pub struct TestSection {
+    pub name: String,
+    /// 1-based line of the `-- @test` marker.
+    pub line: usize,
+    /// The `-- @before-each` blocks above the test, in file order.
+    pub before_each: Vec<String>,
+    pub body: String,
+}
+
+/// A test file split into its sections. Every block keeps its position:
+/// lines outside it are blanked, so step references and diagnostics
+/// still point into the original file.
+#[derive(Debug)]
+pub struct TestSections {
+    /// Everything before the first marker; runs once, outside any test.
+    pub setup: String,
+    pub tests: Vec<TestSection>,
+}
+
+/// Where a line of SQL starts: in code, or inside a comment or quoted
+/// token that an earlier line left open.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Lexical {
+    Code,
+    BlockComment,
+    /// Inside a string or quoted identifier, closed by this byte.
+    Quoted(u8),
+}
+
+/// Scan one line and return the state the next line starts in. Dot
+/// commands are taken whole; a doubled quote re-enters the same token
+/// on the next byte, so escapes need no special case.
+fn scan_line(line: &str, mut state: Lexical) -> Lexical {
+    if state == Lexical::Code && line.trim_start().starts_with('.') {
+        return state;
+    }
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match state {
+            Lexical::Code => match bytes[i] {
+                b'-' if bytes.get(i + 1) == Some(&b'-') => return state,
+                b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                    state = Lexical::BlockComment;
+                    i += 1;
+                }
+                q @ (b'\'' | b'"' | b'`') => state = Lexical::Quoted(q),
+                b'[' => state = Lexical::Quoted(b']'),
+                _ => {}
+            },
+            Lexical::BlockComment => {
+                if bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/') {
+                    state = Lexical::Code;
+                    i += 1;
+                }
+            }
+            Lexical::Quoted(q) => {
+                if bytes[i] == q {
+                    state = Lexical::Code;
+                }
+            }
+        }
+        i += 1;
+    }
+    state
+}
+
+/// Split a test file on `-- @test <name>` and `-- @before-each` marker
+/// lines, rewriting `-- @fixture <file>` lines to `.run <file>`. Markers
+/// inside block comments or multi-line strings are left as text. A file
+/// without markers is a single setup block. Errors carry the 1-based line.
+pub fn split_sections(content: &str) -> Result<TestSections, (usize, String)> {
+    enum Block {
+        Setup,
+        BeforeEach,
+        Test(String, usize),
+    }
+
+    fn finish(
+        block: Block,
+        text: String,
+        setup: &mut String,
+        before_each: &mut Vec<String>,
+        tests: &mut Vec<TestSection>,
+    ) {
+        match block {
+            Block::Setup => *setup = text,
+            Block::BeforeEach => before_each.push(text),
+            Block::Test(name, line) => tests.push(TestSection {
+                name,
+                line,
+                before_each: before_each.clone(),
+                body: text,
+            }),
+        }
+    }
+
+    let mut setup = String::new();
+    let mut before_each: Vec<String> = vec![];
+    let mut tests: Vec<TestSection> = vec![];
+    let mut block = Block::Setup;
+    let mut text = String::new();
+    let mut lexical = Lexical::Code;
+
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        let newline = &line[line.trim_end_matches(['\n', '\r']).len()..];
+        let outside = lexical == Lexical::Code;
+        lexical = scan_line(line, lexical);
+        let directive = line
+            .trim()
+            .strip_prefix("--")
+            .filter(|_| outside)
+            .map(str::trim);
+        if let Some(name) = directive.and_then(|d| strip_directive(d, "@test")) {
+            if name.is_empty() {
+                return Err((
+                    i + 1,
+                    "@test requires a name (e.g. @test inserts-a-row)".to_string(),
+                ));
+            }
+            if tests.iter().any(|t| t.name == name)
+                || matches!(&block, Block::Test(current, _) if current == name)
+            {
+                return Err((i + 1, format!("duplicate test name '{}'", name)));
+            }
+            let previous = std::mem::replace(&mut block, Block::Test(name.to_string(), i + 1));
+            finish(previous, std::mem::take(&mut text), &mut setup, &mut before_each, &mut tests);
+        } else if let Some(rest) = directive.and_then(|d| strip_directive(d, "@before-each")) {
+            if !rest.is_empty() {
+                return Err((i + 1, "@before-each takes no arguments".to_string()));
+            }
+            let previous = std::mem::replace(&mut block, Block::BeforeEach);
+            finish(previous, std::mem::take(&mut text), &mut setup, &mut before_each, &mut tests);
+        } else if let Some(file) = directive.and_then(|d| strip_directive(d, "@fixture")) {
+            if file.is_empty() {
+                return Err((
+                    i + 1,
+                    "@fixture requires a file (e.g. @fixture fixtures/users.sql)".to_string(),
+                ));
+            }
+            text.push_str(&format!(".run {}{}", file, newline));
+            continue;
+        } else {
+            text.push_str(line);
+            continue;
+        }
+        // A new block starts: blank everything above it
+        text = "\n".repeat(i + 1);
+    }
+    finish(block, text, &mut setup, &mut before_each, &mut tests);
+    Ok(TestSections { setup, tests })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -422,4 +769,147 @@ mod tests {
         let epilogue = parse_epilogue_comment(raw);
         assert!(parse_snap_directive(&epilogue).unwrap().is_none());
     }
+
+    // --- structured assertion tests ---
+
+    #[test]
+    fn test_parse_assertion_directives() {
+        assert!(matches!(parse_assertion("@rows 3"), Ok(Some(Assertion::Rows(3)))));
+        assert!(matches!(parse_assertion("@rows 0"), Ok(Some(Assertion::Rows(0)))));
+        assert!(parse_assertion("@rows").is_err());
+        assert!(parse_assertion("@rows three").is_err());
+        assert!(matches!(
+            parse_assertion("@type INTEGER"),
+            Ok(Some(Assertion::Type("integer")))
+        ));
+        assert!(parse_assertion("@type int").is_err());
+        assert!(matches!(
+            parse_assertion("@unordered"),
+            Ok(Some(Assertion::Table { unordered: true, .. }))
+        ));
+        assert!(parse_assertion("@table 1 | 2").is_err());
+        // No word boundary: a plain value, as before
+        assert!(parse_assertion("@rowsy").unwrap().is_none());
+        assert!(parse_assertion("42").unwrap().is_none());
+        assert!(parse_assertion("'~'").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_assertion_approx() {
+        let approx = |s: &str| match parse_assertion(s) {
+            Ok(Some(Assertion::Approx { expected, tolerance })) => (expected, tolerance),
+            other => panic!("{s}: {other:?}"),
+        };
+        assert_eq!(approx("~3.14 ±0.01"), (3.14, 0.01));
+        assert_eq!(approx("~3.14 +/- 0.5"), (3.14, 0.5));
+        let (_, tolerance) = approx("~3.14");
+        assert!((tolerance - 0.005).abs() < 1e-12);
+        let (_, tolerance) = approx("~2.5e3");
+        assert!((tolerance - 50.0).abs() < 1e-9);
+        assert!(parse_assertion("~3.14 ±-1").is_err());
+        assert!(parse_assertion("~pi").is_err());
+    }
+
+    #[test]
+    fn test_parse_assertion_text_matchers() {
+        match parse_assertion(r"~/^user_\d+$/") {
+            Ok(Some(Assertion::Regex(regex))) => assert!(regex.is_match("user_42")),
+            other => panic!("{other:?}"),
+        }
+        assert!(parse_assertion("~/(/").is_err());
+        match parse_assertion("~'it''s%'") {
+            Ok(Some(Assertion::Like(pattern))) => assert_eq!(pattern, "it's%"),
+            other => panic!("{other:?}"),
+        }
+        assert!(parse_assertion("~'open").is_err());
+        assert!(parse_assertion("~'a' trailing").is_err());
+    }
+
+    #[test]
+    fn test_table_rows() {
+        let content = "SELECT * FROM t; -- @table\n-- 1 | 'a|b'\n  --2|NULL\n\n-- not a row\n";
+        let offset = content.find('\n').unwrap();
+        assert_eq!(
+            table_rows(content, offset).unwrap(),
+            vec![vec!["1", "'a|b'"], vec!["2", "NULL"]]
+        );
+        assert_eq!(split_row("'it''s' | x").unwrap(), vec!["'it''s'", "x"]);
+        assert!(table_rows("SELECT 1; -- @table\n-- 'open\n", 19).is_err());
+        assert!(table_rows("SELECT 1; -- @table\nSELECT 2;\n", 19).is_err());
+    }
+
+    // --- test section tests ---
+
+    #[test]
+    fn test_split_sections() {
+        let content = "CREATE TABLE t(x);\n\
+                       -- @fixture fixtures/rows.sql\n\
+                       -- @before-each\n\
+                       INSERT INTO t VALUES (1);\n\
+                       -- @test first\n\
+                       SELECT count(*) FROM t; -- 1\n\
+                       --   @test   second one\n\
+                       SELECT 2; -- 2\n";
+        let sections = split_sections(content).unwrap();
+        assert_eq!(sections.setup, "CREATE TABLE t(x);\n.run fixtures/rows.sql\n");
+        assert_eq!(sections.tests.len(), 2);
+
+        let first = &sections.tests[0];
+        assert_eq!((first.name.as_str(), first.line), ("first", 5));
+        assert_eq!(first.before_each, vec!["\n\n\nINSERT INTO t VALUES (1);\n"]);
+        assert_eq!(first.body, "\n\n\n\n\nSELECT count(*) FROM t; -- 1\n");
+        // Line numbers survive the split
+        assert_eq!(line_col_to_offset(&first.body, 6, 1), Some(5));
+
+        let second = &sections.tests[1];
+        assert_eq!((second.name.as_str(), second.line), ("second one", 7));
+        assert_eq!(second.before_each.len(), 1);
+    }
+
+    #[test]
+    fn test_split_sections_without_markers() {
+        let content = "SELECT 1; -- 1\n-- @testing is just a comment\n";
+        let sections = split_sections(content).unwrap();
+        assert_eq!(sections.setup, content);
+        assert!(sections.tests.is_empty());
+    }
+
+    #[test]
+    fn test_split_sections_skips_comments_and_strings() {
+        let content = "/*\n\
+                       -- @test in-comment\n\
+                       */\n\
+                       SELECT 'first line\n\
+                       -- @test in-string\n\
+                       it''s still a string';\n\
+                       SELECT \"a /* b\"; -- @test not-a-marker\n\
+                       -- @test real\n\
+                       SELECT 1;\n";
+        let sections = split_sections(content).unwrap();
+        assert_eq!(sections.tests.len(), 1);
+        assert_eq!((sections.tests[0].name.as_str(), sections.tests[0].line), ("real", 8));
+        assert!(sections.setup.contains("-- @test in-string"));
+    }
+
+    #[test]
+    fn test_scan_line() {
+        assert_eq!(scan_line("SELECT 1; /* open\n", Lexical::Code), Lexical::BlockComment);
+        assert_eq!(scan_line("still */ SELECT 'x\n", Lexical::BlockComment), Lexical::Quoted(b'\''));
+        assert_eq!(scan_line("SELECT 'it''s';\n", Lexical::Code), Lexical::Code);
+        assert_eq!(scan_line("SELECT [a\n", Lexical::Code), Lexical::Quoted(b']'));
+        assert_eq!(scan_line("SELECT 1; -- it's /*\n", Lexical::Code), Lexical::Code);
+        assert_eq!(scan_line(".print it's\n", Lexical::Code), Lexical::Code);
+    }
+
+    #[test]
+    fn test_split_sections_errors() {
+        assert_eq!(split_sections("SELECT 1;\n-- @test\n").unwrap_err().0, 2);
+        assert!(split_sections("-- @test a\n-- @test a\n")
+            .unwrap_err()
+            .1
+            .contains("duplicate"));
+        assert!(split_sections("-- @test a\n-- @test b\n-- @test a\n").is_err());
+        assert!(split_sections("-- @before-each now\n").is_err());
+        assert!(split_sections("-- @fixture\n").is_err());
+    }
 }
@@ -1,11 +1,26 @@
 //! Test result reporting and diagnostics.
+//!
+//! Besides the human-readable output, every assertion is recorded as a
+//! [`TestCase`] so the run can be written out as JUnit XML ([`junit_report`])
+//! or JSON ([`json_report`]) for CI.
+//!
+//! All human-readable output goes through [`write_human`] and
+//! [`emit_diagnostic`], so a file run on a `--jobs` worker can [`capture`]
+//! it and have it replayed in file order.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 use codespan_reporting::diagnostic::{Diagnostic, Label};
 use codespan_reporting::files::SimpleFiles;
 use codespan_reporting::term::{
     self,
-    termcolor::{ColorChoice, StandardStream},
+    termcolor::{Buffer, ColorChoice, StandardStream, WriteColor},
 };
+use solite_core::sqlite::SQLiteError;
 
 /// Report a test assertion mismatch with source location.
 ///
@@ -29,9 +44,15 @@ pub fn report_mismatch(
         .with_labels(vec![Label::primary(id, start..end)
             .with_message(format!("expected: {}\nactual: {}", expected, actual))]);
 
-    let writer = StandardStream::stderr(ColorChoice::Auto);
     let config = term::Config::default();
-    let _ = term::emit(&mut writer.lock(), &config, &files, &diagnostic);
+    emit_diagnostic(|writer| {
+        let _ = term::emit(writer, &config, &files, &diagnostic);
+    });
+}
+
+/// [`crate::errors::report_error`], through [`emit_diagnostic`].
+pub fn report_error(file_name: &str, sql: &str, error: &SQLiteError, offset: Option<usize>) {
+    emit_diagnostic(|writer| crate::errors::report_error_to(writer, file_name, sql, error, offset));
 }
 
 /// Compute the byte span for a given 1-based line number.
@@ -52,6 +73,175 @@ fn compute_line_span(content: &str, line: usize) -> (usize, usize) {
     }
 }
 
+/// Whether the human-readable output goes to stderr, because a machine
+/// report is being written to stdout.
+static HUMAN_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+pub fn set_human_to_stderr(value: bool) {
+    HUMAN_TO_STDERR.store(value, Ordering::Relaxed);
+}
+
+pub fn human_to_stderr() -> bool {
+    HUMAN_TO_STDERR.load(Ordering::Relaxed)
+}
+
+/// Which stream a piece of human-readable output belongs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    /// Progress and summaries: stdout, unless [`human_to_stderr`].
+    Out,
+    /// Diagnostics.
+    Err,
+}
+
+thread_local! {
+    /// Output captured on this thread by [`capture`].
+    static CAPTURED: RefCell<Option<Captured>> = const { RefCell::new(None) };
+}
+
+/// Human-readable output captured while running one test file.
+#[derive(Debug, Default)]
+pub struct Captured {
+    chunks: Vec<(Stream, Vec<u8>)>,
+}
+
+impl Captured {
+    fn push(&mut self, stream: Stream, bytes: &[u8]) {
+        match self.chunks.last_mut() {
+            Some((last, chunk)) if *last == stream => chunk.extend_from_slice(bytes),
+            _ => self.chunks.push((stream, bytes.to_vec())),
+        }
+    }
+
+    /// Write the output to its streams, in the order it was produced.
+    pub fn replay(self) {
+        for (stream, bytes) in self.chunks {
+            let _ = match stream {
+                Stream::Out if !human_to_stderr() => std::io::stdout().write_all(&bytes),
+                _ => std::io::stderr().write_all(&bytes),
+            };
+        }
+        let _ = std::io::stdout().flush();
+    }
+}
+
+/// Run `f`, capturing the human-readable output it writes on this thread.
+pub fn capture<T>(f: impl FnOnce() -> T) -> (T, Captured) {
+    CAPTURED.with(|captured| *captured.borrow_mut() = Some(Captured::default()));
+    let result = f();
+    let captured = CAPTURED.with(|captured| captured.borrow_mut().take());
+    (result, captured.unwrap_or_default())
+}
+
+/// Append to this thread's capture, if it has one. Returns whether it did.
+fn write_captured(stream: Stream, bytes: &[u8]) -> bool {
+    CAPTURED.with(|captured| match captured.borrow_mut().as_mut() {
+        Some(captured) => {
+            captured.push(stream, bytes);
+            true
+        }
+        None => false,
+    })
+}
+
+/// Write human-readable output to `stream`, or to this thread's capture.
+/// Used by the `human_print!` family of macros.
+pub fn write_human(stream: Stream, args: fmt::Arguments) {
+    if write_captured(stream, fmt::format(args).as_bytes()) {
+        return;
+    }
+    match stream {
+        Stream::Out if !human_to_stderr() => print!("{}", args),
+        _ => eprint!("{}", args),
+    }
+}
+
+/// Emit a codespan diagnostic on stderr, or into this thread's capture
+/// (colored if stderr would be).
+pub fn emit_diagnostic(emit: impl FnOnce(&mut dyn WriteColor)) {
+    let capturing = CAPTURED.with(|captured| captured.borrow().is_some());
+    if capturing {
+        let mut buffer = if console::colors_enabled_stderr() {
+            Buffer::ansi()
+        } else {
+            Buffer::no_color()
+        };
+        emit(&mut buffer);
+        write_captured(Stream::Err, buffer.as_slice());
+    } else {
+        let writer = StandardStream::stderr(ColorChoice::Auto);
+        emit(&mut writer.lock());
+    }
+}
+
+/// How a test case ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    /// Failed, with a one-line reason.
+    Failed(String),
+    /// A `-- TODO` annotation, with its text.
+    Todo(String),
+}
+
+/// How a `@snap` assertion's output compared against its snapshot file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapOutcome {
+    Matched,
+    Created,
+    Updated,
+    Rejected,
+}
+
+impl SnapOutcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            SnapOutcome::Matched => "matched",
+            SnapOutcome::Created => "created",
+            SnapOutcome::Updated => "updated",
+            SnapOutcome::Rejected => "rejected",
+        }
+    }
+}
+
+/// The result of one assertion (or of a failed setup step), for the
+/// machine-readable reports.
+#[derive(Debug, Clone)]
+pub struct TestCase {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    /// The statement's SQL, or a description of the failed step.
+    pub sql: String,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+    pub outcome: Outcome,
+    /// Set for `@snap` assertions.
+    pub snapshot: Option<SnapOutcome>,
+    /// The `-- @test` section the case ran in, if any.
+    pub test: Option<String>,
+    pub duration: Duration,
+}
+
+impl TestCase {
+    /// The case's name in reports: its test, position and first line of SQL.
+    fn name(&self) -> String {
+        let sql = self.sql.trim().lines().next().unwrap_or("");
+        match &self.test {
+            Some(test) => format!("{}: {}:{} {}", test, self.line, self.column, sql),
+            None => format!("{}:{} {}", self.line, self.column, sql),
+        }
+    }
+
+    /// The JUnit classname: the file, and the test within it.
+    fn classname(&self) -> String {
+        match &self.test {
+            Some(test) => format!("{}::{}", self.file, test),
+            None => self.file.clone(),
+        }
+    }
+}
+
 /// Test result statistics.
 #[derive(Debug, Default)]
 pub struct TestStats {
@@ -61,6 +251,8 @@ pub struct TestStats {
     pub failures: usize,
     /// TODO items: (file, line, column, message).
     pub todos: Vec<(String, usize, usize, String)>,
+    /// Every recorded case, in run order.
+    pub cases: Vec<TestCase>,
 }
 
 impl TestStats {
@@ -84,6 +276,21 @@ impl TestStats {
         self.todos.push((file, line, col, msg));
     }
 
+    /// Record a case, counting it by outcome. `@snap` cases are counted by
+    /// [`SnapState`](super::snap::SnapState) instead, so are only kept.
+    pub fn record(&mut self, case: TestCase) {
+        if case.snapshot.is_none() {
+            match &case.outcome {
+                Outcome::Passed => self.record_success(),
+                Outcome::Failed(_) => self.record_failure(),
+                Outcome::Todo(msg) => {
+                    self.record_todo(case.file.clone(), case.line, case.column, msg.clone())
+                }
+            }
+        }
+        self.cases.push(case);
+    }
+
     /// Check if there were any failures or TODOs.
     pub fn has_failures(&self) -> bool {
         self.failures > 0 || !self.todos.is_empty()
@@ -91,17 +298,186 @@ impl TestStats {
 
     /// Print the final summary.
     pub fn print_summary(&self) {
-        println!();
-        println!("{} successes", self.successes);
-        println!("{} failures", self.failures);
+        human_println!();
+        human_println!("{} successes", self.successes);
+        human_println!("{} failures", self.failures);
 
         if !self.todos.is_empty() {
-            println!("{} TODO(s):", self.todos.len());
+            human_println!("{} TODO(s):", self.todos.len());
             for (file, line, col, msg) in &self.todos {
-                println!(" - {}:{}:{} {}", file, line, col, msg);
+                human_println!(" - {}:{}:{} {}", file, line, col, msg);
+            }
+        }
+
+        let failed = self.failed_tests();
+        if !failed.is_empty() {
+            human_println!("{} failed test(s):", failed.len());
+            for (file, test) in failed {
+                human_println!(" - {} {}", file, test);
+            }
+        }
+    }
+
+    /// The `-- @test` sections with a failed case, as (file, test), in order.
+    pub fn failed_tests(&self) -> Vec<(&str, &str)> {
+        let mut failed: Vec<(&str, &str)> = vec![];
+        for case in &self.cases {
+            if let (Some(test), Outcome::Failed(_)) = (&case.test, &case.outcome) {
+                let key = (case.file.as_str(), test.as_str());
+                if !failed.contains(&key) {
+                    failed.push(key);
+                }
+            }
+        }
+        failed
+    }
+}
+
+/// Escape text for an XML attribute or element.
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than tab/newline aren't allowed in XML 1.0
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => escaped.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The cases grouped by file, keeping run order.
+fn by_file(cases: &[TestCase]) -> Vec<(&str, Vec<&TestCase>)> {
+    let mut files: Vec<(&str, Vec<&TestCase>)> = Vec::new();
+    for case in cases {
+        match files.iter_mut().find(|(file, _)| *file == case.file) {
+            Some((_, file_cases)) => file_cases.push(case),
+            None => files.push((&case.file, vec![case])),
+        }
+    }
+    files
+}
+
+/// A JUnit XML report: one `<testsuite>` per file and one `<testcase>` per
+/// case. TODOs fail the run, so they're reported as failures too.
+pub fn junit_report(cases: &[TestCase]) -> String {
+    fn failed(case: &TestCase) -> bool {
+        !matches!(case.outcome, Outcome::Passed)
+    }
+    let total: Duration = cases.iter().map(|c| c.duration).sum();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"solite test\" tests=\"{}\" failures=\"{}\" time=\"{:.6}\">\n",
+        cases.len(),
+        cases.iter().filter(|case| failed(case)).count(),
+        total.as_secs_f64(),
+    ));
+    for (file, file_cases) in by_file(cases) {
+        let time: Duration = file_cases.iter().map(|c| c.duration).sum();
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.6}\">\n",
+            xml_escape(file),
+            file_cases.len(),
+            file_cases.iter().filter(|case| failed(case)).count(),
+            time.as_secs_f64(),
+        ));
+        for case in file_cases {
+            xml.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{}\" file=\"{}\" line=\"{}\" time=\"{:.6}\"",
+                xml_escape(&case.name()),
+                xml_escape(&case.classname()),
+                xml_escape(file),
+                case.line,
+                case.duration.as_secs_f64(),
+            ));
+            let (kind, message) = match &case.outcome {
+                Outcome::Passed if case.snapshot.is_none() => {
+                    xml.push_str("/>\n");
+                    continue;
+                }
+                Outcome::Passed => (None, ""),
+                Outcome::Failed(message) => (Some("failure"), message.as_str()),
+                Outcome::Todo(message) => (Some("todo"), message.as_str()),
+            };
+            xml.push_str(">\n");
+            if let Some(snapshot) = case.snapshot {
+                xml.push_str(&format!(
+                    "      <properties><property name=\"snapshot\" value=\"{}\"/></properties>\n",
+                    snapshot.as_str()
+                ));
+            }
+            if let Some(kind) = kind {
+                let mut detail = String::new();
+                if let Some(expected) = &case.expected {
+                    detail.push_str(&format!("expected: {}\n", expected));
+                }
+                if let Some(actual) = &case.actual {
+                    detail.push_str(&format!("actual: {}\n", actual));
+                }
+                xml.push_str(&format!(
+                    "      <failure type=\"{}\" message=\"{}\">{}{}:{}:{}</failure>\n",
+                    kind,
+                    xml_escape(message),
+                    xml_escape(&detail),
+                    xml_escape(file),
+                    case.line,
+                    case.column,
+                ));
             }
+            xml.push_str("    </testcase>\n");
         }
+        xml.push_str("  </testsuite>\n");
     }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// A JSON report: a summary and every case.
+pub fn json_report(cases: &[TestCase]) -> String {
+    let count = |f: fn(&Outcome) -> bool| cases.iter().filter(|c| f(&c.outcome)).count();
+    let total: Duration = cases.iter().map(|c| c.duration).sum();
+    let cases: Vec<_> = cases
+        .iter()
+        .map(|case| {
+            let (outcome, message) = match &case.outcome {
+                Outcome::Passed => ("passed", None),
+                Outcome::Failed(message) => ("failed", Some(message)),
+                Outcome::Todo(message) => ("todo", Some(message)),
+            };
+            serde_json::json!({
+                "file": case.file,
+                "line": case.line,
+                "column": case.column,
+                "test": case.test,
+                "sql": case.sql,
+                "outcome": outcome,
+                "message": message,
+                "expected": case.expected,
+                "actual": case.actual,
+                "snapshot": case.snapshot.map(SnapOutcome::as_str),
+                "duration_ms": case.duration.as_secs_f64() * 1000.0,
+            })
+        })
+        .collect();
+    let report = serde_json::json!({
+        "summary": {
+            "tests": cases.len(),
+            "passed": count(|o| matches!(o, Outcome::Passed)),
+            "failed": count(|o| matches!(o, Outcome::Failed(_))),
+            "todo": count(|o| matches!(o, Outcome::Todo(_))),
+            "duration_ms": total.as_secs_f64() * 1000.0,
+        },
+        "cases": cases,
+    });
+    let mut json = serde_json::to_string_pretty(&report).expect("JSON values always serialize");
+    json.push('\n');
+    json
 }
 
 #[cfg(test)]
@@ -168,6 +544,121 @@ mod tests {
         assert!(stats.has_failures());
     }
 
+    fn case(line: usize, outcome: Outcome) -> TestCase {
+        TestCase {
+            file: "t.sql".to_string(),
+            line,
+            column: 1,
+            sql: "SELECT 1 + 1;".to_string(),
+            expected: Some("2".to_string()),
+            actual: Some("3".to_string()),
+            outcome,
+            snapshot: None,
+            test: None,
+            duration: Duration::from_millis(2),
+        }
+    }
+
+    #[test]
+    fn test_record_counts_by_outcome() {
+        let mut stats = TestStats::new();
+        stats.record(case(1, Outcome::Passed));
+        stats.record(case(2, Outcome::Failed("mismatch".to_string())));
+        stats.record(case(3, Outcome::Todo("TODO".to_string())));
+        stats.record(TestCase {
+            snapshot: Some(SnapOutcome::Rejected),
+            ..case(4, Outcome::Failed("snapshot".to_string()))
+        });
+        assert_eq!((stats.successes, stats.failures, stats.todos.len()), (1, 1, 1));
+        assert_eq!(stats.cases.len(), 4);
+    }
+
+    #[test]
+    fn test_junit_report() {
+        let xml = junit_report(&[
+            case(1, Outcome::Passed),
+            case(2, Outcome::Failed("expected 2, got <3>".to_string())),
+        ]);
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.contains(r#"<testsuite name="t.sql" tests="2" failures="1""#));
+        assert!(xml.contains(r#"<testcase name="1:1 SELECT 1 + 1;" classname="t.sql" file="t.sql" line="1" time="0.002000"/>"#));
+        assert!(xml.contains(r#"<failure type="failure" message="expected 2, got &lt;3&gt;">expected: 2"#));
+        assert!(xml.contains("actual: 3\nt.sql:2:1</failure>"));
+    }
+
+    #[test]
+    fn test_reports_name_the_test_section() {
+        let in_test = |line, outcome| TestCase {
+            test: Some("adds-a-row".to_string()),
+            ..case(line, outcome)
+        };
+        let cases = [
+            in_test(3, Outcome::Failed("mismatch".to_string())),
+            in_test(4, Outcome::Failed("mismatch".to_string())),
+            case(5, Outcome::Failed("mismatch".to_string())),
+        ];
+        let xml = junit_report(&cases);
+        assert!(xml.contains(r#"<testcase name="adds-a-row: 3:1 SELECT 1 + 1;" classname="t.sql::adds-a-row""#));
+        let value: serde_json::Value = serde_json::from_str(&json_report(&cases)).unwrap();
+        assert_eq!(value["cases"][0]["test"], "adds-a-row");
+        assert_eq!(value["cases"][2]["test"], serde_json::Value::Null);
+
+        let mut stats = TestStats::new();
+        for case in cases {
+            stats.record(case);
+        }
+        assert_eq!(stats.failed_tests(), vec![("t.sql", "adds-a-row")]);
+    }
+
+    #[test]
+    fn test_json_report() {
+        let json = json_report(&[
+            case(1, Outcome::Passed),
+            TestCase {
+                snapshot: Some(SnapOutcome::Created),
+                ..case(2, Outcome::Passed)
+            },
+        ]);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["summary"]["tests"], 2);
+        assert_eq!(value["summary"]["passed"], 2);
+        assert_eq!(value["cases"][0]["line"], 1);
+        assert_eq!(value["cases"][0]["expected"], "2");
+        assert_eq!(value["cases"][0]["snapshot"], serde_json::Value::Null);
+        assert_eq!(value["cases"][1]["snapshot"], "created");
+    }
+
+    #[test]
+    fn test_capture_keeps_order_per_stream() {
+        let ((), captured) = capture(|| {
+            write_human(Stream::Out, format_args!("a"));
+            write_human(Stream::Out, format_args!("b"));
+            write_human(Stream::Err, format_args!("oops"));
+            write_human(Stream::Out, format_args!("c"));
+        });
+        let chunks: Vec<_> = captured
+            .chunks
+            .iter()
+            .map(|(stream, bytes)| (*stream, String::from_utf8_lossy(bytes).into_owned()))
+            .collect();
+        assert_eq!(
+            chunks,
+            vec![
+                (Stream::Out, "ab".to_string()),
+                (Stream::Err, "oops".to_string()),
+                (Stream::Out, "c".to_string()),
+            ]
+        );
+        // Nothing is captured once `capture` returns
+        assert!(!write_captured(Stream::Out, b"d"));
+    }
+
+    #[test]
+    fn test_xml_escape() {
+        assert_eq!(xml_escape(r#"<a href="x">&'"#), "&lt;a href=&quot;x&quot;&gt;&amp;&apos;");
+        assert_eq!(xml_escape("tab\tok\u{1}"), "tab\tok\\u{1}");
+    }
+
     #[test]
     fn test_stats_no_failures() {
         let mut stats = TestStats::new();
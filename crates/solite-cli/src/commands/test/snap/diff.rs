@@ -11,11 +11,11 @@ pub fn print_diff(original: &str, new: &str) {
         .diff_lines(original, new);
 
     let width = console::Term::stdout().size().1 as usize;
-    println!("────────────┬{:─^1$}", "", width.saturating_sub(13));
+    human_println!("────────────┬{:─^1$}", "", width.saturating_sub(13));
 
     for (idx, group) in diff.grouped_ops(4).iter().enumerate() {
         if idx > 0 {
-            println!("┈┈┈┈┈┈┈┈┈┈┈┈┼{:┈^1$}", "", width.saturating_sub(13));
+            human_println!("┈┈┈┈┈┈┈┈┈┈┈┈┼{:┈^1$}", "", width.saturating_sub(13));
         }
         for op in group {
             for change in diff.iter_inline_changes(op) {
@@ -25,7 +25,7 @@ pub fn print_diff(original: &str, new: &str) {
                             .new_index()
                             .map(|i| i.to_string())
                             .unwrap_or_default();
-                        print!(
+                        human_print!(
                             "{:>5} {:>5} │{}",
                             "",
                             console::style(line_num).cyan().bold().dim(),
@@ -33,9 +33,9 @@ pub fn print_diff(original: &str, new: &str) {
                         );
                         for &(emphasized, text) in change.values() {
                             if emphasized {
-                                print!("{}", console::style(text).green().underlined());
+                                human_print!("{}", console::style(text).green().underlined());
                             } else {
-                                print!("{}", console::style(text).green());
+                                human_print!("{}", console::style(text).green());
                             }
                         }
                     }
@@ -44,7 +44,7 @@ pub fn print_diff(original: &str, new: &str) {
                             .old_index()
                             .map(|i| i.to_string())
                             .unwrap_or_default();
-                        print!(
+                        human_print!(
                             "{:>5} {:>5} │{}",
                             console::style(line_num).cyan().dim(),
                             "",
@@ -52,9 +52,9 @@ pub fn print_diff(original: &str, new: &str) {
                         );
                         for &(emphasized, text) in change.values() {
                             if emphasized {
-                                print!("{}", console::style(text).red().underlined());
+                                human_print!("{}", console::style(text).red().underlined());
                             } else {
-                                print!("{}", console::style(text).red());
+                                human_print!("{}", console::style(text).red());
                             }
                         }
                     }
@@ -67,30 +67,30 @@ pub fn print_diff(original: &str, new: &str) {
                             .new_index()
                             .map(|i| i.to_string())
                             .unwrap_or_default();
-                        print!(
+                        human_print!(
                             "{:>5} {:>5} │ ",
                             console::style(&old_num).cyan().dim(),
                             console::style(&new_num).cyan().dim().bold(),
                         );
                         for &(_, text) in change.values() {
-                            print!("{}", console::style(text).dim());
+                            human_print!("{}", console::style(text).dim());
                         }
                     }
                 }
             }
         }
     }
-    println!("────────────┴{:─^1$}", "", width.saturating_sub(13));
+    human_println!("────────────┴{:─^1$}", "", width.saturating_sub(13));
 }
 
 /// Print the decision prompt for accepting/rejecting a snapshot.
 pub fn print_decision() {
-    println!(
+    human_println!(
         "  {} accept     {}",
         console::style("a").green().bold(),
         console::style("keep the new snapshot").dim()
     );
-    println!(
+    human_println!(
         "  {} reject     {}",
         console::style("r").red().bold(),
         console::style("reject the new snapshot").dim()
@@ -42,14 +42,14 @@ pub fn generate_snapshot_contents(source: String, stmt: &mut Statement) -> Optio
     )
     .is_err()
     {
-        eprintln!("Warning: Failed to write snapshot header");
+        human_eprintln!("Warning: Failed to write snapshot header");
         return None;
     }
 
     let columns = match stmt.column_names() {
         Ok(cols) => cols,
         Err(e) => {
-            eprintln!("Warning: Failed to get column names: {}", e);
+            human_eprintln!("Warning: Failed to get column names: {}", e);
             return None;
         }
     };
@@ -83,37 +83,37 @@ impl SnapState {
         if !self.has_snapshots() && self.removed == 0 {
             return;
         }
-        println!();
+        human_println!();
         if self.matches > 0 {
-            println!(
+            human_println!(
                 "{:>4} snapshot{} passed",
                 self.matches,
                 if self.matches == 1 { "" } else { "s" }
             );
         }
         if self.new > 0 {
-            println!(
+            human_println!(
                 "{:>4} snapshot{} created",
                 self.new,
                 if self.new == 1 { "" } else { "s" }
             );
         }
         if self.updated > 0 {
-            println!(
+            human_println!(
                 "{:>4} snapshot{} updated",
                 self.updated,
                 if self.updated == 1 { "" } else { "s" }
             );
         }
         if self.rejected > 0 {
-            println!(
+            human_println!(
                 "{:>4} snapshot{} rejected",
                 self.rejected,
                 if self.rejected == 1 { "" } else { "s" }
             );
         }
         if self.removed > 0 {
-            println!(
+            human_println!(
                 "{:>4} snapshot{} removed",
                 self.removed,
                 if self.removed == 1 { "" } else { "s" }
@@ -135,9 +135,9 @@ pub fn handle_snap_assertion(
 ) {
     // Ensure snapshots directory exists
     if let Err(e) = state.ensure_dir() {
-        eprintln!("{}", e);
+        human_eprintln!("{}", e);
         state.rejected += 1;
-        print!("{}", Style::new().red().apply_to("x"));
+        human_print!("{}", Style::new().red().apply_to("x"));
         let _ = std::io::stdout().flush();
         return;
     }
@@ -159,7 +159,7 @@ pub fn handle_snap_assertion(
             // Statement produced no snappable output (e.g. CREATE TABLE)
             // Still counts as a pass - the statement executed successfully
             state.matches += 1;
-            print!("{}", Style::new().green().apply_to("."));
+            human_print!("{}", Style::new().green().apply_to("."));
             let _ = std::io::stdout().flush();
             return;
         }
@@ -170,13 +170,13 @@ pub fn handle_snap_assertion(
         let original = match std::fs::read_to_string(&snapshot_path) {
             Ok(s) => s.replace("\r\n", "\n"),
             Err(e) => {
-                eprintln!(
+                human_eprintln!(
                     "Failed to read snapshot {}: {}",
                     snapshot_path.display(),
                     e
                 );
                 state.rejected += 1;
-                print!("{}", Style::new().red().apply_to("x"));
+                human_print!("{}", Style::new().red().apply_to("x"));
                 let _ = std::io::stdout().flush();
                 return;
             }
@@ -184,7 +184,7 @@ pub fn handle_snap_assertion(
 
         if original == snapshot_contents {
             state.matches += 1;
-            print!("{}", Style::new().green().apply_to("."));
+            human_print!("{}", Style::new().green().apply_to("."));
         } else {
             handle_mismatch(state, &snapshot_path, &original, &snapshot_contents);
         }
@@ -204,23 +204,23 @@ fn handle_mismatch(
 ) {
     match state.mode {
         SnapMode::Default => {
-            println!("\nSnapshot mismatch: {}", snapshot_path.display());
+            human_println!("\nSnapshot mismatch: {}", snapshot_path.display());
             print_diff(original, new_contents);
             state.rejected += 1;
-            print!("{}", Style::new().red().apply_to("x"));
+            human_print!("{}", Style::new().red().apply_to("x"));
         }
         SnapMode::Update => {
             if write_snapshot(snapshot_path, new_contents).is_ok() {
-                println!("\nUpdated: {}", snapshot_path.display());
+                human_println!("\nUpdated: {}", snapshot_path.display());
                 state.updated += 1;
-                print!("{}", Style::new().yellow().apply_to("u"));
+                human_print!("{}", Style::new().yellow().apply_to("u"));
             } else {
                 state.rejected += 1;
-                print!("{}", Style::new().red().apply_to("x"));
+                human_print!("{}", Style::new().red().apply_to("x"));
             }
         }
         SnapMode::Review => {
-            println!("\nSnapshot changed: {}", snapshot_path.display());
+            human_println!("\nSnapshot changed: {}", snapshot_path.display());
             print_diff(original, new_contents);
             print_decision();
 
@@ -230,17 +230,17 @@ fn handle_mismatch(
                     Ok(Key::Char('a') | Key::Char('A') | Key::Enter) => {
                         if write_snapshot(snapshot_path, new_contents).is_ok() {
                             state.updated += 1;
-                            print!("{}", Style::new().yellow().apply_to("u"));
+                            human_print!("{}", Style::new().yellow().apply_to("u"));
                         } else {
                             state.rejected += 1;
-                            print!("{}", Style::new().red().apply_to("x"));
+                            human_print!("{}", Style::new().red().apply_to("x"));
                         }
                         break;
                     }
                     // reject on a read error too (e.g. no tty) — never loop
                     Ok(Key::Char('r') | Key::Char('R')) | Err(_) => {
                         state.rejected += 1;
-                        print!("{}", Style::new().red().apply_to("x"));
+                        human_print!("{}", Style::new().red().apply_to("x"));
                         break;
                     }
                     Ok(_) => { /* unrecognized key: keep waiting */ }
@@ -254,23 +254,23 @@ fn handle_mismatch(
 fn handle_new_snapshot(state: &mut SnapState, snapshot_path: &Path, contents: &str) {
     match state.mode {
         SnapMode::Default => {
-            println!("\nNew snapshot: {}", snapshot_path.display());
+            human_println!("\nNew snapshot: {}", snapshot_path.display());
             print_diff("", contents);
             state.rejected += 1;
-            print!("{}", Style::new().red().apply_to("x"));
+            human_print!("{}", Style::new().red().apply_to("x"));
         }
         SnapMode::Update => {
             if write_snapshot(snapshot_path, contents).is_ok() {
-                println!("\nCreated: {}", snapshot_path.display());
+                human_println!("\nCreated: {}", snapshot_path.display());
                 state.new += 1;
-                print!("{}", Style::new().green().apply_to("+"));
+                human_print!("{}", Style::new().green().apply_to("+"));
             } else {
                 state.rejected += 1;
-                print!("{}", Style::new().red().apply_to("x"));
+                human_print!("{}", Style::new().red().apply_to("x"));
             }
         }
         SnapMode::Review => {
-            println!("\nNew snapshot: {}", snapshot_path.display());
+            human_println!("\nNew snapshot: {}", snapshot_path.display());
             print_diff("", contents);
             print_decision();
 
@@ -280,17 +280,17 @@ fn handle_new_snapshot(state: &mut SnapState, snapshot_path: &Path, contents: &s
                     Ok(Key::Char('a') | Key::Char('A') | Key::Enter) => {
                         if write_snapshot(snapshot_path, contents).is_ok() {
                             state.new += 1;
-                            print!("{}", Style::new().green().apply_to("+"));
+                            human_print!("{}", Style::new().green().apply_to("+"));
                         } else {
                             state.rejected += 1;
-                            print!("{}", Style::new().red().apply_to("x"));
+                            human_print!("{}", Style::new().red().apply_to("x"));
                         }
                         break;
                     }
                     // reject on a read error too (e.g. no tty) — never loop
                     Ok(Key::Char('r') | Key::Char('R')) | Err(_) => {
                         state.rejected += 1;
-                        print!("{}", Style::new().red().apply_to("x"));
+                        human_print!("{}", Style::new().red().apply_to("x"));
                         break;
                     }
                     Ok(_) => { /* unrecognized key: keep waiting */ }
@@ -339,7 +339,7 @@ pub fn handle_orphans(state: &mut SnapState, filestem: &str, source_path: &Path)
                     }
                     Some(_) => { /* owned by another test file: leave alone */ }
                     None => {
-                        eprintln!(
+                        human_eprintln!(
                             "Warning: snapshot {} has no readable 'Source:' header; leaving it in place",
                             path.display()
                         );
@@ -355,13 +355,13 @@ pub fn handle_orphans(state: &mut SnapState, filestem: &str, source_path: &Path)
         let path = state.snapshots_dir.join(orphan);
         match state.mode {
             SnapMode::Default => {
-                eprintln!("Warning: orphaned snapshot: {}", path.display());
+                human_eprintln!("Warning: orphaned snapshot: {}", path.display());
             }
             SnapMode::Update => {
                 if let Err(e) = std::fs::remove_file(&path) {
-                    eprintln!("Failed to remove orphan {}: {}", path.display(), e);
+                    human_eprintln!("Failed to remove orphan {}: {}", path.display(), e);
                 } else {
-                    println!("Removed orphan: {}", path.display());
+                    human_println!("Removed orphan: {}", path.display());
                     state.removed += 1;
                 }
             }
@@ -371,14 +371,14 @@ pub fn handle_orphans(state: &mut SnapState, filestem: &str, source_path: &Path)
                     Err(_) => continue,
                 };
                 print_diff(&contents, "");
-                println!("Remove {}? [y/n]", path.display());
+                human_println!("Remove {}? [y/n]", path.display());
 
                 let term = Term::stdout();
                 loop {
                     match term.read_key() {
                         Ok(Key::Char('y') | Key::Char('Y')) => {
                             if let Err(e) = std::fs::remove_file(&path) {
-                                eprintln!("Failed to remove {}: {}", path.display(), e);
+                                human_eprintln!("Failed to remove {}: {}", path.display(), e);
                             } else {
                                 state.removed += 1;
                             }
@@ -386,7 +386,7 @@ pub fn handle_orphans(state: &mut SnapState, filestem: &str, source_path: &Path)
                         }
                         // keep on a read error too (e.g. no tty) — never loop
                         Ok(Key::Char('n') | Key::Char('N')) | Err(_) => {
-                            println!("Keeping {}", path.display());
+                            human_println!("Keeping {}", path.display());
                             break;
                         }
                         Ok(_) => { /* unrecognized key: keep waiting */ }
@@ -420,13 +420,13 @@ pub(crate) fn write_snapshot(path: &Path, contents: &str) -> Result<(), ()> {
     match file {
         Ok(mut f) => {
             if let Err(e) = f.write_all(contents.as_bytes()) {
-                eprintln!("Failed to write snapshot {}: {}", path.display(), e);
+                human_eprintln!("Failed to write snapshot {}: {}", path.display(), e);
                 return Err(());
             }
             Ok(())
         }
         Err(e) => {
-            eprintln!("Failed to open snapshot {}: {}", path.display(), e);
+            human_eprintln!("Failed to open snapshot {}: {}", path.display(), e);
             Err(())
         }
     }
@@ -107,6 +107,102 @@ pub fn values_numerically_equal(expected: &str, actual: &str) -> bool {
     }
 }
 
+/// The storage class of a value, as SQLite's `typeof()` names it.
+pub fn value_type(v: &ValueRefX) -> &'static str {
+    match &v.value {
+        ValueRefXValue::Null => "null",
+        ValueRefXValue::Int(_) => "integer",
+        ValueRefXValue::Double(_) => "real",
+        ValueRefXValue::Text(_) => "text",
+        ValueRefXValue::Blob(_) => "blob",
+    }
+}
+
+/// A value as a number, if it's an INTEGER or REAL.
+pub fn value_as_f64(v: &ValueRefX) -> Option<f64> {
+    match &v.value {
+        ValueRefXValue::Int(i) => Some(*i as f64),
+        ValueRefXValue::Double(d) => Some(*d),
+        _ => None,
+    }
+}
+
+/// A value's text, without quoting, if it's TEXT.
+pub fn value_as_text(v: &ValueRefX) -> Option<String> {
+    match &v.value {
+        ValueRefXValue::Text(b) => Some(String::from_utf8_lossy(b).into_owned()),
+        _ => None,
+    }
+}
+
+/// Whether a rendered actual value matches an expected one, exactly or
+/// numerically (see [`values_numerically_equal`]).
+pub fn values_match(expected: &str, actual: &str) -> bool {
+    expected == actual || values_numerically_equal(expected, actual)
+}
+
+/// Compare the rows of a `@table` literal against the rendered actual
+/// rows: in order, or as a multiset when `unordered`.
+pub fn tables_match(expected: &[Vec<String>], actual: &[Vec<String>], unordered: bool) -> bool {
+    let rows_match = |e: &Vec<String>, a: &Vec<String>| {
+        e.len() == a.len() && e.iter().zip(a).all(|(e, a)| values_match(e, a))
+    };
+    if expected.len() != actual.len() {
+        return false;
+    }
+    if !unordered {
+        return expected.iter().zip(actual).all(|(e, a)| rows_match(e, a));
+    }
+    // Matching is an equivalence, so pairing greedily can't miss a match
+    let mut remaining: Vec<&Vec<String>> = actual.iter().collect();
+    expected.iter().all(|e| {
+        match remaining.iter().position(|a| rows_match(e, a)) {
+            Some(i) => {
+                remaining.swap_remove(i);
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+/// Render rows the way `@table` literals are written, one `a | b` per line.
+pub fn format_table(rows: &[Vec<String>]) -> String {
+    rows.iter()
+        .map(|row| row.join(" | "))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// SQLite's `LIKE`: `%` matches any run of characters, `_` any one
+/// character, and ASCII letters match case-insensitively.
+pub fn like_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // The last `%` seen, and where in the text it's currently matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if pattern.get(p) == Some(&'%') {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if pattern
+            .get(p)
+            .is_some_and(|&c| c == '_' || c.eq_ignore_ascii_case(&text[t]))
+        {
+            p += 1;
+            t += 1;
+        } else if let Some((star, matched)) = backtrack {
+            backtrack = Some((star, matched + 1));
+            p = star + 1;
+            t = matched + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '%')
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -163,4 +259,36 @@ mod tests {
         // NaN never equals itself
         assert!(!values_numerically_equal("NaN", "NaN"));
     }
+
+    #[test]
+    fn test_like_match() {
+        assert!(like_match("user_%", "user_42"));
+        assert!(like_match("USER%", "user"));
+        assert!(like_match("%b%", "abc"));
+        assert!(like_match("a_c", "abc"));
+        assert!(like_match("%", ""));
+        assert!(!like_match("a_c", "ac"));
+        assert!(!like_match("user_%", "admin_1"));
+        assert!(like_match("%a%a", "banana"));
+        assert!(!like_match("%a%b", "banana"));
+    }
+
+    #[test]
+    fn test_tables_match() {
+        let rows = |rows: &[&[&str]]| -> Vec<Vec<String>> {
+            rows.iter()
+                .map(|row| row.iter().map(|c| c.to_string()).collect())
+                .collect()
+        };
+        let expected = rows(&[&["1", "'a'"], &["2.0", "'b'"]]);
+        let actual = rows(&[&["1", "'a'"], &["2", "'b'"]]);
+        let swapped = rows(&[&["2", "'b'"], &["1", "'a'"]]);
+        assert!(tables_match(&expected, &actual, false));
+        assert!(!tables_match(&expected, &swapped, false));
+        assert!(tables_match(&expected, &swapped, true));
+        assert!(!tables_match(&expected, &actual[..1], true));
+        assert!(!tables_match(&expected, &rows(&[&["1", "'a'"], &["1", "'a'"]]), true));
+        assert!(!tables_match(&rows(&[&["1"]]), &rows(&[&["1", "'a'"]]), false));
+        assert_eq!(format_table(&expected), "1 | 'a'\n2.0 | 'b'");
+    }
 }
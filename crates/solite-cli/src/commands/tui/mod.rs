@@ -7,6 +7,7 @@ mod copy_popup;
 mod help_bar;
 mod help_popup;
 mod listing_page;
+mod remote_paging;
 mod row_page;
 mod table_page;
 mod tui_theme;
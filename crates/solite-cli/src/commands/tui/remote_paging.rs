@@ -0,0 +1,283 @@
+//! Paging a table on a remote connection. Windows are assembled from
+//! fixed-size blocks of rows fetched with the server's `Page` request, and
+//! the blocks on either side of the visible window are fetched ahead on a
+//! background thread, so scrolling rarely waits on a round-trip.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use solite_core::sqlite::{OwnedValue, RemotePager};
+
+use crate::commands::tui::table_page::{Data, WINDOW_SIZE};
+
+/// Rows per fetched block.
+const BLOCK_ROWS: usize = WINDOW_SIZE;
+
+/// Blocks kept on either side of the visible window.
+const KEEP_BLOCKS: usize = 3;
+
+/// A block to fetch on the background thread.
+struct Fetch {
+    generation: u64,
+    sql: String,
+    block: usize,
+}
+
+/// A block fetched on the background thread.
+struct Fetched {
+    generation: u64,
+    block: usize,
+    result: Result<Block, String>,
+}
+
+struct Block {
+    columns: Vec<String>,
+    rows: Vec<Vec<OwnedValue>>,
+}
+
+pub struct RemoteWindows {
+    pager: RemotePager,
+    /// The SELECT being paged, including any ORDER BY.
+    sql: String,
+    /// Bumped whenever `sql` changes, to drop stale background results.
+    generation: u64,
+    blocks: HashMap<usize, Block>,
+    in_flight: HashSet<usize>,
+    fetches: Sender<Fetch>,
+    fetched: Receiver<Fetched>,
+}
+
+impl RemoteWindows {
+    /// Page `sql` through `pager`. The background thread exits once this
+    /// is dropped.
+    pub fn new(pager: RemotePager, sql: String) -> Self {
+        let (fetches, requests) = channel::<Fetch>();
+        let (results, fetched) = channel();
+        let background = pager.clone();
+        std::thread::spawn(move || {
+            for fetch in requests {
+                let result = fetch_block(&background, &fetch.sql, fetch.block);
+                let fetched = Fetched {
+                    generation: fetch.generation,
+                    block: fetch.block,
+                    result,
+                };
+                if results.send(fetched).is_err() {
+                    return;
+                }
+            }
+        });
+        Self {
+            pager,
+            sql,
+            generation: 0,
+            blocks: HashMap::new(),
+            in_flight: HashSet::new(),
+            fetches,
+            fetched,
+        }
+    }
+
+    /// Switch to paging `sql` (e.g. after a sort), dropping cached blocks.
+    /// Returns the previous query.
+    pub fn set_query(&mut self, sql: String) -> String {
+        self.generation += 1;
+        self.blocks.clear();
+        self.in_flight.clear();
+        std::mem::replace(&mut self.sql, sql)
+    }
+
+    /// Rows `start..start + len` (fewer at the end of the table). Waits for
+    /// blocks already being fetched, fetches missing ones, then starts
+    /// fetching the neighbouring blocks in the background.
+    pub fn window(&mut self, start: usize, len: usize) -> Result<Data, String> {
+        self.poll();
+        let (first, last) = block_span(start, len);
+        let mut columns = vec![];
+        let mut rows = Vec::with_capacity(len);
+        for block in first..=last {
+            self.load(block)?;
+            let loaded = &self.blocks[&block];
+            if columns.is_empty() {
+                columns = loaded.columns.clone();
+            }
+            let (skip, take) = block_slice(block, start, len - rows.len());
+            rows.extend(loaded.rows.iter().skip(skip).take(take).cloned());
+            if loaded.rows.len() < BLOCK_ROWS {
+                // the end of the table
+                break;
+            }
+        }
+
+        self.blocks.retain(|block, _| is_kept(*block, first, last));
+        let more = self.blocks.get(&last).is_some_and(|b| b.rows.len() == BLOCK_ROWS);
+        if more {
+            self.prefetch(last + 1);
+        }
+        if first > 0 {
+            self.prefetch(first - 1);
+        }
+        Ok(Data { columns, rows })
+    }
+
+    /// Install blocks the background thread has finished. Non-blocking.
+    pub fn poll(&mut self) {
+        while let Ok(fetched) = self.fetched.try_recv() {
+            self.install(fetched);
+        }
+    }
+
+    /// Make sure `block` is cached.
+    fn load(&mut self, block: usize) -> Result<(), String> {
+        while !self.blocks.contains_key(&block) && self.in_flight.contains(&block) {
+            let Ok(fetched) = self.fetched.recv() else {
+                // The background thread died; fetch it ourselves
+                self.in_flight.remove(&block);
+                break;
+            };
+            if fetched.generation == self.generation && fetched.block == block {
+                if let Err(e) = &fetched.result {
+                    self.in_flight.remove(&block);
+                    return Err(e.clone());
+                }
+            }
+            self.install(fetched);
+        }
+        if !self.blocks.contains_key(&block) {
+            let fetched = fetch_block(&self.pager, &self.sql, block)?;
+            self.blocks.insert(block, fetched);
+        }
+        Ok(())
+    }
+
+    fn install(&mut self, fetched: Fetched) {
+        if fetched.generation != self.generation {
+            return;
+        }
+        self.in_flight.remove(&fetched.block);
+        // A failed prefetch is retried in the foreground when needed
+        if let Ok(block) = fetched.result {
+            self.blocks.insert(fetched.block, block);
+        }
+    }
+
+    fn prefetch(&mut self, block: usize) {
+        if self.blocks.contains_key(&block) || self.in_flight.contains(&block) {
+            return;
+        }
+        let fetch = Fetch {
+            generation: self.generation,
+            sql: self.sql.clone(),
+            block,
+        };
+        if self.fetches.send(fetch).is_ok() {
+            self.in_flight.insert(block);
+        }
+    }
+}
+
+/// The first and last blocks holding rows `start..start + len`. An empty
+/// window still needs the block `start` falls in, for the column names.
+fn block_span(start: usize, len: usize) -> (usize, usize) {
+    (start / BLOCK_ROWS, (start + len.max(1) - 1) / BLOCK_ROWS)
+}
+
+/// The rows of `block` that belong to the window starting at `start`, as
+/// `(skip, take)`, when `remaining` rows of it are still to be collected.
+fn block_slice(block: usize, start: usize, remaining: usize) -> (usize, usize) {
+    (start.saturating_sub(block * BLOCK_ROWS), remaining)
+}
+
+/// Whether `block` stays cached while the window spans `first..=last`.
+fn is_kept(block: usize, first: usize, last: usize) -> bool {
+    block + KEEP_BLOCKS >= first && block <= last + KEEP_BLOCKS
+}
+
+fn fetch_block(pager: &RemotePager, sql: &str, block: usize) -> Result<Block, String> {
+    let page = pager
+        .page(sql, block * BLOCK_ROWS, BLOCK_ROWS, false)
+        .map_err(|e| format!("Query error: {}", e))?;
+    Ok(Block {
+        columns: page.columns,
+        rows: page.rows,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_span() {
+        assert_eq!(block_span(0, BLOCK_ROWS), (0, 0));
+        assert_eq!(block_span(1, BLOCK_ROWS), (0, 1));
+        assert_eq!(block_span(BLOCK_ROWS - 1, 1), (0, 0));
+        assert_eq!(block_span(BLOCK_ROWS, 1), (1, 1));
+        assert_eq!(block_span(3 * BLOCK_ROWS + 5, 2 * BLOCK_ROWS), (3, 5));
+        // An empty window still covers the block `start` is in
+        assert_eq!(block_span(0, 0), (0, 0));
+        assert_eq!(block_span(2 * BLOCK_ROWS, 0), (2, 2));
+    }
+
+    /// Rows `start..start + len` of a table of `total` rows numbered from 0,
+    /// collected block by block the way `window` does.
+    fn assemble(start: usize, len: usize, total: usize) -> Vec<usize> {
+        let (first, last) = block_span(start, len);
+        let mut rows = vec![];
+        for block in first..=last {
+            let block_rows: Vec<usize> =
+                (block * BLOCK_ROWS..((block + 1) * BLOCK_ROWS).min(total)).collect();
+            let (skip, take) = block_slice(block, start, len - rows.len());
+            rows.extend(block_rows.iter().skip(skip).take(take));
+            if block_rows.len() < BLOCK_ROWS {
+                break;
+            }
+        }
+        rows
+    }
+
+    #[test]
+    fn test_window_rows() {
+        let total = 5 * BLOCK_ROWS + 7;
+        for (start, len) in [
+            (0, BLOCK_ROWS),
+            (3, BLOCK_ROWS),
+            (BLOCK_ROWS - 1, 2),
+            (2 * BLOCK_ROWS + 9, 2 * BLOCK_ROWS),
+            (5 * BLOCK_ROWS, BLOCK_ROWS),
+            (0, 0),
+        ] {
+            let expected: Vec<usize> = (start..(start + len).min(total)).collect();
+            assert_eq!(assemble(start, len, total), expected, "rows {start}+{len}");
+        }
+        // Past the end of the table
+        assert!(assemble(6 * BLOCK_ROWS, BLOCK_ROWS, total).is_empty());
+    }
+
+    #[test]
+    fn test_is_kept() {
+        // Blocks within KEEP_BLOCKS on either side of the window survive
+        assert!(is_kept(10 - KEEP_BLOCKS, 10, 11));
+        assert!(!is_kept(10 - KEEP_BLOCKS - 1, 10, 11));
+        assert!(is_kept(11 + KEEP_BLOCKS, 10, 11));
+        assert!(!is_kept(11 + KEEP_BLOCKS + 1, 10, 11));
+        assert!(is_kept(10, 10, 11));
+        // No underflow at the start of the table
+        assert!(is_kept(0, 0, 0));
+        assert!(is_kept(KEEP_BLOCKS, 0, 0));
+        assert!(!is_kept(KEEP_BLOCKS + 1, 0, 0));
+    }
+
+    #[test]
+    fn test_prefetched_neighbours_are_kept() {
+        // The blocks `window` prefetches must survive its own retain, or
+        // they'd be dropped as soon as they arrive.
+        for start in [0, 1, BLOCK_ROWS - 1, BLOCK_ROWS, 7 * BLOCK_ROWS + 3] {
+            let (first, last) = block_span(start, BLOCK_ROWS);
+            assert!(is_kept(last + 1, first, last));
+            if first > 0 {
+                assert!(is_kept(first - 1, first, last));
+            }
+        }
+    }
+}
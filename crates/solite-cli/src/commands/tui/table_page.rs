@@ -2,6 +2,7 @@ use std::fmt::Write;
 
 use crate::commands::tui::copy_popup::{CopyOption, CopyPopup};
 use crate::commands::tui::help_popup::{help_bar_from, HelpPopup, TABLE_KEYS};
+use crate::commands::tui::remote_paging::RemoteWindows;
 use crate::commands::tui::row_page::{get_primary_keys, PrimaryKeyInfo};
 use crate::commands::tui::utils::render_value_for_display_capped;
 use crate::commands::tui::tui_theme::TuiTheme;
@@ -13,7 +14,7 @@ use ratatui::layout::{Constraint, HorizontalAlignment, Layout, Rect};
 use ratatui::style::Style;
 use ratatui::text::Text;
 use ratatui::widgets::{Cell, Row, Table, TableState};
-use solite_core::sqlite::{escape_string, quote_identifier, OwnedValue};
+use solite_core::sqlite::{escape_string, quote_identifier, OwnedValue, RemotePager};
 use solite_core::Runtime;
 
 #[derive(Debug)]
@@ -67,11 +68,13 @@ const SPINNER_CHARS: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦'
 
 /// Where an in-progress count is coming from.
 enum CountSource {
-    /// `SELECT COUNT(*)` running on its own read-only connection in a
-    /// background thread; the result arrives on this channel.
+    /// `SELECT COUNT(*)` running in a background thread, on its own
+    /// read-only connection or on the remote server; the result arrives on
+    /// this channel.
     Background(std::sync::mpsc::Receiver<Result<usize, String>>),
     /// Incremental OFFSET probing on the UI connection. Fallback for
-    /// databases a second connection can't reach (in-memory, remote).
+    /// databases a background thread can't reach (in-memory, remote
+    /// servers without `Page`).
     Probe,
 }
 
@@ -105,17 +108,24 @@ impl RowCount {
     ///
     /// When the database is a local file, spawns a background thread that
     /// runs `SELECT COUNT(*)` on its own read-only connection — a single
-    /// optimized scan, off the UI thread. In-memory and remote databases
-    /// can't be reopened by a second connection, so those keep the
-    /// incremental OFFSET probe.
+    /// optimized scan, off the UI thread. On a remote connection the thread
+    /// asks the server on a second session, which counts once and caches
+    /// the total. In-memory databases can't be reopened by a second
+    /// connection, so those keep the incremental OFFSET probe.
     fn start(initial_known: usize, runtime: &Runtime, table: &str) -> Self {
         let mut row_count = Self::new(initial_known);
         if row_count.is_complete {
             return row_count;
         }
-        let Some(path) = background_countable_path(runtime) else {
-            return row_count;
-        };
+        let table = table.to_owned();
+        let count: Box<dyn FnOnce() -> Result<usize, String> + Send> =
+            if let Some(pager) = runtime.connection.remote_pager() {
+                Box::new(move || count_remote_rows(&pager, &table))
+            } else if let Some(path) = background_countable_path(runtime) {
+                Box::new(move || count_rows(&path, &table))
+            } else {
+                return row_count;
+            };
 
         // The thread is detached and never cancelled: if the page closes
         // mid-count the send simply fails and the thread exits. On a huge
@@ -123,9 +133,8 @@ impl RowCount {
         // briefly make concurrent writes return SQLITE_BUSY; add a
         // cancellation flag here if that ever bites.
         let (tx, rx) = std::sync::mpsc::channel();
-        let table = table.to_owned();
         std::thread::spawn(move || {
-            let result = count_rows(&path, &table);
+            let result = count();
             // Receiver may be gone (page closed); nothing to do then.
             let _ = tx.send(result);
         });
@@ -260,6 +269,23 @@ fn count_rows(path: &str, table: &str) -> Result<usize, String> {
     Ok(row[0].as_int64().max(0) as usize)
 }
 
+/// `SELECT COUNT(*)` of `table` on the remote server, through a `Page`
+/// request on the connection's second session (opened by the first count
+/// and reused by later ones), so the count doesn't hold up the pages the
+/// UI fetches meanwhile. Runs on the background counting thread.
+fn count_remote_rows(pager: &RemotePager, table: &str) -> Result<usize, String> {
+    let session = pager
+        .session()
+        .map_err(|e| format!("Failed to open a session for counting: {}", e))?;
+    let sql = format!("SELECT * FROM {}", quote_identifier(table));
+    let page = session
+        .page(&sql, 0, 0, true)
+        .map_err(|e| format!("Count error: {}", e))?;
+    page.total
+        .map(|total| total as usize)
+        .ok_or_else(|| "The server returned no count".to_owned())
+}
+
 /// Load rows with full, untruncated values (`SELECT *`).
 pub fn load_table_data(
     runtime: &Runtime,
@@ -281,25 +307,7 @@ fn load_table_data_with_select(
     offset: usize,
     limit: usize,
 ) -> LoadResult {
-    let mut sql: String = String::new();
-    // Use quoted identifier to handle special table names
-    let _ = writeln!(
-        &mut sql,
-        "SELECT {} FROM {}",
-        select_list,
-        quote_identifier(table)
-    );
-    if let Some(order) = order {
-        let _ = writeln!(
-            &mut sql,
-            "ORDER BY {} {}",
-            order.column_idx + 1,
-            match order.direction {
-                SortDirection::Ascending => "ASC",
-                SortDirection::Descending => "DESC",
-            }
-        );
-    }
+    let mut sql = table_select_sql(table, select_list, order.as_ref());
     let _ = writeln!(&mut sql, "LIMIT {} OFFSET {}", limit, offset);
 
     let mut stmt = match runtime.connection.prepare(&sql) {
@@ -345,6 +353,31 @@ fn load_table_data_with_select(
     }
 }
 
+/// `SELECT {select_list} FROM "table"`, ordered by `order`, without a
+/// LIMIT.
+fn table_select_sql(table: &str, select_list: &str, order: Option<&Order>) -> String {
+    let mut sql: String = String::new();
+    // Use quoted identifier to handle special table names
+    let _ = writeln!(
+        &mut sql,
+        "SELECT {} FROM {}",
+        select_list,
+        quote_identifier(table)
+    );
+    if let Some(order) = order {
+        let _ = writeln!(
+            &mut sql,
+            "ORDER BY {} {}",
+            order.column_idx + 1,
+            match order.direction {
+                SortDirection::Ascending => "ASC",
+                SortDirection::Descending => "DESC",
+            }
+        );
+    }
+    sql
+}
+
 /// The column names exactly as `SELECT *` produces them, via a prepared
 /// `LIMIT 0` probe. Crucially this includes generated columns, which
 /// `pragma_table_info` omits — the truncating window SELECT list, the
@@ -605,6 +638,9 @@ pub struct TablePage<'a> {
     rowids: Option<Vec<i64>>,
     /// SELECT list for window loads (truncates oversized values)
     select_list: String,
+    /// Server-side paging with background prefetch, on remote connections
+    /// whose server supports it. Replaces the rowid keyset reads.
+    remote: Option<RemoteWindows>,
     /// Measured display width per column for the loaded window
     col_widths: Vec<u16>,
     /// Width of the table area at the last render (used by `L` to compute
@@ -628,9 +664,17 @@ impl<'a> TablePage<'a> {
         theme: TuiTheme,
         clipboard: SharedClipboard,
     ) -> Self {
-        let use_rowid = rowid_keyset_usable(runtime, table_name);
         let select_list = window_select_list(&table_column_names(runtime, table_name));
-        let (data, rowids, error) = if use_rowid {
+        let mut remote = runtime.connection.remote_pager().map(|pager| {
+            RemoteWindows::new(pager, table_select_sql(table_name, &select_list, None))
+        });
+        let use_rowid = remote.is_none() && rowid_keyset_usable(runtime, table_name);
+        let (data, rowids, error) = if let Some(remote) = &mut remote {
+            match remote.window(0, WINDOW_SIZE) {
+                Ok(data) => (data, None, None),
+                Err(e) => (Data::empty(), None, Some(e)),
+            }
+        } else if use_rowid {
             match load_rowid_window(
                 runtime,
                 table_name,
@@ -673,6 +717,7 @@ impl<'a> TablePage<'a> {
             use_rowid,
             rowids,
             select_list,
+            remote,
             col_widths: vec![],
             last_table_width: 80,
             clipboard,
@@ -736,6 +781,14 @@ impl<'a> TablePage<'a> {
     /// Load the window starting at `new_start`, preferring keyset (rowid
     /// anchored, O(window)) reads over OFFSET (O(offset)) ones.
     fn load_window(&mut self, new_start: usize) {
+        if let Some(remote) = &mut self.remote {
+            match remote.window(new_start, WINDOW_SIZE) {
+                Ok(data) => self.apply_window(new_start, data, None),
+                Err(e) => self.error = Some(e),
+            }
+            return;
+        }
+
         let keyset_eligible = self.use_rowid && self.current_order.is_none();
         if keyset_eligible && self.load_window_keyset(new_start) {
             return;
@@ -906,14 +959,30 @@ impl<'a> TablePage<'a> {
 
     /// Run the (blocking) sorted reload for `order`.
     fn apply_sort(&mut self, order: Order) {
-        let result = load_table_data_with_select(
-            self.runtime,
-            &self.table_name,
-            &self.select_list,
-            Some(order.clone()),
-            0,
-            WINDOW_SIZE,
-        );
+        let result = match &mut self.remote {
+            Some(remote) => {
+                let sql = table_select_sql(&self.table_name, &self.select_list, Some(&order));
+                let previous = remote.set_query(sql);
+                match remote.window(0, WINDOW_SIZE) {
+                    Ok(data) => LoadResult { data, error: None },
+                    Err(e) => {
+                        remote.set_query(previous);
+                        LoadResult {
+                            data: Data::empty(),
+                            error: Some(e),
+                        }
+                    }
+                }
+            }
+            None => load_table_data_with_select(
+                self.runtime,
+                &self.table_name,
+                &self.select_list,
+                Some(order.clone()),
+                0,
+                WINDOW_SIZE,
+            ),
+        };
         if let Some(err) = result.error {
             // Keep the previous view; just report the failure.
             self.footer_message = Some(format!("Sort error: {}", err));
@@ -1476,6 +1545,11 @@ impl TablePage<'_> {
                 message_rect,
             );
 
+            // Install windows prefetched in the background
+            if let Some(remote) = &mut self.remote {
+                remote.poll();
+            }
+
             // Continue counting if not complete (poll the background
             // COUNT(*), or advance the OFFSET probe one batch)
             if !self.row_count.is_complete {
@@ -3,7 +3,7 @@ use std::ops::Range;
 use codespan_reporting::diagnostic::{Diagnostic, Label};
 use codespan_reporting::files::SimpleFiles;
 use codespan_reporting::term;
-use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
+use codespan_reporting::term::termcolor::{ColorChoice, StandardStream, WriteColor};
 use solite_core::sqlite::SQLiteError;
 use termcolor::Buffer;
 
@@ -37,11 +37,21 @@ pub(crate) fn report_error(
     error: &SQLiteError,
     additional_offset: Option<usize>,
 ) {
-    let (files, diagnostic) = error_diagnostic(file_name, sql, error, additional_offset);
     let writer = StandardStream::stderr(ColorChoice::Auto);
+    report_error_to(&mut writer.lock(), file_name, sql, error, additional_offset);
+}
 
+/// [`report_error`], to any color-capable writer.
+pub(crate) fn report_error_to(
+    writer: &mut dyn WriteColor,
+    file_name: &str,
+    sql: &str,
+    error: &SQLiteError,
+    additional_offset: Option<usize>,
+) {
+    let (files, diagnostic) = error_diagnostic(file_name, sql, error, additional_offset);
     let config = term::Config::default();
-    term::emit(&mut writer.lock(), &config, &files, &diagnostic).unwrap();
+    term::emit(writer, &config, &files, &diagnostic).unwrap();
 }
 
 pub(crate) fn report_error_string(
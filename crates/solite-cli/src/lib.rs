@@ -88,9 +88,11 @@ pub fn run_main() {
         cli::Commands::Schema(args) => {
             commands::schema::schema(args.database, args.pattern, args.format, allow_ssh)
         }
-        cli::Commands::Backup(args) => commands::backup::backup(args),
+        cli::Commands::Backup(args) => commands::backup::backup(args, allow_ssh),
         cli::Commands::Vacuum(args) => commands::vacuum::vacuum(args),
         cli::Commands::Serve(args) => commands::serve::serve(args),
+        cli::Commands::Remote(cmd) => commands::remote::remote(cmd),
+        cli::Commands::Mcp(args) => commands::mcp::mcp(args),
         cli::Commands::Completions(args) => commands::completions::completions(args),
         #[cfg(feature = "ritestream")]
         cli::Commands::Stream(cmd) => commands::stream::stream(cmd),
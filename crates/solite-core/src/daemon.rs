@@ -0,0 +1,629 @@
+//! `solite remote daemon`: keeps SSH transports to `solite serve` warm
+//! between CLI invocations, like an SSH control master.
+//!
+//! The daemon listens on a Unix socket in a per-user directory
+//! ([`socket_path`]) that only its user may enter, and clients check the
+//! process listening there runs as them too ([`connect`]). A client opens with [`Request::Attach`] naming the
+//! `ssh://` URL it wants, and the daemon hands it an idle transport for that
+//! URL (or spawns one), then relays frames both ways. When the client sends
+//! `Close` or goes away, the daemon sends [`Request::Reset`] instead of
+//! ending the remote session and keeps the transport for the next client,
+//! unless the server ends the session instead (it loaded an extension).
+//! Transports left idle longer than the daemon's timeout are closed.
+//!
+//! [`Connection::open_remote`](crate::sqlite::Connection::open_remote)
+//! attaches through the daemon whenever its socket answers, so reuse is
+//! transparent; without a daemon it spawns `ssh` as before.
+
+use std::collections::HashMap;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::rpc::{
+    self, Listener, Request, Response, SocketAddress, CAPABILITIES, CAP_RESET, PROTOCOL_VERSION,
+};
+use crate::sqlite::{ssh_command, SQLiteError};
+
+/// Overrides the daemon's socket path, for both the daemon and clients.
+pub const SOCKET_ENV: &str = "SOLITE_REMOTE_DAEMON";
+
+/// How long a transport may sit unused before the daemon closes it.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// How often the daemon looks for idle transports to close.
+const EXPIRE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The daemon's socket: `$SOLITE_REMOTE_DAEMON`, else `remote.sock` in a
+/// `solite` directory under `$XDG_RUNTIME_DIR` (or a per-user directory in
+/// the temp dir).
+pub fn socket_path() -> PathBuf {
+    if let Some(path) = std::env::var_os(SOCKET_ENV) {
+        return PathBuf::from(path);
+    }
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("solite"),
+        None => {
+            let user = std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "user".to_string());
+            std::env::temp_dir().join(format!("solite-{}", user))
+        }
+    };
+    dir.join("remote.sock")
+}
+
+/// What a pooled transport is for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+    url: String,
+    remote_bin: Option<String>,
+}
+
+/// A `solite serve` session over `ssh`, owned by the daemon.
+struct Remote {
+    /// The `ssh` process; `None` for sessions served in-process (tests).
+    child: Option<Child>,
+    reader: BufReader<Box<dyn Read + Send>>,
+    writer: BufWriter<Box<dyn Write + Send>>,
+    /// The server answers `Reset`, so the session can outlive its client.
+    resettable: bool,
+    idle_since: Instant,
+}
+
+impl Remote {
+    /// Spawn `ssh` and check the server answers a `Hello`, so a bad host
+    /// or missing binary is reported before a client is attached.
+    fn spawn(key: &Key) -> Result<Self, SQLiteError> {
+        let mut child = ssh_command(&key.url, key.remote_bin.as_deref())?
+            .spawn()
+            .map_err(|e| SQLiteError::custom("SSH_ERROR", format!("Failed to spawn ssh: {}", e)))?;
+        let reader = Box::new(child.stdout.take().unwrap());
+        let writer = Box::new(child.stdin.take().unwrap());
+        Remote::hello(Some(child), reader, writer)
+    }
+
+    /// Agree on a protocol version and capabilities with the server at
+    /// the other end of `reader` and `writer`.
+    fn hello(
+        child: Option<Child>,
+        reader: Box<dyn Read + Send>,
+        writer: Box<dyn Write + Send>,
+    ) -> Result<Self, SQLiteError> {
+        let mut remote = Remote {
+            child,
+            reader: BufReader::new(reader),
+            writer: BufWriter::new(writer),
+            resettable: false,
+            idle_since: Instant::now(),
+        };
+        let hello = Request::Hello {
+            version: PROTOCOL_VERSION,
+            capabilities: CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+        };
+        let err = match rpc::write_frame(&mut remote.writer, &hello)
+            .and_then(|()| rpc::read_frame(&mut remote.reader))
+        {
+            Ok(Response::Hello {
+                version,
+                capabilities,
+                ..
+            }) if version == PROTOCOL_VERSION => {
+                remote.resettable = capabilities.iter().any(|c| c == CAP_RESET);
+                return Ok(remote);
+            }
+            Ok(Response::Hello { version, .. }) => rpc::version_mismatch(PROTOCOL_VERSION, version),
+            Ok(Response::Error(e)) => e.message,
+            Ok(other) => format!("Unexpected response from remote: {:?}", other),
+            Err(e) => format!("Failed to connect to remote database: {}", e),
+        };
+        Err(SQLiteError::custom("SSH_ERROR", err))
+    }
+
+    /// Whether an idle session still answers; the SSH connection may have
+    /// dropped while it sat in the pool.
+    fn alive(&mut self) -> bool {
+        matches!(
+            rpc::write_frame(&mut self.writer, &Request::InTransaction)
+                .and_then(|()| rpc::read_frame(&mut self.reader)),
+            Ok(Response::InTransaction { value: false })
+        )
+    }
+}
+
+impl Drop for Remote {
+    fn drop(&mut self) {
+        let _ = rpc::write_frame(&mut self.writer, &Request::Close);
+        if let Some(child) = &mut self.child {
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Idle transports by what they're for.
+#[derive(Default)]
+struct Pool {
+    idle: Mutex<HashMap<Key, Vec<Remote>>>,
+}
+
+impl Pool {
+    /// An idle transport for `key` that still answers, if any.
+    fn take(&self, key: &Key) -> Option<Remote> {
+        loop {
+            let mut remote = self.idle.lock().unwrap().get_mut(key)?.pop()?;
+            if remote.alive() {
+                return Some(remote);
+            }
+        }
+    }
+
+    fn put(&self, key: Key, mut remote: Remote) {
+        remote.idle_since = Instant::now();
+        self.idle.lock().unwrap().entry(key).or_default().push(remote);
+    }
+
+    /// Close transports idle for longer than `timeout`.
+    fn expire(&self, timeout: Duration) {
+        let mut expired = Vec::new();
+        {
+            let mut idle = self.idle.lock().unwrap();
+            for remotes in idle.values_mut() {
+                let (keep, old): (Vec<_>, Vec<_>) = std::mem::take(remotes)
+                    .into_iter()
+                    .partition(|remote| remote.idle_since.elapsed() < timeout);
+                *remotes = keep;
+                expired.extend(old);
+            }
+            idle.retain(|_, remotes| !remotes.is_empty());
+        }
+        // Closing waits on each ssh process, so do it outside the lock
+        drop(expired);
+    }
+}
+
+/// Run the daemon on `socket` until the listener fails, closing transports
+/// idle for longer than `idle_timeout`.
+pub fn run(socket: &Path, idle_timeout: Duration) -> io::Result<()> {
+    if let Some(dir) = socket.parent() {
+        create_private_dir(dir)?;
+    }
+    let listener = Listener::bind(&SocketAddress::Unix(socket.to_path_buf()))?;
+    let pool = Arc::new(Pool::default());
+    {
+        let pool = Arc::clone(&pool);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(EXPIRE_INTERVAL.min(idle_timeout));
+            pool.expire(idle_timeout);
+        });
+    }
+    loop {
+        let (reader, writer) = match listener.accept() {
+            Ok(streams) => streams,
+            Err(e) if e.kind() == io::ErrorKind::ConnectionAborted => continue,
+            Err(e) => return Err(e),
+        };
+        let pool = Arc::clone(&pool);
+        std::thread::spawn(move || {
+            if let Err(e) = attach(&pool, BufReader::new(reader), BufWriter::new(writer)) {
+                eprintln!("solite remote daemon: client ended: {}", e);
+            }
+        });
+    }
+}
+
+/// Connect to the daemon's socket. `Ok(None)` when no daemon is
+/// listening; an error when the process listening there belongs to
+/// another user, which would see every query sent through it.
+pub fn connect() -> io::Result<Option<rpc::SocketStreams>> {
+    #[cfg(unix)]
+    {
+        let path = socket_path();
+        let Ok(stream) = std::os::unix::net::UnixStream::connect(&path) else {
+            return Ok(None);
+        };
+        if peer_uid(&stream)? != current_uid() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("the daemon socket {} belongs to another user", path.display()),
+            ));
+        }
+        Ok(Some((Box::new(stream.try_clone()?), Box::new(stream))))
+    }
+    #[cfg(not(unix))]
+    Ok(None)
+}
+
+/// Only the user running the daemon may reach its socket. `DirBuilder`'s
+/// mode only applies to directories it creates, so an existing directory
+/// is checked instead.
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(dir)?;
+    #[cfg(unix)]
+    check_private_dir(dir)?;
+    Ok(())
+}
+
+/// Fail unless `dir` is a directory (not a link to one) owned by the
+/// current user, which no other user can read, write or enter.
+#[cfg(unix)]
+fn check_private_dir(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = std::fs::symlink_metadata(dir)?;
+    let problem = if !metadata.is_dir() {
+        "isn't a directory"
+    } else if metadata.uid() != current_uid() {
+        "belongs to another user"
+    } else if metadata.mode() & 0o077 != 0 {
+        "is open to other users (chmod 700 it)"
+    } else {
+        return Ok(());
+    };
+    Err(io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        format!("the daemon socket's directory {} {}", dir.display(), problem),
+    ))
+}
+
+#[cfg(unix)]
+fn current_uid() -> u32 {
+    // SAFETY: geteuid can't fail and has no preconditions
+    unsafe { libc::geteuid() }
+}
+
+/// The user running the process at the other end of `stream`.
+#[cfg(unix)]
+fn peer_uid(stream: &std::os::unix::net::UnixStream) -> io::Result<u32> {
+    use std::os::unix::io::AsRawFd;
+    let fd = stream.as_raw_fd();
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let mut cred: libc::ucred = unsafe { std::mem::zeroed() };
+        let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+        // SAFETY: `cred` and `len` are valid for writes of a ucred
+        let rc = unsafe {
+            libc::getsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                (&mut cred as *mut libc::ucred).cast(),
+                &mut len,
+            )
+        };
+        if rc != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(cred.uid)
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let mut uid = 0;
+        let mut gid = 0;
+        // SAFETY: `uid` and `gid` are valid for writes
+        if unsafe { libc::getpeereid(fd, &mut uid, &mut gid) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(uid)
+    }
+}
+
+/// Serve one client: attach it to a transport, relay its session, then keep
+/// the transport if it can be reset.
+fn attach<R: Read, W: Write + Send>(pool: &Pool, mut reader: R, mut writer: W) -> io::Result<()> {
+    let key = match rpc::read_frame(&mut reader)? {
+        Request::Attach { url, remote_bin } => Key { url, remote_bin },
+        _ => {
+            let error = SQLiteError::custom("PROTOCOL_ERROR", "Expected an Attach request first");
+            return rpc::write_frame(&mut writer, &Response::Error(error));
+        }
+    };
+    let (mut remote, reused) = match pool.take(&key) {
+        Some(remote) => (remote, true),
+        None => match Remote::spawn(&key) {
+            Ok(remote) => (remote, false),
+            Err(e) => return rpc::write_frame(&mut writer, &Response::Error(e)),
+        },
+    };
+    rpc::write_frame(&mut writer, &Response::Attached { reused })?;
+
+    let (closed, reset) = relay(&mut reader, &mut writer, &mut remote);
+    if reset {
+        pool.put(key, remote);
+    }
+    if closed {
+        rpc::write_frame(&mut writer, &Response::Closed)?;
+    }
+    Ok(())
+}
+
+/// Relay frames between a client and `remote` until the client sends
+/// `Close` or goes away, then reset the session (or close it, if the server
+/// can't reset). Frames are passed through undecoded, except to spot
+/// `Close` and `Reset`. Returns whether the client closed, and whether
+/// `remote` was reset for the next client.
+fn relay<R: Read, W: Write + Send>(
+    client_reader: &mut R,
+    client_writer: &mut W,
+    remote: &mut Remote,
+) -> (bool, bool) {
+    let Remote {
+        reader: remote_reader,
+        writer: remote_writer,
+        resettable,
+        ..
+    } = remote;
+    let resettable = *resettable;
+    std::thread::scope(|scope| {
+        // Responses arrive in request order, so everything before the
+        // answer to `Reset` (or `Close`) belongs to this client. `Closed`
+        // means the server ended the session rather than reset it.
+        let responses = scope.spawn(move || -> io::Result<bool> {
+            let mut client_gone = false;
+            loop {
+                let frame = rpc::read_raw_frame(remote_reader)?;
+                match rpc::decode_frame(&frame) {
+                    Ok(Response::Reset) => return Ok(true),
+                    Ok(Response::Closed) => return Ok(false),
+                    _ => {}
+                }
+                // Keep draining after the client goes away, to stay in sync
+                if !client_gone {
+                    client_gone = rpc::write_raw_frame(client_writer, &frame).is_err();
+                }
+            }
+        });
+
+        let mut closed = false;
+        let mut sent = Ok(());
+        while let Ok(frame) = rpc::read_raw_frame(client_reader) {
+            if matches!(rpc::decode_frame(&frame), Ok(Request::Close)) {
+                closed = true;
+                break;
+            }
+            sent = rpc::write_raw_frame(remote_writer, &frame);
+            if sent.is_err() {
+                break;
+            }
+        }
+
+        let ending = if !resettable {
+            // The server answers `Close` itself and hangs up
+            vec![Request::Close]
+        } else if closed {
+            vec![Request::Reset]
+        } else {
+            // The client went away, maybe mid-query
+            vec![Request::Interrupt, Request::Reset]
+        };
+        let sent = sent.and_then(|()| {
+            ending
+                .iter()
+                .try_for_each(|request| rpc::write_frame(remote_writer, request))
+        });
+        let reset = sent.is_ok() && matches!(responses.join(), Ok(Ok(true)));
+        (closed, resettable && reset)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_socket_path_override() {
+        std::env::set_var(SOCKET_ENV, "/tmp/solite-test/remote.sock");
+        assert_eq!(socket_path(), PathBuf::from("/tmp/solite-test/remote.sock"));
+        std::env::remove_var(SOCKET_ENV);
+        assert!(socket_path().ends_with("remote.sock"));
+    }
+
+    /// A session on an in-memory database, served on a thread.
+    #[cfg(unix)]
+    fn served(session: impl FnOnce(rpc::Session) -> rpc::Session) -> Remote {
+        let (ours, theirs) = std::os::unix::net::UnixStream::pair().unwrap();
+        let session = session(rpc::Session::new(crate::sqlite::Connection::open_in_memory().unwrap()));
+        let reader = theirs.try_clone().unwrap();
+        std::thread::spawn(move || rpc::serve_session(session, reader, theirs));
+        Remote::hello(None, Box::new(ours.try_clone().unwrap()), Box::new(ours)).unwrap()
+    }
+
+    /// Send `request` straight to `remote`, bypassing any relay.
+    #[cfg(unix)]
+    fn ask(remote: &mut Remote, request: &Request) -> Response {
+        rpc::write_frame(&mut remote.writer, request).unwrap();
+        rpc::read_frame(&mut remote.reader).unwrap()
+    }
+
+    /// Relay a client that sends `requests`, then hangs up (or sends
+    /// `Close`, if it's the last request). Returns what `relay` returned
+    /// and the responses the client got.
+    #[cfg(unix)]
+    fn relay_client(remote: &mut Remote, requests: Vec<Request>) -> ((bool, bool), Vec<Response>) {
+        let (client, daemon) = std::os::unix::net::UnixStream::pair().unwrap();
+        let client_thread = std::thread::spawn(move || {
+            let mut reader = BufReader::new(client.try_clone().unwrap());
+            let mut writer = BufWriter::new(client);
+            let mut responses = vec![];
+            for request in requests {
+                rpc::write_frame(&mut writer, &request).unwrap();
+                if !matches!(request, Request::Close) {
+                    responses.push(rpc::read_frame(&mut reader).unwrap());
+                }
+            }
+            responses
+        });
+        let mut reader = BufReader::new(daemon.try_clone().unwrap());
+        let mut writer = BufWriter::new(daemon);
+        let ended = relay(&mut reader, &mut writer, remote);
+        (ended, client_thread.join().unwrap())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_relay_resets_between_clients() {
+        let mut remote = served(|session| session);
+        assert!(remote.resettable);
+
+        let script = "create table t(x); insert into t values (1);
+                      create temp table scratch(y); attach ':memory:' as aux;
+                      begin; insert into t values (2);";
+        let (ended, responses) = relay_client(
+            &mut remote,
+            vec![
+                Request::ExecuteScript {
+                    sql: script.to_string(),
+                },
+                Request::Close,
+            ],
+        );
+        assert_eq!(ended, (true, true));
+        assert!(matches!(responses[..], [Response::ScriptOk]), "{responses:?}");
+
+        // The next client finds the table, but nothing the last one left
+        let count = |sql: &str| Request::Query {
+            sql: sql.to_string(),
+            params: vec![],
+        };
+        let (ended, responses) = relay_client(
+            &mut remote,
+            vec![
+                count("select count(*) from t"),
+                count("select count(*) from temp.sqlite_master"),
+                count("select count(*) from pragma_database_list where name = 'aux'"),
+                Request::InTransaction,
+            ],
+        );
+        // It hung up without `Close`, which still resets the session
+        assert_eq!(ended, (false, true));
+        let counts: Vec<i64> = responses[..3]
+            .iter()
+            .map(|response| match response {
+                Response::Query(result) => match result.rows[0][0].value {
+                    crate::sqlite::OwnedValue::Integer(v) => v,
+                    ref other => panic!("expected integer, got {other:?}"),
+                },
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(counts, vec![1, 0, 0]);
+        assert!(matches!(responses[3], Response::InTransaction { value: false }));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_relay_closes_sessions_that_cant_reset() {
+        let mut remote = served(|session| session.without(CAP_RESET));
+        assert!(!remote.resettable);
+        let (ended, _) = relay_client(&mut remote, vec![Request::InTransaction, Request::Close]);
+        assert_eq!(ended, (true, false));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_relay_restores_pragmas() {
+        let mut remote = served(|session| session);
+        let pragma = |sql: &str| Request::ExecuteScript {
+            sql: sql.to_string(),
+        };
+        let (ended, _) =
+            relay_client(&mut remote, vec![pragma("pragma foreign_keys = on"), Request::Close]);
+        assert_eq!(ended, (true, true));
+
+        let (_, responses) = relay_client(
+            &mut remote,
+            vec![Request::Query {
+                sql: "pragma foreign_keys".to_string(),
+                params: vec![],
+            }],
+        );
+        match &responses[0] {
+            Response::Query(result) => assert!(matches!(
+                result.rows[0][0].value,
+                crate::sqlite::OwnedValue::Integer(0)
+            )),
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pool() {
+        let key = Key {
+            url: "ssh://host/db".to_string(),
+            remote_bin: None,
+        };
+        let pool = Pool::default();
+        assert!(pool.take(&key).is_none());
+
+        let mut alive = served(|session| session);
+        ask(&mut alive, &Request::ExecuteScript {
+            sql: "create table marker(x)".to_string(),
+        });
+        pool.put(key.clone(), alive);
+        // A session whose server went away is skipped (and dropped)
+        let mut dead = served(|session| session);
+        ask(&mut dead, &Request::Close);
+        pool.put(key.clone(), dead);
+
+        let other = Key {
+            remote_bin: Some("/opt/solite".to_string()),
+            ..key.clone()
+        };
+        assert!(pool.take(&other).is_none());
+        let mut taken = pool.take(&key).unwrap();
+        assert!(matches!(
+            ask(&mut taken, &Request::ExecuteScript {
+                sql: "select * from marker".to_string(),
+            }),
+            Response::ScriptOk
+        ));
+        assert!(pool.take(&key).is_none());
+
+        pool.put(key.clone(), taken);
+        pool.expire(Duration::from_secs(60));
+        assert!(pool.idle.lock().unwrap().contains_key(&key));
+        pool.expire(Duration::ZERO);
+        assert!(pool.idle.lock().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_create_private_dir() {
+        use std::os::unix::fs::PermissionsExt;
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("a").join("solite");
+        create_private_dir(&dir).unwrap();
+        let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        // Existing and still private
+        create_private_dir(&dir).unwrap();
+
+        // An existing directory others can enter isn't tightened for us
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let err = create_private_dir(&dir).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(err.to_string().contains("open to other users"), "{err}");
+
+        // Nor is a link to a private directory
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700)).unwrap();
+        let link = tmp.path().join("link");
+        std::os::unix::fs::symlink(&dir, &link).unwrap();
+        let err = create_private_dir(&link).unwrap_err();
+        assert!(err.to_string().contains("isn't a directory"), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_peer_uid() {
+        let (a, _b) = std::os::unix::net::UnixStream::pair().unwrap();
+        assert_eq!(peer_uid(&a).unwrap(), current_uid());
+    }
+}
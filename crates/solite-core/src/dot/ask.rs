@@ -9,26 +9,49 @@
 //! .ask What are the top 10 customers by order count?
 //! ```
 //!
+//! The model is given the `CREATE` statements of every table, view, index
+//! and trigger, the [sqlite-docs] comments (`--!` / `---`) on tables and
+//! columns, and a few sample rows from each table. The SQL it answers with
+//! is prepared against the database; if that fails, the SQLite error is
+//! sent back and the model gets another try, up to `max_repairs` times.
+//!
+//! [sqlite-docs]: https://github.com/asg017/sqlite-docs
+//!
 //! # Configuration
 //!
-//! Requires the `OPENROUTER_API_KEY` environment variable to be set.
+//! Any OpenAI-compatible chat completions endpoint works; the default is
+//! OpenRouter, which requires the `OPENROUTER_API_KEY` environment variable.
+//! Settings are read from `solite-ask.toml` in the current or a parent
+//! directory, then `~/.config/solite/ask.toml`:
 //!
-//! # TODO
+//! ```toml
+//! base_url = "https://llm.internal.example.com/v1"
+//! model = "qwen2.5-coder-32b"
+//! api_key_env = "GATEWAY_API_KEY"   # "" sends no Authorization header
+//! prompt_file = "ask-prompt.txt"    # relative to this file
+//! sample_rows = 3                   # 0 keeps table contents out of prompts
+//! max_repairs = 2
+//! ```
 //!
-//! - Make prompt template configurable
-//! - Make model configurable
-//! - Make endpoint configurable
+//! Each key can be overridden with an environment variable (so `.dotenv`
+//! works too): `SOLITE_ASK_BASE_URL`, `SOLITE_ASK_MODEL`,
+//! `SOLITE_ASK_API_KEY_ENV`, `SOLITE_ASK_PROMPT_FILE`,
+//! `SOLITE_ASK_SAMPLE_ROWS` and `SOLITE_ASK_MAX_REPAIRS`. Prompt templates
+//! use `{SCHEMA}` and `{QUESTION}` placeholders.
 
 use crate::dot::DotError;
+use crate::sqlite::{quote_identifier, SQLiteError, ValueRefXValue};
 use crate::Runtime;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
 
-/// System prompt template for SQL generation.
+/// Default system prompt template for SQL generation.
 static PROMPT: &str = r#"
 Given the following SQLite database schema,
 write a SQL query to answer the question below.
+Comments describe tables and columns, and show a few sample rows.
 Use the most efficient query possible.
 Provide only the SQL query as output.
 
@@ -39,6 +62,198 @@ Provide only the SQL query as output.
 
 "#;
 
+/// Name of the project-level config file, searched for upwards from the
+/// current directory.
+const CONFIG_FILE_NAME: &str = "solite-ask.toml";
+
+/// Sample values longer than this are cut off in the schema context.
+const SAMPLE_VALUE_MAX_CHARS: usize = 64;
+
+/// Provider settings for `.ask`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AskConfig {
+    /// Base URL of an OpenAI-compatible API; `/chat/completions` is appended.
+    pub base_url: String,
+    /// Model name sent with each request.
+    pub model: String,
+    /// Name of the environment variable holding the API key. Empty means
+    /// the endpoint needs no authentication.
+    pub api_key_env: String,
+    /// Prompt template file with `{SCHEMA}` and `{QUESTION}` placeholders.
+    pub prompt_file: Option<PathBuf>,
+    /// Rows sampled from each table into the schema context.
+    pub sample_rows: usize,
+    /// How many times a query that fails to prepare is sent back to the
+    /// model for repair.
+    pub max_repairs: usize,
+}
+
+impl Default for AskConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "https://openrouter.ai/api/v1".to_string(),
+            model: "openai/gpt-4o".to_string(),
+            api_key_env: "OPENROUTER_API_KEY".to_string(),
+            prompt_file: None,
+            sample_rows: 3,
+            max_repairs: 2,
+        }
+    }
+}
+
+impl AskConfig {
+    /// Discover the config file from the current directory, then apply
+    /// `SOLITE_ASK_*` environment overrides.
+    pub fn discover() -> Result<Self, DotError> {
+        let mut config = Self::discover_from(std::env::current_dir().ok())?;
+        config.apply_env_overrides()?;
+        Ok(config)
+    }
+
+    /// Look for `solite-ask.toml` in `start_dir` and its parents, then in
+    /// `~/.config/solite/ask.toml`. Defaults when neither exists.
+    pub fn discover_from(start_dir: Option<PathBuf>) -> Result<Self, DotError> {
+        if let Some(mut dir) = start_dir {
+            loop {
+                let config_path = dir.join(CONFIG_FILE_NAME);
+                if config_path.exists() {
+                    return Self::load(&config_path);
+                }
+                if !dir.pop() {
+                    break;
+                }
+            }
+        }
+        if let Some(home) = home_dir() {
+            let global_config = home.join(".config/solite/ask.toml");
+            if global_config.exists() {
+                return Self::load(&global_config);
+            }
+        }
+        Ok(Self::default())
+    }
+
+    /// Load a config file. A relative `prompt_file` is resolved against the
+    /// file's directory.
+    pub fn load(path: &Path) -> Result<Self, DotError> {
+        let content = std::fs::read_to_string(path)?;
+        let mut config: AskConfig = toml::from_str(&content).map_err(|e| {
+            DotError::InvalidData(format!("invalid .ask config {}: {e}", path.display()))
+        })?;
+        if let (Some(prompt_file), Some(dir)) = (&config.prompt_file, path.parent()) {
+            if prompt_file.is_relative() {
+                config.prompt_file = Some(dir.join(prompt_file));
+            }
+        }
+        Ok(config)
+    }
+
+    fn apply_env_overrides(&mut self) -> Result<(), DotError> {
+        if let Ok(base_url) = std::env::var("SOLITE_ASK_BASE_URL") {
+            self.base_url = base_url;
+        }
+        if let Ok(model) = std::env::var("SOLITE_ASK_MODEL") {
+            self.model = model;
+        }
+        if let Ok(api_key_env) = std::env::var("SOLITE_ASK_API_KEY_ENV") {
+            self.api_key_env = api_key_env;
+        }
+        if let Ok(prompt_file) = std::env::var("SOLITE_ASK_PROMPT_FILE") {
+            self.prompt_file = Some(PathBuf::from(prompt_file));
+        }
+        if let Some(sample_rows) = env_usize("SOLITE_ASK_SAMPLE_ROWS")? {
+            self.sample_rows = sample_rows;
+        }
+        if let Some(max_repairs) = env_usize("SOLITE_ASK_MAX_REPAIRS")? {
+            self.max_repairs = max_repairs;
+        }
+        Ok(())
+    }
+
+    /// The prompt template: the configured file, or the built-in default.
+    pub fn prompt_template(&self) -> Result<String, DotError> {
+        match &self.prompt_file {
+            Some(path) => std::fs::read_to_string(path).map_err(|e| {
+                DotError::InvalidData(format!(
+                    "failed to read .ask prompt file {}: {e}",
+                    path.display()
+                ))
+            }),
+            None => Ok(PROMPT.to_string()),
+        }
+    }
+
+    /// The API key, or `None` when `api_key_env` is empty.
+    fn api_key(&self) -> Result<Option<String>, DotError> {
+        if self.api_key_env.is_empty() {
+            return Ok(None);
+        }
+        std::env::var(&self.api_key_env).map(Some).map_err(|_| {
+            let hint = if self.api_key_env == "OPENROUTER_API_KEY" {
+                "get a key at https://openrouter.ai; "
+            } else {
+                ""
+            };
+            DotError::InvalidData(format!(
+                ".ask requires the {var} environment variable ({hint}export it before \
+                 launching solite, or set it with `.env set {var} <key>`)",
+                var = self.api_key_env,
+            ))
+        })
+    }
+
+    fn completions_url(&self) -> String {
+        format!("{}/chat/completions", self.base_url.trim_end_matches('/'))
+    }
+}
+
+/// Read a non-negative integer setting from the environment.
+fn env_usize(name: &str) -> Result<Option<usize>, DotError> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| DotError::InvalidData(format!("{name} must be a number, got '{value}'"))),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Get the user's home directory using environment variables.
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// Progress reported while `.ask` runs.
+#[derive(Debug)]
+pub enum AskEvent<'a> {
+    /// A piece of the model's streamed answer.
+    Chunk(&'a str),
+    /// The answer failed to prepare and is being sent back for repair.
+    Repair {
+        /// 1-based repair attempt about to be made.
+        attempt: usize,
+        /// The configured `max_repairs`.
+        max: usize,
+        /// Why the previous answer did not prepare.
+        error: &'a SQLiteError,
+    },
+}
+
+/// The final answer of an `.ask` command.
+#[derive(Debug)]
+pub struct AskOutcome {
+    /// The SQL from the model's last answer, without Markdown fences.
+    pub sql: String,
+    /// Set when the last answer still failed to prepare.
+    pub error: Option<SQLiteError>,
+    /// Number of repair round-trips made.
+    pub repairs: usize,
+}
+
 /// Command to ask the AI assistant for SQL help.
 #[derive(Serialize, Debug, PartialEq)]
 pub struct AskCommand {
@@ -47,123 +262,295 @@ pub struct AskCommand {
 }
 
 impl AskCommand {
-    /// Build the full prompt including schema and question.
+    /// Build the full prompt including schema and question, from the
+    /// default template and settings.
     pub fn prompt(&self, runtime: &mut Runtime) -> String {
-        let schema = self.schema(runtime);
-        PROMPT
-            .replace("{SCHEMA}", &schema)
-            .replace("{QUESTION}", &self.message)
+        self.prompt_from_template(runtime, PROMPT, AskConfig::default().sample_rows)
     }
 
-    /// Extract the database schema for context.
-    fn schema(&self, runtime: &mut Runtime) -> String {
-        let result = runtime
-            .connection
-            .prepare("SELECT sql FROM sqlite_master WHERE type = 'table'");
-
-        let mut stmt = match result {
-            Ok((_, Some(stmt))) => stmt,
-            _ => return String::new(),
-        };
-
-        let mut schema = String::new();
-        loop {
-            match stmt.nextx() {
-                Ok(None) => break,
-                Ok(Some(row)) => {
-                    schema.push_str(row.value_at(0).as_str());
-                    schema.push('\n');
-                }
-                Err(_) => break,
-            }
-        }
-        schema
+    fn prompt_from_template(
+        &self,
+        runtime: &mut Runtime,
+        template: &str,
+        sample_rows: usize,
+    ) -> String {
+        let schema = schema_context(runtime, sample_rows);
+        template
+            .replace("{SCHEMA}", &schema)
+            .replace("{QUESTION}", &self.message)
     }
 
-    /// Execute the ask command, streaming AI responses.
+    /// Execute the ask command with the discovered [`AskConfig`].
     ///
     /// # Arguments
     ///
     /// * `runtime` - The runtime context containing the database connection
+    /// * `on_event` - Called with each streamed chunk and before each repair
     ///
     /// # Returns
     ///
-    /// A receiver for streaming response chunks, or an error if the API
-    /// key is not set or the request fails.
+    /// The final SQL, which may still carry a prepare error once the
+    /// repair attempts run out. Errors if the config is invalid, the API
+    /// key is not set, or a request fails.
     pub fn execute(
         &self,
         runtime: &mut Runtime,
-    ) -> Result<std::sync::mpsc::Receiver<anyhow::Result<String>>, DotError> {
-        let prompt = self.prompt(runtime);
-        open_router_completions(&prompt)
+        on_event: impl FnMut(AskEvent<'_>),
+    ) -> Result<AskOutcome, DotError> {
+        self.execute_with_config(runtime, &AskConfig::discover()?, on_event)
     }
+
+    /// Execute the ask command against an explicit provider config.
+    pub fn execute_with_config(
+        &self,
+        runtime: &mut Runtime,
+        config: &AskConfig,
+        mut on_event: impl FnMut(AskEvent<'_>),
+    ) -> Result<AskOutcome, DotError> {
+        let api_key = config.api_key()?;
+        let prompt =
+            self.prompt_from_template(runtime, &config.prompt_template()?, config.sample_rows);
+        let mut messages = vec![serde_json::json!({"role": "user", "content": prompt})];
+        let mut repairs = 0;
+        loop {
+            let response = chat_completions(config, api_key.as_deref(), &messages, &mut |chunk: &str| {
+                on_event(AskEvent::Chunk(chunk))
+            })
+            .map_err(|e| DotError::Command(e.to_string()))?;
+            let sql = extract_sql(&response);
+            let error = match check_sql(runtime, &sql) {
+                Ok(()) => None,
+                Err(error) => Some(error),
+            };
+            match error {
+                Some(error) if repairs < config.max_repairs => {
+                    repairs += 1;
+                    on_event(AskEvent::Repair {
+                        attempt: repairs,
+                        max: config.max_repairs,
+                        error: &error,
+                    });
+                    messages.push(serde_json::json!({"role": "assistant", "content": response}));
+                    messages.push(serde_json::json!({
+                        "role": "user",
+                        "content": format!(
+                            "That query failed to prepare in SQLite with this error:\n\n{error}\n\n\
+                             Reply with only the corrected SQL query."
+                        ),
+                    }));
+                }
+                error => return Ok(AskOutcome { sql, error, repairs }),
+            }
+        }
+    }
+}
+
+/// Render the schema context sent to the model: each object's `CREATE`
+/// statement, followed for tables by their sqlite-docs comments and up to
+/// `sample_rows` rows.
+fn schema_context(runtime: &mut Runtime, sample_rows: usize) -> String {
+    let result = runtime.connection.prepare(
+        "SELECT type, name, sql FROM sqlite_master \
+         WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%' \
+         ORDER BY CASE type WHEN 'table' THEN 0 WHEN 'view' THEN 1 WHEN 'index' THEN 2 ELSE 3 END, rowid",
+    );
+    let mut stmt = match result {
+        Ok((_, Some(stmt))) => stmt,
+        _ => return String::new(),
+    };
+
+    let mut objects = vec![];
+    while let Ok(Some(row)) = stmt.nextx() {
+        objects.push((
+            row.value_at(0).as_str().to_string(),
+            row.value_at(1).as_str().to_string(),
+            row.value_at(2).as_str().to_string(),
+        ));
+    }
+    drop(stmt);
+
+    let mut schema = String::new();
+    for (kind, name, sql) in objects {
+        schema.push_str(sql.trim_end());
+        schema.push_str(";\n");
+        if kind == "table" {
+            schema.push_str(&table_docs(&name, &sql));
+            if sample_rows > 0 {
+                schema.push_str(&table_samples(runtime, &name, sample_rows));
+            }
+        }
+        schema.push('\n');
+    }
+    schema
 }
 
-/// Make a streaming request to the OpenRouter API.
-fn open_router_completions(
-    prompt: &str,
-) -> Result<std::sync::mpsc::Receiver<anyhow::Result<String>>, DotError> {
-    let (tx, rx) = std::sync::mpsc::channel::<anyhow::Result<String>>();
+/// The sqlite-docs comments of a `CREATE TABLE` statement as `--` lines,
+/// one per table or column description and tag.
+fn table_docs(table: &str, sql: &str) -> String {
+    let Ok(program) = solite_parser::parse_program(sql) else {
+        return String::new();
+    };
+    let Some(solite_ast::Statement::CreateTable(create)) = program.statements.first() else {
+        return String::new();
+    };
+
+    let mut docs = String::new();
+    let mut push_doc = |subject: String, doc: &solite_ast::DocComment| {
+        if doc.is_empty() {
+            return;
+        }
+        docs.push_str(&format!("-- {subject}: {}\n", doc.description));
+        let mut tags: Vec<_> = doc.tags.iter().collect();
+        tags.sort();
+        for (tag, values) in tags {
+            for value in values {
+                docs.push_str(&format!("--   @{tag} {value}\n"));
+            }
+        }
+    };
+    if let Some(doc) = &create.doc {
+        push_doc(table.to_string(), doc);
+    }
+    for column in &create.columns {
+        if let Some(doc) = &column.doc {
+            push_doc(format!("{table}.{}", column.name), doc);
+        }
+    }
+    docs
+}
 
-    let api_key = std::env::var("OPENROUTER_API_KEY").map_err(|_| {
-        DotError::InvalidData(
-            ".ask requires the OPENROUTER_API_KEY environment variable \
-             (get a key at https://openrouter.ai; export it before launching \
-             solite, or set it with `.env set OPENROUTER_API_KEY <key>`)"
-                .to_string(),
-        )
-    })?;
+/// Up to `limit` rows of `table` as `--` lines, with a header of column
+/// names. Empty when the table has no rows or cannot be read.
+fn table_samples(runtime: &mut Runtime, table: &str, limit: usize) -> String {
+    let sql = format!("SELECT * FROM {} LIMIT {limit}", quote_identifier(table));
+    let mut stmt = match runtime.connection.prepare(&sql) {
+        Ok((_, Some(stmt))) => stmt,
+        _ => return String::new(),
+    };
+    let columns = match stmt.column_names() {
+        Ok(columns) => columns,
+        Err(_) => return String::new(),
+    };
+
+    let mut rows = vec![];
+    while let Ok(Some(row)) = stmt.next() {
+        let values: Vec<String> = row
+            .iter()
+            .map(|value| match &value.value {
+                ValueRefXValue::Null => "NULL".to_string(),
+                ValueRefXValue::Int(v) => v.to_string(),
+                ValueRefXValue::Double(v) => v.to_string(),
+                ValueRefXValue::Text(bytes) => {
+                    let text = String::from_utf8_lossy(bytes);
+                    if text.chars().count() > SAMPLE_VALUE_MAX_CHARS {
+                        let cut: String = text.chars().take(SAMPLE_VALUE_MAX_CHARS).collect();
+                        format!("{cut}…")
+                    } else {
+                        text.into_owned()
+                    }
+                }
+                ValueRefXValue::Blob(bytes) => format!("<blob {} bytes>", bytes.len()),
+            })
+            .collect();
+        rows.push(values.join(" | "));
+    }
+    if rows.is_empty() {
+        return String::new();
+    }
+
+    let mut samples = format!("-- sample rows from {table}:\n-- {}\n", columns.join(" | "));
+    for row in rows {
+        samples.push_str(&format!("-- {row}\n"));
+    }
+    samples
+}
+
+/// The SQL in a model answer: the body of the first fenced code block if
+/// there is one, otherwise the whole answer.
+fn extract_sql(response: &str) -> String {
+    if let Some(start) = response.find("```") {
+        let block = &response[start + 3..];
+        // skip the info string, e.g. ```sql
+        let block = block.split_once('\n').map_or("", |(_, body)| body);
+        let body = block.find("```").map_or(block, |end| &block[..end]);
+        return body.trim().to_string();
+    }
+    response.trim().to_string()
+}
+
+/// Prepare the query in `sql` the way the REPL would run it, with
+/// `.param` values and replacement scans for file paths, without stepping
+/// it. Only the first statement is checked: the model is asked for a
+/// single query. Remote connections execute what they prepare, so there
+/// it is wrapped in `EXPLAIN` instead.
+fn check_sql(runtime: &Runtime, sql: &str) -> Result<(), SQLiteError> {
+    if runtime.connection.is_remote() {
+        runtime.prepare_with_replacement_scans(&format!("EXPLAIN {sql}"))?;
+    } else {
+        runtime.prepare_with_replacement_scans(sql)?;
+    }
+    Ok(())
+}
 
-    let url = "https://openrouter.ai/api/v1/chat/completions";
+/// Make a streaming request to an OpenAI-compatible chat completions API,
+/// passing each content delta to `on_chunk`. Returns the full answer.
+fn chat_completions(
+    config: &AskConfig,
+    api_key: Option<&str>,
+    messages: &[Value],
+    on_chunk: &mut dyn FnMut(&str),
+) -> anyhow::Result<String> {
+    let url = config.completions_url();
 
     let payload = serde_json::json!({
-        "model": "openai/gpt-4o",
-        "messages": [{"role": "user", "content": prompt}],
+        "model": config.model,
+        "messages": messages,
         "stream": true
     });
 
-    std::thread::spawn(move || -> anyhow::Result<()> {
-        let resp = ureq::post(url)
-            .header("Authorization", &format!("Bearer {}", api_key))
-            .header("Content-Type", "application/json")
-            .header("Accept", "text/event-stream")
-            .send(&payload.to_string())?;
-
-        let reader = BufReader::new(resp.into_body().into_reader());
-        for line in reader.lines() {
-            let line = match line {
-                Ok(line) => line,
-                Err(_) => break,
-            };
-            let trimmed = line.trim();
+    let mut request = ureq::post(&url)
+        .header("Content-Type", "application/json")
+        .header("Accept", "text/event-stream");
+    if let Some(api_key) = api_key {
+        request = request.header("Authorization", &format!("Bearer {}", api_key));
+    }
+    let resp = request
+        .send(&payload.to_string())
+        .map_err(|e| anyhow::anyhow!("request to {url} failed: {e}"))?;
+
+    let mut response = String::new();
+    let reader = BufReader::new(resp.into_body().into_reader());
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        let trimmed = line.trim();
 
-            if trimmed.is_empty() || trimmed.starts_with(':') {
-                continue;
-            }
+        if trimmed.is_empty() || trimmed.starts_with(':') {
+            continue;
+        }
 
-            if let Some(data) = trimmed.strip_prefix("data: ") {
-                if data == "[DONE]" {
-                    break;
-                }
+        if let Some(data) = trimmed.strip_prefix("data: ") {
+            if data == "[DONE]" {
+                break;
+            }
 
-                if let Ok(v) = serde_json::from_str::<Value>(data) {
-                    if let Some(content) = v["choices"]
-                        .get(0)
-                        .and_then(|c| c.get("delta"))
-                        .and_then(|d| d.get("content"))
-                        .and_then(|c| c.as_str())
-                    {
-                        if tx.send(Ok(content.to_string())).is_err() {
-                            break;
-                        }
-                    }
+            if let Ok(v) = serde_json::from_str::<Value>(data) {
+                if let Some(content) = v["choices"]
+                    .get(0)
+                    .and_then(|c| c.get("delta"))
+                    .and_then(|d| d.get("content"))
+                    .and_then(|c| c.as_str())
+                {
+                    on_chunk(content);
+                    response.push_str(content);
                 }
             }
         }
-        Ok(())
-    });
+    }
 
-    Ok(rx)
+    Ok(response)
 }
 
 #[cfg(test)]
@@ -191,6 +578,297 @@ mod tests {
         assert!(prompt.contains("CREATE TABLE users"));
     }
 
+    #[test]
+    fn test_schema_context_includes_views_indexes_docs_and_samples() {
+        let mut runtime = Runtime::new(None).unwrap();
+        runtime
+            .connection
+            .execute_script(
+                "CREATE TABLE students (
+                   --! All students at Foo University.
+                   --! @details https://foo.edu/students
+
+                   --- Student ID assigned at orientation
+                   --- @example 'S10483'
+                   student_id TEXT PRIMARY KEY,
+                   name TEXT
+                 );
+                 CREATE INDEX idx_students_name ON students(name);
+                 CREATE VIEW named AS SELECT name FROM students;
+                 INSERT INTO students VALUES ('S1', 'alex'), ('S2', 'sam'), ('S3', 'kim'), ('S4', 'lee');",
+            )
+            .unwrap();
+
+        let schema = schema_context(&mut runtime, 2);
+        assert!(schema.contains("CREATE INDEX idx_students_name"), "{schema}");
+        assert!(schema.contains("CREATE VIEW named"), "{schema}");
+        assert!(
+            schema.contains("-- students: All students at Foo University.\n--   @details https://foo.edu/students\n"),
+            "{schema}"
+        );
+        assert!(
+            schema.contains("-- students.student_id: Student ID assigned at orientation\n--   @example 'S10483'\n"),
+            "{schema}"
+        );
+        assert!(
+            schema.contains("-- sample rows from students:\n-- student_id | name\n-- S1 | alex\n-- S2 | sam\n"),
+            "{schema}"
+        );
+        assert!(!schema.contains("S3"), "{schema}");
+
+        let schema = schema_context(&mut runtime, 0);
+        assert!(!schema.contains("sample rows"), "{schema}");
+    }
+
+    #[test]
+    fn test_extract_sql() {
+        assert_eq!(extract_sql("  SELECT 1;\n"), "SELECT 1;");
+        assert_eq!(
+            extract_sql("Here you go:\n```sql\nSELECT 1;\n```\nThat counts."),
+            "SELECT 1;"
+        );
+        assert_eq!(extract_sql("```\nSELECT 2\n```"), "SELECT 2");
+    }
+
+    #[test]
+    fn test_check_sql_prepares_the_query() {
+        let runtime = Runtime::new(None).unwrap();
+        runtime.connection.execute("CREATE TABLE t(a)").unwrap();
+        assert!(check_sql(&runtime, "SELECT a FROM t").is_ok());
+        let error = check_sql(&runtime, "SELECT b FROM t").unwrap_err();
+        assert!(error.message.contains("no such column: b"), "{error}");
+        // only the first statement is the query
+        assert!(check_sql(&runtime, "SELECT a FROM t; trailing prose").is_ok());
+        // nothing was run
+        assert!(check_sql(&runtime, "DROP TABLE t").is_ok());
+        assert!(check_sql(&runtime, "SELECT a FROM t").is_ok());
+    }
+
+    #[test]
+    fn test_check_sql_uses_replacement_scans() {
+        let dir = tempfile::tempdir().unwrap();
+        let csv = dir.path().join("nums.csv");
+        std::fs::write(&csv, "a,b\n1,2\n").unwrap();
+
+        let runtime = Runtime::new(None).unwrap();
+        let sql = format!("SELECT sum(b) FROM \"{}\" WHERE a = :a", csv.to_str().unwrap());
+        assert!(check_sql(&runtime, &sql).is_ok());
+        let missing = check_sql(&runtime, "SELECT * FROM \"solite_missing_ask.csv\"");
+        assert!(missing.is_err());
+    }
+
+    /// An OpenAI-compatible server that answers one request per entry in
+    /// `responses`, streaming its chunks as SSE deltas, and hands back the
+    /// raw requests it received.
+    fn stub_server(
+        responses: &'static [&'static [&'static str]],
+    ) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut requests = vec![];
+            for chunks in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                // read headers, then the Content-Length body
+                let body_start = loop {
+                    let n = stream.read(&mut buf).unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break pos + 4;
+                    }
+                };
+                let head = String::from_utf8_lossy(&request[..body_start]).to_lowercase();
+                let content_length: usize = head
+                    .lines()
+                    .find_map(|l| l.strip_prefix("content-length:"))
+                    .map(|v| v.trim().parse().unwrap())
+                    .unwrap_or(0);
+                while request.len() < body_start + content_length {
+                    let n = stream.read(&mut buf).unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                }
+                let mut body = String::new();
+                for chunk in *chunks {
+                    let event = serde_json::json!({"choices": [{"delta": {"content": chunk}}]});
+                    body.push_str(&format!("data: {event}\n\n"));
+                }
+                body.push_str("data: [DONE]\n\n");
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+                requests.push(String::from_utf8_lossy(&request).into_owned());
+            }
+            requests
+        });
+        (base_url, handle)
+    }
+
+    #[test]
+    fn test_execute_with_config_streams_from_custom_endpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let prompt_file = dir.path().join("prompt.txt");
+        std::fs::write(&prompt_file, "custom prompt: {QUESTION}").unwrap();
+
+        let (base_url, server) = stub_server(&[&["SELECT ", "1;"]]);
+        let config = AskConfig {
+            base_url,
+            model: "local-model".to_string(),
+            api_key_env: String::new(),
+            prompt_file: Some(prompt_file),
+            ..Default::default()
+        };
+        let cmd = AskCommand {
+            message: "anything".to_string(),
+        };
+        let mut runtime = Runtime::new(None).unwrap();
+        let mut streamed = String::new();
+        let outcome = cmd
+            .execute_with_config(&mut runtime, &config, |event| {
+                if let AskEvent::Chunk(chunk) = event {
+                    streamed.push_str(chunk);
+                }
+            })
+            .unwrap();
+        assert_eq!(streamed, "SELECT 1;");
+        assert_eq!(outcome.sql, "SELECT 1;");
+        assert!(outcome.error.is_none());
+        assert_eq!(outcome.repairs, 0);
+
+        let requests = server.join().unwrap();
+        let request = &requests[0];
+        assert!(request.starts_with("POST /v1/chat/completions"), "{request}");
+        assert!(request.contains("\"model\":\"local-model\""), "{request}");
+        assert!(request.contains("custom prompt: anything"), "{request}");
+        assert!(!request.to_lowercase().contains("authorization"), "{request}");
+    }
+
+    #[test]
+    fn test_prepare_error_is_sent_back_for_repair() {
+        let (base_url, server) = stub_server(&[
+            &["SELECT nme FROM users;"],
+            &["```sql\nSELECT name FROM users;\n```"],
+        ]);
+        let config = AskConfig {
+            base_url,
+            api_key_env: String::new(),
+            ..Default::default()
+        };
+        let cmd = AskCommand {
+            message: "list user names".to_string(),
+        };
+        let mut runtime = Runtime::new(None).unwrap();
+        runtime
+            .connection
+            .execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)")
+            .unwrap();
+        let mut repair_errors = vec![];
+        let outcome = cmd
+            .execute_with_config(&mut runtime, &config, |event| {
+                if let AskEvent::Repair { attempt, max, error } = event {
+                    repair_errors.push((attempt, max, error.message.clone()));
+                }
+            })
+            .unwrap();
+        assert_eq!(outcome.sql, "SELECT name FROM users;");
+        assert!(outcome.error.is_none());
+        assert_eq!(outcome.repairs, 1);
+        assert_eq!(
+            repair_errors,
+            vec![(1, 2, "no such column: nme".to_string())]
+        );
+
+        let requests = server.join().unwrap();
+        let repair = &requests[1];
+        assert!(repair.contains("\"role\":\"assistant\""), "{repair}");
+        assert!(repair.contains("SELECT nme FROM users;"), "{repair}");
+        assert!(repair.contains("no such column: nme"), "{repair}");
+    }
+
+    #[test]
+    fn test_repairs_stop_at_max_repairs() {
+        let (base_url, server) = stub_server(&[&["SELECT oops FROM nowhere"], &["SELECT still FROM nowhere"]]);
+        let config = AskConfig {
+            base_url,
+            api_key_env: String::new(),
+            max_repairs: 1,
+            ..Default::default()
+        };
+        let cmd = AskCommand {
+            message: "test".to_string(),
+        };
+        let mut runtime = Runtime::new(None).unwrap();
+        let outcome = cmd
+            .execute_with_config(&mut runtime, &config, |_| {})
+            .unwrap();
+        assert_eq!(outcome.sql, "SELECT still FROM nowhere");
+        assert_eq!(outcome.repairs, 1);
+        assert!(outcome.error.unwrap().message.contains("no such table"));
+        assert_eq!(server.join().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_request_failure_is_an_error() {
+        // nothing listens on this port once the listener is dropped
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let config = AskConfig {
+            base_url,
+            api_key_env: String::new(),
+            ..Default::default()
+        };
+        let cmd = AskCommand {
+            message: "test".to_string(),
+        };
+        let mut runtime = Runtime::new(None).unwrap();
+        let result = cmd.execute_with_config(&mut runtime, &config, |_| {});
+        assert!(matches!(result, Err(DotError::Command(_))));
+    }
+
+    #[test]
+    fn test_config_load_resolves_prompt_file_and_rejects_unknown_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(
+            &path,
+            "base_url = \"http://localhost:8080/v1\"\nmodel = \"m\"\nprompt_file = \"p.txt\"\nmax_repairs = 5\n",
+        )
+        .unwrap();
+        let config = AskConfig::load(&path).unwrap();
+        assert_eq!(config.base_url, "http://localhost:8080/v1");
+        assert_eq!(config.model, "m");
+        assert_eq!(config.max_repairs, 5);
+        // unspecified keys keep their defaults
+        assert_eq!(config.api_key_env, "OPENROUTER_API_KEY");
+        assert_eq!(config.sample_rows, 3);
+        assert_eq!(config.prompt_file, Some(dir.path().join("p.txt")));
+        assert_eq!(
+            config.completions_url(),
+            "http://localhost:8080/v1/chat/completions"
+        );
+
+        std::fs::write(&path, "modle = \"typo\"\n").unwrap();
+        assert!(matches!(AskConfig::load(&path), Err(DotError::InvalidData(_))));
+    }
+
+    #[test]
+    fn test_config_discovered_from_parent_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(CONFIG_FILE_NAME), "model = \"found\"\n").unwrap();
+        let nested = dir.path().join("a/b");
+        std::fs::create_dir_all(&nested).unwrap();
+        let config = AskConfig::discover_from(Some(nested)).unwrap();
+        assert_eq!(config.model, "found");
+    }
+
     #[test]
     fn test_execute_missing_api_key() {
         // Ensure the API key is not set
@@ -201,7 +879,7 @@ mod tests {
         };
 
         let mut runtime = Runtime::new(None).unwrap();
-        let result = cmd.execute(&mut runtime);
+        let result = cmd.execute(&mut runtime, |_| {});
 
         match result {
             Err(DotError::InvalidData(msg)) => assert!(msg.contains("OPENROUTER_API_KEY")),
@@ -1,15 +1,22 @@
 //! Query result export command.
 //!
 //! This module implements the `.export` command which exports query results
-//! to a file in various formats (CSV, JSON, etc.).
+//! to a file in various formats (CSV, JSON, Parquet, etc.).
 //!
 //! # Usage
 //!
 //! ```sql
 //! .export output.csv SELECT * FROM users
 //! .export :date.json SELECT * FROM orders  -- Uses parameter substitution
+//! .export s3://bucket/big.csv.zst SELECT * FROM events
+//! .export events.arrow SELECT * FROM events  -- typed, columnar
+//! .export slice.sql SELECT * FROM users WHERE org = 7  -- INSERT statements
 //! ```
 //!
+//! Object store targets (`s3://`, `t3://`, `gs://`, `az://`, `file://`,
+//! `memory://`) are streamed as a multipart upload, so large exports never
+//! sit in memory.
+//!
 //! # Parameter Substitution
 //!
 //! The target path supports parameter substitution using `:param_name` syntax.
@@ -17,7 +24,7 @@
 
 use crate::dot::DotError;
 #[cfg(feature = "object_store")]
-use crate::exporter::write_output_to_bytes;
+use crate::exporter::write_output_to_writer;
 use crate::exporter::{format_from_path, output_from_path, write_output, BlobLimit};
 #[cfg(feature = "object_store")]
 use crate::object_store;
@@ -101,10 +108,11 @@ impl ExportCommand {
         {
             let target_str = self.target.to_string_lossy();
             if object_store::is_object_store_url(&target_str) {
-                let data = write_output_to_bytes(&mut self.statement, format, BlobLimit::Default)
-                    .map_err(|e| DotError::Io(std::io::Error::other(e.to_string())))?;
-                object_store::upload(&target_str, data)
-                    .map_err(|e| DotError::Io(std::io::Error::other(e.to_string())))?;
+                let statement = &mut self.statement;
+                object_store::upload_to_url(&target_str, |writer| {
+                    write_output_to_writer(statement, writer, format, BlobLimit::Default)
+                })
+                .map_err(|e| DotError::Io(std::io::Error::other(e.to_string())))?;
                 return Ok(());
             }
         }
@@ -14,7 +14,7 @@ const COMMANDS: &[HelpEntry] = &[
         name: "ask",
         aliases: &[],
         usage: ".ask <question>",
-        description: "Ask the AI assistant (shorthand: ?<question>; requires OPENROUTER_API_KEY)",
+        description: "Ask the AI assistant (shorthand: ?<question>; provider set in solite-ask.toml)",
     },
     HelpEntry {
         name: "bench",
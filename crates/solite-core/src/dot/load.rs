@@ -9,12 +9,20 @@
 //! .load ./myextension.so              -- Load from file
 //! .load ./myextension.so entry_point  -- Load with custom entry point
 //! .load uv:sqlite-vec                 -- Load from Python package via uv
+//! .load s3://bucket/ext/myextension.so -- Download from object storage
 //! ```
 //!
 //! # Sources
 //!
 //! - **Path**: Direct path to a `.so`, `.dll`, or `.dylib` file
 //! - **UV**: Python package containing SQLite extensions, installed via `uv tool`
+//! - **Object storage**: `s3://`, `gs://`, `az://` and other object store
+//!   URLs are downloaded to a temporary file first (requires the
+//!   `object_store` feature)
+//!
+//! On a remote connection the extension is loaded by the server, so the
+//! path is a path on the server; `uv:` packages and object store URLs,
+//! which resolve to local files, are refused.
 
 use crate::dot::DotError;
 use crate::Connection;
@@ -197,12 +205,26 @@ impl LoadCommand {
     ///
     /// Information about the loaded extension source, or an error if loading fails.
     pub fn execute(&self, connection: &mut Connection) -> Result<LoadCommandSource, DotError> {
+        if connection.is_remote() && (self.is_uv || self.path.contains("://")) {
+            return Err(DotError::Extension(
+                "On a remote connection, .load takes a path on the server".into(),
+            ));
+        }
         if self.is_uv {
             uv_load(connection, &self.path, &self.entrypoint).map(|path| LoadCommandSource::Uv {
                 directory: path,
                 package: self.path.clone(),
             })
         } else {
+            #[cfg(feature = "object_store")]
+            if crate::object_store::is_object_store_url(&self.path) {
+                let dir = connection.download_dir()?;
+                let local = crate::object_store::download(&self.path, dir)?;
+                connection
+                    .load_extension(&local.to_string_lossy(), &self.entrypoint)
+                    .map_err(|e| DotError::Extension(e.to_string()))?;
+                return Ok(LoadCommandSource::Path(self.path.clone()));
+            }
             connection
                 .load_extension(&self.path, &self.entrypoint)
                 .map_err(|e| DotError::Extension(e.to_string()))?;
@@ -56,7 +56,7 @@ mod vegalite;
 pub mod stream;
 
 pub use crate::dot::{
-    ask::AskCommand,
+    ask::{AskCommand, AskConfig, AskEvent, AskOutcome},
     bench::BenchCommand,
     call::CallCommand,
     clear::ClearCommand,
@@ -11,6 +11,7 @@
 //! .run file.sql procedureName
 //! .run file.sql --name=alex --age 20
 //! .run file.sql procedureName --name alex --age=20
+//! .run s3://bucket/scripts/report.sql
 //! ```
 
 use serde::Serialize;
@@ -1,7 +1,8 @@
 //! Data export functionality for SQL query results.
 //!
 //! This module provides utilities for exporting SQL query results to various
-//! formats including CSV, TSV, JSON, NDJSON, clipboard, and raw values.
+//! formats including CSV, TSV, JSON, NDJSON, Markdown, SQL, Excel, Parquet,
+//! Arrow IPC, clipboard, and raw values.
 //!
 //! # Supported Formats
 //!
@@ -9,9 +10,18 @@
 //! - **TSV**: Tab-separated values
 //! - **JSON**: JSON array of objects
 //! - **NDJSON**: Newline-delimited JSON (one object per line)
+//! - **Markdown**: GitHub-flavored Markdown table
+//! - **SQL**: `INSERT INTO ... VALUES` statements, replayable into another database
+//! - **Xlsx**: Excel workbook
+//! - **Parquet**: Columnar Parquet file, one row group per batch of rows
+//! - **Arrow**: Arrow IPC file format (also readable as Feather v2)
 //! - **Clipboard**: HTML table copied to system clipboard
 //! - **Value**: Raw value output (single cell)
 //!
+//! Xlsx, Parquet and Arrow need the `binary_exports` feature (on by
+//! default); without it those formats are still recognized but fail to
+//! export.
+//!
 //! # Example
 //!
 //! ```ignore
@@ -30,6 +40,10 @@ use std::{
 };
 
 use crate::sqlite::{OwnedValue, Statement, ValueRefX, ValueRefXValue};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "binary_exports")]
+mod arrow;
 
 /// Errors that can occur during export operations.
 #[derive(Debug)]
@@ -65,6 +79,19 @@ pub enum ExportError {
         /// The active limit in bytes.
         limit: u64,
     },
+    /// Arrow or Parquet encoding error.
+    Arrow(String),
+    /// Excel workbook error.
+    Xlsx(String),
+    /// A value didn't fit its column's Arrow type (Parquet/Arrow exports).
+    TypeMismatch {
+        /// Name of the column.
+        column: String,
+        /// SQL type the column was exported as.
+        expected: String,
+        /// SQL type of the offending value.
+        found: String,
+    },
 }
 
 impl fmt::Display for ExportError {
@@ -99,6 +126,18 @@ impl fmt::Display for ExportError {
                 limit,
                 size.div_ceil(1024 * 1024),
             ),
+            ExportError::Arrow(msg) => write!(f, "Arrow error: {}", msg),
+            ExportError::Xlsx(msg) => write!(f, "Excel error: {}", msg),
+            ExportError::TypeMismatch {
+                column,
+                expected,
+                found,
+            } => write!(
+                f,
+                "column '{}' is exported as {} but holds a {} value; CAST it in the \
+                 query (e.g. CAST({} AS TEXT)) to pick the column type",
+                column, expected, found, column,
+            ),
         }
     }
 }
@@ -132,8 +171,29 @@ impl From<serde_json::Error> for ExportError {
     }
 }
 
+#[cfg(feature = "binary_exports")]
+impl From<arrow_schema::ArrowError> for ExportError {
+    fn from(e: arrow_schema::ArrowError) -> Self {
+        ExportError::Arrow(e.to_string())
+    }
+}
+
+#[cfg(feature = "binary_exports")]
+impl From<parquet::errors::ParquetError> for ExportError {
+    fn from(e: parquet::errors::ParquetError) -> Self {
+        ExportError::Arrow(e.to_string())
+    }
+}
+
+#[cfg(feature = "binary_exports")]
+impl From<rust_xlsxwriter::XlsxError> for ExportError {
+    fn from(e: rust_xlsxwriter::XlsxError) -> Self {
+        ExportError::Xlsx(e.to_string())
+    }
+}
+
 /// Output format for exported data.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ExportFormat {
     /// Comma-separated values.
     Csv,
@@ -143,6 +203,17 @@ pub enum ExportFormat {
     Json,
     /// Newline-delimited JSON.
     Ndjson,
+    /// GitHub-flavored Markdown table.
+    Markdown,
+    /// `INSERT` statements into `table` (default: the query's source
+    /// table, or `data`).
+    SqlInsert { table: Option<String> },
+    /// Excel workbook.
+    Xlsx,
+    /// Parquet file.
+    Parquet,
+    /// Arrow IPC file (Feather v2).
+    Arrow,
     /// Raw value output (single cell).
     Value,
     /// HTML table to clipboard.
@@ -161,7 +232,7 @@ pub const DEFAULT_FILE_BLOB_LIMIT: u64 = 10 * 1024 * 1024;
 /// truncation, so exports can't silently dump or mangle a giant blob. The
 /// [`ExportFormat::Value`] path is always unlimited: explicitly requesting a
 /// single raw value is intentional.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum BlobLimit {
     /// Format-dependent default: [`DEFAULT_CLIPBOARD_BLOB_LIMIT`] for
     /// clipboard exports, [`DEFAULT_FILE_BLOB_LIMIT`] otherwise.
@@ -459,6 +530,197 @@ fn write_ndjson<W: Write>(
     Ok(())
 }
 
+/// Escape a cell for a GitHub-flavored Markdown table.
+fn markdown_escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+/// Write statement results as a GitHub-flavored Markdown table.
+fn write_markdown<W: Write>(
+    stmt: &mut Statement,
+    mut output: W,
+    blob_limit: Option<u64>,
+) -> Result<(), ExportError> {
+    let columns = stmt.column_names().map_err(|e| ExportError::Sql(format!("{:?}", e)))?;
+
+    let header: Vec<String> = columns.iter().map(|c| markdown_escape(c)).collect();
+    writeln!(output, "| {} |", header.join(" | "))?;
+    writeln!(output, "|{}", " --- |".repeat(columns.len()))?;
+
+    loop {
+        match stmt.next() {
+            Ok(Some(row)) => {
+                check_blob_limit(&row, &columns, blob_limit)?;
+                let cells = row
+                    .iter()
+                    .map(|value| value_to_string(value).map(|s| markdown_escape(&s)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                writeln!(output, "| {} |", cells.join(" | "))?;
+            }
+            Ok(None) => break,
+            Err(e) => return Err(ExportError::Sql(e.to_string())),
+        }
+    }
+
+    Ok(())
+}
+
+/// Rows per `INSERT` statement in SQL exports.
+const SQL_INSERT_BATCH_ROWS: usize = 500;
+
+/// Render a value as a SQL literal that reads back as the same value and
+/// storage class.
+fn value_to_sql_literal(value: &ValueRefX) -> Result<String, ExportError> {
+    match &value.value {
+        ValueRefXValue::Null => Ok("NULL".to_owned()),
+        ValueRefXValue::Int(v) => Ok(v.to_string()),
+        ValueRefXValue::Double(v) if v.is_nan() => Ok("NULL".to_owned()),
+        // SQLite reads overflowing literals as ±Inf
+        ValueRefXValue::Double(v) if v.is_infinite() => {
+            Ok(if *v > 0.0 { "9e999" } else { "-9e999" }.to_owned())
+        }
+        // Debug formatting keeps a `.0`/exponent, so the value isn't read
+        // back as INTEGER
+        ValueRefXValue::Double(v) => Ok(format!("{:?}", v)),
+        ValueRefXValue::Text(bytes) => {
+            let text = std::str::from_utf8(bytes).map_err(|_| ExportError::InvalidUtf8)?;
+            Ok(format!("'{}'", text.replace('\'', "''")))
+        }
+        ValueRefXValue::Blob(bytes) => Ok(blob_to_hex_literal(bytes)),
+    }
+}
+
+/// The table name for SQL exports: `table` when given, else the source
+/// table when every column comes from the same one, else `data`.
+fn sql_insert_table(stmt: &Statement, table: Option<&str>) -> String {
+    if let Some(table) = table {
+        return table.to_owned();
+    }
+    let meta = stmt.column_meta();
+    let origin = meta.first().and_then(|c| c.origin_table.clone());
+    match origin {
+        Some(origin) if meta.iter().all(|c| c.origin_table.as_ref() == Some(&origin)) => origin,
+        _ => "data".to_owned(),
+    }
+}
+
+/// Write statement results as `INSERT INTO ... VALUES` statements in
+/// batches of [`SQL_INSERT_BATCH_ROWS`], wrapped in a transaction so the
+/// output can be replayed into another database with `sqlite3 db < out.sql`.
+fn write_sql_insert<W: Write>(
+    stmt: &mut Statement,
+    mut output: W,
+    table: Option<&str>,
+    blob_limit: Option<u64>,
+) -> Result<(), ExportError> {
+    let columns = stmt.column_names().map_err(|e| ExportError::Sql(format!("{:?}", e)))?;
+    let insert = format!(
+        "INSERT INTO {}({}) VALUES",
+        crate::sqlite::quote_identifier(&sql_insert_table(stmt, table)),
+        columns
+            .iter()
+            .map(|c| crate::sqlite::quote_identifier(c))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    writeln!(output, "BEGIN;")?;
+    let mut batch_rows = 0;
+    loop {
+        match stmt.next() {
+            Ok(Some(row)) => {
+                check_blob_limit(&row, &columns, blob_limit)?;
+                let values = row
+                    .iter()
+                    .map(value_to_sql_literal)
+                    .collect::<Result<Vec<_>, _>>()?;
+                if batch_rows == 0 {
+                    writeln!(output, "{}", insert)?;
+                } else {
+                    writeln!(output, ",")?;
+                }
+                write!(output, "  ({})", values.join(", "))?;
+                batch_rows += 1;
+                if batch_rows == SQL_INSERT_BATCH_ROWS {
+                    writeln!(output, ";")?;
+                    batch_rows = 0;
+                }
+            }
+            Ok(None) => break,
+            Err(e) => return Err(ExportError::Sql(e.to_string())),
+        }
+    }
+    if batch_rows > 0 {
+        writeln!(output, ";")?;
+    }
+    writeln!(output, "COMMIT;")?;
+
+    Ok(())
+}
+
+/// Integers beyond this lose precision as Excel numbers, so they're
+/// written as text instead.
+#[cfg(feature = "binary_exports")]
+const XLSX_MAX_SAFE_INTEGER: i64 = 1 << 53;
+
+/// Write statement results as an Excel workbook with a single sheet and a
+/// bold header row.
+///
+/// The xlsx container is a zip archive, so the workbook is assembled in
+/// memory and written out at the end.
+#[cfg(feature = "binary_exports")]
+fn write_xlsx<W: Write>(
+    stmt: &mut Statement,
+    mut output: W,
+    blob_limit: Option<u64>,
+) -> Result<(), ExportError> {
+    use rust_xlsxwriter::{Format, Workbook};
+
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    let bold = Format::new().set_bold();
+
+    let columns = stmt.column_names().map_err(|e| ExportError::Sql(format!("{:?}", e)))?;
+    for (col, name) in columns.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, name, &bold)?;
+    }
+
+    let mut row_num: u32 = 0;
+    loop {
+        match stmt.next() {
+            Ok(Some(row)) => {
+                check_blob_limit(&row, &columns, blob_limit)?;
+                row_num += 1;
+                for (col, value) in row.iter().enumerate() {
+                    let col = col as u16;
+                    match &value.value {
+                        ValueRefXValue::Null => {}
+                        ValueRefXValue::Int(v) if v.unsigned_abs() <= XLSX_MAX_SAFE_INTEGER as u64 => {
+                            sheet.write_number(row_num, col, *v as f64)?;
+                        }
+                        ValueRefXValue::Double(v) if v.is_finite() => {
+                            sheet.write_number(row_num, col, *v)?;
+                        }
+                        _ => {
+                            sheet.write_string(row_num, col, value_to_string(value)?)?;
+                        }
+                    }
+                }
+            }
+            Ok(None) => break,
+            Err(e) => return Err(ExportError::Sql(e.to_string())),
+        }
+    }
+    sheet.autofit();
+
+    output.write_all(&workbook.save_to_buffer()?)?;
+    output.flush()?;
+    Ok(())
+}
+
 /// Write statement results to clipboard as HTML table.
 /// Returns the number of rows written; the caller is responsible for any
 /// user-facing confirmation message.
@@ -585,7 +847,9 @@ pub fn output_from_path(path: &Path) -> Result<Box<dyn Write>, ExportError> {
     }
 }
 
-/// Write statement results to output in the specified format.
+/// Write statement results to output in the specified format. Remote
+/// statements are exported on the server when it can (see
+/// [`Statement::export_on_server`]).
 ///
 /// `blob_limit` bounds the raw size of any BLOB cell (see [`BlobLimit`]);
 /// the `Value` format is never limited.
@@ -594,43 +858,91 @@ pub fn output_from_path(path: &Path) -> Result<Box<dyn Write>, ExportError> {
 /// and need a caller-printed confirmation), `None` for stream formats.
 pub fn write_output(
     stmt: &mut Statement,
-    output: Box<dyn Write>,
+    mut output: Box<dyn Write>,
     format: ExportFormat,
     blob_limit: BlobLimit,
 ) -> Result<Option<usize>, ExportError> {
+    if format != ExportFormat::Clipboard {
+        if let Some(result) = stmt.export_on_server(&format, blob_limit, &mut output) {
+            result.map_err(|e| ExportError::Sql(e.to_string()))?;
+            output.flush()?;
+            return Ok(None);
+        }
+    }
     let limit = blob_limit.resolve(&format);
     match format {
         ExportFormat::Csv => write_csv(stmt, output, limit).map(|()| None),
         ExportFormat::Tsv => write_tsv(stmt, output, limit).map(|()| None),
         ExportFormat::Json => write_json(stmt, output, limit).map(|()| None),
         ExportFormat::Ndjson => write_ndjson(stmt, output, limit).map(|()| None),
+        ExportFormat::Markdown => write_markdown(stmt, output, limit).map(|()| None),
+        ExportFormat::SqlInsert { table } => {
+            write_sql_insert(stmt, output, table.as_deref(), limit).map(|()| None)
+        }
+        #[cfg(feature = "binary_exports")]
+        ExportFormat::Xlsx => write_xlsx(stmt, output, limit).map(|()| None),
+        #[cfg(feature = "binary_exports")]
+        ExportFormat::Parquet => arrow::write_parquet(stmt, output, limit).map(|()| None),
+        #[cfg(feature = "binary_exports")]
+        ExportFormat::Arrow => arrow::write_arrow_ipc(stmt, output, limit).map(|()| None),
+        #[cfg(not(feature = "binary_exports"))]
+        ExportFormat::Xlsx | ExportFormat::Parquet | ExportFormat::Arrow => {
+            Err(binary_exports_disabled())
+        }
         ExportFormat::Clipboard => write_clipboard(stmt, limit).map(Some),
         ExportFormat::Value => write_value(stmt, output).map(|()| None),
     }
 }
 
-/// Write statement results to an in-memory buffer.
-#[cfg(feature = "object_store")]
-pub fn write_output_to_bytes(
+/// Write statement results to a borrowed writer, for sinks that must be
+/// finished by the caller (such as object store uploads).
+///
+/// Clipboard exports are rejected, since they have no byte stream.
+pub fn write_output_to_writer(
     stmt: &mut Statement,
+    output: &mut dyn Write,
     format: ExportFormat,
     blob_limit: BlobLimit,
-) -> Result<Vec<u8>, ExportError> {
-    let mut buf = Vec::new();
+) -> Result<(), ExportError> {
+    if format != ExportFormat::Clipboard {
+        if let Some(result) = stmt.export_on_server(&format, blob_limit, output) {
+            return result.map(|_| ()).map_err(|e| ExportError::Sql(e.to_string()));
+        }
+    }
     let limit = blob_limit.resolve(&format);
     match format {
-        ExportFormat::Csv => write_csv(stmt, &mut buf, limit)?,
-        ExportFormat::Tsv => write_tsv(stmt, &mut buf, limit)?,
-        ExportFormat::Json => write_json(stmt, &mut buf, limit)?,
-        ExportFormat::Ndjson => write_ndjson(stmt, &mut buf, limit)?,
-        ExportFormat::Value => write_value(stmt, &mut buf)?,
-        ExportFormat::Clipboard => {
-            return Err(ExportError::Io(std::io::Error::other(
-                "clipboard export is not supported for remote targets",
-            )));
+        ExportFormat::Csv => write_csv(stmt, output, limit),
+        ExportFormat::Tsv => write_tsv(stmt, output, limit),
+        ExportFormat::Json => write_json(stmt, output, limit),
+        ExportFormat::Ndjson => write_ndjson(stmt, output, limit),
+        ExportFormat::Markdown => write_markdown(stmt, output, limit),
+        ExportFormat::SqlInsert { table } => {
+            write_sql_insert(stmt, output, table.as_deref(), limit)
+        }
+        #[cfg(feature = "binary_exports")]
+        ExportFormat::Xlsx => write_xlsx(stmt, output, limit),
+        #[cfg(feature = "binary_exports")]
+        ExportFormat::Parquet => arrow::write_parquet(stmt, output, limit),
+        #[cfg(feature = "binary_exports")]
+        ExportFormat::Arrow => arrow::write_arrow_ipc(stmt, output, limit),
+        #[cfg(not(feature = "binary_exports"))]
+        ExportFormat::Xlsx | ExportFormat::Parquet | ExportFormat::Arrow => {
+            Err(binary_exports_disabled())
         }
+        ExportFormat::Value => write_value(stmt, output),
+        ExportFormat::Clipboard => Err(ExportError::Io(std::io::Error::other(
+            "clipboard export is not supported for remote targets",
+        ))),
     }
-    Ok(buf)
+}
+
+/// The error for Xlsx, Parquet and Arrow exports in builds without them.
+#[cfg(not(feature = "binary_exports"))]
+fn binary_exports_disabled() -> ExportError {
+    ExportError::Io(std::io::Error::other(
+        "Excel, Parquet and Arrow exports are not available: solite was built \
+         without the `binary_exports` feature",
+    ))
 }
 
 /// Determine export format from file path extension.
@@ -654,6 +966,11 @@ pub fn format_from_path(path: &Path) -> Option<ExportFormat> {
         "tsv" => Some(ExportFormat::Tsv),
         "json" => Some(ExportFormat::Json),
         "ndjson" | "jsonl" => Some(ExportFormat::Ndjson),
+        "md" | "markdown" => Some(ExportFormat::Markdown),
+        "sql" => Some(ExportFormat::SqlInsert { table: None }),
+        "xlsx" => Some(ExportFormat::Xlsx),
+        "parquet" => Some(ExportFormat::Parquet),
+        "arrow" | "feather" => Some(ExportFormat::Arrow),
         _ => None,
     }
 }
@@ -699,6 +1016,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_format_from_path_columnar() {
+        assert_eq!(
+            format_from_path(&PathBuf::from("data.parquet")),
+            Some(ExportFormat::Parquet)
+        );
+        assert_eq!(
+            format_from_path(&PathBuf::from("data.arrow")),
+            Some(ExportFormat::Arrow)
+        );
+        assert_eq!(
+            format_from_path(&PathBuf::from("data.feather")),
+            Some(ExportFormat::Arrow)
+        );
+    }
+
+    #[test]
+    fn test_format_from_path_markdown_sql_xlsx() {
+        assert_eq!(
+            format_from_path(&PathBuf::from("report.md")),
+            Some(ExportFormat::Markdown)
+        );
+        assert_eq!(
+            format_from_path(&PathBuf::from("slice.sql.gz")),
+            Some(ExportFormat::SqlInsert { table: None })
+        );
+        assert_eq!(
+            format_from_path(&PathBuf::from("report.xlsx")),
+            Some(ExportFormat::Xlsx)
+        );
+    }
+
     #[test]
     fn test_format_from_path_compressed() {
         assert_eq!(
@@ -987,6 +1336,96 @@ mod tests {
         assert!(buf.is_empty());
     }
 
+    #[test]
+    fn test_write_markdown() {
+        let mut stmt = first_value_of(
+            "select 1 as id, 'a|b' as name, null as note union all select 2, 'two\nlines', x'FF'",
+        );
+        let mut buf = Vec::new();
+        write_markdown(&mut stmt, &mut buf, None).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "| id | name | note |\n\
+             | --- | --- | --- |\n\
+             | 1 | a\\|b |  |\n\
+             | 2 | two<br>lines | x'FF' |\n"
+        );
+    }
+
+    #[test]
+    fn test_value_to_sql_literal() {
+        let mut stmt = first_value_of("select null, 42, 1.0, 'it''s', x'00FF', 1e999");
+        let row = stmt.next().unwrap().unwrap();
+        let literals: Vec<String> = row
+            .iter()
+            .map(|v| value_to_sql_literal(v).unwrap())
+            .collect();
+        assert_eq!(
+            literals,
+            vec!["NULL", "42", "1.0", "'it''s'", "x'00FF'", "9e999"]
+        );
+    }
+
+    #[test]
+    fn test_sql_insert_round_trips() {
+        let source = crate::sqlite::Connection::open_in_memory().unwrap();
+        source
+            .execute_script(
+                "create table users(id integer, name text, score real, avatar blob);
+                 insert into users values (1, 'alex', 1.0, x'DEADBEEF'), (2, 'o''brien', null, null);",
+            )
+            .unwrap();
+        let (_, stmt) = source.prepare("select * from users").unwrap();
+        let mut buf = Vec::new();
+        write_sql_insert(&mut stmt.unwrap(), &mut buf, None, None).unwrap();
+        let sql = String::from_utf8(buf).unwrap();
+        assert!(sql.contains("INSERT INTO \"users\"(\"id\", \"name\", \"score\", \"avatar\") VALUES"));
+
+        let target = crate::sqlite::Connection::open_in_memory().unwrap();
+        target
+            .execute_script("create table users(id integer, name text, score real, avatar blob);")
+            .unwrap();
+        target.execute_script(&sql).unwrap();
+        let (_, stmt) = target
+            .prepare("select id, name, typeof(score), hex(avatar) from users order by id")
+            .unwrap();
+        let mut out = Vec::new();
+        write_csv(&mut stmt.unwrap(), &mut out, None).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "id,name,typeof(score),hex(avatar)\n1,alex,real,DEADBEEF\n2,o'brien,null,\n"
+        );
+    }
+
+    #[test]
+    fn test_sql_insert_table_name_and_batches() {
+        // expressions have no source table; an explicit name wins
+        let n = SQL_INSERT_BATCH_ROWS + 1;
+        let mut stmt = first_value_of(&format!(
+            "with recursive c(x) as (select 1 union all select x + 1 from c limit {n}) select x from c"
+        ));
+        let mut buf = Vec::new();
+        write_sql_insert(&mut stmt, &mut buf, None, None).unwrap();
+        let sql = String::from_utf8(buf).unwrap();
+        assert_eq!(sql.matches("INSERT INTO \"data\"").count(), 2);
+        assert!(sql.starts_with("BEGIN;\n") && sql.ends_with(";\nCOMMIT;\n"));
+
+        let mut stmt = first_value_of("select 1 as x");
+        let mut buf = Vec::new();
+        write_sql_insert(&mut stmt, &mut buf, Some("my table"), None).unwrap();
+        assert!(String::from_utf8(buf).unwrap().contains("INSERT INTO \"my table\"(\"x\")"));
+    }
+
+    #[cfg(feature = "binary_exports")]
+    #[test]
+    fn test_write_xlsx_is_a_workbook() {
+        let mut stmt = first_value_of("select 1 as id, 'alex' as name, 9007199254740993 as big");
+        let mut buf = Vec::new();
+        write_xlsx(&mut stmt, &mut buf, None).unwrap();
+        // xlsx files are zip archives
+        assert!(buf.starts_with(b"PK\x03\x04"));
+    }
+
     #[test]
     fn test_export_error_display() {
         let err = ExportError::NoRows;
@@ -0,0 +1,535 @@
+//! Columnar exports: Parquet and Arrow IPC.
+//!
+//! Unlike the row formats, these need a schema up front. It is built from
+//! each column's declared type (using SQLite's affinity rules on
+//! [`ColumnMeta::decltype`]) together with the values in the first batch.
+//! Declared types are only affinities outside STRICT tables, so a column
+//! whose first batch holds values its declared type can't (an `INTEGER`
+//! column holding `'n/a'`) widens like an untyped one: integers to floats,
+//! any other mix to text. Columns with neither a declared type nor a
+//! non-NULL value in the first batch are text. Every field is nullable.
+//!
+//! Rows are read in batches of [`BATCH_ROWS`]. Each batch becomes one
+//! Parquet row group (or one IPC record batch) and is written out before
+//! the next is read, so memory stays bounded by a single batch regardless
+//! of result size. The schema can't change after the first batch, so a
+//! later value that doesn't fit its column fails the export with a hint to
+//! CAST the column.
+
+use std::io::Write;
+use std::sync::Arc;
+
+use arrow_array::builder::{BinaryBuilder, Float64Builder, Int64Builder, StringBuilder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+
+use super::{blob_to_hex_literal, check_blob_limit, ExportError};
+use crate::sqlite::{ColumnMeta, OwnedValue, Statement};
+
+/// Rows per Parquet row group / Arrow record batch.
+pub(crate) const BATCH_ROWS: usize = 64 * 1024;
+
+/// The Arrow type a result column is exported as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    Int64,
+    Float64,
+    Utf8,
+    Binary,
+}
+
+impl ColumnType {
+    /// Map a declared column type to an Arrow type, following SQLite's
+    /// type affinity rules. `None` for NUMERIC affinity and untyped
+    /// columns, whose values can be anything.
+    fn from_decltype(decltype: &str) -> Option<Self> {
+        let decltype = decltype.to_ascii_uppercase();
+        if decltype.contains("INT") {
+            Some(ColumnType::Int64)
+        } else if ["CHAR", "CLOB", "TEXT"].iter().any(|t| decltype.contains(t)) {
+            Some(ColumnType::Utf8)
+        } else if decltype.contains("BLOB") {
+            Some(ColumnType::Binary)
+        } else if ["REAL", "FLOA", "DOUB"].iter().any(|t| decltype.contains(t)) {
+            Some(ColumnType::Float64)
+        } else {
+            None
+        }
+    }
+
+    /// The type of a column typed `sniffed` so far once it also holds
+    /// `value`: a type that holds every value, where integers widen to
+    /// floats and any other mix becomes text. `None` until the first
+    /// non-NULL value.
+    fn widen(sniffed: Option<Self>, value: &OwnedValue) -> Option<Self> {
+        let ty = match value {
+            OwnedValue::Null => return sniffed,
+            OwnedValue::Integer(_) => ColumnType::Int64,
+            OwnedValue::Double(_) => ColumnType::Float64,
+            OwnedValue::Text(_) => ColumnType::Utf8,
+            OwnedValue::Blob(_) => ColumnType::Binary,
+        };
+        Some(match (sniffed, ty) {
+            (None, ty) => ty,
+            (Some(a), b) if a == b => a,
+            (Some(ColumnType::Int64), ColumnType::Float64)
+            | (Some(ColumnType::Float64), ColumnType::Int64) => ColumnType::Float64,
+            _ => ColumnType::Utf8,
+        })
+    }
+
+    fn data_type(self) -> DataType {
+        match self {
+            ColumnType::Int64 => DataType::Int64,
+            ColumnType::Float64 => DataType::Float64,
+            ColumnType::Utf8 => DataType::Utf8,
+            ColumnType::Binary => DataType::Binary,
+        }
+    }
+}
+
+/// Reads a statement's rows in batches and converts them to Arrow.
+struct BatchReader<'s> {
+    stmt: &'s mut Statement,
+    columns: Vec<String>,
+    blob_limit: Option<u64>,
+    schema: SchemaRef,
+    types: Vec<ColumnType>,
+}
+
+impl<'s> BatchReader<'s> {
+    /// Start reading `stmt`: read its first batch and build the schema
+    /// from it. Returns the batch, to be written first.
+    fn open(
+        stmt: &'s mut Statement,
+        blob_limit: Option<u64>,
+    ) -> Result<(Self, Vec<Vec<OwnedValue>>), ExportError> {
+        let columns = stmt.column_names().map_err(|e| ExportError::Sql(format!("{:?}", e)))?;
+        let meta = stmt.column_meta();
+        let rows = read_batch(stmt, &columns, blob_limit)?;
+        let (schema, types) = schema_for(&meta, &rows);
+        let reader = BatchReader {
+            stmt,
+            columns,
+            blob_limit,
+            schema,
+            types,
+        };
+        Ok((reader, rows))
+    }
+
+    /// Read up to [`BATCH_ROWS`] rows. A short batch means the statement
+    /// is exhausted.
+    fn read(&mut self) -> Result<Vec<Vec<OwnedValue>>, ExportError> {
+        read_batch(self.stmt, &self.columns, self.blob_limit)
+    }
+
+    /// Convert a batch of rows to a record batch.
+    fn record_batch(&self, rows: &[Vec<OwnedValue>]) -> Result<RecordBatch, ExportError> {
+        let arrays = self
+            .columns
+            .iter()
+            .zip(&self.types)
+            .enumerate()
+            .map(|(idx, (name, ty))| column_array(rows, idx, name, *ty))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(RecordBatch::try_new(self.schema.clone(), arrays)?)
+    }
+}
+
+fn read_batch(
+    stmt: &mut Statement,
+    columns: &[String],
+    blob_limit: Option<u64>,
+) -> Result<Vec<Vec<OwnedValue>>, ExportError> {
+    let mut rows = Vec::new();
+    while rows.len() < BATCH_ROWS {
+        match stmt.next() {
+            Ok(Some(row)) => {
+                check_blob_limit(&row, columns, blob_limit)?;
+                rows.push(row.iter().map(OwnedValue::from_value_ref).collect());
+            }
+            Ok(None) => break,
+            Err(e) => return Err(ExportError::Sql(e.to_string())),
+        }
+    }
+    Ok(rows)
+}
+
+fn declared_type(column: &ColumnMeta) -> Option<ColumnType> {
+    column.decltype.as_deref().and_then(ColumnType::from_decltype)
+}
+
+/// Build the schema from the declared types widened to hold the values in
+/// `rows`, the first batch. Columns with neither are text.
+fn schema_for(meta: &[ColumnMeta], rows: &[Vec<OwnedValue>]) -> (SchemaRef, Vec<ColumnType>) {
+    let types: Vec<ColumnType> = meta
+        .iter()
+        .enumerate()
+        .map(|(idx, column)| {
+            rows.iter()
+                .fold(declared_type(column), |ty, row| ColumnType::widen(ty, &row[idx]))
+                .unwrap_or(ColumnType::Utf8)
+        })
+        .collect();
+    let fields: Vec<Field> = meta
+        .iter()
+        .zip(&types)
+        .map(|(column, ty)| Field::new(&column.name, ty.data_type(), true))
+        .collect();
+    (Arc::new(Schema::new(fields)), types)
+}
+
+fn type_mismatch(column: &str, expected: ColumnType, found: &OwnedValue) -> ExportError {
+    let found = match found {
+        OwnedValue::Null => "NULL",
+        OwnedValue::Integer(_) => "INTEGER",
+        OwnedValue::Double(_) => "REAL",
+        OwnedValue::Text(_) => "TEXT",
+        OwnedValue::Blob(_) => "BLOB",
+    };
+    let expected = match expected {
+        ColumnType::Int64 => "INTEGER",
+        ColumnType::Float64 => "REAL",
+        ColumnType::Utf8 => "TEXT",
+        ColumnType::Binary => "BLOB",
+    };
+    ExportError::TypeMismatch {
+        column: column.to_owned(),
+        expected: expected.to_owned(),
+        found: found.to_owned(),
+    }
+}
+
+/// Convert one column of a batch to an Arrow array.
+fn column_array(
+    rows: &[Vec<OwnedValue>],
+    idx: usize,
+    name: &str,
+    ty: ColumnType,
+) -> Result<ArrayRef, ExportError> {
+    let values = rows.iter().map(|row| &row[idx]);
+    let array: ArrayRef = match ty {
+        ColumnType::Int64 => {
+            let mut builder = Int64Builder::with_capacity(rows.len());
+            for value in values {
+                match value {
+                    OwnedValue::Null => builder.append_null(),
+                    OwnedValue::Integer(v) => builder.append_value(*v),
+                    other => return Err(type_mismatch(name, ty, other)),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        ColumnType::Float64 => {
+            let mut builder = Float64Builder::with_capacity(rows.len());
+            for value in values {
+                match value {
+                    OwnedValue::Null => builder.append_null(),
+                    OwnedValue::Integer(v) => builder.append_value(*v as f64),
+                    OwnedValue::Double(v) => builder.append_value(*v),
+                    other => return Err(type_mismatch(name, ty, other)),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        // Text holds anything, stringified the same way as CSV
+        ColumnType::Utf8 => {
+            let mut builder = StringBuilder::new();
+            for value in values {
+                match value {
+                    OwnedValue::Null => builder.append_null(),
+                    OwnedValue::Integer(v) => builder.append_value(v.to_string()),
+                    OwnedValue::Double(v) => builder.append_value(v.to_string()),
+                    OwnedValue::Text(bytes) => builder.append_value(
+                        std::str::from_utf8(bytes).map_err(|_| ExportError::InvalidUtf8)?,
+                    ),
+                    OwnedValue::Blob(bytes) => builder.append_value(blob_to_hex_literal(bytes)),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        ColumnType::Binary => {
+            let mut builder = BinaryBuilder::new();
+            for value in values {
+                match value {
+                    OwnedValue::Null => builder.append_null(),
+                    OwnedValue::Text(bytes) | OwnedValue::Blob(bytes) => {
+                        builder.append_value(bytes)
+                    }
+                    other => return Err(type_mismatch(name, ty, other)),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+    };
+    Ok(array)
+}
+
+/// Write statement results as a Parquet file, one row group per batch.
+pub(crate) fn write_parquet<W: Write>(
+    stmt: &mut Statement,
+    mut output: W,
+    blob_limit: Option<u64>,
+) -> Result<(), ExportError> {
+    let (mut reader, mut rows) = BatchReader::open(stmt, blob_limit)?;
+    let props = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_max_row_group_size(BATCH_ROWS)
+        .build();
+    // ArrowWriter needs a `Send` sink, which `output` may not be: encode
+    // into a buffer and drain it to `output` after every row group.
+    let mut writer = ArrowWriter::try_new(Vec::new(), reader.schema.clone(), Some(props))?;
+    loop {
+        if !rows.is_empty() {
+            writer.write(&reader.record_batch(&rows)?)?;
+            writer.flush()?;
+            output.write_all(&std::mem::take(writer.inner_mut()))?;
+        }
+        if rows.len() < BATCH_ROWS {
+            break;
+        }
+        rows = reader.read()?;
+    }
+    output.write_all(&writer.into_inner()?)?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Write statement results in the Arrow IPC file format (`.arrow`, also
+/// read as Feather v2), one record batch per batch of rows.
+pub(crate) fn write_arrow_ipc<W: Write>(
+    stmt: &mut Statement,
+    output: W,
+    blob_limit: Option<u64>,
+) -> Result<(), ExportError> {
+    let (mut reader, mut rows) = BatchReader::open(stmt, blob_limit)?;
+    let mut writer = arrow_ipc::writer::FileWriter::try_new(output, &reader.schema)?;
+    loop {
+        if !rows.is_empty() {
+            writer.write(&reader.record_batch(&rows)?)?;
+        }
+        if rows.len() < BATCH_ROWS {
+            break;
+        }
+        rows = reader.read()?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::{Array, BinaryArray, Float64Array, Int64Array, StringArray};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn statement(conn: &crate::sqlite::Connection, sql: &str) -> Statement {
+        let (_, stmt) = conn.prepare(sql).unwrap();
+        stmt.unwrap()
+    }
+
+    fn read_parquet(bytes: Vec<u8>) -> Vec<RecordBatch> {
+        ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(bytes))
+            .unwrap()
+            .build()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_column_type_from_decltype() {
+        assert_eq!(ColumnType::from_decltype("INTEGER"), Some(ColumnType::Int64));
+        assert_eq!(ColumnType::from_decltype("bigint"), Some(ColumnType::Int64));
+        assert_eq!(ColumnType::from_decltype("VARCHAR(20)"), Some(ColumnType::Utf8));
+        assert_eq!(ColumnType::from_decltype("BLOB"), Some(ColumnType::Binary));
+        assert_eq!(ColumnType::from_decltype("DOUBLE PRECISION"), Some(ColumnType::Float64));
+        assert_eq!(ColumnType::from_decltype("NUMERIC"), None);
+        assert_eq!(ColumnType::from_decltype(""), None);
+    }
+
+    #[test]
+    fn test_column_type_widen() {
+        use OwnedValue::*;
+        let sniff = |values: &[OwnedValue]| {
+            values.iter().fold(None, |sniffed, value| ColumnType::widen(sniffed, value))
+        };
+        let text = || Text(b"a".to_vec());
+        assert_eq!(sniff(&[Integer(1), Null, Integer(2)]), Some(ColumnType::Int64));
+        assert_eq!(sniff(&[Integer(1), Double(2.5)]), Some(ColumnType::Float64));
+        assert_eq!(sniff(&[Double(2.5), Integer(1)]), Some(ColumnType::Float64));
+        assert_eq!(sniff(&[Integer(1), text()]), Some(ColumnType::Utf8));
+        assert_eq!(sniff(&[text(), Integer(1), Double(2.5)]), Some(ColumnType::Utf8));
+        assert_eq!(sniff(&[Blob(vec![1])]), Some(ColumnType::Binary));
+        assert_eq!(sniff(&[Null, Null]), None);
+        assert_eq!(sniff(&[]), None);
+    }
+
+    #[test]
+    fn test_parquet_round_trip_uses_declared_types() {
+        let conn = crate::sqlite::Connection::open_in_memory().unwrap();
+        conn.execute_script(
+            "create table t(id integer, name text, score real, data blob, misc);
+             insert into t values (1, 'alex', 1.5, x'00FF', 'x'), (2, null, 2, null, 3);",
+        )
+        .unwrap();
+        let mut stmt = statement(&conn, "select id, name, score, data, misc, id * 2 as twice from t");
+        let mut buf = Vec::new();
+        write_parquet(&mut stmt, &mut buf, None).unwrap();
+
+        let batches = read_parquet(buf);
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        let schema = batch.schema();
+        let types: Vec<_> = schema.fields().iter().map(|f| f.data_type().clone()).collect();
+        assert_eq!(
+            types,
+            vec![
+                DataType::Int64,
+                DataType::Utf8,
+                DataType::Float64,
+                DataType::Binary,
+                // untyped column with mixed values → text; expression → sniffed
+                DataType::Utf8,
+                DataType::Int64,
+            ]
+        );
+
+        let ids = batch.column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(ids.values().to_vec(), vec![1, 2]);
+        let names = batch.column(1).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(names.value(0), "alex");
+        assert!(names.is_null(1));
+        let scores = batch.column(2).as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(scores.values().to_vec(), vec![1.5, 2.0]);
+        let data = batch.column(3).as_any().downcast_ref::<BinaryArray>().unwrap();
+        assert_eq!(data.value(0), &[0x00, 0xFF]);
+        let misc = batch.column(4).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(misc.value(1), "3");
+    }
+
+    #[test]
+    fn test_parquet_writes_one_row_group_per_batch() {
+        let conn = crate::sqlite::Connection::open_in_memory().unwrap();
+        let n = BATCH_ROWS + 10;
+        let mut stmt = statement(
+            &conn,
+            &format!("with recursive c(x) as (select 1 union all select x + 1 from c limit {n}) select x from c"),
+        );
+        let mut buf = Vec::new();
+        write_parquet(&mut stmt, &mut buf, None).unwrap();
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(buf)).unwrap();
+        assert_eq!(builder.metadata().num_row_groups(), 2);
+        let rows: usize = builder.build().unwrap().map(|b| b.unwrap().num_rows()).sum();
+        assert_eq!(rows, n);
+    }
+
+    #[test]
+    fn test_parquet_declared_type_widens_to_values() {
+        let conn = crate::sqlite::Connection::open_in_memory().unwrap();
+        conn.execute_script(
+            "create table t(id integer, score integer);
+             insert into t values (1, 1), ('n/a', 2.5), (null, null);",
+        )
+        .unwrap();
+        let mut stmt = statement(&conn, "select id, score from t");
+        let mut buf = Vec::new();
+        write_parquet(&mut stmt, &mut buf, None).unwrap();
+
+        let batches = read_parquet(buf);
+        let schema = batches[0].schema();
+        let types: Vec<_> = schema.fields().iter().map(|f| f.data_type().clone()).collect();
+        assert_eq!(types, vec![DataType::Utf8, DataType::Float64]);
+        let ids = batches[0].column(0).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(ids.value(0), "1");
+        assert_eq!(ids.value(1), "n/a");
+        assert!(ids.is_null(2));
+        let scores = batches[0].column(1).as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(scores.value(0), 1.0);
+        assert_eq!(scores.value(1), 2.5);
+    }
+
+    #[test]
+    fn test_parquet_mismatch_after_first_batch_errors() {
+        let conn = crate::sqlite::Connection::open_in_memory().unwrap();
+        // Integers for a full batch, then text: the schema is already written
+        let n = BATCH_ROWS + 2;
+        let mut stmt = statement(
+            &conn,
+            &format!(
+                "with recursive c(x) as (select 1 union all select x + 1 from c limit {n})
+                 select case when x = {n} then 'last' else x end as v from c"
+            ),
+        );
+        let err = write_parquet(&mut stmt, Vec::new(), None).unwrap_err();
+        assert!(
+            matches!(&err, ExportError::TypeMismatch { column, .. } if column == "v"),
+            "{err:?}"
+        );
+        assert!(err.to_string().contains("CAST"));
+    }
+
+    #[test]
+    fn test_writes_run_once() {
+        let conn = crate::sqlite::Connection::open_in_memory().unwrap();
+        conn.execute_script("create table t(id integer, misc);").unwrap();
+        let mut stmt = statement(
+            &conn,
+            "insert into t values (1, 2), (2, 3) returning misc, id + 1 as next",
+        );
+        let mut buf = Vec::new();
+        write_arrow_ipc(&mut stmt, &mut buf, None).unwrap();
+
+        let reader =
+            arrow_ipc::reader::FileReader::try_new(std::io::Cursor::new(buf), None).unwrap();
+        let types: Vec<_> =
+            reader.schema().fields().iter().map(|f| f.data_type().clone()).collect();
+        assert_eq!(types, vec![DataType::Int64, DataType::Int64]);
+        let (_, count) = conn.prepare("select count(*) from t").unwrap();
+        assert_eq!(count.unwrap().next().unwrap().unwrap()[0].as_int64(), 2);
+    }
+
+    #[test]
+    fn test_parquet_empty_result_has_schema() {
+        let conn = crate::sqlite::Connection::open_in_memory().unwrap();
+        conn.execute_script("create table t(id integer, name text);").unwrap();
+        let mut stmt = statement(&conn, "select * from t");
+        let mut buf = Vec::new();
+        write_parquet(&mut stmt, &mut buf, None).unwrap();
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(buf)).unwrap();
+        assert_eq!(builder.schema().fields().len(), 2);
+        assert_eq!(builder.metadata().file_metadata().num_rows(), 0);
+    }
+
+    #[test]
+    fn test_arrow_ipc_round_trip() {
+        let conn = crate::sqlite::Connection::open_in_memory().unwrap();
+        let mut stmt = statement(&conn, "select 1 as a, 'one' as b union all select 2, null");
+        let mut buf = Vec::new();
+        write_arrow_ipc(&mut stmt, &mut buf, None).unwrap();
+
+        let reader =
+            arrow_ipc::reader::FileReader::try_new(std::io::Cursor::new(buf), None).unwrap();
+        let batches: Vec<RecordBatch> = reader.collect::<Result<_, _>>().unwrap();
+        assert_eq!(batches.len(), 1);
+        let a = batches[0].column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(a.values().to_vec(), vec![1, 2]);
+        let b = batches[0].column(1).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(b.value(0), "one");
+        assert!(b.is_null(1));
+    }
+
+    #[test]
+    fn test_arrow_blob_limit_enforced() {
+        let conn = crate::sqlite::Connection::open_in_memory().unwrap();
+        let mut stmt = statement(&conn, "select zeroblob(32) as payload");
+        let err = write_arrow_ipc(&mut stmt, Vec::new(), Some(16)).unwrap_err();
+        assert!(matches!(err, ExportError::BlobTooLarge { .. }), "{err:?}");
+    }
+}
@@ -1,3 +1,4 @@
+pub mod daemon;
 pub mod dot;
 pub mod procedure;
 pub mod replacement_scans;
@@ -290,6 +291,15 @@ impl Runtime {
         if let Some(content) = self.virtual_files.get(path) {
             return Ok(content.clone());
         }
+        #[cfg(feature = "object_store")]
+        if object_store::is_object_store_url(path) {
+            return self
+                .connection
+                .download_dir()
+                .and_then(|dir| object_store::download(path, dir))
+                .and_then(std::fs::read_to_string)
+                .map_err(|e| format!("Failed to read '{}': {}", path, e));
+        }
         std::fs::read_to_string(path)
             .map_err(|e| format!("Failed to read '{}': {}", path, e))
     }
@@ -454,13 +464,16 @@ impl Runtime {
                     }
                 }
 
-                // Resolve .run file path relative to the calling file's directory
+                // Resolve .run file path relative to the calling file's
+                // directory; URLs like s3://bucket/setup.sql are left as is
                 if let DotCommand::Run(ref mut run_cmd) = cmd {
-                    let resolved = PathBuf::from(&block.name)
-                        .parent()
-                        .map(|dir| dir.join(&run_cmd.file))
-                        .unwrap_or_else(|| PathBuf::from(&run_cmd.file));
-                    run_cmd.file = resolved.to_string_lossy().replace('\\', "/");
+                    if !run_cmd.file.contains("://") {
+                        let resolved = PathBuf::from(&block.name)
+                            .parent()
+                            .map(|dir| dir.join(&run_cmd.file))
+                            .unwrap_or_else(|| PathBuf::from(&run_cmd.file));
+                        run_cmd.file = resolved.to_string_lossy().replace('\\', "/");
+                    }
                 }
 
                 if !rest.is_empty() {
@@ -630,7 +643,15 @@ impl Runtime {
         !self.stack.is_empty()
     }
 
-    fn init_sqlite_parameters_table(&mut self) {
+    /// Drop every queued block without running it.
+    pub fn clear_queue(&mut self) {
+        self.stack.clear();
+    }
+
+    /// Create `temp.sqlite_parameters` if it doesn't exist yet. The parameter
+    /// methods do this lazily; call it up front before running statements
+    /// that may be rolled back, which would drop the table with them.
+    pub fn init_sqlite_parameters_table(&mut self) {
         if self.initialized_sqlite_parameters_table {
             return;
         }
@@ -742,8 +763,9 @@ impl Runtime {
         }
     }
 
-    /// `prepare_with_parameters`, retrying after csv/tsv replacement scans
-    /// (`select * from "data.csv"` auto-creates a temp vtab over the file).
+    /// `prepare_with_parameters`, retrying after replacement scans
+    /// (`select * from "data.csv"` auto-creates a temp vtab over the file;
+    /// json/ndjson/txt files are loaded into a temp table).
     ///
     /// A single statement can reference several distinct data files (e.g. a
     /// join), so this loops until no more replacement scans apply — capped to
@@ -1254,6 +1276,137 @@ select not_exist();",
         assert_eq!(err.message, "no such table: nope_missing.csv");
     }
 
+    #[test]
+    fn test_prepare_with_replacement_scans_ndjson_and_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let ndjson = dir.path().join("events.ndjson");
+        std::fs::write(&ndjson, "{\"id\": 1, \"kind\": \"open\"}\n{\"id\": 2}\n").unwrap();
+        let json = dir.path().join("dump.json");
+        std::fs::write(&json, r#"[{"id": 2, "ok": true}]"#).unwrap();
+
+        let rt = Runtime::new(None).unwrap();
+        let sql = format!(
+            "select e.id, e.kind, d.ok from \"{}\" e left join \"{}\" d using (id) order by e.id",
+            ndjson.to_str().unwrap(),
+            json.to_str().unwrap()
+        );
+        let (_, stmt) = rt.prepare_with_replacement_scans(&sql).unwrap();
+        let mut stmt = stmt.unwrap();
+        let row = stmt.next().unwrap().unwrap();
+        assert_eq!(row[0].as_int64(), 1);
+        assert_eq!(row[1].as_str(), "open");
+        let row = stmt.next().unwrap().unwrap();
+        assert_eq!(row[0].as_int64(), 2);
+        assert_eq!(row[2].as_int64(), 1);
+    }
+
+    #[cfg(feature = "object_store")]
+    #[test]
+    fn test_prepare_with_replacement_scans_reads_object_store_urls() {
+        use std::io::Write;
+        crate::object_store::upload_to_url("memory://scans/users.csv", |w| {
+            Ok(w.write_all(b"id,name\n1,alex\n2,sam\n")?)
+        })
+        .unwrap();
+        crate::object_store::upload_to_url("memory://scans/events.ndjson.gz", |w| {
+            Ok(w.write_all(b"{\"user\": 2, \"kind\": \"login\"}\n")?)
+        })
+        .unwrap();
+
+        let rt = Runtime::new(None).unwrap();
+        let (_, stmt) = rt
+            .prepare_with_replacement_scans(
+                "select u.name, e.kind from 'memory://scans/users.csv' u \
+                 join 'memory://scans/events.ndjson.gz' e on e.user = cast(u.id as integer)",
+            )
+            .unwrap();
+        let mut stmt = stmt.unwrap();
+        let row = stmt.next().unwrap().unwrap();
+        assert_eq!(row[0].as_str(), "sam");
+        assert_eq!(row[1].as_str(), "login");
+
+        let err = rt
+            .prepare_with_replacement_scans("select * from 'memory://scans/missing.csv'")
+            .unwrap_err();
+        assert_eq!(err.message, "no such table: memory://scans/missing.csv");
+    }
+
+    #[test]
+    fn test_prepare_with_replacement_scans_txt_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let txt = dir.path().join("log.txt");
+        std::fs::write(&txt, "alpha\nbeta\n").unwrap();
+
+        let rt = Runtime::new(None).unwrap();
+        let sql = format!("select count(*), max(line) from \"{}\"", txt.to_str().unwrap());
+        let (_, stmt) = rt.prepare_with_replacement_scans(&sql).unwrap();
+        let mut stmt = stmt.unwrap();
+        let row = stmt.next().unwrap().unwrap();
+        assert_eq!(row[0].as_int64(), 2);
+        assert_eq!(row[1].as_str(), "beta");
+    }
+
+    #[test]
+    fn test_prepare_with_replacement_scans_invalid_json_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("broken.json");
+        std::fs::write(&json, "[{").unwrap();
+
+        let rt = Runtime::new(None).unwrap();
+        let sql = format!("select * from \"{}\"", json.to_str().unwrap());
+        let err = rt.prepare_with_replacement_scans(&sql).unwrap_err();
+        assert!(err.message.contains("invalid JSON"), "{}", err.message);
+    }
+
+    fn create_database(path: &std::path::Path, sql: &str) {
+        Connection::open(path.to_str().unwrap())
+            .unwrap()
+            .execute_script(sql)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_prepare_with_replacement_scans_attaches_database_readonly() {
+        let dir = tempfile::tempdir().unwrap();
+        let other = dir.path().join("other.db");
+        create_database(&other, "create table users(name); insert into users values ('alex');");
+
+        let rt = Runtime::new(None).unwrap();
+        let sql = format!("select name from \"{}\".users", other.to_str().unwrap());
+        let (_, stmt) = rt.prepare_with_replacement_scans(&sql).unwrap();
+        let mut stmt = stmt.unwrap();
+        assert_eq!(stmt.next().unwrap().unwrap()[0].as_str(), "alex");
+        drop(stmt);
+
+        // attached read-only: the snapshot is never modified
+        let sql = format!("delete from \"{}\".users", other.to_str().unwrap());
+        let (_, stmt) = rt.prepare_with_replacement_scans(&sql).unwrap();
+        assert!(stmt.unwrap().execute().is_err());
+
+        // a missing table in an attached database is the original error
+        let sql = format!("select * from \"{}\".nope", other.to_str().unwrap());
+        let err = rt.prepare_with_replacement_scans(&sql).unwrap_err();
+        assert!(err.message.starts_with("no such table"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_prepare_with_replacement_scans_bare_database_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let single = dir.path().join("archive.sqlite");
+        create_database(&single, "create table events(id); insert into events values (7);");
+        let multi = dir.path().join("multi.db");
+        create_database(&multi, "create table a(x); create table b(y);");
+
+        let rt = Runtime::new(None).unwrap();
+        let sql = format!("select id from \"{}\"", single.to_str().unwrap());
+        let (_, stmt) = rt.prepare_with_replacement_scans(&sql).unwrap();
+        assert_eq!(stmt.unwrap().next().unwrap().unwrap()[0].as_int64(), 7);
+
+        let sql = format!("select * from \"{}\"", multi.to_str().unwrap());
+        let err = rt.prepare_with_replacement_scans(&sql).unwrap_err();
+        assert!(err.message.contains("has 2 tables"), "{}", err.message);
+    }
+
     #[test]
     fn test_call_recreates_replacement_scan_vtab() {
         // A registered procedure whose body references a csv must be callable
@@ -1,87 +1,469 @@
-//! Object store integration for exporting to S3-compatible storage.
+//! Object store integration for cloud and local storage.
 //!
-//! Supports `s3://` and `t3://` URL schemes. Defaults to the Tigris
-//! endpoint (`https://t3.storage.dev`) unless `AWS_ENDPOINT_URL_S3` is set.
+//! The backend is chosen by URL scheme, each with its own credential
+//! discovery:
+//!
+//! | Scheme | Backend | Configuration |
+//! |--------|---------|---------------|
+//! | `s3://bucket/key`, `t3://bucket/key` | S3-compatible | the standard `AWS_*` variables (`AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`, `AWS_SESSION_TOKEN`, `AWS_REGION`, ...); endpoint defaults to Tigris (`https://t3.storage.dev`) and region to `auto` unless `AWS_ENDPOINT_URL`, `AWS_ENDPOINT_URL_S3` or `AWS_REGION` is set |
+//! | `gs://bucket/key` | Google Cloud Storage | `GOOGLE_SERVICE_ACCOUNT` / `GOOGLE_APPLICATION_CREDENTIALS`, or application default credentials |
+//! | `az://container/key` | Azure Blob Storage | `AZURE_STORAGE_ACCOUNT_NAME` plus `AZURE_STORAGE_ACCOUNT_KEY`, a SAS token, or managed identity |
+//! | `file:///path` | Local filesystem | none; relative paths resolve against the current directory |
+//! | `memory://key` | In-process memory, shared for the process lifetime (for tests) | none |
+//!
+//! Exports are streamed as multipart uploads, so they never have to fit
+//! in memory. Reads (replacement scans, `.load`, `.run`) download the
+//! object into the connection's download directory first, in ranged
+//! chunks. [`list`],
+//! [`download_into`], [`read_heads`] and [`delete`] work on objects under a
+//! prefix, e.g. a `solite stream` replica.
 
 use crate::exporter::ExportError;
-use object_store::aws::AmazonS3Builder;
+use indicatif::{ProgressBar, ProgressStyle};
+use object_store::aws::{AmazonS3, AmazonS3Builder, AmazonS3ConfigKey};
+use object_store::azure::MicrosoftAzureBuilder;
+use object_store::gcp::GoogleCloudStorageBuilder;
+use object_store::local::LocalFileSystem;
+use object_store::memory::InMemory;
 use object_store::path::Path;
-use object_store::ObjectStoreExt;
+use object_store::{ObjectStore, ObjectStoreExt, WriteMultipart};
+use std::fs::File;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock};
+
+/// URL schemes served by an object store backend.
+const SCHEMES: [&str; 6] = ["s3://", "t3://", "gs://", "az://", "file://", "memory://"];
+
+/// The store behind `memory://` URLs, shared by every use in the process.
+static MEMORY_STORE: LazyLock<Arc<InMemory>> = LazyLock::new(|| Arc::new(InMemory::new()));
 
 /// Check if a target string is an object store URL.
 pub fn is_object_store_url(path: &str) -> bool {
-    path.starts_with("s3://") || path.starts_with("t3://")
+    SCHEMES.iter().any(|scheme| path.starts_with(scheme))
 }
 
-/// Parse an object store URL into (bucket, key).
+/// Parse a bucket-style object store URL into (bucket, key).
 ///
-/// Accepts `s3://bucket/path/to/file.csv` or `t3://bucket/path/to/file.csv`.
-fn parse_url(url: &str) -> Result<(&str, &str), ExportError> {
-    let without_scheme = url
-        .strip_prefix("s3://")
-        .or_else(|| url.strip_prefix("t3://"))
-        .ok_or_else(|| ExportError::Io(std::io::Error::other("unsupported URL scheme")))?;
+/// Accepts `s3://`, `t3://`, `gs://` and `az://` URLs, e.g.
+/// `s3://bucket/path/to/file.csv`.
+fn parse_url(url: &str) -> std::io::Result<(&str, &str)> {
+    let without_scheme = ["s3://", "t3://", "gs://", "az://"]
+        .iter()
+        .find_map(|scheme| url.strip_prefix(scheme))
+        .ok_or_else(|| std::io::Error::other("unsupported URL scheme"))?;
 
     let (bucket, key) = without_scheme
         .split_once('/')
-        .ok_or_else(|| ExportError::Io(std::io::Error::other("URL must include a key path after the bucket name")))?;
+        .ok_or_else(|| std::io::Error::other("URL must include a key path after the bucket name"))?;
 
     if bucket.is_empty() || key.is_empty() {
-        return Err(ExportError::Io(std::io::Error::other(
-            "bucket and key must not be empty",
-        )));
+        return Err(std::io::Error::other("bucket and key must not be empty"));
     }
 
     Ok((bucket, key))
 }
 
-/// Upload bytes to an S3-compatible object store.
+/// Build the store serving `url`, returning it with the object's path.
+fn store_for_url(url: &str) -> std::io::Result<(Arc<dyn ObjectStore>, Path)> {
+    if url.starts_with("s3://") || url.starts_with("t3://") {
+        let (bucket, key) = parse_url(url)?;
+        return Ok((Arc::new(s3_store(bucket)?), Path::from(key)));
+    }
+    if url.starts_with("gs://") {
+        let (bucket, key) = parse_url(url)?;
+        let store = GoogleCloudStorageBuilder::from_env()
+            .with_bucket_name(bucket)
+            .build()
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        return Ok((Arc::new(store), Path::from(key)));
+    }
+    if url.starts_with("az://") {
+        let (container, key) = parse_url(url)?;
+        let store = MicrosoftAzureBuilder::from_env()
+            .with_container_name(container)
+            .build()
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        return Ok((Arc::new(store), Path::from(key)));
+    }
+    if let Some(file) = url.strip_prefix("file://") {
+        if file.is_empty() {
+            return Err(std::io::Error::other("file:// URL must include a path"));
+        }
+        let path = Path::from_absolute_path(std::path::absolute(file)?)
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        return Ok((Arc::new(LocalFileSystem::new()), path));
+    }
+    if let Some(key) = url.strip_prefix("memory://") {
+        if key.is_empty() {
+            return Err(std::io::Error::other("memory:// URL must include a key"));
+        }
+        let store: Arc<dyn ObjectStore> = MEMORY_STORE.clone();
+        return Ok((store, Path::from(key)));
+    }
+    Err(std::io::Error::other("unsupported URL scheme"))
+}
+
+/// Build an S3 client for `bucket`.
 ///
-/// Defaults to the Tigris endpoint. Override with `AWS_ENDPOINT_URL_S3`.
-/// Credentials are read from `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`.
-pub fn upload(url: &str, data: Vec<u8>) -> Result<(), ExportError> {
-    let (bucket, key) = parse_url(url)?;
-
-    let endpoint = std::env::var("AWS_ENDPOINT_URL_S3")
-        .unwrap_or_else(|_| "https://t3.storage.dev".to_string());
-
-    let store = AmazonS3Builder::new()
-        .with_endpoint(&endpoint)
-        .with_region(
-            std::env::var("AWS_REGION")
-                .unwrap_or_else(|_| "auto".to_string()),
-        )
+/// Configuration comes from the `AWS_*` environment variables (see
+/// [`AmazonS3Builder::from_env`]), so session tokens, profiles and instance
+/// credentials work as usual. Only the endpoint and region are defaulted,
+/// and only when the environment sets neither.
+fn s3_store(bucket: &str) -> std::io::Result<AmazonS3> {
+    with_s3_defaults(AmazonS3Builder::from_env())
         .with_bucket_name(bucket)
-        .with_access_key_id(
-            std::env::var("AWS_ACCESS_KEY_ID")
-                .map_err(|_| ExportError::Io(std::io::Error::other(
-                    "AWS_ACCESS_KEY_ID environment variable is not set",
-                )))?,
-        )
-        .with_secret_access_key(
-            std::env::var("AWS_SECRET_ACCESS_KEY")
-                .map_err(|_| ExportError::Io(std::io::Error::other(
-                    "AWS_SECRET_ACCESS_KEY environment variable is not set",
-                )))?,
-        )
         .build()
+        .map_err(|e| std::io::Error::other(e.to_string()))
+}
+
+/// Point `builder` at Tigris (`https://t3.storage.dev`, region `auto`)
+/// unless it already has an endpoint or region of its own.
+fn with_s3_defaults(mut builder: AmazonS3Builder) -> AmazonS3Builder {
+    let has_endpoint = builder.get_config_value(&AmazonS3ConfigKey::Endpoint).is_some()
+        || builder.get_config_value(&AmazonS3ConfigKey::S3Endpoint).is_some();
+    if !has_endpoint {
+        builder = builder.with_endpoint("https://t3.storage.dev");
+    }
+    if builder.get_config_value(&AmazonS3ConfigKey::Region).is_none() {
+        builder = builder.with_region("auto");
+    }
+    builder
+}
+
+/// Stream `write_fn`'s output to the object `url` points at as a multipart
+/// upload, gzip- or zstd-compressed when the key ends in `.gz` or `.zst`.
+///
+/// Nothing is buffered beyond the parts in flight, so exports of any size
+/// run in bounded memory. If `write_fn` or any part upload fails, the
+/// upload is aborted and no object is created. A spinner on stderr shows
+/// the bytes uploaded so far. Returns the number of bytes uploaded.
+pub fn upload_to_url(
+    url: &str,
+    write_fn: impl FnOnce(&mut dyn Write) -> Result<(), ExportError>,
+) -> Result<u64, ExportError> {
+    let (store, path) = store_for_url(url)?;
+    upload_to_store(store.as_ref(), &path, write_fn)
+}
+
+/// Parts uploading at once before writes wait for one to finish, which
+/// bounds memory to about this many [`PART_SIZE`] buffers.
+const MAX_CONCURRENT_PARTS: usize = 8;
+
+/// Size of each multipart part (the S3 minimum is 5 MiB).
+const PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// Adapter: `std::io::Write` → [`WriteMultipart::write`], which buffers
+/// into [`PART_SIZE`] parts and uploads each on a tokio task.
+struct MultipartWriter<'a> {
+    inner: WriteMultipart,
+    runtime: &'a tokio::runtime::Runtime,
+    progress: &'a ProgressBar,
+    /// Bytes written since the last wait for upload capacity.
+    unchecked: usize,
+}
+
+impl Write for MultipartWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.unchecked >= PART_SIZE {
+            self.runtime
+                .block_on(self.inner.wait_for_capacity(MAX_CONCURRENT_PARTS))
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            self.unchecked = 0;
+        }
+        // part uploads are spawned onto the runtime
+        let _guard = self.runtime.enter();
+        self.inner.write(buf);
+        self.unchecked += buf.len();
+        self.progress.inc(buf.len() as u64);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        // the last part is sent by finish()
+        Ok(())
+    }
+}
+
+/// [`upload_to_url`], to `path` in an already-built `store`.
+fn upload_to_store(
+    store: &dyn ObjectStore,
+    path: &Path,
+    write_fn: impl FnOnce(&mut dyn Write) -> Result<(), ExportError>,
+) -> Result<u64, ExportError> {
+    let runtime = tokio::runtime::Runtime::new().map_err(ExportError::Io)?;
+    let upload = runtime
+        .block_on(store.put_multipart(path))
         .map_err(|e| ExportError::Io(std::io::Error::other(e.to_string())))?;
 
-    let path = Path::from(key);
+    let progress = ProgressBar::new_spinner();
+    if let Ok(style) = ProgressStyle::with_template(
+        "{spinner:.cyan} uploading {msg} {bytes} ({bytes_per_sec})",
+    ) {
+        progress.set_style(style);
+    }
+    progress.set_message(path.to_string());
+
+    let mut writer = MultipartWriter {
+        inner: WriteMultipart::new_with_chunk_size(upload, PART_SIZE),
+        runtime: &runtime,
+        progress: &progress,
+        unchecked: 0,
+    };
+    let written = write_compressed(path.as_ref(), &mut writer, write_fn);
+    let uploaded = progress.position();
+    progress.finish_and_clear();
+
+    let MultipartWriter { inner, .. } = writer;
+    match written {
+        Ok(()) => {
+            runtime
+                .block_on(inner.finish())
+                .map_err(|e| ExportError::Io(std::io::Error::other(e.to_string())))?;
+            Ok(uploaded)
+        }
+        Err(e) => {
+            // best effort: the export error is the one worth reporting
+            let _ = runtime.block_on(inner.abort());
+            Err(e)
+        }
+    }
+}
+
+/// Run `write_fn` against `writer`, through a compressor chosen by the
+/// extension of `key`, and finish the compressed stream.
+fn write_compressed(
+    key: &str,
+    writer: &mut dyn Write,
+    write_fn: impl FnOnce(&mut dyn Write) -> Result<(), ExportError>,
+) -> Result<(), ExportError> {
+    if key.ends_with(".gz") {
+        let mut encoder = flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+        write_fn(&mut encoder)?;
+        encoder.finish()?;
+    } else if key.ends_with(".zst") {
+        let mut encoder = zstd::stream::write::Encoder::new(writer, 3)
+            .map_err(|e| ExportError::Compression(e.to_string()))?;
+        write_fn(&mut encoder)?;
+        encoder
+            .finish()
+            .map_err(|e| ExportError::Compression(e.to_string()))?;
+    } else {
+        write_fn(writer)?;
+    }
+    Ok(())
+}
+
+/// Objects are downloaded in ranged requests of this size, so no more
+/// than one chunk is held in memory.
+const DOWNLOAD_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Download the object at `url` into `dir` (usually
+/// [`Connection::download_dir`](crate::sqlite::Connection::download_dir))
+/// and return the path of the local file.
+///
+/// The file keeps the object's name (`s3://bucket/a/events.csv.gz` →
+/// `<dir>/<hash>/events.csv.gz`), so suffix-based format and compression
+/// detection work on it unchanged. A missing object is an error of kind
+/// [`std::io::ErrorKind::NotFound`].
+pub fn download(url: &str, dir: &std::path::Path) -> std::io::Result<PathBuf> {
+    let (store, path) = store_for_url(url)?;
+    let local = local_path(url, dir);
+    download_to(store.as_ref(), &path, &local)?;
+    Ok(local)
+}
+
+/// Where the object at `url` is downloaded to under `dir`.
+fn local_path(url: &str, dir: &std::path::Path) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    let file_name = url.rsplit('/').next().unwrap_or(url);
+    dir.join(format!("{:016x}", hasher.finish())).join(file_name)
+}
+
+/// Download `path` from `store` to `local`, through a `.part` file that is
+/// renamed into place once complete.
+fn download_to(
+    store: &dyn ObjectStore,
+    path: &Path,
+    local: &std::path::Path,
+) -> std::io::Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    let meta = runtime.block_on(store.head(path)).map_err(io_error)?;
+
+    if let Some(dir) = local.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut partial = local.as_os_str().to_owned();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+
+    let progress = ProgressBar::new(meta.size);
+    if let Ok(style) = ProgressStyle::with_template(
+        "{spinner:.cyan} downloading {msg} {bytes}/{total_bytes} ({bytes_per_sec})",
+    ) {
+        progress.set_style(style);
+    }
+    progress.set_message(path.to_string());
+
+    let result = copy_ranges(&runtime, store, path, meta.size, &partial, &progress);
+    progress.finish_and_clear();
+
+    match result {
+        Ok(()) => std::fs::rename(&partial, local),
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+/// Write `size` bytes of `path` to `local`, [`DOWNLOAD_CHUNK_SIZE`] at a time.
+fn copy_ranges(
+    runtime: &tokio::runtime::Runtime,
+    store: &dyn ObjectStore,
+    path: &Path,
+    size: u64,
+    local: &std::path::Path,
+    progress: &ProgressBar,
+) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(local)?);
+    let mut start = 0;
+    while start < size {
+        let end = (start + DOWNLOAD_CHUNK_SIZE).min(size);
+        let bytes = runtime
+            .block_on(store.get_range(path, start..end))
+            .map_err(io_error)?;
+        file.write_all(&bytes)?;
+        progress.inc(bytes.len() as u64);
+        start = end;
+    }
+    file.flush()
+}
+
+/// An object found by [`list`].
+#[derive(Debug, Clone)]
+pub struct ObjectEntry {
+    /// Path relative to the listed prefix, `/`-separated.
+    pub path: String,
+    pub size: u64,
+    pub last_modified: std::time::SystemTime,
+}
+
+/// List every object under the prefix `url`, recursively, sorted by path.
+/// A prefix with nothing under it lists as empty.
+pub fn list(url: &str) -> std::io::Result<Vec<ObjectEntry>> {
+    let (store, prefix) = store_for_url(url)?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    let mut entries = Vec::new();
+    let mut pending = vec![prefix.clone()];
+    while let Some(dir) = pending.pop() {
+        let listing = match runtime.block_on(store.list_with_delimiter(Some(&dir))) {
+            Ok(listing) => listing,
+            Err(object_store::Error::NotFound { .. }) => continue,
+            Err(e) => return Err(io_error(e)),
+        };
+        pending.extend(listing.common_prefixes);
+        entries.extend(listing.objects.into_iter().map(|meta| ObjectEntry {
+            path: meta
+                .location
+                .as_ref()
+                .strip_prefix(prefix.as_ref())
+                .unwrap_or(meta.location.as_ref())
+                .trim_start_matches('/')
+                .to_string(),
+            size: meta.size,
+            last_modified: meta.last_modified.into(),
+        }));
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+/// The path of `relative` (as listed by [`list`]) under `prefix`.
+fn entry_path(prefix: &Path, relative: &str) -> Path {
+    prefix.parts().chain(Path::from(relative).parts()).collect()
+}
+
+/// Download `entries` (as listed by [`list`] under `url`) into `dir`,
+/// keeping their relative paths. Each is copied in ranged chunks, like
+/// [`download`]. Returns the number of bytes written.
+pub fn download_into(
+    url: &str,
+    entries: &[ObjectEntry],
+    dir: &std::path::Path,
+) -> std::io::Result<u64> {
+    let (store, prefix) = store_for_url(url)?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    let bytes = entries.iter().map(|entry| entry.size).sum();
 
-    let rt = tokio::runtime::Runtime::new()
-        .map_err(|e| ExportError::Io(e))?;
+    let progress = ProgressBar::new(bytes);
+    if let Ok(style) = ProgressStyle::with_template(
+        "{spinner:.cyan} downloading {msg} {bytes}/{total_bytes} ({bytes_per_sec})",
+    ) {
+        progress.set_style(style);
+    }
+    progress.set_message(url.to_string());
+
+    let result = entries.iter().try_for_each(|entry| {
+        let local = dir.join(&entry.path);
+        if let Some(parent) = local.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let path = entry_path(&prefix, &entry.path);
+        copy_ranges(&runtime, store.as_ref(), &path, entry.size, &local, &progress)
+    });
+    progress.finish_and_clear();
+    result.map(|()| bytes)
+}
 
-    rt.block_on(async {
-        store
-            .put(&path, data.into())
-            .await
-            .map_err(|e| ExportError::Io(std::io::Error::other(e.to_string())))
-    })?;
+/// The first `len` bytes of each of `entries` (as listed by [`list`] under
+/// `url`), or the whole object when it's shorter, e.g. to read file headers
+/// without downloading the files.
+pub fn read_heads(url: &str, entries: &[ObjectEntry], len: u64) -> std::io::Result<Vec<Vec<u8>>> {
+    let (store, prefix) = store_for_url(url)?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    entries
+        .iter()
+        .map(|entry| {
+            let end = len.min(entry.size);
+            if end == 0 {
+                return Ok(Vec::new());
+            }
+            let path = entry_path(&prefix, &entry.path);
+            let bytes = runtime
+                .block_on(store.get_range(&path, 0..end))
+                .map_err(io_error)?;
+            Ok(bytes.to_vec())
+        })
+        .collect()
+}
 
+/// Delete `paths` (relative, as listed by [`list`]) under the prefix `url`.
+/// Objects that are already gone are skipped.
+pub fn delete(url: &str, paths: &[&str]) -> std::io::Result<()> {
+    let (store, prefix) = store_for_url(url)?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    for relative in paths {
+        match runtime.block_on(store.delete(&entry_path(&prefix, relative))) {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => {}
+            Err(e) => return Err(io_error(e)),
+        }
+    }
     Ok(())
 }
 
+/// Convert an object store error, keeping "not found" recognizable.
+fn io_error(e: object_store::Error) -> std::io::Error {
+    match e {
+        object_store::Error::NotFound { .. } => {
+            std::io::Error::new(std::io::ErrorKind::NotFound, e.to_string())
+        }
+        e => std::io::Error::other(e.to_string()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -90,6 +472,10 @@ mod tests {
     fn test_is_object_store_url() {
         assert!(is_object_store_url("s3://bucket/key.csv"));
         assert!(is_object_store_url("t3://bucket/key.csv"));
+        assert!(is_object_store_url("gs://bucket/key.csv"));
+        assert!(is_object_store_url("az://container/key.csv"));
+        assert!(is_object_store_url("file:///tmp/key.csv"));
+        assert!(is_object_store_url("memory://key.csv"));
         assert!(!is_object_store_url("/tmp/local.csv"));
         assert!(!is_object_store_url("output.csv"));
     }
@@ -108,6 +494,101 @@ mod tests {
         assert_eq!(key, "file.json");
     }
 
+    #[test]
+    fn test_parse_url_gs_and_az() {
+        assert_eq!(parse_url("gs://bkt/a/b.csv").unwrap(), ("bkt", "a/b.csv"));
+        assert_eq!(parse_url("az://container/b.csv").unwrap(), ("container", "b.csv"));
+        assert!(parse_url("file:///tmp/b.csv").is_err());
+    }
+
+    #[test]
+    fn test_s3_defaults_only_fill_gaps() {
+        let endpoint = |b: &AmazonS3Builder| b.get_config_value(&AmazonS3ConfigKey::Endpoint);
+        let region = |b: &AmazonS3Builder| b.get_config_value(&AmazonS3ConfigKey::Region);
+
+        let builder = with_s3_defaults(AmazonS3Builder::new());
+        assert_eq!(endpoint(&builder).as_deref(), Some("https://t3.storage.dev"));
+        assert_eq!(region(&builder).as_deref(), Some("auto"));
+
+        let builder = with_s3_defaults(
+            AmazonS3Builder::new()
+                .with_endpoint("http://localhost:9000")
+                .with_region("eu-west-1"),
+        );
+        assert_eq!(endpoint(&builder).as_deref(), Some("http://localhost:9000"));
+        assert_eq!(region(&builder).as_deref(), Some("eu-west-1"));
+
+        // the S3-specific endpoint wins over the generic one at build time
+        let builder = with_s3_defaults(
+            AmazonS3Builder::new().with_config(AmazonS3ConfigKey::S3Endpoint, "http://minio:9000"),
+        );
+        assert_eq!(endpoint(&builder), None);
+    }
+
+    #[test]
+    fn test_store_for_url_rejects_empty_local_paths() {
+        assert!(store_for_url("file://").is_err());
+        assert!(store_for_url("memory://").is_err());
+        assert!(store_for_url("ftp://host/file.csv").is_err());
+    }
+
+    #[test]
+    fn test_file_url_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("exports/out.csv");
+        let url = format!("file://{}", target.display());
+
+        let uploaded = upload_to_url(&url, |writer| Ok(writer.write_all(b"a\n1\n")?)).unwrap();
+        assert_eq!(uploaded, 4);
+        assert_eq!(std::fs::read(&target).unwrap(), b"a\n1\n");
+
+        let local = download(&url, dir.path()).unwrap();
+        assert!(local.starts_with(dir.path()));
+        assert_eq!(std::fs::read(local).unwrap(), b"a\n1\n");
+    }
+
+    #[test]
+    fn test_memory_url_is_shared_across_calls() {
+        let url = "memory://tests/shared.ndjson";
+        upload_to_url(url, |writer| Ok(writer.write_all(b"{\"a\": 1}\n")?)).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let local = download(url, dir.path()).unwrap();
+        assert_eq!(std::fs::read(local).unwrap(), b"{\"a\": 1}\n");
+
+        let err = download("memory://tests/never-written.csv", dir.path()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_list_and_download_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("replica/ltx/0")).unwrap();
+        std::fs::write(dir.path().join("replica/ltx/0/a.ltx"), b"aa").unwrap();
+        std::fs::write(dir.path().join("replica/meta"), b"m").unwrap();
+        let url = format!("file://{}", dir.path().join("replica").display());
+
+        let entries = list(&url).unwrap();
+        let paths: Vec<_> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["ltx/0/a.ltx", "meta"]);
+        assert_eq!(entries[0].size, 2);
+
+        let copy = dir.path().join("copy");
+        assert_eq!(download_into(&url, &entries[..1], &copy).unwrap(), 2);
+        assert_eq!(std::fs::read(copy.join("ltx/0/a.ltx")).unwrap(), b"aa");
+        assert!(!copy.join("meta").exists());
+
+        let heads = read_heads(&url, &entries, 1).unwrap();
+        assert_eq!(heads, vec![b"a".to_vec(), b"m".to_vec()]);
+        assert_eq!(read_heads(&url, &entries, 10).unwrap()[0], b"aa");
+
+        let missing = format!("file://{}", dir.path().join("nothing").display());
+        assert!(list(&missing).unwrap().is_empty());
+
+        delete(&url, &["meta", "never-there"]).unwrap();
+        let paths: Vec<_> = list(&url).unwrap().into_iter().map(|e| e.path).collect();
+        assert_eq!(paths, vec!["ltx/0/a.ltx"]);
+    }
+
     #[test]
     fn test_parse_url_no_key() {
         assert!(parse_url("s3://bucket-only").is_err());
@@ -123,4 +604,101 @@ mod tests {
     fn test_parse_url_bad_scheme() {
         assert!(parse_url("http://bucket/key").is_err());
     }
+
+    fn read_object(store: &dyn ObjectStore, path: &Path) -> object_store::Result<Vec<u8>> {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async { Ok(store.get(path).await?.bytes().await?.to_vec()) })
+    }
+
+    #[test]
+    fn test_upload_to_store_spans_multiple_parts() {
+        let store = object_store::memory::InMemory::new();
+        let path = Path::from("exports/big.csv");
+        // three parts' worth, written in small pieces like the exporters do
+        let line = b"0123456789abcdef0123456789abcdef0123456789abcdef012345678,\n";
+        let lines = (PART_SIZE * 3 - 1) / line.len() + 1;
+
+        let uploaded = upload_to_store(&store, &path, |writer| {
+            for _ in 0..lines {
+                writer.write_all(line)?;
+            }
+            Ok(())
+        })
+        .unwrap();
+
+        let data = read_object(&store, &path).unwrap();
+        assert_eq!(data.len(), lines * line.len());
+        assert_eq!(uploaded, data.len() as u64);
+        assert!(data.chunks(line.len()).all(|chunk| chunk == line));
+    }
+
+    #[test]
+    fn test_upload_to_store_compresses_by_extension() {
+        let store = object_store::memory::InMemory::new();
+
+        let path = Path::from("out.csv.zst");
+        upload_to_store(&store, &path, |writer| Ok(writer.write_all(b"a,b\n1,2\n")?)).unwrap();
+        let data = read_object(&store, &path).unwrap();
+        assert_eq!(zstd::decode_all(&data[..]).unwrap(), b"a,b\n1,2\n");
+
+        let path = Path::from("out.csv.gz");
+        upload_to_store(&store, &path, |writer| Ok(writer.write_all(b"a,b\n1,2\n")?)).unwrap();
+        let data = read_object(&store, &path).unwrap();
+        let mut decoded = Vec::new();
+        std::io::Read::read_to_end(&mut flate2::read::GzDecoder::new(&data[..]), &mut decoded)
+            .unwrap();
+        assert_eq!(decoded, b"a,b\n1,2\n");
+    }
+
+    #[test]
+    fn test_upload_to_store_aborts_on_error() {
+        let store = object_store::memory::InMemory::new();
+        let path = Path::from("partial.csv");
+
+        let result = upload_to_store(&store, &path, |writer| {
+            writer.write_all(&vec![b'x'; PART_SIZE + 1])?;
+            Err(ExportError::Sql("interrupted".to_string()))
+        });
+
+        assert!(matches!(result, Err(ExportError::Sql(_))));
+        assert!(matches!(
+            read_object(&store, &path),
+            Err(object_store::Error::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_download_to_writes_object_in_chunks() {
+        let store = object_store::memory::InMemory::new();
+        let path = Path::from("data/events.csv");
+        let data: Vec<u8> = (0..DOWNLOAD_CHUNK_SIZE + 10).map(|i| (i % 251) as u8).collect();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime
+            .block_on(store.put(&path, data.clone().into()))
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("nested/events.csv");
+        download_to(&store, &path, &local).unwrap();
+        assert_eq!(std::fs::read(&local).unwrap(), data);
+        assert!(!dir.path().join("nested/events.csv.part").exists());
+    }
+
+    #[test]
+    fn test_download_to_missing_object_is_not_found() {
+        let store = object_store::memory::InMemory::new();
+        let dir = tempfile::tempdir().unwrap();
+        let err = download_to(&store, &Path::from("missing.csv"), &dir.path().join("missing.csv"))
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_local_path_keeps_file_name() {
+        let dir = std::path::Path::new("/downloads");
+        let local = local_path("s3://bucket/a/events.csv.gz", dir);
+        assert!(local.starts_with(dir));
+        assert!(local.ends_with("events.csv.gz"), "{}", local.display());
+        assert_ne!(local, local_path("s3://other/a/events.csv.gz", dir));
+    }
 }
@@ -1,16 +1,29 @@
+use std::io::Read;
+
+use serde_json::Value;
+
 use crate::sqlite::{quote_identifier, Connection, SQLiteError, Statement};
 
-/// If `error` is a "no such table" error referencing a file that looks like
-/// a supported data file (csv/tsv) and exists on disk, return a prepared
-/// `CREATE VIRTUAL TABLE temp."<name>"` statement that the caller should
-/// execute before re-preparing the original SQL.
-///
-/// Returns:
-/// - `None` — the error is not replacement-scannable (wrong error kind,
-///   unsupported suffix, or the file doesn't exist). Callers surface the
-///   original error.
-/// - `Some(Ok(stmt))` — execute `stmt`, then retry the original SQL.
-/// - `Some(Err(e))` — preparing the CREATE VIRTUAL TABLE itself failed.
+/// How many records JSON/NDJSON scans sample to infer their columns. Keys
+/// that first appear after this many records are not columns.
+const INFERENCE_SAMPLE_SIZE: usize = 100;
+
+/// Extensions of SQLite database files that replacement scans ATTACH.
+const DATABASE_EXTENSIONS: [&str; 3] = [".db", ".sqlite", ".sqlite3"];
+
+/// How a replacement-scanned file is read, chosen by its suffix.
+#[derive(Debug, PartialEq)]
+enum ScanFormat {
+    /// Read live through a sqlite-xsv virtual table (`USING ...`).
+    Xsv(&'static str),
+    /// A JSON array of objects (or a single object).
+    Json,
+    /// One JSON object per line (`.ndjson`, `.jsonl`).
+    Ndjson,
+    /// One row per line of text, in a single `line` column.
+    Lines,
+}
+
 /// Strip a recognized compression suffix (`.gz`, `.zst`) so the underlying
 /// format suffix can be matched (`data.csv.gz` → `data.csv`). sqlite-xsv
 /// decompresses gzip and zstd transparently based on the file extension.
@@ -20,6 +33,53 @@ fn strip_compression_suffix(name: &str) -> &str {
         .unwrap_or(name)
 }
 
+fn scan_format(name: &str) -> Option<ScanFormat> {
+    let lower = name.to_lowercase();
+    let base = strip_compression_suffix(&lower);
+    if base.ends_with(".csv") {
+        Some(ScanFormat::Xsv("csv"))
+    } else if base.ends_with(".tsv") {
+        Some(ScanFormat::Xsv("tsv(flexible=true)"))
+    } else if base.ends_with(".json") {
+        Some(ScanFormat::Json)
+    } else if base.ends_with(".ndjson") || base.ends_with(".jsonl") {
+        Some(ScanFormat::Ndjson)
+    } else if base.ends_with(".txt") {
+        Some(ScanFormat::Lines)
+    } else {
+        None
+    }
+}
+
+/// If `error` is a "no such table" error referencing a file that looks like
+/// a supported data file and exists on disk, return a prepared statement
+/// that creates `temp."<name>"`; the caller should execute it before
+/// re-preparing the original SQL.
+///
+/// SQLite database files (`.db`, `.sqlite`, `.sqlite3`) are ATTACHed
+/// read-only under their own path as the schema name, so both
+/// `'other.db'.users` and a bare `FROM 'archive.sqlite'` (for a database
+/// with a single table) resolve; see [`database_scan`].
+///
+/// CSV/TSV files become sqlite-xsv virtual tables that read the file live,
+/// streaming it on every query. JSON, NDJSON/JSONL and `.txt` files are
+/// instead parsed up front and copied into a temp table, with columns
+/// inferred from the first [`INFERENCE_SAMPLE_SIZE`] records. That copy is
+/// a snapshot: later edits to the file aren't seen until the table is
+/// dropped, and loading needs the whole file in memory (see
+/// `prepare_json_table`). All of them may be `.gz`/`.zst` compressed.
+///
+/// With the `object_store` feature, object store URLs (`s3://`, `gs://`,
+/// `az://`, `file://`, ...) of any of these are downloaded and scanned too
+/// (see `object_store_scan`).
+///
+/// Returns:
+/// - `None` — the error is not replacement-scannable (wrong error kind,
+///   unsupported suffix, or the file doesn't exist). Callers surface the
+///   original error.
+/// - `Some(Ok(stmt))` — execute `stmt`, then retry the original SQL.
+/// - `Some(Err(e))` — reading the file or preparing the CREATE statement
+///   itself failed.
 pub fn replacement_scan(
     error: &SQLiteError,
     connection: &Connection,
@@ -27,23 +87,16 @@ pub fn replacement_scan(
     let table_name = error.message.as_str().strip_prefix("no such table: ")?;
 
     /* TODO:
-     * - [ ] JSON
-     * - [ ] NDJSON/JSONL
-     * - [ ] .txt files?
      * - [ ] XML??
      */
-    let lower = table_name.to_lowercase();
-    // The xsv vtab decompresses gzip/zstd based on the final file extension,
-    // so `data.csv.gz` is handled by recognizing the suffix here and letting
-    // the vtab open the full name as-is.
-    let base = strip_compression_suffix(&lower);
-    let using = if base.ends_with(".csv") {
-        "csv"
-    } else if base.ends_with(".tsv") {
-        "tsv(flexible=true)"
-    } else {
-        return None;
-    };
+    #[cfg(feature = "object_store")]
+    if crate::object_store::is_object_store_url(table_name) {
+        return object_store_scan(table_name, connection);
+    }
+    if let Some(scan) = database_scan(table_name, connection) {
+        return Some(scan);
+    }
+    let format = scan_format(table_name)?;
 
     // The table name doubles as the file path (resolved relative to cwd by
     // the vtab). If the file doesn't exist, fall through to the original
@@ -55,12 +108,15 @@ pub fn replacement_scan(
         return None;
     }
 
-    let sql = format!(
-        "create virtual table temp.{} using {}",
-        quote_identifier(table_name),
-        using
-    );
-    match connection.prepare(&sql) {
+    let using = match format {
+        ScanFormat::Xsv(using) => using,
+        format => return Some(prepare_json_table(connection, table_name, table_name, &format)),
+    };
+
+    // The xsv vtab decompresses gzip/zstd based on the final file extension,
+    // so `data.csv.gz` is handled by recognizing the suffix here and letting
+    // the vtab open the full name as-is.
+    match connection.prepare(&xsv_table_sql(table_name, using)) {
         Ok((_, Some(stmt))) => Some(Ok(stmt)),
         // A non-empty CREATE VIRTUAL TABLE always yields a statement; treat
         // the impossible empty-prepare as "not scannable".
@@ -68,3 +124,503 @@ pub fn replacement_scan(
         Err(e) => Some(Err(e)),
     }
 }
+
+/// `CREATE VIRTUAL TABLE` for a sqlite-xsv table over `path`, which the
+/// vtab reads from its own table name.
+fn xsv_table_sql(path: &str, using: &str) -> String {
+    format!(
+        "create virtual table temp.{} using {}",
+        quote_identifier(path),
+        using
+    )
+}
+
+/// Replacement scan for objects in object storage
+/// (`'s3://bucket/events.csv.gz'`, `'gs://bucket/app.db'.users`).
+///
+/// The object is downloaded into the connection's download directory and
+/// scanned like a local file, but under its URL: data files get a
+/// `temp."<url>"` table or view, and databases are ATTACHed with the URL
+/// as the schema name. A missing object falls through to the original
+/// "no such table" error.
+#[cfg(feature = "object_store")]
+fn object_store_scan(
+    name: &str,
+    connection: &Connection,
+) -> Option<Result<Statement, SQLiteError>> {
+    let (url, table) = split_object_reference(name);
+    let format = if is_database_file_name(url) {
+        match is_attached(connection, url) {
+            Ok(false) => None,
+            Ok(true) if table.is_some() => return None,
+            Ok(true) => return Some(prepare_single_table_view(connection, url)),
+            Err(e) => return Some(Err(e)),
+        }
+    } else {
+        Some(scan_format(url)?)
+    };
+
+    let local = match connection
+        .download_dir()
+        .and_then(|dir| crate::object_store::download(url, dir))
+    {
+        Ok(local) => local.to_string_lossy().into_owned(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            return Some(Err(SQLiteError::custom(
+                "REPLACEMENT_SCAN",
+                format!("failed to download '{url}': {e}"),
+            )))
+        }
+    };
+
+    match format {
+        None if !has_sqlite_header(&local) => Some(Err(SQLiteError::custom(
+            "REPLACEMENT_SCAN",
+            format!("'{url}' is not a SQLite database"),
+        ))),
+        None => Some(prepare_attach(connection, &local, url)),
+        Some(ScanFormat::Xsv(using)) => {
+            // the vtab must be named after the local file it reads; a view
+            // gives it the URL's name
+            if let Err(e) = connection.execute(&xsv_table_sql(&local, using)) {
+                return Some(Err(e));
+            }
+            let sql = format!(
+                "create view temp.{} as select * from temp.{}",
+                quote_identifier(url),
+                quote_identifier(&local)
+            );
+            match connection.prepare(&sql) {
+                Ok((_, Some(stmt))) => Some(Ok(stmt)),
+                Ok((_, None)) => None,
+                Err(e) => Some(Err(e)),
+            }
+        }
+        Some(format) => Some(prepare_json_table(connection, url, &local, &format)),
+    }
+}
+
+/// Split an object URL "no such table" name like [`split_database_reference`]
+/// does, but by name alone since the object isn't local yet:
+/// `s3://b/app.db.users` → (`s3://b/app.db`, Some(`users`)).
+#[cfg(feature = "object_store")]
+fn split_object_reference(name: &str) -> (&str, Option<&str>) {
+    if is_database_file_name(name) {
+        return (name, None);
+    }
+    name.match_indices('.')
+        .find_map(|(idx, _)| {
+            let (url, table) = (&name[..idx], &name[idx + 1..]);
+            (!table.is_empty() && is_database_file_name(url)).then_some((url, Some(table)))
+        })
+        .unwrap_or((name, None))
+}
+
+fn is_database_file_name(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    DATABASE_EXTENSIONS.iter().any(|ext| lower.ends_with(ext))
+}
+
+/// Whether `path` is an existing file starting with the SQLite header, so a
+/// stray `notes.db` text file is never ATTACHed.
+fn has_sqlite_header(path: &str) -> bool {
+    let mut header = [0u8; 16];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|()| &header == b"SQLite format 3\0")
+}
+
+/// Split a "no such table" name into the database file it references and
+/// the table inside it: `other.db.users` → (`other.db`, Some(`users`)),
+/// `archive.sqlite` → (`archive.sqlite`, None). SQLite reports a
+/// schema-qualified miss as `schema.table`, and both halves may contain
+/// dots, so every split point is tried against the filesystem.
+fn split_database_reference(name: &str) -> Option<(&str, Option<&str>)> {
+    if is_database_file_name(name) && has_sqlite_header(name) {
+        return Some((name, None));
+    }
+    name.match_indices('.').find_map(|(idx, _)| {
+        let (file, table) = (&name[..idx], &name[idx + 1..]);
+        (!table.is_empty() && is_database_file_name(file) && has_sqlite_header(file))
+            .then_some((file, Some(table)))
+    })
+}
+
+/// A `file:` URI opening `path` read-only. Characters with meaning in URIs
+/// are percent-encoded so any path round-trips.
+fn readonly_uri(path: &str) -> String {
+    let mut uri = String::from("file:");
+    for c in path.chars() {
+        match c {
+            '%' | '?' | '#' => uri.push_str(&format!("%{:02X}", c as u32)),
+            c => uri.push(c),
+        }
+    }
+    uri.push_str("?mode=ro");
+    uri
+}
+
+fn is_attached(connection: &Connection, schema: &str) -> Result<bool, SQLiteError> {
+    let Some(mut stmt) = connection
+        .prepare("select 1 from pragma_database_list where name = ?1")?
+        .1
+    else {
+        return Ok(false);
+    };
+    stmt.bind_text(1, schema)?;
+    Ok(stmt.next()?.is_some())
+}
+
+/// Replacement scan for SQLite database files.
+///
+/// The first miss ATTACHes the file read-only, using the referenced path as
+/// the schema name, which makes `'other.db'.users` resolve on the retry
+/// without rewriting the query. A bare `FROM 'archive.sqlite'` misses again
+/// after the ATTACH; if the database has exactly one table, that second
+/// pass creates a `temp."archive.sqlite"` view over it.
+fn database_scan(
+    name: &str,
+    connection: &Connection,
+) -> Option<Result<Statement, SQLiteError>> {
+    let (file, table) = split_database_reference(name)?;
+    match is_attached(connection, file) {
+        Ok(false) => {}
+        // Already attached: a schema-qualified miss means the table really
+        // doesn't exist, so surface the original error.
+        Ok(true) if table.is_some() => return None,
+        Ok(true) => return Some(prepare_single_table_view(connection, file)),
+        Err(e) => return Some(Err(e)),
+    }
+
+    Some(prepare_attach(connection, file, file))
+}
+
+/// Prepare an ATTACH of the database at `path`, read-only, as `schema`.
+fn prepare_attach(
+    connection: &Connection,
+    path: &str,
+    schema: &str,
+) -> Result<Statement, SQLiteError> {
+    let stmt = match connection.prepare("attach database ?1 as ?2")? {
+        (_, Some(stmt)) => stmt,
+        (_, None) => {
+            return Err(SQLiteError::custom(
+                "REPLACEMENT_SCAN",
+                "internal: replacement scan produced no statement",
+            ))
+        }
+    };
+    stmt.bind_text(1, readonly_uri(path))?;
+    stmt.bind_text(2, schema)?;
+    Ok(stmt)
+}
+
+fn prepare_single_table_view(connection: &Connection, schema: &str) -> Result<Statement, SQLiteError> {
+    let mut tables = vec![];
+    if let (_, Some(mut stmt)) = connection.prepare(
+        "select name from pragma_table_list where schema = ?1 \
+         and type in ('table', 'view', 'virtual') and name not like 'sqlite_%' order by name",
+    )? {
+        stmt.bind_text(1, schema)?;
+        while let Some(row) = stmt.next()? {
+            tables.push(row[0].as_str().to_owned());
+        }
+    }
+    let table = match tables.as_slice() {
+        [table] => table,
+        [] => {
+            return Err(SQLiteError::custom(
+                "REPLACEMENT_SCAN",
+                format!("'{schema}' has no tables"),
+            ))
+        }
+        tables => {
+            return Err(SQLiteError::custom(
+                "REPLACEMENT_SCAN",
+                format!(
+                    "'{schema}' has {} tables; reference one as '{schema}'.<table> ({})",
+                    tables.len(),
+                    tables.join(", ")
+                ),
+            ))
+        }
+    };
+    let sql = format!(
+        "create view temp.{} as select * from {}.{}",
+        quote_identifier(schema),
+        quote_identifier(schema),
+        quote_identifier(table)
+    );
+    match connection.prepare(&sql)? {
+        (_, Some(stmt)) => Ok(stmt),
+        (_, None) => Err(SQLiteError::custom(
+            "REPLACEMENT_SCAN",
+            "internal: replacement scan produced no statement",
+        )),
+    }
+}
+
+/// Read `path`, transparently decompressing `.gz`/`.zst` files.
+fn read_to_string_decompressed(path: &str) -> std::io::Result<String> {
+    let file = std::fs::File::open(path)?;
+    let lower = path.to_lowercase();
+    let mut reader: Box<dyn Read> = if lower.ends_with(".gz") {
+        Box::new(flate2::read::MultiGzDecoder::new(file))
+    } else if lower.ends_with(".zst") {
+        Box::new(zstd::stream::read::Decoder::new(file)?)
+    } else {
+        Box::new(file)
+    };
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+/// The records of a JSON/NDJSON/text file, flattened into rows of values
+/// under a shared list of column names.
+#[derive(Debug, PartialEq)]
+struct Records {
+    columns: Vec<String>,
+    rows: Vec<Vec<Value>>,
+}
+
+fn parse_records(contents: &str, format: &ScanFormat) -> Result<Records, String> {
+    let records: Vec<Value> = match format {
+        ScanFormat::Json => match serde_json::from_str(contents)
+            .map_err(|e| format!("invalid JSON: {e}"))?
+        {
+            Value::Array(values) => values,
+            value => vec![value],
+        },
+        ScanFormat::Ndjson => contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| {
+                serde_json::from_str(line)
+                    .map_err(|e| format!("invalid JSON on line {}: {e}", idx + 1))
+            })
+            .collect::<Result<_, _>>()?,
+        ScanFormat::Lines => {
+            return Ok(Records {
+                columns: vec!["line".to_string()],
+                rows: contents
+                    .lines()
+                    .map(|line| vec![Value::String(line.to_string())])
+                    .collect(),
+            })
+        }
+        ScanFormat::Xsv(_) => unreachable!("xsv files are scanned by a virtual table"),
+    };
+
+    // Columns are the union of object keys, in first-seen order, over the
+    // sampled records. Arrays of scalars land in a single `value` column.
+    let mut columns: Vec<String> = vec![];
+    for record in records.iter().take(INFERENCE_SAMPLE_SIZE) {
+        match record {
+            Value::Object(object) => {
+                for key in object.keys() {
+                    if !columns.contains(key) {
+                        columns.push(key.clone());
+                    }
+                }
+            }
+            _ => {
+                if !columns.iter().any(|c| c == "value") {
+                    columns.push("value".to_string());
+                }
+            }
+        }
+    }
+
+    let rows = records
+        .into_iter()
+        .map(|record| match record {
+            Value::Object(mut object) => columns
+                .iter()
+                .map(|column| object.remove(column).unwrap_or(Value::Null))
+                .collect(),
+            scalar => columns
+                .iter()
+                .map(|column| {
+                    if column == "value" {
+                        scalar.clone()
+                    } else {
+                        Value::Null
+                    }
+                })
+                .collect(),
+        })
+        .collect();
+    Ok(Records { columns, rows })
+}
+
+/// Prepare a `CREATE TABLE temp."<name>" AS SELECT ...` over the records
+/// of the file at `path`, which are bound as a single JSON array-of-arrays
+/// parameter and unpacked positionally with `->>` (so any key, however
+/// quoted, works). `name` is what the query referenced: the path itself,
+/// or an object URL whose download is at `path`.
+///
+/// The file is read whole (decompressed), parsed into JSON values and
+/// re-serialized as the bound parameter, so peak memory is roughly three
+/// times the decompressed size.
+fn prepare_json_table(
+    connection: &Connection,
+    name: &str,
+    path: &str,
+    format: &ScanFormat,
+) -> Result<Statement, SQLiteError> {
+    let contents = read_to_string_decompressed(path).map_err(|e| {
+        SQLiteError::custom("REPLACEMENT_SCAN", format!("failed to read '{name}': {e}"))
+    })?;
+    let records = parse_records(&contents, format).map_err(|e| {
+        SQLiteError::custom("REPLACEMENT_SCAN", format!("failed to parse '{name}': {e}"))
+    })?;
+    if records.columns.is_empty() {
+        return Err(SQLiteError::custom(
+            "REPLACEMENT_SCAN",
+            format!("'{name}' has no records to infer columns from"),
+        ));
+    }
+
+    let projection = records
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(idx, column)| format!("value ->> {idx} AS {}", quote_identifier(column)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!(
+        "create table temp.{} as select {} from json_each(?1)",
+        quote_identifier(name),
+        projection
+    );
+    let stmt = match connection.prepare(&sql)? {
+        (_, Some(stmt)) => stmt,
+        (_, None) => {
+            return Err(SQLiteError::custom(
+                "REPLACEMENT_SCAN",
+                "internal: replacement scan produced no statement",
+            ))
+        }
+    };
+    let rows = Value::Array(records.rows.into_iter().map(Value::Array).collect());
+    stmt.bind_text(1, rows.to_string())?;
+    Ok(stmt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_scan_format_suffixes() {
+        assert_eq!(scan_format("a.csv"), Some(ScanFormat::Xsv("csv")));
+        assert_eq!(scan_format("A.CSV.GZ"), Some(ScanFormat::Xsv("csv")));
+        assert_eq!(scan_format("dump.json"), Some(ScanFormat::Json));
+        assert_eq!(scan_format("dump.json.zst"), Some(ScanFormat::Json));
+        assert_eq!(scan_format("events.ndjson"), Some(ScanFormat::Ndjson));
+        assert_eq!(scan_format("events.jsonl.gz"), Some(ScanFormat::Ndjson));
+        assert_eq!(scan_format("log.txt"), Some(ScanFormat::Lines));
+        assert_eq!(scan_format("users"), None);
+        assert_eq!(scan_format("data.parquet"), None);
+    }
+
+    #[test]
+    fn test_parse_records_json_infers_union_of_keys() {
+        let records = parse_records(
+            r#"[{"id": 1, "name": "a"}, {"id": 2, "tags": [1, 2]}]"#,
+            &ScanFormat::Json,
+        )
+        .unwrap();
+        assert_eq!(records.columns, ["id", "name", "tags"]);
+        assert_eq!(
+            records.rows,
+            [
+                vec![json!(1), json!("a"), Value::Null],
+                vec![json!(2), Value::Null, json!([1, 2])],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_records_json_single_object_and_scalars() {
+        let records = parse_records(r#"{"a": 1}"#, &ScanFormat::Json).unwrap();
+        assert_eq!(records.columns, ["a"]);
+        assert_eq!(records.rows.len(), 1);
+
+        let records = parse_records("[1, 2, 3]", &ScanFormat::Json).unwrap();
+        assert_eq!(records.columns, ["value"]);
+        assert_eq!(records.rows[2], vec![json!(3)]);
+    }
+
+    #[test]
+    fn test_parse_records_ndjson_skips_blank_lines_and_reports_bad_line() {
+        let records =
+            parse_records("{\"a\": 1}\n\n{\"a\": 2}\n", &ScanFormat::Ndjson).unwrap();
+        assert_eq!(records.rows, [vec![json!(1)], vec![json!(2)]]);
+
+        let err = parse_records("{\"a\": 1}\n{oops\n", &ScanFormat::Ndjson).unwrap_err();
+        assert!(err.contains("line 2"), "{err}");
+    }
+
+    #[test]
+    fn test_parse_records_ignores_keys_past_the_sample() {
+        let mut ndjson = "{\"a\": 1}\n".repeat(INFERENCE_SAMPLE_SIZE);
+        ndjson.push_str("{\"a\": 2, \"late\": true}\n");
+        let records = parse_records(&ndjson, &ScanFormat::Ndjson).unwrap();
+        assert_eq!(records.columns, ["a"]);
+        assert_eq!(records.rows.len(), INFERENCE_SAMPLE_SIZE + 1);
+    }
+
+    #[test]
+    fn test_readonly_uri_escapes_uri_characters() {
+        assert_eq!(readonly_uri("a/b.db"), "file:a/b.db?mode=ro");
+        assert_eq!(readonly_uri("50%?#.db"), "file:50%25%3F%23.db?mode=ro");
+    }
+
+    #[test]
+    fn test_split_database_reference() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("snap.v2.db");
+        crate::sqlite::Connection::open(db.to_str().unwrap())
+            .unwrap()
+            .execute("create table t(a)")
+            .unwrap();
+        let not_sqlite = dir.path().join("notes.db");
+        std::fs::write(&not_sqlite, "just text").unwrap();
+
+        let db = db.to_str().unwrap();
+        assert_eq!(split_database_reference(db), Some((db, None)));
+        let qualified = format!("{db}.my.table");
+        assert_eq!(
+            split_database_reference(&qualified),
+            Some((db, Some("my.table")))
+        );
+        assert_eq!(split_database_reference(not_sqlite.to_str().unwrap()), None);
+        assert_eq!(split_database_reference("missing.db.users"), None);
+    }
+
+    #[cfg(feature = "object_store")]
+    #[test]
+    fn test_split_object_reference() {
+        assert_eq!(split_object_reference("s3://b/app.db"), ("s3://b/app.db", None));
+        assert_eq!(
+            split_object_reference("s3://b/app.db.users"),
+            ("s3://b/app.db", Some("users"))
+        );
+        assert_eq!(
+            split_object_reference("s3://b/events.csv.gz"),
+            ("s3://b/events.csv.gz", None)
+        );
+    }
+
+    #[test]
+    fn test_parse_records_lines() {
+        let records = parse_records("first\nsecond\n", &ScanFormat::Lines).unwrap();
+        assert_eq!(records.columns, ["line"]);
+        assert_eq!(records.rows, [vec![json!("first")], vec![json!("second")]]);
+    }
+}
@@ -1,7 +1,118 @@
+//! The remote protocol spoken between a client [`Connection`] and
+//! `solite serve`: length-prefixed MessagePack frames carrying one
+//! [`Request`] and one [`Response`] at a time.
+//!
+//! Queries run through cursors: [`Request::Open`] prepares a statement and
+//! returns its metadata with a first page of rows, [`Request::Fetch`] pulls
+//! further pages, and [`Request::CloseCursor`] finalizes it early. Cursors
+//! that run to completion are closed by the server. The server side lives
+//! in [`Session`], shared by `solite serve` and the `solite-serve` binary.
+//!
+//! Clients open with a [`Request::Hello`] handshake: both sides must speak
+//! the same [`PROTOCOL_VERSION`], and optional requests are only sent when
+//! the server lists their capability (see [`CAPABILITIES`]). Servers from
+//! before the handshake hang up on `Hello`; over SSH or a `--transport`
+//! command the client then starts a new one, checks it with the
+//! `InTransaction` ping those servers expect, and sticks to the requests
+//! they know (`Query` rather than cursors, no capabilities).
+//!
+//! [`Request::Interrupt`] is the one out-of-band request: [`serve`] reads
+//! requests on a separate thread, so an interrupt reaches the database
+//! while another request is still running, and it gets no response.
+//!
+//! Besides stdin/stdout (reached over SSH or a `--transport` command), the
+//! server can [`listen`] on a TCP or Unix socket ([`SocketAddress`]). Socket
+//! clients must open with [`Request::Auth`] carrying the server's shared
+//! token, and each gets a session with its own connection.
+//!
+//! `solite remote daemon` ([`crate::daemon`]) relays sessions to warm SSH
+//! transports: clients open with [`Request::Attach`], and between clients
+//! the daemon clears the session with [`Request::Reset`].
+
 use serde::{Deserialize, Serialize};
-use std::io::{self, Read, Write};
+use std::collections::HashMap;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::exporter::{write_output_to_writer, BlobLimit, ExportFormat};
+use crate::sqlite::{
+    quote_identifier, ColumnMeta, Connection, IsExplain, OwnedValue, SQLiteError, Statement,
+};
+
+/// Version of the request/response protocol, exchanged in
+/// [`Request::Hello`]. Bumped only for changes that break existing peers;
+/// requests a peer can do without are announced as capabilities instead.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Server-side cursors: `Open`, `Fetch` and `CloseCursor`. Without it,
+/// clients fall back to `Query`.
+pub const CAP_CURSORS: &str = "cursors";
+/// Out-of-band `Interrupt`.
+pub const CAP_INTERRUPT: &str = "interrupt";
+/// `LoadExtension`. Never offered on sockets (see [`listen`]).
+pub const CAP_LOAD_EXTENSION: &str = "load_extension";
+/// Server-side `Export`.
+pub const CAP_EXPORT: &str = "export";
+/// Page-by-page `Backup`.
+pub const CAP_BACKUP: &str = "backup";
+/// `Reset`, which lets `solite remote daemon` reuse a session.
+pub const CAP_RESET: &str = "reset";
+/// `Page`, offset/limit windows with a cached total for paging UIs.
+pub const CAP_PAGE: &str = "page";
+
+/// Optional requests this build supports, announced in the handshake.
+pub const CAPABILITIES: &[&str] = &[
+    CAP_CURSORS,
+    CAP_INTERRUPT,
+    CAP_LOAD_EXTENSION,
+    CAP_EXPORT,
+    CAP_BACKUP,
+    CAP_RESET,
+    CAP_PAGE,
+];
+
+/// Connection settings a client can change with `PRAGMA`, which `Reset`
+/// puts back to what they were when the session started.
+pub const SESSION_PRAGMAS: &[&str] = &[
+    "analysis_limit",
+    "automatic_index",
+    "busy_timeout",
+    "cache_size",
+    "cache_spill",
+    "cell_size_check",
+    "defer_foreign_keys",
+    "foreign_keys",
+    "ignore_check_constraints",
+    "legacy_alter_table",
+    "locking_mode",
+    "mmap_size",
+    "query_only",
+    "recursive_triggers",
+    "reverse_unordered_selects",
+    "synchronous",
+    "temp_store",
+    "trusted_schema",
+    "writable_schema",
+];
 
-use crate::sqlite::{ColumnMeta, OwnedValue, SQLiteError};
+/// Bytes per `Chunk` frame when streaming an export or backup.
+const CHUNK_BYTES: usize = 256 * 1024;
+
+/// Rows per page a client requests when opening or fetching from a cursor.
+pub const FETCH_ROWS: usize = 1000;
+
+/// Largest `Auth` frame a socket client may open with, before it has
+/// proven it holds the token.
+const MAX_AUTH_BYTES: usize = 4 * 1024;
+
+/// How long a socket client has to send its `Auth` frame.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default cap on the requests a socket client sends once authenticated
+/// (see [`listen`]).
+pub const MAX_REQUEST_BYTES: usize = 64 * 1024 * 1024;
 
 /// A value sent over the wire, with subtype preserved (needed for JSON subtype=74).
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -20,9 +131,28 @@ pub struct QueryResult {
     pub is_explain: Option<u8>,
 }
 
+/// Metadata of a statement opened as a server-side cursor.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CursorInfo {
+    /// Server-assigned cursor id, used by `Fetch` and `CloseCursor`.
+    pub id: u64,
+    pub sql: String,
+    pub columns: Vec<ColumnMeta>,
+    pub readonly: bool,
+    pub is_explain: Option<u8>,
+}
+
 /// Client → Server request.
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Request {
+    /// The handshake: the client's protocol version and capabilities. Sent
+    /// first (after `Auth` on a socket); servers also accept clients that
+    /// never send it.
+    Hello {
+        version: u32,
+        capabilities: Vec<String>,
+    },
+
     /// Prepare, bind params, execute fully, return complete result with all rows.
     Query {
         sql: String,
@@ -36,6 +166,31 @@ pub enum Request {
         params: Vec<(String, OwnedValue)>,
     },
 
+    /// Present the shared token; must be the first request on a socket.
+    Auth { token: String },
+
+    /// Ask `solite remote daemon` for a session on the `ssh://` (or
+    /// scp-style) `url`; must be the first request to the daemon.
+    Attach {
+        url: String,
+        remote_bin: Option<String>,
+    },
+
+    /// Prepare and bind a statement as a cursor, returning its metadata and
+    /// up to `fetch` rows. Statements that write run to completion here, so
+    /// they take effect even if the client never steps them.
+    Open {
+        sql: String,
+        params: Vec<(String, OwnedValue)>,
+        fetch: usize,
+    },
+
+    /// Fetch up to `n` more rows from an open cursor.
+    Fetch { cursor: u64, n: usize },
+
+    /// Finalize a cursor before it has run to completion.
+    CloseCursor { cursor: u64 },
+
     /// Execute multiple statements via sqlite3_exec. For scripts.
     ExecuteScript { sql: String },
 
@@ -45,12 +200,50 @@ pub enum Request {
     /// Check if the connection is in a transaction.
     InTransaction,
 
-    /// Interrupt a long-running query.
+    /// Interrupt a long-running query. Handled as soon as it's read, even
+    /// while another request is running, and never answered.
     Interrupt,
 
     /// Serialize the entire database to bytes.
     Serialize,
 
+    /// Load an extension from a path on the server.
+    LoadExtension {
+        path: String,
+        entrypoint: Option<String>,
+    },
+
+    /// Export a statement's rows on the server, streaming the encoded bytes
+    /// back as `Chunk`s followed by `Streamed`.
+    Export {
+        sql: String,
+        params: Vec<(String, OwnedValue)>,
+        format: ExportFormat,
+        blob_limit: BlobLimit,
+    },
+
+    /// Stream a consistent copy of database `schema` (`main`, or an
+    /// attached name) page by page, as `Chunk`s followed by `Streamed`.
+    Backup { schema: String },
+
+    /// Rows `offset..offset + limit` of the read-only query `sql`, plus its
+    /// total row count when `count` is set. Totals are computed once and
+    /// cached until the database changes, so a paging UI can ask again
+    /// cheaply.
+    Page {
+        sql: String,
+        offset: usize,
+        limit: usize,
+        count: bool,
+    },
+
+    /// Finalize all cursors, roll back any open transaction, restore the
+    /// [`SESSION_PRAGMAS`] the session started with, detach attached
+    /// databases and drop everything in the temp schema, leaving the
+    /// session ready for another client. Extensions can't be unloaded, so
+    /// a session that loaded one answers `Closed` and ends instead.
+    Reset,
+
     /// Close the connection and shut down the server.
     Close,
 }
@@ -58,9 +251,42 @@ pub enum Request {
 /// Server → Client response.
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Response {
+    /// The server's side of the handshake: its protocol version, the
+    /// capabilities both sides support, and the solite-core version it was
+    /// built from.
+    Hello {
+        version: u32,
+        capabilities: Vec<String>,
+        server_version: String,
+    },
+
     /// Full query result with all rows materialized.
     Query(QueryResult),
 
+    /// Token accepted.
+    Authenticated,
+
+    /// The daemon attached the client to a session; `reused` when it was
+    /// kept warm from an earlier client.
+    Attached { reused: bool },
+
+    /// A newly opened cursor with its first page of rows. `done` means the
+    /// statement ran to completion and the server already closed it.
+    Opened {
+        cursor: CursorInfo,
+        rows: Vec<Vec<WireValue>>,
+        done: bool,
+    },
+
+    /// A page of rows from a cursor; `done` as for `Opened`.
+    Rows {
+        rows: Vec<Vec<WireValue>>,
+        done: bool,
+    },
+
+    /// Cursor finalized.
+    CursorClosed,
+
     /// Result of an Execute request.
     Executed {
         count: usize,
@@ -76,12 +302,32 @@ pub enum Response {
     /// Transaction state.
     InTransaction { value: bool },
 
-    /// Interrupt acknowledged.
+    /// Interrupt acknowledged (only by [`Session::handle`]; [`serve`]
+    /// handles interrupts out of band).
     Interrupted,
 
     /// Serialized database bytes.
     Serialized { data: Vec<u8> },
 
+    /// Extension loaded.
+    ExtensionLoaded,
+
+    /// Part of the output of an `Export` or `Backup`.
+    Chunk { data: Vec<u8> },
+
+    /// An `Export` or `Backup` finished after sending `bytes` in `Chunk`s.
+    Streamed { bytes: u64 },
+
+    /// A window of rows from `Page`; `total` when it asked for a count.
+    Page {
+        columns: Vec<ColumnMeta>,
+        rows: Vec<Vec<WireValue>>,
+        total: Option<u64>,
+    },
+
+    /// Session reset.
+    Reset,
+
     /// Server shutting down.
     Closed,
 
@@ -91,24 +337,1490 @@ pub enum Response {
 
 /// Read a length-prefixed MessagePack frame from a reader.
 pub fn read_frame<R: Read, T: for<'de> Deserialize<'de>>(reader: &mut R) -> io::Result<T> {
-    let mut len_buf = [0u8; 4];
-    reader.read_exact(&mut len_buf)?;
-    let len = u32::from_be_bytes(len_buf) as usize;
-
-    let mut buf = vec![0u8; len];
-    reader.read_exact(&mut buf)?;
+    read_frame_limited(reader, usize::MAX)
+}
 
-    rmp_serde::from_slice(&buf)
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+/// [`read_frame`], failing with [`io::ErrorKind::InvalidInput`] on frames
+/// longer than `limit` bytes. The stream is out of sync after that error.
+pub fn read_frame_limited<R: Read, T: for<'de> Deserialize<'de>>(
+    reader: &mut R,
+    limit: usize,
+) -> io::Result<T> {
+    decode_frame(&read_limited_frame(reader, limit)?)
 }
 
 /// Write a length-prefixed MessagePack frame to a writer.
 pub fn write_frame<W: Write, T: Serialize>(writer: &mut W, value: &T) -> io::Result<()> {
     let buf = rmp_serde::to_vec(value)
         .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-    let len = buf.len() as u32;
+    write_raw_frame(writer, &buf)
+}
+
+/// Read one frame's payload without decoding it, for relaying.
+pub(crate) fn read_raw_frame<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    read_limited_frame(reader, usize::MAX)
+}
+
+fn read_limited_frame<R: Read>(reader: &mut R, limit: usize) -> io::Result<Vec<u8>> {
+    let mut len_buf = [0u8; 4];
+    reader.read_exact(&mut len_buf)?;
+    let len = u32::from_be_bytes(len_buf) as usize;
+    if len > limit {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("frame of {} bytes exceeds the {} byte limit", len, limit),
+        ));
+    }
+
+    // Grow with the bytes that actually arrive rather than trusting the
+    // length up front
+    let mut buf = Vec::new();
+    reader.by_ref().take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(buf)
+}
+
+/// Write a payload read by [`read_raw_frame`] as a frame.
+pub(crate) fn write_raw_frame<W: Write>(writer: &mut W, payload: &[u8]) -> io::Result<()> {
+    let len = payload.len() as u32;
     writer.write_all(&len.to_be_bytes())?;
-    writer.write_all(&buf)?;
+    writer.write_all(payload)?;
     writer.flush()?;
     Ok(())
 }
+
+pub(crate) fn decode_frame<T: for<'de> Deserialize<'de>>(payload: &[u8]) -> io::Result<T> {
+    rmp_serde::from_slice(payload)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Server side of one client connection: the database plus the cursors the
+/// client has open on it.
+pub struct Session {
+    // Declared before `connection` so statements finalize before it closes.
+    cursors: HashMap<u64, Statement>,
+    next_cursor: u64,
+    /// Capabilities this session offers; a subset of [`CAPABILITIES`].
+    capabilities: Vec<&'static str>,
+    /// Row counts of `Page` queries, with the database state they were
+    /// taken in.
+    counts: HashMap<String, (u64, DataVersion)>,
+    /// Largest request frame [`serve_session`] reads.
+    max_request: usize,
+    /// Values of the [`SESSION_PRAGMAS`] when the session started.
+    pragmas: Vec<(&'static str, String)>,
+    /// Whether a client loaded an extension, which `Reset` can't undo.
+    extension_loaded: bool,
+    connection: Connection,
+}
+
+/// `total_changes()` and `PRAGMA data_version` together: if neither moved,
+/// no connection has changed the database.
+type DataVersion = (i64, i64);
+
+impl Session {
+    pub fn new(connection: Connection) -> Self {
+        let mut session = Session {
+            cursors: HashMap::new(),
+            next_cursor: 1,
+            capabilities: CAPABILITIES.to_vec(),
+            counts: HashMap::new(),
+            max_request: usize::MAX,
+            pragmas: Vec::new(),
+            extension_loaded: false,
+            connection,
+        };
+        session.pragmas = session.pragma_values();
+        session
+    }
+
+    /// Stop offering (and refuse requests for) `capability`.
+    pub fn without(mut self, capability: &str) -> Self {
+        self.capabilities.retain(|c| *c != capability);
+        self
+    }
+
+    /// Hang up on clients that send a request frame over `bytes`.
+    pub fn max_request(mut self, bytes: usize) -> Self {
+        self.max_request = bytes;
+        self
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Handle one request. `Response::Closed` means the client hung up.
+    pub fn handle(&mut self, request: Request) -> Response {
+        match request {
+            Request::Hello {
+                version,
+                capabilities,
+            } => self.hello(version, &capabilities),
+            // Only meaningful as the handshake on a socket (see `listen`)
+            Request::Auth { .. } => Response::Authenticated,
+            Request::Attach { .. } => Response::Error(SQLiteError::custom(
+                "PROTOCOL_ERROR",
+                "Attach is only understood by `solite remote daemon`",
+            )),
+            Request::Query { sql, params } => self.query(&sql, &params),
+            Request::Open { sql, params, fetch } => self.open(&sql, &params, fetch),
+            Request::Fetch { cursor, n } => self.fetch(cursor, n),
+            Request::CloseCursor { cursor } => {
+                self.cursors.remove(&cursor);
+                Response::CursorClosed
+            }
+            Request::Execute { sql, params } => self.execute(&sql, &params),
+            Request::ExecuteScript { sql } => match self.connection.execute_script(&sql) {
+                Ok(()) => Response::ScriptOk,
+                Err(e) => Response::Error(e),
+            },
+            Request::DbName => Response::DbName {
+                name: self.connection.db_name(),
+            },
+            Request::InTransaction => Response::InTransaction {
+                value: self.connection.in_transaction(),
+            },
+            Request::Interrupt => {
+                self.connection.interrupt();
+                Response::Interrupted
+            }
+            Request::Serialize => match self.connection.serialize() {
+                Ok(data) => Response::Serialized { data },
+                Err(e) => Response::Error(e),
+            },
+            Request::LoadExtension { path, entrypoint } => {
+                if !self.capabilities.contains(&CAP_LOAD_EXTENSION) {
+                    return Response::Error(SQLiteError::custom(
+                        "PROTOCOL_ERROR",
+                        "This server doesn't allow loading extensions",
+                    ));
+                }
+                match self.connection.load_extension(&path, &entrypoint) {
+                    Ok(()) => {
+                        self.extension_loaded = true;
+                        Response::ExtensionLoaded
+                    }
+                    Err(e) => Response::Error(SQLiteError::custom("EXTENSION_ERROR", e.to_string())),
+                }
+            }
+            Request::Export { .. } | Request::Backup { .. } => Response::Error(SQLiteError::custom(
+                "PROTOCOL_ERROR",
+                "Export and Backup stream their output; handle them with Session::respond",
+            )),
+            Request::Page {
+                sql,
+                offset,
+                limit,
+                count,
+            } => self.page(&sql, offset, limit, count),
+            Request::Reset if self.extension_loaded => {
+                self.cursors.clear();
+                Response::Closed
+            }
+            Request::Reset => match self.reset() {
+                Ok(()) => Response::Reset,
+                Err(e) => Response::Error(e),
+            },
+            Request::Close => {
+                self.cursors.clear();
+                Response::Closed
+            }
+        }
+    }
+
+    /// Handle one request and write its response frames to `writer`: a run
+    /// of `Chunk`s first for `Export` and `Backup`, then the response.
+    /// Returns `true` once the client has closed the session.
+    pub fn respond<W: Write>(&mut self, request: Request, writer: &mut W) -> io::Result<bool> {
+        let response = match request {
+            Request::Export {
+                sql,
+                params,
+                format,
+                blob_limit,
+            } => self.stream(writer, |session, out| {
+                session.export(&sql, &params, format, blob_limit, out)
+            })?,
+            Request::Backup { schema } => self.stream(writer, |session, out| {
+                if !session.capabilities.contains(&CAP_BACKUP) {
+                    return Err(SQLiteError::custom("PROTOCOL_ERROR", "Backup is not supported"));
+                }
+                session.connection.backup_to(&schema, out).map(|_| ())
+            })?,
+            request => self.handle(request),
+        };
+        write_frame(writer, &response)?;
+        Ok(matches!(response, Response::Closed))
+    }
+
+    /// Run `f` with a writer that sends its output as `Chunk` frames, and
+    /// turn the outcome into the closing response.
+    fn stream<W: Write>(
+        &mut self,
+        writer: &mut W,
+        f: impl FnOnce(&mut Self, &mut ChunkWriter<'_, W>) -> Result<(), SQLiteError>,
+    ) -> io::Result<Response> {
+        let mut chunks = ChunkWriter {
+            writer,
+            buf: Vec::new(),
+            bytes: 0,
+        };
+        let result = f(self, &mut chunks);
+        chunks.flush()?;
+        Ok(match result {
+            Ok(()) => Response::Streamed {
+                bytes: chunks.bytes,
+            },
+            Err(e) => Response::Error(e),
+        })
+    }
+
+    /// Answer a `Hello`: refuse other protocol versions, and keep the
+    /// capabilities both sides have.
+    fn hello(&self, version: u32, capabilities: &[String]) -> Response {
+        if version != PROTOCOL_VERSION {
+            return Response::Error(SQLiteError::custom(
+                "PROTOCOL_ERROR",
+                version_mismatch(version, PROTOCOL_VERSION),
+            ));
+        }
+        Response::Hello {
+            version: PROTOCOL_VERSION,
+            capabilities: self
+                .capabilities
+                .iter()
+                .filter(|capability| capabilities.iter().any(|c| c == *capability))
+                .map(|capability| capability.to_string())
+                .collect(),
+            server_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    fn export(
+        &self,
+        sql: &str,
+        params: &[(String, OwnedValue)],
+        format: ExportFormat,
+        blob_limit: BlobLimit,
+        out: &mut dyn Write,
+    ) -> Result<(), SQLiteError> {
+        if !self.capabilities.contains(&CAP_EXPORT) {
+            return Err(SQLiteError::custom("PROTOCOL_ERROR", "Export is not supported"));
+        }
+        let mut stmt = match self.prepare(sql, params)? {
+            (_, Some(stmt)) => stmt,
+            (_, None) => {
+                return Err(SQLiteError::custom("EXPORT_ERROR", "No SQL statement provided"))
+            }
+        };
+        write_output_to_writer(&mut stmt, out, format, blob_limit)
+            .map_err(|e| SQLiteError::custom("EXPORT_ERROR", e.to_string()))
+    }
+
+    /// Prepare the first statement in `sql` and bind `params` to it.
+    fn prepare(
+        &self,
+        sql: &str,
+        params: &[(String, OwnedValue)],
+    ) -> Result<(Option<usize>, Option<Statement>), SQLiteError> {
+        let (remaining, stmt) = self.connection.prepare(sql)?;
+        if let Some(stmt) = &stmt {
+            bind_params(stmt, params)?;
+        }
+        Ok((remaining, stmt))
+    }
+
+    fn query(&self, sql: &str, params: &[(String, OwnedValue)]) -> Response {
+        let mut stmt = match self.prepare(sql, params) {
+            Ok((_, Some(stmt))) => stmt,
+            Ok((_, None)) => {
+                return Response::Query(QueryResult {
+                    sql: sql.to_string(),
+                    columns: vec![],
+                    rows: vec![],
+                    readonly: true,
+                    is_explain: None,
+                });
+            }
+            Err(e) => return Response::Error(e),
+        };
+        let info = cursor_info(0, &stmt);
+        match read_rows(&mut stmt, usize::MAX) {
+            Ok((rows, _)) => Response::Query(QueryResult {
+                sql: info.sql,
+                columns: info.columns,
+                rows,
+                readonly: info.readonly,
+                is_explain: info.is_explain,
+            }),
+            Err(e) => Response::Error(e),
+        }
+    }
+
+    fn page(&mut self, sql: &str, offset: usize, limit: usize, count: bool) -> Response {
+        let sql = match self.subquery(sql) {
+            Ok(sql) => sql,
+            Err(e) => return Response::Error(e),
+        };
+        // Newlines keep a trailing `--` comment from swallowing the `)`
+        let paged = format!("SELECT * FROM (\n{}\n) LIMIT {} OFFSET {}", sql, limit, offset);
+        let mut stmt = match self.connection.prepare(&paged) {
+            Ok((_, Some(stmt))) => stmt,
+            Ok((_, None)) => {
+                return Response::Error(SQLiteError::custom("PROTOCOL_ERROR", "Nothing to page"))
+            }
+            Err(e) => return Response::Error(e),
+        };
+        if !stmt.readonly() {
+            return Response::Error(SQLiteError::custom(
+                "PROTOCOL_ERROR",
+                "Page only runs read-only queries",
+            ));
+        }
+        let columns = stmt.column_meta();
+        let rows = match read_rows(&mut stmt, usize::MAX) {
+            Ok((rows, _)) => rows,
+            Err(e) => return Response::Error(e),
+        };
+        drop(stmt);
+        let total = if count {
+            match self.count(sql) {
+                Ok(total) => Some(total),
+                Err(e) => return Response::Error(e),
+            }
+        } else {
+            None
+        };
+        Response::Page {
+            columns,
+            rows,
+            total,
+        }
+    }
+
+    /// The one statement in `sql`, without its `;`, to wrap in a subquery.
+    fn subquery<'a>(&self, sql: &'a str) -> Result<&'a str, SQLiteError> {
+        let (rest, stmt) = self.connection.prepare(sql)?;
+        if stmt.is_none() {
+            return Err(SQLiteError::custom("PROTOCOL_ERROR", "Nothing to page"));
+        }
+        let end = rest.unwrap_or(sql.len());
+        if let (_, Some(_)) = self.connection.prepare(&sql[end..])? {
+            return Err(SQLiteError::custom(
+                "PROTOCOL_ERROR",
+                "Page runs a single statement",
+            ));
+        }
+        Ok(sql[..end].trim_end().trim_end_matches(';'))
+    }
+
+    /// Undo what a client left on the connection (see [`Request::Reset`]).
+    fn reset(&mut self) -> Result<(), SQLiteError> {
+        self.cursors.clear();
+        self.counts.clear();
+        if self.connection.in_transaction() {
+            self.connection.execute_script("ROLLBACK")?;
+        }
+        // First, in case a client turned on `query_only`
+        let now = self.pragma_values();
+        let mut pragmas = String::new();
+        for (name, value) in &self.pragmas {
+            if !now.contains(&(*name, value.clone())) {
+                pragmas += &format!("PRAGMA {} = {};\n", name, value);
+            }
+        }
+        self.connection.execute_script(&pragmas)?;
+        let mut script = String::new();
+        for row in self.rows(
+            "SELECT name FROM pragma_database_list WHERE name NOT IN ('main', 'temp')",
+        )? {
+            script += &format!("DETACH DATABASE {};\n", quote_identifier(&row[0]));
+        }
+        // Dropping a table drops its indexes and triggers, so a later DROP
+        // of one of those needs IF EXISTS
+        for row in self.rows(
+            "SELECT type, name FROM temp.sqlite_master
+             WHERE type IN ('table', 'view', 'trigger') AND name NOT LIKE 'sqlite_%'",
+        )? {
+            script += &format!(
+                "DROP {} IF EXISTS temp.{};\n",
+                row[0].to_uppercase(),
+                quote_identifier(&row[1])
+            );
+        }
+        self.connection.execute_script(&script)
+    }
+
+    /// The current value of each of the [`SESSION_PRAGMAS`] this build of
+    /// SQLite has.
+    fn pragma_values(&self) -> Vec<(&'static str, String)> {
+        SESSION_PRAGMAS
+            .iter()
+            .filter_map(|name| {
+                let rows = self.rows(&format!("PRAGMA {}", name)).ok()?;
+                Some((*name, rows.first()?.first()?.clone()))
+            })
+            .collect()
+    }
+
+    /// Every row of `sql`, as text.
+    fn rows(&self, sql: &str) -> Result<Vec<Vec<String>>, SQLiteError> {
+        let (_, Some(mut stmt)) = self.connection.prepare(sql)? else {
+            return Ok(vec![]);
+        };
+        let mut rows = vec![];
+        while let Some(row) = stmt.next()? {
+            rows.push(row.iter().map(|value| value.as_str().to_string()).collect());
+        }
+        Ok(rows)
+    }
+
+    /// `SELECT count(*)` of `sql` (a [`Session::subquery`]), from the cache
+    /// while the database is unchanged.
+    fn count(&mut self, sql: &str) -> Result<u64, SQLiteError> {
+        let version = self.data_version()?;
+        if let Some((total, counted_at)) = self.counts.get(sql) {
+            if *counted_at == version {
+                return Ok(*total);
+            }
+        }
+        let total = self.scalar(&format!("SELECT count(*) FROM (\n{}\n)", sql))?.max(0) as u64;
+        self.counts.insert(sql.to_string(), (total, version));
+        Ok(total)
+    }
+
+    fn data_version(&self) -> Result<DataVersion, SQLiteError> {
+        Ok((
+            self.scalar("SELECT total_changes()")?,
+            self.scalar("PRAGMA data_version")?,
+        ))
+    }
+
+    fn scalar(&self, sql: &str) -> Result<i64, SQLiteError> {
+        let (_, Some(mut stmt)) = self.connection.prepare(sql)? else {
+            return Ok(0);
+        };
+        let value = stmt.next()?.map_or(0, |row| row[0].as_int64());
+        Ok(value)
+    }
+
+    fn open(&mut self, sql: &str, params: &[(String, OwnedValue)], fetch: usize) -> Response {
+        let mut stmt = match self.prepare(sql, params) {
+            Ok((_, Some(stmt))) => stmt,
+            Ok((_, None)) => {
+                return Response::Opened {
+                    cursor: CursorInfo {
+                        id: 0,
+                        sql: sql.to_string(),
+                        columns: vec![],
+                        readonly: true,
+                        is_explain: None,
+                    },
+                    rows: vec![],
+                    done: true,
+                };
+            }
+            Err(e) => return Response::Error(e),
+        };
+        let id = self.next_cursor;
+        self.next_cursor += 1;
+        let cursor = cursor_info(id, &stmt);
+        // Always step at least once, so BEGIN/COMMIT and friends run on open
+        let fetch = if cursor.readonly { fetch.max(1) } else { usize::MAX };
+        match read_rows(&mut stmt, fetch) {
+            Ok((rows, done)) => {
+                if !done {
+                    self.cursors.insert(id, stmt);
+                }
+                Response::Opened { cursor, rows, done }
+            }
+            Err(e) => Response::Error(e),
+        }
+    }
+
+    fn fetch(&mut self, cursor: u64, n: usize) -> Response {
+        let Some(stmt) = self.cursors.get_mut(&cursor) else {
+            return Response::Error(SQLiteError::custom(
+                "PROTOCOL_ERROR",
+                format!("No open cursor {}", cursor),
+            ));
+        };
+        let result = read_rows(stmt, n.max(1));
+        if !matches!(result, Ok((_, false))) {
+            self.cursors.remove(&cursor);
+        }
+        match result {
+            Ok((rows, done)) => Response::Rows { rows, done },
+            Err(e) => Response::Error(e),
+        }
+    }
+
+    fn execute(&self, sql: &str, params: &[(String, OwnedValue)]) -> Response {
+        match self.prepare(sql, params) {
+            Ok((remaining, Some(stmt))) => match stmt.execute() {
+                Ok(count) => Response::Executed {
+                    count,
+                    remaining_offset: remaining,
+                },
+                Err(e) => Response::Error(e),
+            },
+            Ok((remaining, None)) => Response::Executed {
+                count: 0,
+                remaining_offset: remaining,
+            },
+            Err(e) => Response::Error(e),
+        }
+    }
+}
+
+/// Buffers streamed output into `Response::Chunk` frames.
+struct ChunkWriter<'a, W: Write> {
+    writer: &'a mut W,
+    buf: Vec<u8>,
+    /// Everything written so far.
+    bytes: u64,
+}
+
+impl<W: Write> Write for ChunkWriter<'_, W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        self.bytes += data.len() as u64;
+        if self.buf.len() >= CHUNK_BYTES {
+            self.flush()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let data = std::mem::take(&mut self.buf);
+        write_frame(self.writer, &Response::Chunk { data })
+    }
+}
+
+/// Serve requests from `reader` until the client closes the connection or
+/// hangs up.
+///
+/// Requests are read on their own thread and handed to this one, so an
+/// [`Request::Interrupt`] can cancel the request currently running.
+pub fn serve<R: Read + Send + 'static, W: Write>(
+    connection: Connection,
+    reader: R,
+    writer: W,
+) -> io::Result<()> {
+    serve_session(Session::new(connection), reader, writer)
+}
+
+/// [`serve`] with a configured [`Session`].
+pub fn serve_session<R: Read + Send + 'static, W: Write>(
+    mut session: Session,
+    mut reader: R,
+    mut writer: W,
+) -> io::Result<()> {
+    let interrupt = session.connection().interrupt_handle();
+    let max_request = session.max_request;
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || loop {
+        match read_frame_limited::<_, Request>(&mut reader, max_request) {
+            Ok(Request::Interrupt) => interrupt.interrupt(),
+            Ok(request) => {
+                let close = matches!(request, Request::Close);
+                if tx.send(Ok(request)).is_err() || close {
+                    return;
+                }
+            }
+            // A whole frame arrived but didn't decode, so the stream is
+            // still in sync: most likely a request from a newer client
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                if tx.send(Err(e)).is_err() {
+                    return;
+                }
+            }
+            Err(e) => {
+                let _ = tx.send(Err(e));
+                return;
+            }
+        }
+    });
+
+    for request in rx {
+        let request = match request {
+            Ok(request) => request,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            // The rest of the frame is still unread, so the session can't
+            // go on
+            Err(e) if e.kind() == io::ErrorKind::InvalidInput => {
+                let error = SQLiteError::custom("REQUEST_TOO_LARGE", e.to_string());
+                let _ = write_frame(&mut writer, &Response::Error(error));
+                return Err(e);
+            }
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                let error = SQLiteError::custom(
+                    "PROTOCOL_ERROR",
+                    format!(
+                        "Unrecognized request ({}); the client may be a newer solite than \
+                         this server",
+                        e
+                    ),
+                );
+                write_frame(&mut writer, &Response::Error(error))?;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        if session.respond(request, &mut writer)? {
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+/// A socket address for `solite serve --listen`, and the target of a
+/// `tcp://`/`unix:` client URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SocketAddress {
+    /// `host:port`
+    Tcp(String),
+    /// Path of a Unix domain socket.
+    Unix(PathBuf),
+}
+
+impl SocketAddress {
+    /// Parse a `tcp://host:port` or `unix:/path/to.sock` (also
+    /// `unix:///path/to.sock`) URL. `None` for anything else.
+    pub fn from_url(url: &str) -> Option<Self> {
+        if let Some(addr) = url.strip_prefix("tcp://") {
+            return Some(SocketAddress::Tcp(addr.trim_end_matches('/').to_string()));
+        }
+        let path = url.strip_prefix("unix:")?;
+        let path = path.strip_prefix("//").unwrap_or(path);
+        Some(SocketAddress::Unix(PathBuf::from(path)))
+    }
+
+    /// Parse a `--listen` address: a URL as for [`SocketAddress::from_url`],
+    /// or a bare `host:port`.
+    pub fn from_listen(s: &str) -> Result<Self, String> {
+        if let Some(address) = Self::from_url(s) {
+            return Ok(address);
+        }
+        match s.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+                Ok(SocketAddress::Tcp(s.to_string()))
+            }
+            _ => Err(format!(
+                "invalid listen address '{}': expected host:port, tcp://host:port or \
+                 unix:/path/to.sock",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for SocketAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SocketAddress::Tcp(addr) => write!(f, "tcp://{}", addr),
+            SocketAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Both halves of a connected socket, as boxed streams.
+pub type SocketStreams = (Box<dyn Read + Send>, Box<dyn Write + Send>);
+
+/// Connect to a listening `solite serve`.
+pub fn connect(address: &SocketAddress) -> io::Result<SocketStreams> {
+    match address {
+        SocketAddress::Tcp(addr) => {
+            let stream = std::net::TcpStream::connect(addr)?;
+            stream.set_nodelay(true)?;
+            Ok((Box::new(stream.try_clone()?), Box::new(stream)))
+        }
+        #[cfg(unix)]
+        SocketAddress::Unix(path) => {
+            let stream = std::os::unix::net::UnixStream::connect(path)?;
+            Ok((Box::new(stream.try_clone()?), Box::new(stream)))
+        }
+        #[cfg(not(unix))]
+        SocketAddress::Unix(_) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Unix sockets are not supported on this platform",
+        )),
+    }
+}
+
+pub(crate) enum Listener {
+    Tcp(std::net::TcpListener),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixListener),
+}
+
+impl Listener {
+    pub(crate) fn bind(address: &SocketAddress) -> io::Result<Self> {
+        match address {
+            SocketAddress::Tcp(addr) => Ok(Listener::Tcp(std::net::TcpListener::bind(addr)?)),
+            #[cfg(unix)]
+            SocketAddress::Unix(path) => {
+                use std::os::unix::net::{UnixListener, UnixStream};
+                match UnixListener::bind(path) {
+                    // A socket file nobody answers on is left over from a
+                    // server that didn't shut down cleanly
+                    Err(e)
+                        if e.kind() == io::ErrorKind::AddrInUse
+                            && UnixStream::connect(path).is_err() =>
+                    {
+                        std::fs::remove_file(path)?;
+                        Ok(Listener::Unix(UnixListener::bind(path)?))
+                    }
+                    result => Ok(Listener::Unix(result?)),
+                }
+            }
+            #[cfg(not(unix))]
+            SocketAddress::Unix(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Unix sockets are not supported on this platform",
+            )),
+        }
+    }
+
+    pub(crate) fn accept(&self) -> io::Result<SocketStreams> {
+        self.accept_socket()?.streams()
+    }
+
+    fn accept_socket(&self) -> io::Result<Socket> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nodelay(true)?;
+                Ok(Socket::Tcp(stream))
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                let (stream, _) = listener.accept()?;
+                Ok(Socket::Unix(stream))
+            }
+        }
+    }
+}
+
+/// An accepted client, kept after splitting into streams to change its
+/// read timeout.
+enum Socket {
+    Tcp(std::net::TcpStream),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixStream),
+}
+
+impl Socket {
+    fn streams(&self) -> io::Result<SocketStreams> {
+        match self {
+            Socket::Tcp(stream) => {
+                Ok((Box::new(stream.try_clone()?), Box::new(stream.try_clone()?)))
+            }
+            #[cfg(unix)]
+            Socket::Unix(stream) => {
+                Ok((Box::new(stream.try_clone()?), Box::new(stream.try_clone()?)))
+            }
+        }
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Socket::Tcp(stream) => stream.set_read_timeout(timeout),
+            #[cfg(unix)]
+            Socket::Unix(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+}
+
+/// Compare tokens without leaking, through timing, how much of a guess
+/// was right.
+pub fn token_matches(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// Check the `Auth` handshake that opens every socket session.
+fn authenticate<R: Read, W: Write>(reader: &mut R, writer: &mut W, token: &str) -> io::Result<bool> {
+    let response = match read_frame_limited::<_, Request>(reader, MAX_AUTH_BYTES)? {
+        Request::Auth { token: given } if token_matches(token, &given) => {
+            write_frame(writer, &Response::Authenticated)?;
+            return Ok(true);
+        }
+        Request::Auth { .. } => "Invalid token",
+        _ => "Expected an Auth request first",
+    };
+    write_frame(
+        writer,
+        &Response::Error(SQLiteError::custom("AUTH_ERROR", response)),
+    )?;
+    Ok(false)
+}
+
+/// Accept clients on `address` until the listener fails. Each client
+/// authenticates with `token`, then gets its own session on a connection
+/// from `open`, served on its own thread.
+///
+/// Until it authenticates, a client gets a few seconds and a few kilobytes
+/// to send its `Auth` frame; after that, requests over `max_request` bytes
+/// end the session. Socket sessions don't offer `LoadExtension`, but an
+/// authenticated client can do anything else the connection from `open`
+/// allows, so the token is as sensitive as a login on the server.
+pub fn listen<F>(
+    address: &SocketAddress,
+    token: String,
+    max_request: usize,
+    open: F,
+) -> io::Result<()>
+where
+    F: Fn() -> Result<Connection, SQLiteError> + Send + Sync + 'static,
+{
+    let listener = Listener::bind(address)?;
+    let token = Arc::new(token);
+    let open = Arc::new(open);
+    loop {
+        let socket = match listener.accept_socket() {
+            Ok(socket) => socket,
+            // One client failing to connect shouldn't stop the server
+            Err(e) if e.kind() == io::ErrorKind::ConnectionAborted => continue,
+            Err(e) => return Err(e),
+        };
+        let token = Arc::clone(&token);
+        let open = Arc::clone(&open);
+        std::thread::spawn(move || {
+            let Ok((reader, writer)) = socket.streams() else {
+                return;
+            };
+            let mut reader = BufReader::new(reader);
+            let mut writer = BufWriter::new(writer);
+            let authenticated = socket
+                .set_read_timeout(Some(HANDSHAKE_TIMEOUT))
+                .and_then(|()| authenticate(&mut reader, &mut writer, &token))
+                .and_then(|ok| socket.set_read_timeout(None).map(|()| ok));
+            if !matches!(authenticated, Ok(true)) {
+                return;
+            }
+            let connection = match open() {
+                Ok(connection) => connection,
+                Err(e) => {
+                    let _ = write_frame(&mut writer, &Response::Error(e));
+                    return;
+                }
+            };
+            let session = Session::new(connection)
+                .without(CAP_LOAD_EXTENSION)
+                .max_request(max_request);
+            if let Err(e) = serve_session(session, reader, writer) {
+                eprintln!("solite serve: session ended: {}", e);
+            }
+        });
+    }
+}
+
+/// Explain a [`PROTOCOL_VERSION`] disagreement between client and server.
+pub fn version_mismatch(client: u32, server: u32) -> String {
+    format!(
+        "Remote protocol mismatch: this client speaks version {} but the server speaks \
+         version {}. Install the same solite release on both machines.",
+        client, server
+    )
+}
+
+fn cursor_info(id: u64, stmt: &Statement) -> CursorInfo {
+    CursorInfo {
+        id,
+        sql: stmt.sql(),
+        columns: stmt.column_meta(),
+        readonly: stmt.readonly(),
+        is_explain: stmt.is_explain().map(|e| match e {
+            IsExplain::Explain => 1,
+            IsExplain::ExplainQueryPlan => 2,
+        }),
+    }
+}
+
+/// Step up to `n` rows; `true` once the statement is exhausted.
+pub fn read_rows(stmt: &mut Statement, n: usize) -> Result<(Vec<Vec<WireValue>>, bool), SQLiteError> {
+    let mut rows = Vec::new();
+    while rows.len() < n {
+        match stmt.next()? {
+            Some(row) => rows.push(
+                row.iter()
+                    .map(|v| WireValue {
+                        value: OwnedValue::from_value_ref(v),
+                        subtype: v.subtype(),
+                    })
+                    .collect(),
+            ),
+            None => return Ok((rows, true)),
+        }
+    }
+    Ok((rows, false))
+}
+
+/// Bind named parameters, matching names without their `:`/`$`/`@`/`?`
+/// prefix. Parameters the statement doesn't use are ignored.
+pub fn bind_params(stmt: &Statement, params: &[(String, OwnedValue)]) -> Result<(), SQLiteError> {
+    let names = stmt.bind_parameters();
+    for (name, value) in params {
+        let Some(idx) = names.iter().position(|p| {
+            p.trim_start_matches([':', '$', '@', '?'])
+                == name.trim_start_matches([':', '$', '@', '?'])
+        }) else {
+            continue;
+        };
+        let idx = (idx + 1) as i32;
+        match value {
+            OwnedValue::Null => stmt.bind_null(idx)?,
+            OwnedValue::Integer(v) => stmt.bind_int64(idx, *v)?,
+            OwnedValue::Double(v) => stmt.bind_double(idx, *v)?,
+            OwnedValue::Text(v) => stmt.bind_text(idx, String::from_utf8_lossy(v))?,
+            OwnedValue::Blob(v) => stmt.bind_blob(idx, v)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Session {
+        let session = Session::new(Connection::open_in_memory().unwrap());
+        session
+            .connection()
+            .execute_script(
+                "create table t(x);
+                 with recursive c(x) as (select 1 union all select x + 1 from c limit 10)
+                 insert into t select x from c;",
+            )
+            .unwrap();
+        session
+    }
+
+    fn ints(rows: &[Vec<WireValue>]) -> Vec<i64> {
+        rows.iter()
+            .map(|row| match row[0].value {
+                OwnedValue::Integer(v) => v,
+                ref other => panic!("expected integer, got {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cursor_pages() {
+        let mut session = session();
+        let (id, rows, done) = match session.handle(Request::Open {
+            sql: "select x from t where x > :min order by x".to_string(),
+            params: vec![(":min".to_string(), OwnedValue::Integer(3))],
+            fetch: 4,
+        }) {
+            Response::Opened { cursor, rows, done } => (cursor.id, rows, done),
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(ints(&rows), vec![4, 5, 6, 7]);
+        assert!(!done);
+
+        match session.handle(Request::Fetch { cursor: id, n: 4 }) {
+            Response::Rows { rows, done } => {
+                assert_eq!(ints(&rows), vec![8, 9, 10]);
+                assert!(done);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        // exhausted cursors are closed by the server
+        assert!(matches!(
+            session.handle(Request::Fetch { cursor: id, n: 4 }),
+            Response::Error(_)
+        ));
+    }
+
+    #[test]
+    fn test_cursor_close_early() {
+        let mut session = session();
+        let id = match session.handle(Request::Open {
+            sql: "select x from t".to_string(),
+            params: vec![],
+            fetch: 1,
+        }) {
+            Response::Opened { cursor, done: false, .. } => cursor.id,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert!(matches!(
+            session.handle(Request::CloseCursor { cursor: id }),
+            Response::CursorClosed
+        ));
+        assert!(session.cursors.is_empty());
+    }
+
+    #[test]
+    fn test_open_runs_writes_to_completion() {
+        let mut session = session();
+        match session.handle(Request::Open {
+            sql: "insert into t values (11), (12) returning x".to_string(),
+            params: vec![],
+            fetch: 1,
+        }) {
+            Response::Opened { rows, done, .. } => {
+                assert_eq!(ints(&rows), vec![11, 12]);
+                assert!(done);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(session.cursors.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_serve_interrupts_running_query() {
+        use std::os::unix::net::UnixStream;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let (mut client, server) = UnixStream::pair().unwrap();
+        let server_reader = server.try_clone().unwrap();
+        let server_thread = std::thread::spawn(move || {
+            serve(Connection::open_in_memory().unwrap(), server_reader, server)
+        });
+
+        let endless = "with recursive c(x) as (select 1 union all select x + 1 from c) \
+                       select count(*) from c";
+        write_frame(
+            &mut client,
+            &Request::Open {
+                sql: endless.to_string(),
+                params: vec![],
+                fetch: 1,
+            },
+        )
+        .unwrap();
+
+        // Keep interrupting until the query notices: the first interrupt
+        // may land before the statement starts running
+        let answered = Arc::new(AtomicBool::new(false));
+        let interrupter = {
+            let mut client = client.try_clone().unwrap();
+            let answered = Arc::clone(&answered);
+            std::thread::spawn(move || {
+                while !answered.load(Ordering::SeqCst) {
+                    write_frame(&mut client, &Request::Interrupt).unwrap();
+                    std::thread::sleep(std::time::Duration::from_millis(20));
+                }
+            })
+        };
+        let response: Response = read_frame(&mut client).unwrap();
+        answered.store(true, Ordering::SeqCst);
+        interrupter.join().unwrap();
+        match response {
+            Response::Error(e) => assert!(e.message.contains("interrupt"), "{}", e.message),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        write_frame(&mut client, &Request::Close).unwrap();
+        assert!(matches!(
+            read_frame::<_, Response>(&mut client).unwrap(),
+            Response::Closed
+        ));
+        server_thread.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_hello() {
+        let mut session = Session::new(Connection::open_in_memory().unwrap());
+        match session.handle(Request::Hello {
+            version: PROTOCOL_VERSION,
+            capabilities: vec![CAP_CURSORS.to_string(), "time_travel".to_string()],
+        }) {
+            Response::Hello {
+                version,
+                capabilities,
+                ..
+            } => {
+                assert_eq!(version, PROTOCOL_VERSION);
+                // only what both sides support
+                assert_eq!(capabilities, vec![CAP_CURSORS.to_string()]);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        match session.handle(Request::Hello {
+            version: PROTOCOL_VERSION + 1,
+            capabilities: vec![],
+        }) {
+            Response::Error(e) => {
+                assert_eq!(e.code_description, "PROTOCOL_ERROR");
+                assert!(e.message.contains("Install the same solite release"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_serve_survives_unknown_requests() {
+        // a frame this build can't decode, as a newer client might send
+        let mut input = Vec::new();
+        write_frame(&mut input, &"Teleport").unwrap();
+        write_frame(&mut input, &Request::InTransaction).unwrap();
+        write_frame(&mut input, &Request::Close).unwrap();
+
+        let mut output = Vec::new();
+        serve(
+            Connection::open_in_memory().unwrap(),
+            std::io::Cursor::new(input),
+            &mut output,
+        )
+        .unwrap();
+
+        let mut output = output.as_slice();
+        match read_frame::<_, Response>(&mut output).unwrap() {
+            Response::Error(e) => assert_eq!(e.code_description, "PROTOCOL_ERROR"),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(
+            read_frame::<_, Response>(&mut output).unwrap(),
+            Response::InTransaction { value: false }
+        ));
+        assert!(matches!(
+            read_frame::<_, Response>(&mut output).unwrap(),
+            Response::Closed
+        ));
+    }
+
+    #[test]
+    fn test_export_streams_chunks() {
+        let mut session = session();
+        let mut output = Vec::new();
+        let closed = session
+            .respond(
+                Request::Export {
+                    sql: "select x from t where x <= :max".to_string(),
+                    params: vec![(":max".to_string(), OwnedValue::Integer(3))],
+                    format: ExportFormat::Csv,
+                    blob_limit: BlobLimit::Default,
+                },
+                &mut output,
+            )
+            .unwrap();
+        assert!(!closed);
+
+        let mut output = output.as_slice();
+        let mut data = Vec::new();
+        loop {
+            match read_frame::<_, Response>(&mut output).unwrap() {
+                Response::Chunk { data: chunk } => data.extend(chunk),
+                Response::Streamed { bytes } => {
+                    assert_eq!(bytes, data.len() as u64);
+                    break;
+                }
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        assert_eq!(String::from_utf8(data).unwrap(), "x\n1\n2\n3\n");
+    }
+
+    #[test]
+    fn test_socket_sessions_refuse_extensions() {
+        let mut session = session().without(CAP_LOAD_EXTENSION);
+        match session.handle(Request::LoadExtension {
+            path: "./nope".to_string(),
+            entrypoint: None,
+        }) {
+            Response::Error(e) => assert_eq!(e.code_description, "PROTOCOL_ERROR"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reset_clears_session() {
+        let mut session = session();
+        session.handle(Request::ExecuteScript {
+            sql: "begin; delete from t;".to_string(),
+        });
+        session.handle(Request::Open {
+            sql: "select 1 union all select 2".to_string(),
+            params: vec![],
+            fetch: 1,
+        });
+        assert_eq!(session.cursors.len(), 1);
+
+        assert!(matches!(session.handle(Request::Reset), Response::Reset));
+        assert!(session.cursors.is_empty());
+        assert!(!session.connection().in_transaction());
+        match session.handle(Request::Query {
+            sql: "select count(*) from t".to_string(),
+            params: vec![],
+        }) {
+            Response::Query(result) => assert_eq!(ints(&result.rows), vec![10]),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reset_restores_pragmas() {
+        let mut session = session();
+        let foreign_keys = session.scalar("pragma foreign_keys").unwrap();
+        let cache_size = session.scalar("pragma cache_size").unwrap();
+        session
+            .connection()
+            .execute_script(
+                "pragma foreign_keys = on; pragma cache_size = -1234;
+                 pragma query_only = on; create temp table scratch(z);",
+            )
+            .unwrap();
+
+        assert!(matches!(session.handle(Request::Reset), Response::Reset));
+        assert_eq!(session.scalar("pragma foreign_keys").unwrap(), foreign_keys);
+        assert_eq!(session.scalar("pragma cache_size").unwrap(), cache_size);
+        assert_eq!(session.scalar("pragma query_only").unwrap(), 0);
+        assert_eq!(session.scalar("select count(*) from temp.sqlite_master").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_reset_ends_sessions_with_extensions() {
+        let mut session = session();
+        session.extension_loaded = true;
+        assert!(matches!(session.handle(Request::Reset), Response::Closed));
+    }
+
+    #[test]
+    fn test_reset_detaches_and_drops_temp_schema() {
+        let mut session = session();
+        session
+            .connection()
+            .execute_script(
+                "attach ':memory:' as \"other db\";
+                 create table \"other db\".u(y);
+                 create temp table scratch(z);
+                 create index temp.scratch_z on scratch(z);
+                 create temp view v as select * from t;
+                 create temp trigger keep after insert on main.t begin select 1; end;",
+            )
+            .unwrap();
+
+        assert!(matches!(session.handle(Request::Reset), Response::Reset));
+        assert_eq!(
+            session
+                .rows("select name from pragma_database_list where name != 'temp'")
+                .unwrap(),
+            vec![vec!["main".to_string()]]
+        );
+        assert!(session
+            .rows("select name from temp.sqlite_master")
+            .unwrap()
+            .is_empty());
+        // The main schema is untouched, and a reset of a clean session
+        // is a no-op
+        assert!(matches!(session.handle(Request::Reset), Response::Reset));
+        assert_eq!(session.scalar("select count(*) from t").unwrap(), 10);
+    }
+
+    #[test]
+    fn test_page_with_cached_count() {
+        let mut session = session();
+        let page = |session: &mut Session, offset, count| match session.handle(Request::Page {
+            sql: "select x from t order by x desc".to_string(),
+            offset,
+            limit: 3,
+            count,
+        }) {
+            Response::Page { rows, total, .. } => (ints(&rows), total),
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(page(&mut session, 0, true), (vec![10, 9, 8], Some(10)));
+        assert_eq!(page(&mut session, 8, false), (vec![2, 1], None));
+        assert_eq!(session.counts.len(), 1);
+
+        // a write invalidates the cached count
+        session.connection().execute_script("insert into t values (11)").unwrap();
+        assert_eq!(page(&mut session, 0, true), (vec![11, 10, 9], Some(11)));
+
+        assert!(matches!(
+            session.handle(Request::Page {
+                sql: "delete from t returning x".to_string(),
+                offset: 0,
+                limit: 1,
+                count: false,
+            }),
+            Response::Error(_)
+        ));
+    }
+
+    #[test]
+    fn test_page_statement_tail() {
+        let mut session = session();
+        let mut page = |sql: &str| match session.handle(Request::Page {
+            sql: sql.to_string(),
+            offset: 0,
+            limit: 2,
+            count: true,
+        }) {
+            Response::Page { rows, total, .. } => Ok((ints(&rows), total)),
+            Response::Error(e) => Err(e.message),
+            other => panic!("unexpected {:?}", other),
+        };
+        for sql in [
+            "select x from t;",
+            "select x from t ;\n",
+            "select x from t -- the table",
+            "select x from t; -- the table",
+            "select x from t /* the table */ ;",
+        ] {
+            assert_eq!(page(sql), Ok((vec![1, 2], Some(10))), "{}", sql);
+        }
+        assert!(page("select x from t; select 1").is_err());
+        assert!(page("-- nothing").is_err());
+    }
+
+    #[test]
+    fn test_socket_address_parsing() {
+        assert_eq!(
+            SocketAddress::from_url("tcp://127.0.0.1:7878"),
+            Some(SocketAddress::Tcp("127.0.0.1:7878".to_string()))
+        );
+        assert_eq!(
+            SocketAddress::from_url("unix:/run/app.sock"),
+            Some(SocketAddress::Unix(PathBuf::from("/run/app.sock")))
+        );
+        assert_eq!(
+            SocketAddress::from_url("unix:///run/app.sock"),
+            Some(SocketAddress::Unix(PathBuf::from("/run/app.sock")))
+        );
+        assert_eq!(SocketAddress::from_url("ssh://host/app.db"), None);
+
+        assert_eq!(
+            SocketAddress::from_listen("127.0.0.1:7878"),
+            Ok(SocketAddress::Tcp("127.0.0.1:7878".to_string()))
+        );
+        assert!(SocketAddress::from_listen("app.db").is_err());
+        assert!(SocketAddress::from_listen(":7878").is_err());
+    }
+
+    #[test]
+    fn test_token_matches() {
+        assert!(token_matches("s3cret", "s3cret"));
+        assert!(!token_matches("s3cret", "s3cres"));
+        assert!(!token_matches("s3cret", "s3cret!"));
+        assert!(!token_matches("s3cret", ""));
+    }
+
+    #[test]
+    fn test_listen_requires_token() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let address = SocketAddress::Tcp(format!("127.0.0.1:{}", port));
+        {
+            let address = address.clone();
+            std::thread::spawn(move || {
+                listen(&address, "s3cret".to_string(), 1024, Connection::open_in_memory)
+            });
+        }
+        let streams = || loop {
+            match connect(&address) {
+                Ok(streams) => return streams,
+                Err(_) => std::thread::sleep(std::time::Duration::from_millis(10)),
+            }
+        };
+
+        // a wrong token is refused
+        let (mut reader, mut writer) = streams();
+        let auth = |token: &str| Request::Auth {
+            token: token.to_string(),
+        };
+        write_frame(&mut writer, &auth("guess")).unwrap();
+        match read_frame::<_, Response>(&mut reader).unwrap() {
+            Response::Error(e) => assert_eq!(e.code_description, "AUTH_ERROR"),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // so is an Auth frame too large to hold any token
+        let (mut reader, mut writer) = streams();
+        writer.write_all(&u32::MAX.to_be_bytes()).unwrap();
+        writer.flush().unwrap();
+        assert!(read_frame::<_, Response>(&mut reader).is_err());
+
+        // two sessions with the right token get separate connections
+        let (mut reader_a, mut writer_a) = streams();
+        let (mut reader_b, mut writer_b) = streams();
+        for (reader, writer) in [(&mut reader_a, &mut writer_a), (&mut reader_b, &mut writer_b)] {
+            write_frame(writer, &auth("s3cret")).unwrap();
+            assert!(matches!(
+                read_frame::<_, Response>(reader).unwrap(),
+                Response::Authenticated
+            ));
+        }
+        let script = Request::ExecuteScript {
+            sql: "create table t(x)".to_string(),
+        };
+        write_frame(&mut writer_a, &script).unwrap();
+        assert!(matches!(
+            read_frame::<_, Response>(&mut reader_a).unwrap(),
+            Response::ScriptOk
+        ));
+        // each in-memory database is its own, so the table is new again
+        write_frame(&mut writer_b, &script).unwrap();
+        assert!(matches!(
+            read_frame::<_, Response>(&mut reader_b).unwrap(),
+            Response::ScriptOk
+        ));
+
+        // requests over the limit end the session
+        let script = Request::ExecuteScript {
+            sql: format!("select '{}'", "x".repeat(2048)),
+        };
+        write_frame(&mut writer_a, &script).unwrap();
+        // The server hangs up with the frame partly unread, which TCP may
+        // turn into a reset before the error response is read
+        match read_frame::<_, Response>(&mut reader_a) {
+            Ok(Response::Error(e)) => {
+                assert_eq!(e.code_description, "REQUEST_TOO_LARGE");
+                assert!(read_frame::<_, Response>(&mut reader_a).is_err());
+            }
+            Ok(other) => panic!("unexpected {:?}", other),
+            Err(_) => {}
+        }
+    }
+
+    #[test]
+    fn test_read_frame_limited() {
+        let mut frame = Vec::new();
+        write_frame(&mut frame, &Request::Close).unwrap();
+        assert!(matches!(
+            read_frame_limited::<_, Request>(&mut frame.as_slice(), 64).unwrap(),
+            Request::Close
+        ));
+        let err = read_frame_limited::<_, Request>(&mut frame.as_slice(), 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        // a length the stream never delivers fails without allocating it
+        let truncated = [0xff, 0xff, 0xff, 0xff, 0x00];
+        let err = read_raw_frame(&mut &truncated[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_open_nothing_to_prepare() {
+        let mut session = session();
+        match session.handle(Request::Open {
+            sql: "-- just a comment".to_string(),
+            params: vec![],
+            fetch: 10,
+        }) {
+            Response::Opened { cursor, rows, done } => {
+                assert!(cursor.columns.is_empty() && rows.is_empty() && done);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
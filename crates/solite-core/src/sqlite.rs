@@ -8,12 +8,18 @@
 //!            └─ Remote { RemoteTransport }         `solite serve` over SSH/pipe
 //!
 //! Statement ─── Local    { *mut sqlite3_stmt }     stepped row by row
+//!            ├─ Cursor   { RemoteCursor }          a server-side cursor,
+//!            │                                     fetched a page at a time
 //!            └─ Buffered { QueryResult, cursor }   fully materialized rows
 //!                                                  returned by a remote server
 //! ```
 //!
-//! A remote `prepare()` executes the query on the server and returns a
-//! `Buffered` statement; stepping it just walks the materialized rows.
+//! A remote `prepare()` opens a cursor on the server, which steps the
+//! statement once (running writes to completion) and returns a first page
+//! of rows; stepping the `Cursor` statement fetches further pages as
+//! they're needed, so large results never sit in memory on either end.
+//! Servers that predate cursors (see [`crate::rpc::CAP_CURSORS`]) get a
+//! `Query` instead and come back as a `Buffered` statement.
 //! Methods that only make sense on one flavor are documented as no-ops or
 //! errors on the other.
 //!
@@ -378,6 +384,8 @@ enum StatementInner {
     Local {
         statement: *mut sqlite3_stmt,
     },
+    /// A remote statement whose rows are fetched page by page.
+    Cursor { cursor: RefCell<RemoteCursor> },
     /// A statement whose results have been fully materialized (from remote execution).
     Buffered {
         result: crate::rpc::QueryResult,
@@ -391,6 +399,14 @@ impl std::fmt::Debug for StatementInner {
             StatementInner::Local { statement } => {
                 f.debug_struct("Local").field("statement", statement).finish()
             }
+            StatementInner::Cursor { cursor } => {
+                let cursor = cursor.borrow();
+                f.debug_struct("Cursor")
+                    .field("sql", &cursor.info.sql)
+                    .field("id", &cursor.info.id)
+                    .field("done", &cursor.done)
+                    .finish()
+            }
             StatementInner::Buffered { result, cursor } => f
                 .debug_struct("Buffered")
                 .field("sql", &result.sql)
@@ -420,6 +436,7 @@ impl Statement {
                 let s = CStr::from_ptr(z);
                 s.to_string_lossy().to_string()
             },
+            StatementInner::Cursor { cursor } => cursor.borrow().info.sql.clone(),
             StatementInner::Buffered { result, .. } => result.sql.clone(),
         }
     }
@@ -439,6 +456,7 @@ impl Statement {
                     }
                 }
             }
+            StatementInner::Cursor { cursor } => Some(cursor.borrow().info.sql.clone()),
             StatementInner::Buffered { result, .. } => Some(result.sql.clone()),
         }
     }
@@ -455,6 +473,11 @@ impl Statement {
                     _ => None,
                 }
             }
+            StatementInner::Cursor { cursor } => match cursor.borrow().info.is_explain {
+                Some(1) => Some(IsExplain::Explain),
+                Some(2) => Some(IsExplain::ExplainQueryPlan),
+                _ => None,
+            },
             StatementInner::Buffered { result, .. } => match result.is_explain {
                 Some(1) => Some(IsExplain::Explain),
                 Some(2) => Some(IsExplain::ExplainQueryPlan),
@@ -475,6 +498,9 @@ impl Statement {
                 }
                 Ok(columns)
             },
+            StatementInner::Cursor { cursor } => {
+                Ok(cursor.borrow().info.columns.iter().map(|c| c.name.clone()).collect())
+            }
             StatementInner::Buffered { result, .. } => {
                 Ok(result.columns.iter().map(|c| c.name.clone()).collect())
             }
@@ -536,6 +562,7 @@ impl Statement {
                 }
                 columns
             },
+            StatementInner::Cursor { cursor } => cursor.borrow().info.columns.clone(),
             StatementInner::Buffered { result, .. } => result.columns.clone(),
         }
     }
@@ -559,7 +586,7 @@ impl Statement {
     /// # }
     /// ```
     pub fn next(&mut self) -> Result<Option<Vec<ValueRefX<'_>>>, SQLiteError> {
-        match &self.inner {
+        match &mut self.inner {
             StatementInner::Local { statement } => {
                 let rc = unsafe { sqlite3_step(*statement) };
                 match rc {
@@ -579,27 +606,21 @@ impl Statement {
                     }),
                 }
             }
+            StatementInner::Cursor { cursor } => {
+                let cursor = cursor.get_mut();
+                if !cursor.fill()? {
+                    return Ok(None);
+                }
+                cursor.pos += 1;
+                Ok(Some(wire_row(&cursor.rows[cursor.pos - 1])))
+            }
             StatementInner::Buffered { result, cursor } => {
                 let idx = cursor.get();
                 if idx >= result.rows.len() {
                     return Ok(None);
                 }
                 cursor.set(idx + 1);
-                let wire_row = &result.rows[idx];
-                let row: Vec<ValueRefX<'_>> = wire_row
-                    .iter()
-                    .map(|wv| {
-                        let value = match &wv.value {
-                            OwnedValue::Null => ValueRefXValue::Null,
-                            OwnedValue::Integer(v) => ValueRefXValue::Int(*v),
-                            OwnedValue::Double(v) => ValueRefXValue::Double(*v),
-                            OwnedValue::Text(v) => ValueRefXValue::Text(v.as_slice()),
-                            OwnedValue::Blob(v) => ValueRefXValue::Blob(v.as_slice()),
-                        };
-                        ValueRefX::from_owned(value, wv.subtype)
-                    })
-                    .collect();
-                Ok(Some(row))
+                Ok(Some(wire_row(&result.rows[idx])))
             }
         }
     }
@@ -627,7 +648,7 @@ impl Statement {
                     }),
                 }
             }
-            StatementInner::Buffered { .. } => {
+            StatementInner::Cursor { .. } | StatementInner::Buffered { .. } => {
                 // nextx returns a Row that wraps a raw pointer — not compatible with buffered.
                 // Callers should use next() for buffered statements.
                 Err(SQLiteError::custom(
@@ -659,6 +680,15 @@ impl Statement {
                 }
                 Ok(n)
             }
+            StatementInner::Cursor { cursor } => {
+                let mut cursor = cursor.borrow_mut();
+                let mut n = 0;
+                while cursor.fill()? {
+                    n += cursor.rows.len() - cursor.pos;
+                    cursor.pos = cursor.rows.len();
+                }
+                Ok(n)
+            }
             StatementInner::Buffered { result, .. } => Ok(result.rows.len()),
         }
     }
@@ -771,32 +801,80 @@ impl Statement {
                 }
                 bind_parameters
             },
-            StatementInner::Buffered { .. } => {
-                // Buffered statements already have params bound
+            StatementInner::Cursor { .. } | StatementInner::Buffered { .. } => {
+                // Remote statements already have params bound
                 vec![]
             }
         }
     }
 
     /// Reset the statement so it can be stepped again from the start.
-    /// For buffered statements, rewinds the row cursor.
+    /// For buffered statements, rewinds the row cursor; remote cursors are
+    /// closed and re-opened on the next step, re-running the query on the
+    /// server (so a failure surfaces from [`Statement::next`]).
     pub fn reset(&self) {
         match &self.inner {
             StatementInner::Local { statement } => {
                 unsafe { sqlite3_reset(*statement) };
             }
+            StatementInner::Cursor { cursor } => {
+                let mut cursor = cursor.borrow_mut();
+                cursor.close();
+                cursor.reopen = true;
+            }
             StatementInner::Buffered { cursor, .. } => {
                 cursor.set(0);
             }
         }
     }
 
+    /// Export this statement's rows on the server and stream the encoded
+    /// bytes into `out`, instead of fetching the rows and encoding them
+    /// here. Returns the bytes written, or `None` when the caller should
+    /// export the rows itself: local and buffered statements, servers
+    /// without the capability, and statements that write (the server
+    /// re-runs the query, which would apply the write twice).
+    pub fn export_on_server(
+        &self,
+        format: &crate::exporter::ExportFormat,
+        blob_limit: crate::exporter::BlobLimit,
+        out: &mut dyn std::io::Write,
+    ) -> Option<Result<u64, SQLiteError>> {
+        let StatementInner::Cursor { cursor } = &self.inner else {
+            return None;
+        };
+        let mut cursor = cursor.borrow_mut();
+        if !cursor.info.readonly
+            || !cursor.transport.lock().unwrap().supports(crate::rpc::CAP_EXPORT)
+        {
+            return None;
+        }
+        cursor.close();
+        let request = crate::rpc::Request::Export {
+            sql: cursor.sql.clone(),
+            params: cursor.params.clone(),
+            format: format.clone(),
+            blob_limit,
+        };
+        let response = cursor.transport.lock().unwrap().send_streaming(&request, out);
+        Some(match response {
+            Ok(crate::rpc::Response::Streamed { bytes }) => Ok(bytes),
+            Ok(crate::rpc::Response::Error(e)) => Err(e),
+            Ok(other) => Err(SQLiteError::custom(
+                "PROTOCOL_ERROR",
+                format!("Unexpected response: {:?}", other),
+            )),
+            Err(e) => Err(e),
+        })
+    }
+
     /// Whether the statement makes no direct changes to the database.
     pub fn readonly(&self) -> bool {
         match &self.inner {
             StatementInner::Local { statement } => {
                 unsafe { sqlite3_stmt_readonly(*statement) != 0 }
             }
+            StatementInner::Cursor { cursor } => cursor.borrow().info.readonly,
             StatementInner::Buffered { result, .. } => result.readonly,
         }
     }
@@ -809,8 +887,8 @@ impl Statement {
     pub fn pointer(&self) -> *mut sqlite3_stmt {
         match &self.inner {
             StatementInner::Local { statement } => *statement,
-            StatementInner::Buffered { .. } => {
-                panic!("Cannot access raw sqlite3_stmt pointer on a buffered statement")
+            StatementInner::Cursor { .. } | StatementInner::Buffered { .. } => {
+                panic!("Cannot access raw sqlite3_stmt pointer on a remote statement")
             }
         }
     }
@@ -818,12 +896,15 @@ impl Statement {
 
 impl Drop for Statement {
     fn drop(&mut self) {
-        if let StatementInner::Local { statement } = &self.inner {
-            unsafe {
+        match &mut self.inner {
+            StatementInner::Local { statement } => unsafe {
                 sqlite3_finalize(*statement);
-            }
+            },
+            // Free the server-side statement if it wasn't run to completion
+            StatementInner::Cursor { cursor } => cursor.get_mut().close(),
+            // Buffered statements have no resources to free
+            StatementInner::Buffered { .. } => {}
         }
-        // Buffered statements have no resources to free
     }
 }
 
@@ -884,77 +965,609 @@ pub unsafe fn bytecode_steps(pstmt: *mut sqlite3_stmt) -> Result<Vec<BytecodeSte
     }
     Ok(steps)
 }
-/// Transport for communicating with a remote `solite serve` process over SSH.
+/// The request half of a remote transport.
+type RemoteWriter = std::io::BufWriter<Box<dyn std::io::Write + Send>>;
+
+/// Transport for communicating with a remote `solite serve` process, over
+/// the pipes of an SSH/transport process or over a socket.
 pub struct RemoteTransport {
-    child: std::process::Child,
-    reader: std::io::BufReader<std::process::ChildStdout>,
-    writer: std::io::BufWriter<std::process::ChildStdin>,
+    /// The SSH/transport process; `None` for socket connections.
+    child: Option<std::process::Child>,
+    reader: std::io::BufReader<Box<dyn std::io::Read + Send>>,
+    /// Locked separately from the transport so an [`InterruptHandle`] can
+    /// send an `Interrupt` while a request is waiting on its response.
+    writer: Arc<StdMutex<RemoteWriter>>,
+    /// Set once the connection has sent `Close`, so statements that
+    /// outlive it don't talk to a finished server.
+    closed: bool,
+    /// Capabilities agreed in the `Hello` handshake (see
+    /// [`crate::rpc::CAPABILITIES`]).
+    capabilities: Vec<String>,
 }
 
-// SAFETY: RemoteTransport owns its child process and pipes outright; moving
-// it to another thread is sound. Not Sync: &self methods mutate the pipes.
-unsafe impl std::marker::Send for RemoteTransport {}
+/// A transport's process (if any) and pipes, before the handshake.
+type RemotePipes = (
+    Option<std::process::Child>,
+    Box<dyn std::io::Read + Send>,
+    Box<dyn std::io::Write + Send>,
+);
+
+/// Why [`RemoteTransport::handshake`] failed.
+enum HandshakeError {
+    /// The server hung up on `Hello` or sent something undecodable, as
+    /// servers from before the handshake do.
+    Unanswered(SQLiteError),
+    Failed(SQLiteError),
+}
+
+impl HandshakeError {
+    fn into_inner(self) -> SQLiteError {
+        match self {
+            HandshakeError::Unanswered(e) | HandshakeError::Failed(e) => e,
+        }
+    }
+}
 
 impl RemoteTransport {
-    /// Take the child's pipes and verify the connection with a ping request.
-    /// Catches transport failures (bad hostname, auth rejected, remote binary
-    /// not found). `error_label` becomes the error's code description
-    /// (e.g. "SSH_ERROR", "TRANSPORT_ERROR").
-    fn connect(mut child: std::process::Child, error_label: &str) -> Result<Self, SQLiteError> {
-        let stdin = child.stdin.take().unwrap();
-        let stdout = child.stdout.take().unwrap();
-
-        let mut transport = RemoteTransport {
+    /// Spawn a `solite serve` process with `spawn`, take its pipes and
+    /// agree on a protocol with it. Catches transport failures (bad
+    /// hostname, auth rejected, remote binary not found). `error_label`
+    /// becomes the error's code description (e.g. "SSH_ERROR",
+    /// "TRANSPORT_ERROR").
+    fn connect(
+        spawn: impl Fn() -> Result<std::process::Child, SQLiteError>,
+        error_label: &str,
+    ) -> Result<Self, SQLiteError> {
+        Self::connect_pipes(
+            || {
+                let mut child = spawn()?;
+                let stdin = child.stdin.take().unwrap();
+                let stdout = child.stdout.take().unwrap();
+                let pipes: RemotePipes = (Some(child), Box::new(stdout), Box::new(stdin));
+                Ok(pipes)
+            },
+            error_label,
+        )
+    }
+
+    /// Handshake over pipes from `open`. A server from before the
+    /// handshake can't decode `Hello` and exits, so when `Hello` goes
+    /// unanswered, `open` is called again and the new server gets the
+    /// `InTransaction` ping those servers expect, with no capabilities.
+    fn connect_pipes(
+        open: impl Fn() -> Result<RemotePipes, SQLiteError>,
+        error_label: &str,
+    ) -> Result<Self, SQLiteError> {
+        let (child, reader, writer) = open()?;
+        match Self::handshake(child, reader, writer, None, error_label) {
+            Ok(transport) => Ok(transport),
+            Err(HandshakeError::Failed(e)) => Err(e),
+            Err(HandshakeError::Unanswered(_)) => {
+                let (child, reader, writer) = open()?;
+                Self::legacy(child, reader, writer, error_label)
+            }
+        }
+    }
+
+    fn new(
+        child: Option<std::process::Child>,
+        reader: Box<dyn std::io::Read + Send>,
+        writer: Box<dyn std::io::Write + Send>,
+    ) -> Self {
+        RemoteTransport {
             child,
-            reader: std::io::BufReader::new(stdout),
-            writer: std::io::BufWriter::new(stdin),
+            reader: std::io::BufReader::new(reader),
+            writer: Arc::new(StdMutex::new(std::io::BufWriter::new(writer))),
+            closed: false,
+            capabilities: Vec::new(),
+        }
+    }
+
+    /// Reap the process of a transport that failed to connect, and fail
+    /// with `message`.
+    fn abandon(mut self, error_label: &str, message: String) -> SQLiteError {
+        // Wait for the child to finish so we don't leave zombies
+        if let Some(child) = &mut self.child {
+            let _ = child.wait();
+        }
+        self.closed = true;
+        SQLiteError::custom(error_label, message)
+    }
+
+    /// Verify a server from before the handshake with the ping it
+    /// understands. Only requests without a capability are sent to it.
+    fn legacy(
+        child: Option<std::process::Child>,
+        reader: Box<dyn std::io::Read + Send>,
+        writer: Box<dyn std::io::Write + Send>,
+        error_label: &str,
+    ) -> Result<Self, SQLiteError> {
+        let mut transport = Self::new(child, reader, writer);
+        let message = match transport.send_request(&crate::rpc::Request::InTransaction) {
+            Ok(crate::rpc::Response::InTransaction { .. }) => return Ok(transport),
+            Ok(other) => format!("Unexpected response from remote: {:?}", other),
+            Err(e) => format!("Failed to connect to remote database: {}", e.message),
         };
+        Err(transport.abandon(error_label, message))
+    }
 
-        match transport.send_request(&crate::rpc::Request::InTransaction) {
-            Ok(crate::rpc::Response::InTransaction { .. }) => Ok(transport),
-            Ok(other) => {
-                // Wait for the child to finish so we don't leave zombies
-                let _ = transport.child.wait();
-                Err(SQLiteError::custom(
-                    error_label,
-                    format!("Unexpected response from remote: {:?}", other),
-                ))
+    /// Attach to a session on `url` kept warm by `solite remote daemon`.
+    /// `Ok(None)` when no daemon is listening.
+    fn connect_daemon(url: &str, remote_bin: Option<&str>) -> Result<Option<Self>, SQLiteError> {
+        let connected = crate::daemon::connect().map_err(|e| {
+            SQLiteError::custom("SSH_ERROR", format!("Refusing to use the remote daemon: {}", e))
+        })?;
+        let Some((reader, writer)) = connected else {
+            return Ok(None);
+        };
+        let attach = crate::rpc::Request::Attach {
+            url: url.to_string(),
+            remote_bin: remote_bin.map(str::to_string),
+        };
+        Self::handshake(None, reader, writer, Some(attach), "SSH_ERROR")
+            .map(Some)
+            .map_err(HandshakeError::into_inner)
+    }
+
+    /// Connect to a `solite serve --listen` socket and authenticate with
+    /// `token`.
+    fn connect_socket(address: &crate::rpc::SocketAddress, token: &str) -> Result<Self, SQLiteError> {
+        let (reader, writer) = crate::rpc::connect(address).map_err(|e| {
+            SQLiteError::custom("SOCKET_ERROR", format!("Failed to connect to {}: {}", address, e))
+        })?;
+        let auth = crate::rpc::Request::Auth {
+            token: token.to_string(),
+        };
+        Self::handshake(None, reader, writer, Some(auth), "SOCKET_ERROR")
+            .map_err(HandshakeError::into_inner)
+    }
+
+    /// Send the `opening` request if given (`Auth` on a socket, `Attach`
+    /// to the daemon), then agree on a protocol version and capabilities
+    /// with a `Hello`. Without an `opening`, a `Hello` that fails to get
+    /// a response is [`HandshakeError::Unanswered`].
+    fn handshake(
+        child: Option<std::process::Child>,
+        reader: Box<dyn std::io::Read + Send>,
+        writer: Box<dyn std::io::Write + Send>,
+        opening: Option<crate::rpc::Request>,
+        error_label: &str,
+    ) -> Result<Self, HandshakeError> {
+        let mut transport = Self::new(child, reader, writer);
+        let unanswered = opening.is_none();
+
+        let result = match opening {
+            Some(request) => transport.send_request(&request),
+            None => Ok(crate::rpc::Response::Authenticated),
+        };
+        let result = match result {
+            Ok(crate::rpc::Response::Authenticated | crate::rpc::Response::Attached { .. }) => {
+                match transport.send_request(&crate::rpc::Request::Hello {
+                    version: crate::rpc::PROTOCOL_VERSION,
+                    capabilities: crate::rpc::CAPABILITIES
+                        .iter()
+                        .map(|capability| capability.to_string())
+                        .collect(),
+                }) {
+                    Err(e) if unanswered => {
+                        let message = e.message.clone();
+                        return Err(HandshakeError::Unanswered(transport.abandon(error_label, message)));
+                    }
+                    result => result,
+                }
             }
-            Err(e) => {
-                let _ = transport.child.wait();
-                Err(SQLiteError::custom(
-                    error_label,
-                    format!("Failed to connect to remote database: {}", e.message),
-                ))
+            other => other,
+        };
+        let err = match result {
+            Ok(crate::rpc::Response::Hello {
+                version,
+                capabilities,
+                ..
+            }) if version == crate::rpc::PROTOCOL_VERSION => {
+                transport.capabilities = capabilities;
+                return Ok(transport);
             }
-        }
+            Ok(crate::rpc::Response::Hello { version, .. }) => {
+                crate::rpc::version_mismatch(crate::rpc::PROTOCOL_VERSION, version)
+            }
+            Ok(crate::rpc::Response::Error(e)) => e.message,
+            Ok(other) => format!("Unexpected response from remote: {:?}", other),
+            Err(e) => format!("Failed to connect to remote database: {}", e.message),
+        };
+        Err(HandshakeError::Failed(transport.abandon(error_label, err)))
     }
 
     fn send_request(&mut self, request: &crate::rpc::Request) -> Result<crate::rpc::Response, SQLiteError> {
-        crate::rpc::write_frame(&mut self.writer, request).map_err(|e| {
+        if self.closed {
+            return Err(SQLiteError::custom("IO_ERROR", "The remote connection is closed"));
+        }
+        crate::rpc::write_frame(&mut *self.writer.lock().unwrap(), request).map_err(|e| {
             SQLiteError::custom("IO_ERROR", format!("Failed to send request to remote: {}", e))
         })?;
+        self.read_response()
+    }
+
+    /// Send a request answered by a run of `Chunk`s (`Export`, `Backup`),
+    /// writing each chunk to `out`, and return the final response.
+    fn send_streaming(
+        &mut self,
+        request: &crate::rpc::Request,
+        out: &mut dyn std::io::Write,
+    ) -> Result<crate::rpc::Response, SQLiteError> {
+        let mut response = self.send_request(request)?;
+        // Keep reading after a failed write, so the stream stays in sync
+        let mut write_error = None;
+        while let crate::rpc::Response::Chunk { data } = response {
+            if write_error.is_none() {
+                write_error = out.write_all(&data).err();
+            }
+            response = self.read_response()?;
+        }
+        match write_error {
+            Some(e) => Err(SQLiteError::custom(
+                "IO_ERROR",
+                format!("Failed to write streamed output: {}", e),
+            )),
+            None => Ok(response),
+        }
+    }
+
+    fn read_response(&mut self) -> Result<crate::rpc::Response, SQLiteError> {
         crate::rpc::read_frame(&mut self.reader).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::InvalidData {
+                return SQLiteError::custom(
+                    "PROTOCOL_ERROR",
+                    format!(
+                        "Failed to decode response from remote ({}); the server may be a \
+                         different solite release",
+                        e
+                    ),
+                );
+            }
             SQLiteError::custom("IO_ERROR", format!("Failed to read response from remote: {}", e))
         })
     }
+
+    /// Whether the server agreed to an optional capability.
+    fn supports(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+}
+
+/// A window of rows fetched by [`RemotePager::page`].
+#[derive(Debug)]
+pub struct RemotePage {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<OwnedValue>>,
+    /// The query's total row count, when it was asked for.
+    pub total: Option<u64>,
+}
+
+/// Closes sessions that no [`Connection`] owns (see
+/// [`RemotePager::session`]); a connection closes its own when dropped.
+impl Drop for RemoteTransport {
+    fn drop(&mut self) {
+        if !self.closed {
+            let _ = self.send_request(&crate::rpc::Request::Close);
+            self.closed = true;
+        }
+        if let Some(child) = &mut self.child {
+            let _ = child.wait();
+        }
+    }
+}
+
+/// How a remote connection was opened, so another session on the same
+/// database can be opened later (see [`RemotePager::session`]).
+#[derive(Clone)]
+enum RemoteOrigin {
+    Ssh {
+        url: String,
+        remote_bin: Option<String>,
+    },
+    Socket {
+        address: crate::rpc::SocketAddress,
+        token: String,
+    },
+    Transport {
+        command: String,
+        db_path: String,
+        remote_bin: Option<String>,
+    },
+}
+
+impl RemoteOrigin {
+    /// Open a new session: from `solite remote daemon` if one is running
+    /// (SSH only), else over a fresh transport.
+    fn connect(&self) -> Result<RemoteTransport, SQLiteError> {
+        match self {
+            RemoteOrigin::Ssh { url, remote_bin } => {
+                // Check the URL before trying the daemon
+                ssh_command(url, remote_bin.as_deref())?;
+                if let Some(transport) = RemoteTransport::connect_daemon(url, remote_bin.as_deref())? {
+                    return Ok(transport);
+                }
+                let spawn = || {
+                    ssh_command(url, remote_bin.as_deref())?.spawn().map_err(|e| {
+                        SQLiteError::custom("SSH_ERROR", format!("Failed to spawn ssh: {}", e))
+                    })
+                };
+                RemoteTransport::connect(spawn, "SSH_ERROR")
+            }
+            RemoteOrigin::Socket { address, token } => RemoteTransport::connect_socket(address, token),
+            RemoteOrigin::Transport {
+                command,
+                db_path,
+                remote_bin,
+            } => {
+                let bin = remote_bin.as_deref().unwrap_or("solite");
+                let full_cmd = format!("{} {} serve {}", command, bin, db_path);
+
+                let spawn = || {
+                    std::process::Command::new("sh")
+                        .arg("-c")
+                        .arg(&full_cmd)
+                        .stdin(std::process::Stdio::piped())
+                        .stdout(std::process::Stdio::piped())
+                        .stderr(std::process::Stdio::inherit())
+                        .spawn()
+                        .map_err(|e| {
+                            SQLiteError::custom(
+                                "TRANSPORT_ERROR",
+                                format!("Failed to spawn transport '{}': {}", full_cmd, e),
+                            )
+                        })
+                };
+                RemoteTransport::connect(spawn, "TRANSPORT_ERROR")
+            }
+        }
+    }
 }
 
-/// Map a remote query result to `prepare()`'s return shape: an empty result
-/// (no columns, no rows) means there was nothing to prepare.
+/// A connection's second session for [`RemotePager::session`], opened on
+/// first use.
+type SideSession = Arc<StdMutex<Option<Arc<StdMutex<RemoteTransport>>>>>;
+
+/// Pages through read-only queries on a remote server with `Page` requests
+/// (see [`Connection::remote_pager`]). Cheap to clone and `Send`, so pages
+/// can be fetched on a background thread; fetches take turns with the
+/// connection's own requests on the shared transport. Requests slow enough
+/// to hold those up belong on a [`RemotePager::session`] of their own.
+#[derive(Clone)]
+pub struct RemotePager {
+    transport: Arc<StdMutex<RemoteTransport>>,
+    origin: RemoteOrigin,
+    side: SideSession,
+}
+
+impl RemotePager {
+    /// A pager on a second session to the same database, which doesn't
+    /// share this connection's transport, so a long request on it (a
+    /// `COUNT(*)` of a big table) doesn't hold up the connection's own.
+    /// Every pager of a connection shares one second session, opened on
+    /// first use and again after its transport fails. Fails when the
+    /// database is in-memory or temporary: a new session would open a
+    /// different, empty one.
+    pub fn session(&self) -> Result<RemotePager, SQLiteError> {
+        let mut side = self.side.lock().unwrap();
+        if let Some(transport) = side.as_ref().filter(|t| !t.lock().unwrap().closed) {
+            return Ok(RemotePager {
+                transport: Arc::clone(transport),
+                origin: self.origin.clone(),
+                side: Arc::clone(&self.side),
+            });
+        }
+        let transport = self.origin.connect()?;
+        if !transport.supports(crate::rpc::CAP_PAGE) {
+            return Err(SQLiteError::custom(
+                "PROTOCOL_ERROR",
+                "The server doesn't support paging",
+            ));
+        }
+        let session = RemotePager {
+            transport: Arc::new(StdMutex::new(transport)),
+            origin: self.origin.clone(),
+            side: Arc::clone(&self.side),
+        };
+        let main = session.page(
+            "SELECT file FROM pragma_database_list WHERE name = 'main'",
+            0,
+            1,
+            false,
+        )?;
+        match main.rows.first().and_then(|row| row.first()) {
+            Some(OwnedValue::Text(file)) if !file.is_empty() => {
+                *side = Some(Arc::clone(&session.transport));
+                Ok(session)
+            }
+            _ => Err(SQLiteError::custom(
+                "SESSION_ERROR",
+                "An in-memory database can't be opened by another session",
+            )),
+        }
+    }
+
+    /// Rows `offset..offset + limit` of `sql`, plus its total row count if
+    /// `count` is set. The server caches totals until the database changes.
+    pub fn page(
+        &self,
+        sql: &str,
+        offset: usize,
+        limit: usize,
+        count: bool,
+    ) -> Result<RemotePage, SQLiteError> {
+        let request = crate::rpc::Request::Page {
+            sql: sql.to_string(),
+            offset,
+            limit,
+            count,
+        };
+        let mut transport = self.transport.lock().unwrap();
+        let response = transport.send_request(&request).inspect_err(|e| {
+            // The stream is out of sync after a failed read or write
+            if e.code_description == "IO_ERROR" {
+                transport.closed = true;
+            }
+        })?;
+        match response {
+            crate::rpc::Response::Page {
+                columns,
+                rows,
+                total,
+            } => Ok(RemotePage {
+                columns: columns.into_iter().map(|column| column.name).collect(),
+                rows: rows
+                    .into_iter()
+                    .map(|row| row.into_iter().map(|v| v.value).collect())
+                    .collect(),
+                total,
+            }),
+            crate::rpc::Response::Error(e) => Err(e),
+            other => Err(SQLiteError::custom(
+                "PROTOCOL_ERROR",
+                format!("Unexpected response: {:?}", other),
+            )),
+        }
+    }
+}
+
+/// Client side of a server-side cursor (see [`crate::rpc`]). Holds one
+/// page of rows at a time.
+struct RemoteCursor {
+    transport: Arc<StdMutex<RemoteTransport>>,
+    /// The SQL and parameters it was opened with, to re-open on reset.
+    sql: String,
+    params: Vec<(String, OwnedValue)>,
+    info: crate::rpc::CursorInfo,
+    rows: Vec<Vec<crate::rpc::WireValue>>,
+    /// Index of the next unread row in `rows`.
+    pos: usize,
+    /// The server has no more rows (and has closed the cursor).
+    done: bool,
+    /// Reset was called: re-open the cursor before reading the next row.
+    reopen: bool,
+}
+
+impl RemoteCursor {
+    /// Open `sql` as a cursor on the server. `None` when there was nothing
+    /// to prepare (empty or comment-only SQL).
+    fn open(
+        transport: &Arc<StdMutex<RemoteTransport>>,
+        sql: &str,
+        params: Vec<(String, OwnedValue)>,
+    ) -> Result<Option<Self>, SQLiteError> {
+        let request = crate::rpc::Request::Open {
+            sql: sql.to_string(),
+            params: params.clone(),
+            fetch: crate::rpc::FETCH_ROWS,
+        };
+        let response = transport.lock().unwrap().send_request(&request)?;
+        match response {
+            crate::rpc::Response::Opened { cursor, rows, done } => {
+                if done && cursor.columns.is_empty() && rows.is_empty() {
+                    return Ok(None);
+                }
+                Ok(Some(RemoteCursor {
+                    transport: Arc::clone(transport),
+                    sql: sql.to_string(),
+                    params,
+                    info: cursor,
+                    rows,
+                    pos: 0,
+                    done,
+                    reopen: false,
+                }))
+            }
+            crate::rpc::Response::Error(e) => Err(e),
+            other => Err(SQLiteError::custom(
+                "PROTOCOL_ERROR",
+                format!("Unexpected response: {:?}", other),
+            )),
+        }
+    }
+
+    /// Make sure an unread row is available, re-opening the cursor after a
+    /// reset and fetching the next page once the current one is used up.
+    /// `false` when the cursor is exhausted.
+    fn fill(&mut self) -> Result<bool, SQLiteError> {
+        if self.reopen {
+            self.reopen = false;
+            match RemoteCursor::open(&self.transport, &self.sql, self.params.clone())? {
+                Some(reopened) => *self = reopened,
+                None => return Ok(false),
+            }
+        }
+        while self.pos >= self.rows.len() {
+            if self.done {
+                return Ok(false);
+            }
+            let request = crate::rpc::Request::Fetch {
+                cursor: self.info.id,
+                n: crate::rpc::FETCH_ROWS,
+            };
+            let response = self.transport.lock().unwrap().send_request(&request)?;
+            match response {
+                crate::rpc::Response::Rows { rows, done } => {
+                    self.rows = rows;
+                    self.pos = 0;
+                    self.done = done;
+                }
+                // The server drops a cursor that errors
+                crate::rpc::Response::Error(e) => {
+                    self.done = true;
+                    return Err(e);
+                }
+                other => {
+                    return Err(SQLiteError::custom(
+                        "PROTOCOL_ERROR",
+                        format!("Unexpected response: {:?}", other),
+                    ))
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Finalize the server-side statement, unless it already finished.
+    fn close(&mut self) {
+        if self.done {
+            return;
+        }
+        self.done = true;
+        self.rows.clear();
+        self.pos = 0;
+        if let Ok(mut transport) = self.transport.lock() {
+            let request = crate::rpc::Request::CloseCursor {
+                cursor: self.info.id,
+            };
+            let _ = transport.send_request(&request);
+        }
+    }
+}
+
+/// Borrow a row of wire values as [`ValueRefX`]s.
+fn wire_row(row: &[crate::rpc::WireValue]) -> Vec<ValueRefX<'_>> {
+    row.iter()
+        .map(|wv| {
+            let value = match &wv.value {
+                OwnedValue::Null => ValueRefXValue::Null,
+                OwnedValue::Integer(v) => ValueRefXValue::Int(*v),
+                OwnedValue::Double(v) => ValueRefXValue::Double(*v),
+                OwnedValue::Text(v) => ValueRefXValue::Text(v.as_slice()),
+                OwnedValue::Blob(v) => ValueRefXValue::Blob(v.as_slice()),
+            };
+            ValueRefX::from_owned(value, wv.subtype)
+        })
+        .collect()
+}
+
+/// The statement for a `Query` response. An empty result (no columns, no
+/// rows) means the SQL held no statement, as `prepare` reports with `None`.
 fn buffered_statement(result: crate::rpc::QueryResult) -> (Option<usize>, Option<Statement>) {
     if result.columns.is_empty() && result.rows.is_empty() {
         (None, None)
     } else {
-        (
-            None,
-            Some(Statement {
-                inner: StatementInner::Buffered {
-                    result,
-                    cursor: std::cell::Cell::new(0),
-                },
-            }),
-        )
+        (None, Some(Statement::from_query_result(result)))
     }
 }
 
@@ -976,6 +1589,11 @@ pub struct Connection {
     /// The currently registered progress handler, if any. Owned here so it can
     /// be freed on replace/clear/drop (SQLite only holds the raw pointer).
     progress_handler: std::cell::Cell<Option<*mut ProgressHandlerBox>>,
+    /// Where objects read from object store URLs are downloaded, created on
+    /// first use. Dropped after the database is closed, so attached
+    /// downloads are removed with the connection.
+    #[cfg(feature = "object_store")]
+    downloads: std::cell::OnceCell<tempfile::TempDir>,
 }
 
 enum ConnectionInner {
@@ -984,10 +1602,71 @@ enum ConnectionInner {
         _owned: bool,
     },
     Remote {
-        transport: RefCell<RemoteTransport>,
+        /// Shared with the connection's open [`RemoteCursor`]s.
+        transport: Arc<StdMutex<RemoteTransport>>,
+        /// The transport's request pipe, for [`InterruptHandle`]s. `None`
+        /// when the server can't take out-of-band interrupts.
+        interrupt: Option<Arc<StdMutex<RemoteWriter>>>,
+        origin: RemoteOrigin,
+        /// Shared with the connection's [`RemotePager`]s.
+        side_session: SideSession,
     },
 }
 
+/// Functions [`Connection::deny_host_access`] denies: stdlib functions
+/// that read or write files, reach the clipboard, or block the thread.
+pub const HOST_FUNCTIONS: &[&str] = &[
+    "readfile",
+    "writefile",
+    "lsmode",
+    "realpath",
+    "clipboard_set",
+    "usleep",
+    "load_extension",
+];
+
+/// Table-valued functions [`Connection::deny_host_access`] denies, since
+/// they read files.
+pub const HOST_TABLES: &[&str] = &["fsdir", "zipfile", "csv_reader", "tsv_reader", "xsv_reader"];
+
+/// The authorizer behind [`Connection::deny_host_access`]. For
+/// `SQLITE_FUNCTION` the function name is the second argument; for
+/// `SQLITE_READ` the table is the first (and the column is empty when no
+/// column is read, as in `count(*)`).
+unsafe extern "C" fn host_access_authorizer(
+    _: *mut c_void,
+    action: c_int,
+    arg1: *const c_char,
+    arg2: *const c_char,
+    _: *const c_char,
+    _: *const c_char,
+) -> c_int {
+    let name = |arg: *const c_char| {
+        if arg.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(arg).to_string_lossy().to_ascii_lowercase()
+        }
+    };
+    let denied = match action {
+        SQLITE_ATTACH | SQLITE_DETACH | SQLITE_CREATE_VTABLE => true,
+        SQLITE_FUNCTION => {
+            let function = name(arg2);
+            HOST_FUNCTIONS.contains(&function.as_str()) || function.starts_with("http_")
+        }
+        SQLITE_READ => {
+            let table = name(arg1);
+            HOST_TABLES.contains(&table.as_str()) || table.starts_with("http_")
+        }
+        _ => false,
+    };
+    if denied {
+        SQLITE_DENY
+    } else {
+        SQLITE_OK
+    }
+}
+
 // SAFETY: Send (but NOT Sync) is sound because every open path uses
 // SQLITE_OPEN_FULLMUTEX (serialized mode), so the handle may be used from
 // any one thread at a time. HAZARD: if an open path ever switches to
@@ -1006,6 +1685,9 @@ unsafe impl std::marker::Send for Statement {}
 #[derive(Clone)]
 pub struct InterruptHandle {
     db: Arc<StdMutex<*mut sqlite3>>,
+    /// The request pipe of a remote connection, which the server reads
+    /// concurrently with running requests.
+    remote: Option<Arc<StdMutex<RemoteWriter>>>,
 }
 
 // SAFETY: sqlite3_interrupt is documented as callable from any thread on a
@@ -1020,6 +1702,14 @@ impl InterruptHandle {
     ///
     /// <https://www.sqlite.org/c3ref/interrupt.html>
     pub fn interrupt(&self) {
+        if let Some(writer) = &self.remote {
+            // Not answered by the server, so this can't desync the pipe. A
+            // failed write means the server is gone: nothing to interrupt.
+            if let Ok(mut writer) = writer.lock() {
+                let _ = crate::rpc::write_frame(&mut *writer, &crate::rpc::Request::Interrupt);
+            }
+            return;
+        }
         let db = self.db.lock().unwrap();
         if !db.is_null() {
             unsafe { sqlite3_interrupt(*db) };
@@ -1044,24 +1734,53 @@ impl Connection {
             },
             interrupt_db: Arc::new(StdMutex::new(connection)),
             progress_handler: std::cell::Cell::new(None),
+            #[cfg(feature = "object_store")]
+            downloads: std::cell::OnceCell::new(),
         }
     }
 
-    fn from_remote(transport: RemoteTransport) -> Self {
+    fn from_remote(origin: RemoteOrigin) -> Result<Self, SQLiteError> {
+        let transport = origin.connect()?;
+        Ok(Self::from_transport(transport, origin))
+    }
+
+    fn from_transport(transport: RemoteTransport, origin: RemoteOrigin) -> Self {
         Connection {
             inner: ConnectionInner::Remote {
-                transport: RefCell::new(transport),
+                interrupt: transport
+                    .supports(crate::rpc::CAP_INTERRUPT)
+                    .then(|| Arc::clone(&transport.writer)),
+                transport: Arc::new(StdMutex::new(transport)),
+                origin,
+                side_session: SideSession::default(),
             },
             interrupt_db: Arc::new(StdMutex::new(ptr::null_mut())),
             progress_handler: std::cell::Cell::new(None),
+            #[cfg(feature = "object_store")]
+            downloads: std::cell::OnceCell::new(),
+        }
+    }
+
+    /// The directory objects are downloaded into for this connection. It
+    /// is removed when the connection is dropped.
+    #[cfg(feature = "object_store")]
+    pub fn download_dir(&self) -> std::io::Result<&std::path::Path> {
+        if self.downloads.get().is_none() {
+            let dir = tempfile::Builder::new().prefix("solite-objects").tempdir()?;
+            let _ = self.downloads.set(dir);
         }
+        Ok(self.downloads.get().expect("set above").path())
     }
 
     /// Get a thread-safe handle that can interrupt statements running on this
-    /// connection. No-op for remote connections.
+    /// connection, locally or on the server of a remote connection.
     pub fn interrupt_handle(&self) -> InterruptHandle {
         InterruptHandle {
             db: Arc::clone(&self.interrupt_db),
+            remote: match &self.inner {
+                ConnectionInner::Local { .. } => None,
+                ConnectionInner::Remote { interrupt, .. } => interrupt.clone(),
+            },
         }
     }
 
@@ -1102,9 +1821,9 @@ impl Connection {
     }
 
     /// Open a fresh in-memory database (with per-opcode stats enabled for
-    /// `.bench`).
+    /// `.bench`). URI filenames are enabled so ATTACH accepts `file:` URIs.
     pub fn open_in_memory() -> Result<Self, SQLiteError> {
-        let flags = SQLITE_OPEN_READWRITE | SQLITE_OPEN_FULLMUTEX | SQLITE_OPEN_CREATE;
+        let flags = SQLITE_OPEN_READWRITE | SQLITE_OPEN_FULLMUTEX | SQLITE_OPEN_CREATE | SQLITE_OPEN_URI;
         let conn = Self::open_with_flags(":memory:", flags)?;
         if let ConnectionInner::Local { connection, .. } = &conn.inner {
             unsafe {
@@ -1130,33 +1849,32 @@ impl Connection {
 
     /// Like [`Connection::open_remote`], with an explicit path to the
     /// `solite` binary on the remote host.
+    ///
+    /// When `solite remote daemon` is running, the session comes from it
+    /// (see [`crate::daemon`]) instead of a fresh `ssh` process.
     pub fn open_remote_with_bin(url: &str, remote_bin: Option<&str>) -> Result<Self, SQLiteError> {
-        let (user, host, port, db_path) = parse_remote_path(url)
-            .map_err(|msg| SQLiteError::custom("SSH_ERROR", msg))?;
-
-        let mut cmd = std::process::Command::new("ssh");
-        if let Some(port) = port {
-            cmd.arg("-p").arg(port.to_string());
+        if let Some(address) = crate::rpc::SocketAddress::from_url(url) {
+            return Self::open_socket(&address);
         }
-        let target = match user {
-            Some(u) => format!("{}@{}", u, host),
-            None => host,
-        };
-        let bin = remote_bin.unwrap_or("solite");
-        cmd.arg(&target)
-            .arg(bin)
-            .arg("serve")
-            .arg(&db_path)
-            .stdin(std::process::Stdio::piped())
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::inherit());
-
-        let child = cmd.spawn().map_err(|e| {
-            SQLiteError::custom("SSH_ERROR", format!("Failed to spawn ssh: {}", e))
-        })?;
+        Connection::from_remote(RemoteOrigin::Ssh {
+            url: url.to_string(),
+            remote_bin: remote_bin.map(str::to_string),
+        })
+    }
 
-        let transport = RemoteTransport::connect(child, "SSH_ERROR")?;
-        Ok(Connection::from_remote(transport))
+    /// Open a database served by `solite serve --listen` on a TCP or Unix
+    /// socket. The shared token is read from `SOLITE_SERVE_TOKEN`.
+    pub fn open_socket(address: &crate::rpc::SocketAddress) -> Result<Self, SQLiteError> {
+        let token = std::env::var("SOLITE_SERVE_TOKEN").map_err(|_| {
+            SQLiteError::custom(
+                "SOCKET_ERROR",
+                format!("Set SOLITE_SERVE_TOKEN to the token of the server at {}", address),
+            )
+        })?;
+        Connection::from_remote(RemoteOrigin::Socket {
+            address: address.clone(),
+            token,
+        })
     }
 
     /// Open a remote database via a custom transport command.
@@ -1164,25 +1882,11 @@ impl Connection {
     /// `transport_cmd` is a shell command prefix (e.g. `"fly ssh console -a my-app -C"`).
     /// We append `<remote_bin> serve <db_path>` and spawn it.
     pub fn open_transport(transport_cmd: &str, db_path: &str, remote_bin: Option<&str>) -> Result<Self, SQLiteError> {
-        let bin = remote_bin.unwrap_or("solite");
-        let full_cmd = format!("{} {} serve {}", transport_cmd, bin, db_path);
-
-        let child = std::process::Command::new("sh")
-            .arg("-c")
-            .arg(&full_cmd)
-            .stdin(std::process::Stdio::piped())
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::inherit())
-            .spawn()
-            .map_err(|e| {
-                SQLiteError::custom(
-                    "TRANSPORT_ERROR",
-                    format!("Failed to spawn transport '{}': {}", full_cmd, e),
-                )
-            })?;
-
-        let transport = RemoteTransport::connect(child, "TRANSPORT_ERROR")?;
-        Ok(Connection::from_remote(transport))
+        Connection::from_remote(RemoteOrigin::Transport {
+            command: transport_cmd.to_string(),
+            db_path: db_path.to_string(),
+            remote_bin: remote_bin.map(str::to_string),
+        })
     }
 
     /// Returns true if this is a remote (SSH) connection.
@@ -1232,9 +1936,9 @@ impl Connection {
             ConnectionInner::Local { connection, .. } => {
                 unsafe { sqlite3_get_autocommit(*connection) == 0 }
             }
-            ConnectionInner::Remote { transport } => {
+            ConnectionInner::Remote { transport, .. } => {
                 let request = crate::rpc::Request::InTransaction;
-                match transport.borrow_mut().send_request(&request) {
+                match transport.lock().unwrap().send_request(&request) {
                     Ok(crate::rpc::Response::InTransaction { value }) => value,
                     _ => false,
                 }
@@ -1262,12 +1966,51 @@ impl Connection {
                 }
                 Ok(())
             }
-            ConnectionInner::Remote { .. } => {
-                Err(anyhow::anyhow!("Cannot load extensions on a remote connection"))
+            ConnectionInner::Remote { transport, .. } => {
+                let mut transport = transport.lock().unwrap();
+                if !transport.supports(crate::rpc::CAP_LOAD_EXTENSION) {
+                    return Err(anyhow::anyhow!(
+                        "The remote server doesn't support loading extensions"
+                    ));
+                }
+                let request = crate::rpc::Request::LoadExtension {
+                    path: path.to_string(),
+                    entrypoint: entrypoint.clone(),
+                };
+                match transport.send_request(&request)? {
+                    crate::rpc::Response::ExtensionLoaded => Ok(()),
+                    crate::rpc::Response::Error(e) => Err(anyhow::anyhow!("{}", e.message)),
+                    other => Err(anyhow::anyhow!("Unexpected response: {:?}", other)),
+                }
             }
         }
     }
 
+    /// Deny statements that reach outside the database: ATTACH/DETACH,
+    /// creating virtual tables, and the stdlib functions and table-valued
+    /// functions that touch the filesystem, the network or the clipboard
+    /// ([`HOST_FUNCTIONS`], [`HOST_TABLES`], anything `http_*`). Denied
+    /// statements fail to prepare with "not authorized". `stmt.readonly()`
+    /// alone lets all of these through. Errors on remote connections.
+    pub fn deny_host_access(&self) -> Result<(), SQLiteError> {
+        match &self.inner {
+            ConnectionInner::Local { connection, .. } => {
+                let rc = unsafe {
+                    sqlite3_set_authorizer(*connection, Some(host_access_authorizer), ptr::null_mut())
+                };
+                if rc == SQLITE_OK {
+                    Ok(())
+                } else {
+                    Err(unsafe { SQLiteError::from_latest(*connection, rc) })
+                }
+            }
+            ConnectionInner::Remote { .. } => Err(SQLiteError::custom(
+                "AUTHORIZER",
+                "can't restrict a remote connection",
+            )),
+        }
+    }
+
     /// Prepare and run `sql` to completion, returning the number of result
     /// rows. Comment-only/empty input returns `Ok(0)`.
     pub fn execute(&self, sql: &str) -> Result<usize, SQLiteError> {
@@ -1292,9 +2035,9 @@ impl Connection {
                     Err(unsafe { SQLiteError::from_latest(*connection, rc) })
                 }
             }
-            ConnectionInner::Remote { transport } => {
+            ConnectionInner::Remote { transport, .. } => {
                 let request = crate::rpc::Request::ExecuteScript { sql: sql.to_string() };
-                let response = transport.borrow_mut().send_request(&request)?;
+                let response = transport.lock().unwrap().send_request(&request)?;
                 match response {
                     crate::rpc::Response::ScriptOk => Ok(()),
                     crate::rpc::Response::Error(e) => Err(e),
@@ -1362,6 +2105,71 @@ impl Connection {
         }
     }
 
+    /// Write a consistent copy of database `schema` (`main`, or an attached
+    /// name) to `out`, page by page, returning the bytes written. Reads the
+    /// pages through `sqlite_dbpage` in a single statement, so concurrent
+    /// writers never show up half-applied. Remote connections stream the
+    /// pages from the server.
+    pub fn backup_to(&self, schema: &str, out: &mut dyn std::io::Write) -> Result<u64, SQLiteError> {
+        match &self.inner {
+            ConnectionInner::Local { .. } => {
+                let (_, stmt) = self.prepare("SELECT data FROM sqlite_dbpage(?1) ORDER BY pgno")?;
+                let mut stmt = stmt.expect("backup query is a statement");
+                stmt.bind_text(1, schema)?;
+                let mut bytes = 0;
+                while let Some(row) = stmt.next()? {
+                    if let ValueRefXValue::Blob(page) = row[0].value {
+                        out.write_all(page).map_err(|e| {
+                            SQLiteError::custom("IO_ERROR", format!("Failed to write backup: {}", e))
+                        })?;
+                        bytes += page.len() as u64;
+                    }
+                }
+                Ok(bytes)
+            }
+            ConnectionInner::Remote { transport, .. } => {
+                let mut transport = transport.lock().unwrap();
+                if !transport.supports(crate::rpc::CAP_BACKUP) {
+                    return Err(SQLiteError::custom(
+                        "PROTOCOL_ERROR",
+                        "The remote server doesn't support backups",
+                    ));
+                }
+                let request = crate::rpc::Request::Backup {
+                    schema: schema.to_string(),
+                };
+                match transport.send_streaming(&request, out)? {
+                    crate::rpc::Response::Streamed { bytes } => Ok(bytes),
+                    crate::rpc::Response::Error(e) => Err(e),
+                    other => Err(SQLiteError::custom(
+                        "PROTOCOL_ERROR",
+                        format!("Unexpected response: {:?}", other),
+                    )),
+                }
+            }
+        }
+    }
+
+    /// A pager for offset/limit windows computed on the server, for remote
+    /// connections whose server supports `Page`. `None` otherwise.
+    pub fn remote_pager(&self) -> Option<RemotePager> {
+        match &self.inner {
+            ConnectionInner::Remote {
+                transport,
+                origin,
+                side_session,
+                ..
+            } if transport.lock().unwrap().supports(crate::rpc::CAP_PAGE) => {
+                Some(RemotePager {
+                    transport: Arc::clone(transport),
+                    origin: origin.clone(),
+                    side: Arc::clone(side_session),
+                })
+            }
+            _ => None,
+        }
+    }
+
     /// Serialize the main database to bytes (`sqlite3_serialize`). Asks the
     /// server on remote connections.
     pub fn serialize(&self) -> Result<Vec<u8>, SQLiteError> {
@@ -1380,9 +2188,9 @@ impl Connection {
                 sqlite3_free(ptr.cast());
                 Ok(vec)
             },
-            ConnectionInner::Remote { transport } => {
+            ConnectionInner::Remote { transport, .. } => {
                 let request = crate::rpc::Request::Serialize;
-                let response = transport.borrow_mut().send_request(&request)?;
+                let response = transport.lock().unwrap().send_request(&request)?;
                 match response {
                     crate::rpc::Response::Serialized { data } => Ok(data),
                     crate::rpc::Response::Error(e) => Err(e),
@@ -1405,9 +2213,9 @@ impl Connection {
     /// - `(Some(offset), None)` — leading comment/whitespace consumed, real
     ///   SQL starts at `offset` (only whitespace/comments were prepared).
     ///
-    /// On remote connections this executes the query server-side and
-    /// returns a `Buffered` statement (no parameters; use
-    /// [`Connection::prepare_remote`] to pass any).
+    /// On remote connections this opens a cursor server-side and returns a
+    /// `Cursor` statement (no parameters; use [`Connection::prepare_remote`]
+    /// to pass any).
     pub fn prepare(&self, sql: &str) -> Result<(Option<usize>, Option<Statement>), SQLiteError> {
         match &self.inner {
             ConnectionInner::Local { connection, .. } => {
@@ -1456,25 +2264,37 @@ impl Connection {
         }
     }
 
-    /// Prepare and execute a query on a remote connection with parameters,
-    /// returning a buffered statement. This is the main entry point for remote
-    /// SQL execution. For local connections, delegates to `prepare()`.
+    /// Open a query on a remote connection with parameters, returning a
+    /// statement that fetches its rows from the server as it's stepped. This
+    /// is the main entry point for remote SQL execution. For local
+    /// connections, delegates to `prepare()`.
     pub fn prepare_remote(&self, sql: &str, params: Vec<(String, OwnedValue)>) -> Result<(Option<usize>, Option<Statement>), SQLiteError> {
         match &self.inner {
-            ConnectionInner::Remote { transport } => {
-                let request = crate::rpc::Request::Query {
-                    sql: sql.to_string(),
-                    params,
-                };
-                let response = transport.borrow_mut().send_request(&request)?;
-                match response {
-                    crate::rpc::Response::Query(result) => Ok(buffered_statement(result)),
-                    crate::rpc::Response::Error(e) => Err(e),
-                    other => Err(SQLiteError::custom(
-                        "PROTOCOL_ERROR",
-                        format!("Unexpected response: {:?}", other),
-                    )),
+            ConnectionInner::Remote { transport, .. } => {
+                // Servers without cursors send back every row at once
+                if !transport.lock().unwrap().supports(crate::rpc::CAP_CURSORS) {
+                    let request = crate::rpc::Request::Query {
+                        sql: sql.to_string(),
+                        params,
+                    };
+                    return match transport.lock().unwrap().send_request(&request)? {
+                        crate::rpc::Response::Query(result) => Ok(buffered_statement(result)),
+                        crate::rpc::Response::Error(e) => Err(e),
+                        other => Err(SQLiteError::custom(
+                            "PROTOCOL_ERROR",
+                            format!("Unexpected response: {:?}", other),
+                        )),
+                    };
                 }
+                let cursor = RemoteCursor::open(transport, sql, params)?;
+                Ok((
+                    None,
+                    cursor.map(|cursor| Statement {
+                        inner: StatementInner::Cursor {
+                            cursor: RefCell::new(cursor),
+                        },
+                    }),
+                ))
             }
             ConnectionInner::Local { .. } => {
                 // For local, just delegate to prepare()
@@ -1502,10 +2322,7 @@ impl Connection {
             ConnectionInner::Local { connection, .. } => {
                 unsafe { sqlite3_interrupt(*connection) };
             }
-            ConnectionInner::Remote { .. } => {
-                // For remote, we could kill the SSH process or send an interrupt request.
-                // For now, this is a no-op.
-            }
+            ConnectionInner::Remote { .. } => self.interrupt_handle().interrupt(),
         }
     }
 }
@@ -1531,26 +2348,58 @@ impl Drop for Connection {
                     unsafe { sqlite3_close(*connection) };
                 }
             }
-            ConnectionInner::Remote { transport } => {
+            ConnectionInner::Remote { transport, .. } => {
                 // Try to send Close request, ignore errors
-                let mut t = transport.borrow_mut();
+                let mut t = transport.lock().unwrap();
                 let _ = t.send_request(&crate::rpc::Request::Close);
-                let _ = t.child.wait();
+                t.closed = true;
+                if let Some(child) = &mut t.child {
+                    let _ = child.wait();
+                }
             }
         }
     }
 }
 
+/// The `ssh` command that runs `<remote_bin> serve <path>` for an `ssh://`
+/// or scp-style `url`, with its stdin/stdout piped for the protocol.
+pub(crate) fn ssh_command(
+    url: &str,
+    remote_bin: Option<&str>,
+) -> Result<std::process::Command, SQLiteError> {
+    let (user, host, port, db_path) = parse_remote_path(url)
+        .map_err(|msg| SQLiteError::custom("SSH_ERROR", msg))?;
+
+    let mut cmd = std::process::Command::new("ssh");
+    if let Some(port) = port {
+        cmd.arg("-p").arg(port.to_string());
+    }
+    let target = match user {
+        Some(u) => format!("{}@{}", u, host),
+        None => host,
+    };
+    let bin = remote_bin.unwrap_or("solite");
+    cmd.arg(&target)
+        .arg(bin)
+        .arg("serve")
+        .arg(&db_path)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::inherit());
+    Ok(cmd)
+}
+
 /// Check if a path string refers to a remote database.
 ///
-/// Supports two formats:
+/// Supports these formats:
 /// - URL-style: `ssh://[user@]host[:port]/path`
 /// - scp-style: `[user@]host:/path` (same as sqlite3_rsync)
+/// - `tcp://host:port` and `unix:/path/to.sock`, for `solite serve --listen`
 ///
 /// The scp-style detection mirrors sqlite3_rsync's `hostSeparator()`:
 /// a `:` with no `/` or `\` before it indicates a remote host.
 pub fn is_remote_path(s: &str) -> bool {
-    if s.starts_with("ssh://") {
+    if s.starts_with("ssh://") || crate::rpc::SocketAddress::from_url(s).is_some() {
         return true;
     }
     host_separator(s).is_some()
@@ -1815,6 +2664,45 @@ mod tests {
         assert_eq!(row[1].subtype(), None);
     }
 
+    #[test]
+    fn test_deny_host_access() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        unsafe {
+            solite_stdlib::solite_stdlib_init(conn.db(), ptr::null_mut(), ptr::null_mut());
+        }
+        conn.deny_host_access().unwrap();
+        let path = dir.path().join("out.txt");
+        let denied = [
+            format!("select writefile('{}', 'x')", path.display()),
+            format!("select readfile('{}')", path.display()),
+            "select http_get_body('http://127.0.0.1:9')".to_string(),
+            "select count(*) from fsdir('.')".to_string(),
+            format!("attach '{}' as other", dir.path().join("other.db").display()),
+        ];
+        for sql in denied {
+            let err = conn.prepare(&sql).err().unwrap_or_else(|| panic!("{sql} was allowed"));
+            assert!(err.to_string().contains("not authorized"), "{sql}: {err}");
+        }
+        assert!(!path.exists());
+
+        let (_, stmt) = conn.prepare("select upper('a'), ulid() is not null").unwrap();
+        let mut stmt = stmt.unwrap();
+        let row = stmt.next().unwrap().unwrap();
+        assert_eq!(row[0].as_str(), "A");
+    }
+
+    #[cfg(feature = "object_store")]
+    #[test]
+    fn test_download_dir_is_removed_with_connection() {
+        let conn = Connection::open_in_memory().unwrap();
+        let dir = conn.download_dir().unwrap().to_path_buf();
+        assert_eq!(conn.download_dir().unwrap(), dir);
+        std::fs::write(dir.join("object.csv"), "a\n1\n").unwrap();
+        drop(conn);
+        assert!(!dir.exists());
+    }
+
     fn sample_query_result() -> crate::rpc::QueryResult {
         let column = |name: &str| ColumnMeta {
             name: name.to_string(),
@@ -1971,6 +2859,150 @@ mod tests {
         assert!(err.message.contains("Failed to connect"), "{}", err.message);
     }
 
+    /// Requests servers from before the `Hello` handshake understood (a
+    /// subset; requests are decoded by variant name).
+    #[derive(Deserialize)]
+    enum LegacyRequest {
+        Query {
+            sql: String,
+            params: Vec<(String, OwnedValue)>,
+        },
+        InTransaction,
+        Close,
+    }
+
+    /// Serve `stream` like a server from before the handshake: a request
+    /// it can't decode ends the session.
+    #[cfg(unix)]
+    fn serve_legacy(stream: std::os::unix::net::UnixStream) {
+        use std::io::Write;
+
+        let mut session = crate::rpc::Session::new(Connection::open_in_memory().unwrap());
+        let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        while let Ok(request) = crate::rpc::read_frame::<_, LegacyRequest>(&mut reader) {
+            let request = match request {
+                LegacyRequest::Query { sql, params } => crate::rpc::Request::Query { sql, params },
+                LegacyRequest::InTransaction => crate::rpc::Request::InTransaction,
+                LegacyRequest::Close => return,
+            };
+            crate::rpc::write_frame(&mut writer, &session.handle(request)).unwrap();
+            writer.flush().unwrap();
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_connect_falls_back_for_servers_without_hello() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let opened = AtomicUsize::new(0);
+        let open = || {
+            opened.fetch_add(1, Ordering::SeqCst);
+            let (ours, theirs) = std::os::unix::net::UnixStream::pair().unwrap();
+            std::thread::spawn(move || serve_legacy(theirs));
+            let reader: Box<dyn std::io::Read + Send> = Box::new(ours.try_clone().unwrap());
+            let writer: Box<dyn std::io::Write + Send> = Box::new(ours);
+            Ok((None, reader, writer))
+        };
+        let transport = RemoteTransport::connect_pipes(open, "TRANSPORT_ERROR").unwrap();
+        // The first server hung up on `Hello`; the second got the ping
+        assert_eq!(opened.load(Ordering::SeqCst), 2);
+        assert!(transport.capabilities.is_empty());
+
+        let origin = RemoteOrigin::Transport {
+            command: "false".to_string(),
+            db_path: String::new(),
+            remote_bin: None,
+        };
+        let conn = Connection::from_transport(transport, origin);
+        assert!(!conn.in_transaction());
+        let (_, stmt) = conn.prepare("select 1 as a").unwrap();
+        assert_eq!(stmt.unwrap().next().unwrap().unwrap()[0].as_int64(), 1);
+        // Nothing to prepare comes back as `None`, like a local prepare
+        let (_, stmt) = conn.prepare("-- nothing").unwrap();
+        assert!(stmt.is_none());
+    }
+
+    /// Serve a fresh database holding `t(x)` = 1, 2, 3 over a Unix socket
+    /// and connect to it. Returns the connection and the scratch directory.
+    #[cfg(unix)]
+    fn socket_connection(name: &str) -> (Connection, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("solite-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = dir.join("app.db");
+        Connection::open(db.to_str().unwrap())
+            .unwrap()
+            .execute_script("create table t(x); insert into t values (1), (2), (3);")
+            .unwrap();
+        let address = crate::rpc::SocketAddress::Unix(dir.join("app.sock"));
+        {
+            let address = address.clone();
+            let db = db.to_str().unwrap().to_string();
+            std::thread::spawn(move || {
+                crate::rpc::listen(&address, "s3cret".to_string(), usize::MAX, move || {
+                    Connection::open(&db)
+                })
+            });
+        }
+        let origin = RemoteOrigin::Socket {
+            address,
+            token: "s3cret".to_string(),
+        };
+        let conn = loop {
+            match Connection::from_remote(origin.clone()) {
+                Ok(conn) => break conn,
+                Err(_) => std::thread::sleep(std::time::Duration::from_millis(10)),
+            }
+        };
+        (conn, dir)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remote_pager_reuses_side_session() {
+        let (conn, dir) = socket_connection("side-session");
+
+        let pager = conn.remote_pager().unwrap();
+        let a = pager.session().unwrap();
+        let b = conn.remote_pager().unwrap().session().unwrap();
+        assert!(Arc::ptr_eq(&a.transport, &b.transport));
+        assert!(!Arc::ptr_eq(&a.transport, &pager.transport));
+        let page = b.page("select * from t;", 0, 0, true).unwrap();
+        assert_eq!(page.total, Some(3));
+
+        // a session whose transport failed is replaced
+        a.transport.lock().unwrap().closed = true;
+        let c = pager.session().unwrap();
+        assert!(!Arc::ptr_eq(&a.transport, &c.transport));
+        drop((conn, pager, a, b, c));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remote_cursor_reopens_on_next() {
+        let (conn, dir) = socket_connection("cursor-reset");
+        let (_, stmt) = conn.prepare("select x from t order by x").unwrap();
+        let mut stmt = stmt.unwrap();
+        assert_eq!(stmt.next().unwrap().unwrap()[0].as_int64(), 1);
+        assert_eq!(stmt.next().unwrap().unwrap()[0].as_int64(), 2);
+
+        // reset starts over from the first row
+        stmt.reset();
+        assert_eq!(stmt.next().unwrap().unwrap()[0].as_int64(), 1);
+
+        // a query that can no longer run fails on next() rather than
+        // silently leaving the old cursor in place
+        stmt.reset();
+        conn.execute("drop table t").unwrap();
+        let err = stmt.next().unwrap_err();
+        assert!(err.message.contains("no such table"), "{}", err.message);
+        drop((stmt, conn));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_sql_load_extension_disabled() {
         // Extension loading is enabled for the C API only; the SQL
@@ -2202,6 +3234,11 @@ mod tests {
         assert!(is_remote_path("host:/path/to/db"));
         assert!(is_remote_path("user@myserver.com:/data/app.db"));
 
+        // solite serve --listen sockets
+        assert!(is_remote_path("tcp://127.0.0.1:7878"));
+        assert!(is_remote_path("unix:/run/app.sock"));
+        assert!(is_remote_path("unix:///run/app.sock"));
+
         // Local paths (not remote)
         assert!(!is_remote_path("/path/to/db"));
         assert!(!is_remote_path("relative/path.db"));
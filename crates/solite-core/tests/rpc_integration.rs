@@ -130,3 +130,64 @@ fn test_serve_error_handling() {
     let _ = read_frame::<_, Response>(&mut reader).unwrap();
     let _ = child.wait();
 }
+
+#[test]
+fn test_serve_cursor_fetch() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("test.db");
+
+    let mut child = Command::new(solite_binary())
+        .arg("serve")
+        .arg(db_path.to_str().unwrap())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn solite serve");
+
+    let stdin = child.stdin.take().unwrap();
+    let stdout = child.stdout.take().unwrap();
+    let mut writer = BufWriter::new(stdin);
+    let mut reader = BufReader::new(stdout);
+
+    // Open a cursor with a one-row first page
+    let req = Request::Open {
+        sql: "WITH RECURSIVE c(value) AS (SELECT 1 UNION ALL SELECT value + 1 FROM c LIMIT 5) \
+              SELECT value FROM c"
+            .to_string(),
+        params: vec![],
+        fetch: 1,
+    };
+    write_frame(&mut writer, &req).unwrap();
+    let resp: Response = read_frame(&mut reader).unwrap();
+    let cursor = match resp {
+        Response::Opened { cursor, rows, done } => {
+            assert_eq!(cursor.columns[0].name, "value");
+            assert_eq!(rows.len(), 1);
+            assert!(!done);
+            cursor.id
+        }
+        other => panic!("Expected Opened response, got {:?}", other),
+    };
+
+    // Fetch the rest
+    let req = Request::Fetch { cursor, n: 10 };
+    write_frame(&mut writer, &req).unwrap();
+    let resp: Response = read_frame(&mut reader).unwrap();
+    match resp {
+        Response::Rows { rows, done } => {
+            assert_eq!(rows.len(), 4);
+            assert!(done);
+        }
+        other => panic!("Expected Rows response, got {:?}", other),
+    }
+
+    // Close
+    let req = Request::Close;
+    write_frame(&mut writer, &req).unwrap();
+    let resp: Response = read_frame(&mut reader).unwrap();
+    assert!(matches!(resp, Response::Closed));
+
+    let status = child.wait().unwrap();
+    assert!(status.success());
+}
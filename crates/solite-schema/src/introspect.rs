@@ -356,6 +356,20 @@ pub fn introspect_connection(conn: &Connection) -> Result<IntrospectedSchema, In
     Ok(schema)
 }
 
+/// Introspect the database behind a raw `sqlite3` handle that belongs to
+/// another wrapper, such as a `solite_core` connection. The handle is
+/// borrowed, not closed.
+///
+/// # Safety
+///
+/// `db` must be a valid, open connection handle for the whole call.
+pub unsafe fn introspect_handle(
+    db: *mut rusqlite::ffi::sqlite3,
+) -> Result<IntrospectedSchema, IntrospectError> {
+    let conn = Connection::from_handle(db)?;
+    introspect_connection(&conn)
+}
+
 /// Introspect a single table to get its column information.
 fn introspect_table(
     conn: &Connection,
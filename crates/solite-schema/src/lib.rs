@@ -57,6 +57,9 @@ pub use provider::{DdlSchemaProvider, JsonSchemaProvider, SchemaError, SchemaPro
 #[cfg(not(target_arch = "wasm32"))]
 pub mod introspect;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub use introspect::{introspect_connection, introspect_handle};
+
 #[cfg(not(target_arch = "wasm32"))]
 pub use provider::FileSchemaProvider;
 